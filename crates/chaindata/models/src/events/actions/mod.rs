@@ -1,9 +1,11 @@
 mod auction_finished;
 mod land_bought;
 mod land_nuked;
+mod land_transfer;
 mod new_auction;
 
 pub use auction_finished::AuctionFinishedEventModel;
 pub use land_bought::LandBoughtEventModel;
 pub use land_nuked::LandNukedEventModel;
+pub use land_transfer::LandTransferEventModel;
 pub use new_auction::NewAuctionEventModel;
@@ -1,10 +1,12 @@
 use super::{
     actions::{
-        AuctionFinishedEventModel, LandBoughtEventModel, LandNukedEventModel, NewAuctionEventModel,
+        AuctionFinishedEventModel, LandBoughtEventModel, LandNukedEventModel,
+        LandTransferEventModel, NewAuctionEventModel,
     },
     auth::{AddressAuthorizedEventModel, AddressRemovedEventModel, VerifierUpdatedEventModel},
     EventId as Id, EventType,
 };
+use crate::shared::Location;
 use ponziland_models::events::EventData;
 use sqlx::prelude::FromRow;
 
@@ -20,6 +22,7 @@ pub enum DataModel {
     AuctionFinished(AuctionFinishedEventModel),
     LandBought(LandBoughtEventModel),
     LandNuked(LandNukedEventModel),
+    LandTransfer(LandTransferEventModel),
     NewAuction(NewAuctionEventModel),
     AddressAuthorized(AddressAuthorizedEventModel),
     AddressRemoved(AddressRemovedEventModel),
@@ -32,12 +35,57 @@ impl DataModel {
             DataModel::AuctionFinished(model) => model.id = Some(id),
             DataModel::LandBought(model) => model.id = Some(id),
             DataModel::LandNuked(model) => model.id = Some(id),
+            DataModel::LandTransfer(model) => model.id = Some(id),
             DataModel::NewAuction(model) => model.id = Some(id),
             DataModel::AddressAuthorized(model) => model.id = Some(id),
             DataModel::AddressRemoved(model) => model.id = Some(id),
             DataModel::VerifierUpdated(model) => model.id = Some(id),
         }
     }
+
+    #[must_use]
+    pub fn id(&self) -> Option<&Id> {
+        match self {
+            DataModel::AuctionFinished(model) => model.id.as_ref(),
+            DataModel::LandBought(model) => model.id.as_ref(),
+            DataModel::LandNuked(model) => model.id.as_ref(),
+            DataModel::LandTransfer(model) => model.id.as_ref(),
+            DataModel::NewAuction(model) => model.id.as_ref(),
+            DataModel::AddressAuthorized(model) => model.id.as_ref(),
+            DataModel::AddressRemoved(model) => model.id.as_ref(),
+            DataModel::VerifierUpdated(model) => model.id.as_ref(),
+        }
+    }
+
+    /// The land the event is about, if any.
+    #[must_use]
+    pub fn location(&self) -> Option<Location> {
+        match self {
+            DataModel::AuctionFinished(model) => Some(model.location),
+            DataModel::LandBought(model) => Some(model.location),
+            DataModel::LandNuked(model) => Some(model.location),
+            DataModel::LandTransfer(model) => Some(model.from_location),
+            DataModel::NewAuction(model) => Some(model.location),
+            DataModel::AddressAuthorized(_)
+            | DataModel::AddressRemoved(_)
+            | DataModel::VerifierUpdated(_) => None,
+        }
+    }
+
+    /// The players involved in the event.
+    #[must_use]
+    pub fn addresses(&self) -> Vec<&str> {
+        match self {
+            DataModel::AuctionFinished(model) => vec![&model.buyer],
+            DataModel::LandBought(model) => vec![&model.buyer, &model.seller],
+            DataModel::LandNuked(model) => vec![&model.owner],
+            DataModel::AddressAuthorized(model) => vec![&model.address],
+            DataModel::AddressRemoved(model) => vec![&model.address],
+            DataModel::LandTransfer(_)
+            | DataModel::NewAuction(_)
+            | DataModel::VerifierUpdated(_) => vec![],
+        }
+    }
 }
 
 impl From<EventData> for DataModel {
@@ -46,6 +94,7 @@ impl From<EventData> for DataModel {
             EventData::AuctionFinished(data) => DataModel::AuctionFinished(data.into()),
             EventData::LandBought(data) => DataModel::LandBought(data.into()),
             EventData::LandNuked(data) => DataModel::LandNuked(data.into()),
+            EventData::LandTransfer(data) => DataModel::LandTransfer(data.into()),
             EventData::NewAuction(data) => DataModel::NewAuction(data.into()),
             EventData::AddressAuthorized(address_authorized_event) => {
                 DataModel::AddressAuthorized(address_authorized_event.into())
@@ -67,3 +116,12 @@ pub struct FetchedEvent {
     pub at: chrono::NaiveDateTime,
     pub data: DataModel,
 }
+
+/// A stored event, along with its position in the order the events were stored.
+///
+/// Events can be stored out of chain order, so the consumers follow `seq` rather than the ids.
+#[derive(Clone, Debug)]
+pub struct StoredEvent {
+    pub seq: i64,
+    pub event: FetchedEvent,
+}
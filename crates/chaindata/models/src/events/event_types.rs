@@ -11,6 +11,8 @@ pub enum EventType {
     LandBought,
     #[sqlx(rename = "ponzi_land-LandNukedEvent")]
     LandNuked,
+    #[sqlx(rename = "ponzi_land-LandTransferEvent")]
+    LandTransfer,
     #[sqlx(rename = "ponzi_land-NewAuctionEvent")]
     NewAuction,
     #[sqlx(rename = "ponzi_land-AddressAuthorizedEvent")]
@@ -27,6 +29,7 @@ impl From<&EventDataModel> for EventType {
             EventDataModel::AuctionFinished(_) => EventType::AuctionFinished,
             EventDataModel::LandBought(_) => EventType::LandBought,
             EventDataModel::LandNuked(_) => EventType::LandNuked,
+            EventDataModel::LandTransfer(_) => EventType::LandTransfer,
             EventDataModel::NewAuction(_) => EventType::NewAuction,
             EventDataModel::AddressAuthorized(_) => EventType::AddressAuthorized,
             EventDataModel::AddressRemoved(_) => EventType::AddressRemoved,
@@ -91,6 +91,26 @@ impl Id {
         }
     }
 
+    /// Returns the smallest id that can exist in the given block.
+    ///
+    /// Useful to express block ranges as id ranges, as ids are ordered by chain position.
+    #[must_use]
+    pub fn first_of_block(block_id: u64) -> Self {
+        Self::new(block_id.into(), Felt::ZERO, 0)
+    }
+
+    /// Returns the number of the block the event was emitted in, if it fits in a `u64`.
+    #[must_use]
+    pub fn block_number(&self) -> Option<u64> {
+        let bytes = self.block_id.to_bytes_be();
+        let (high, low) = bytes.split_at(bytes.len() - 8);
+        if high.iter().any(|&byte| byte != 0) {
+            return None;
+        }
+
+        Some(u64::from_be_bytes(low.try_into().ok()?))
+    }
+
     // Get the string representation, computing it if needed
     pub fn as_string(&self) -> String {
         self.string_repr
@@ -333,6 +353,24 @@ mod tests {
         assert_eq!(events, vec![&event_a, &event_b, &event_d, &event_f]);
     }
 
+    #[test]
+    fn test_first_of_block() {
+        let tx_hash =
+            Felt::from_hex("0x5f26258a75882780784979d970a3579c091e92073d61f7e90260e1133f75c8a")
+                .unwrap();
+
+        let first = Id::first_of_block(0xb63a9);
+        let event = Id::new(Felt::from(0xb63a9), tx_hash, 0);
+        let previous_block = Id::new(Felt::from(0xb63a8), tx_hash, 0x10);
+
+        assert!(first <= event);
+        assert!(previous_block < first);
+
+        // The string representation must keep the same ordering, as it is used in SQL queries
+        assert!(first.as_string() <= event.as_string());
+        assert!(previous_block.as_string() < first.as_string());
+    }
+
     #[test]
     fn test_sqlx_implementation() {
         // This test verifies the logic behind SQLx serialization/deserialization
@@ -7,5 +7,5 @@ mod id;
 
 pub use id::Id as EventId;
 
-pub use event::{DataModel as EventDataModel, Event, FetchedEvent};
+pub use event::{DataModel as EventDataModel, Event, FetchedEvent, StoredEvent};
 pub use event_types::EventType;
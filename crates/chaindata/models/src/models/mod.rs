@@ -1,6 +1,8 @@
 mod auction;
 mod land;
 mod land_stake;
+mod token;
 
 pub use land::{Level, Model as LandModel};
 pub use land_stake::Model as LandStakeModel;
+pub use token::{Balance as TokenBalanceModel, Transfer as TokenTransferModel};
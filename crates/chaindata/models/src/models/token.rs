@@ -0,0 +1,49 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use torii_ingester::torii_tokens::{TokenBalance, TokenTransfer};
+
+use crate::shared::U256;
+
+/// Balance of an account in an ERC20 token.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Balance {
+    pub token: String,
+    pub account: String,
+    pub balance: U256,
+}
+
+impl From<TokenBalance> for Balance {
+    fn from(balance: TokenBalance) -> Self {
+        Self {
+            token: format!("{:#x}", balance.contract_address),
+            account: format!("{:#x}", balance.account_address),
+            balance: balance.balance.into(),
+        }
+    }
+}
+
+/// Transfer of an ERC20 token.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Transfer {
+    /// Id given by torii.
+    pub id: String,
+    pub at: NaiveDateTime,
+    pub token: String,
+    pub from_address: String,
+    pub to_address: String,
+    pub amount: U256,
+}
+
+impl From<TokenTransfer> for Transfer {
+    fn from(transfer: TokenTransfer) -> Self {
+        Self {
+            id: transfer.id,
+            at: transfer.executed_at.naive_utc(),
+            token: format!("{:#x}", transfer.contract_address),
+            from_address: format!("{:#x}", transfer.from_address),
+            to_address: format!("{:#x}", transfer.to_address),
+            amount: transfer.amount.into(),
+        }
+    }
+}
@@ -1,8 +1,15 @@
-use std::ops::Deref;
+use std::{
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
 
 use ponziland_models::shared::Location as RawLocation;
 use serde::{Deserialize, Serialize};
-use sqlx::{prelude::Type, Decode, Encode, Postgres};
+use sqlx::{
+    postgres::{PgHasArrayType, PgTypeInfo},
+    prelude::Type,
+    Decode, Encode, Postgres,
+};
 use torii_ingester::{
     conversions::{FromPrimitive, Primitive},
     error::ToriiConversionError,
@@ -14,12 +21,24 @@ use torii_ingester::{
 #[serde(transparent)]
 pub struct Location(RawLocation);
 
+impl Hash for Location {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0 .0.hash(state);
+    }
+}
+
 impl Type<Postgres> for Location {
     fn type_info() -> <Postgres as sqlx::Database>::TypeInfo {
         <i32 as Type<Postgres>>::type_info()
     }
 }
 
+impl PgHasArrayType for Location {
+    fn array_type_info() -> PgTypeInfo {
+        <i32 as PgHasArrayType>::array_type_info()
+    }
+}
+
 impl FromPrimitive for Location {
     fn from_primitive(value: Primitive) -> Result<Location, ToriiConversionError> {
         RawLocation::from_primitive(value).map(Location)
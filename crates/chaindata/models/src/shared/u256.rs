@@ -2,7 +2,11 @@ use std::{fmt::Display, ops::Deref, str::FromStr};
 
 use num_bigint::{BigInt, Sign};
 use serde::{Deserialize, Serialize};
-use sqlx::{types::BigDecimal, Postgres, Type};
+use sqlx::{
+    postgres::{PgHasArrayType, PgTypeInfo},
+    types::BigDecimal,
+    Postgres, Type,
+};
 use torii_ingester::{
     conversions::{FromPrimitive, Primitive},
     error::ToriiConversionError,
@@ -96,6 +100,12 @@ impl Type<Postgres> for U256 {
     }
 }
 
+impl PgHasArrayType for U256 {
+    fn array_type_info() -> PgTypeInfo {
+        <BigDecimal as PgHasArrayType>::array_type_info()
+    }
+}
+
 impl<'r> sqlx::Decode<'r, Postgres> for U256 {
     fn decode(
         value: sqlx::postgres::PgValueRef<'r>,
@@ -0,0 +1,77 @@
+use crate::{CursorStore, Database, Error};
+use sqlx::query;
+
+/// Positions of the consumers of the event dispatcher.
+pub struct Repository {
+    db: Database,
+}
+
+impl Repository {
+    #[must_use]
+    pub fn new(db: impl Into<Database>) -> Self {
+        Self { db: db.into() }
+    }
+}
+
+#[async_trait::async_trait]
+impl CursorStore for Repository {
+    /// Gets the sequence number of the last event handled by the consumer, if any.
+    ///
+    /// # Errors
+    /// Returns an error if the cursor could not be retrieved.
+    async fn get_cursor(&self, consumer_id: &str) -> Result<Option<i64>, Error> {
+        Ok(query!(
+            r#"
+            SELECT last_seq FROM consumer_cursor WHERE consumer_id = $1
+            "#,
+            consumer_id
+        )
+        // Used to resume the consumers, so it must not lag behind
+        .fetch_optional(self.db.writer())
+        .await?
+        .map(|row| row.last_seq))
+    }
+
+    /// Records that the consumer handled every event stored up to the given sequence number.
+    ///
+    /// # Errors
+    /// Returns an error if the cursor could not be saved.
+    async fn save_cursor(&self, consumer_id: &str, seq: i64) -> Result<(), Error> {
+        query!(
+            r#"
+            INSERT INTO consumer_cursor (consumer_id, last_seq, updated_at)
+            VALUES ($1, $2, NOW())
+            ON CONFLICT (consumer_id) DO UPDATE SET
+                last_seq = EXCLUDED.last_seq,
+                updated_at = EXCLUDED.updated_at
+            "#,
+            consumer_id,
+            seq
+        )
+        .execute(self.db.writer())
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use migrations::MIGRATOR;
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_cursor(pool: sqlx::PgPool) -> Result<(), Error> {
+        let repo = Repository::new(pool);
+
+        assert_eq!(repo.get_cursor("consumer").await?, None);
+
+        repo.save_cursor("consumer", 1).await?;
+        repo.save_cursor("consumer", 2).await?;
+        repo.save_cursor("other", 1).await?;
+
+        assert_eq!(repo.get_cursor("consumer").await?, Some(2));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use sqlx::{pool::PoolConnection, query, PgPool, Postgres};
+use tracing::warn;
+
+/// Handle to the database, shared by all repositories.
+///
+/// Writes always go to the primary, while reads go to the read replica if one is configured.
+/// Without a replica, both use the same pool.
+#[derive(Clone, Debug)]
+pub struct Database {
+    writer: PgPool,
+    reader: Option<PgPool>,
+    /// If set, reads fall back to the primary while the replica has not replayed our last write.
+    read_your_writes: bool,
+    /// WAL position of the last write done through this handle (0 if none).
+    last_write_lsn: Arc<AtomicU64>,
+}
+
+impl Database {
+    /// Creates a handle using the same pool for reads and writes.
+    #[must_use]
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            writer: pool,
+            reader: None,
+            read_your_writes: false,
+            last_write_lsn: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Creates a handle writing to `writer` and reading from the `reader` replica.
+    #[must_use]
+    pub fn with_replica(writer: PgPool, reader: PgPool) -> Self {
+        Self {
+            reader: Some(reader),
+            ..Self::new(writer)
+        }
+    }
+
+    /// Enables or disables the "read your own writes" mode.
+    ///
+    /// When enabled, each read checks that the replica replayed the last write done through this
+    /// handle, and uses the primary otherwise.
+    #[must_use]
+    pub fn read_your_writes(mut self, enabled: bool) -> Self {
+        self.read_your_writes = enabled;
+        self
+    }
+
+    /// The pool of the primary, to use for writes (and reads that must be up to date).
+    #[must_use]
+    pub fn writer(&self) -> &PgPool {
+        &self.writer
+    }
+
+    /// Acquires a connection to use for reads.
+    ///
+    /// # Errors
+    /// Returns an error if no connection could be acquired, or if the replica lag could not be checked.
+    pub async fn read(&self) -> Result<PoolConnection<Postgres>, sqlx::Error> {
+        let Some(reader) = &self.reader else {
+            return self.writer.acquire().await;
+        };
+
+        let mut conn = reader.acquire().await?;
+
+        let last_write_lsn = self.last_write_lsn.load(Ordering::Acquire);
+        if !self.read_your_writes || last_write_lsn == 0 {
+            return Ok(conn);
+        }
+
+        let caught_up = query!(
+            r#"
+            SELECT COALESCE(pg_last_wal_replay_lsn() >= $1::text::pg_lsn, true) as "caught_up!"
+            "#,
+            format_lsn(last_write_lsn)
+        )
+        .fetch_one(&mut *conn)
+        .await?
+        .caught_up;
+
+        if caught_up {
+            Ok(conn)
+        } else {
+            self.writer.acquire().await
+        }
+    }
+
+    /// Records that a write was committed, so that following reads can wait for the replica.
+    ///
+    /// Does nothing unless the "read your own writes" mode is enabled with a replica. As the
+    /// write is already committed, failing to get the position of the primary is only logged:
+    /// the following reads may then see the replica slightly behind.
+    pub async fn record_write(&self) {
+        if !self.read_your_writes || self.reader.is_none() {
+            return;
+        }
+
+        let lsn = match query!(r#"SELECT pg_current_wal_lsn()::text as "lsn!""#)
+            .fetch_one(&self.writer)
+            .await
+        {
+            Ok(row) => row.lsn,
+            Err(err) => {
+                warn!("Could not record the position of a write: {err}");
+                return;
+            }
+        };
+
+        if let Some(lsn) = parse_lsn(&lsn) {
+            self.last_write_lsn.fetch_max(lsn, Ordering::AcqRel);
+        }
+    }
+}
+
+impl From<PgPool> for Database {
+    fn from(pool: PgPool) -> Self {
+        Self::new(pool)
+    }
+}
+
+/// Parses a postgres log sequence number (`XXXXXXXX/XXXXXXXX`).
+fn parse_lsn(lsn: &str) -> Option<u64> {
+    let (high, low) = lsn.split_once('/')?;
+    Some((u64::from_str_radix(high, 16).ok()? << 32) | u64::from_str_radix(low, 16).ok()?)
+}
+
+fn format_lsn(lsn: u64) -> String {
+    format!("{:X}/{:X}", lsn >> 32, lsn & 0xFFFF_FFFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use migrations::MIGRATOR;
+
+    #[test]
+    fn test_lsn_roundtrip() {
+        assert_eq!(parse_lsn("16/B374D848"), Some(0x16_B374_D848));
+        assert_eq!(format_lsn(0x16_B374_D848), "16/B374D848");
+        assert_eq!(parse_lsn("invalid"), None);
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_read_your_writes(pool: PgPool) -> Result<(), sqlx::Error> {
+        // The same server plays the replica, which is never behind
+        let db = Database::with_replica(pool.clone(), pool).read_your_writes(true);
+
+        db.record_write().await;
+        assert_ne!(db.last_write_lsn.load(Ordering::Acquire), 0);
+
+        let mut conn = db.read().await?;
+        sqlx::query("SELECT 1").execute(&mut *conn).await?;
+
+        Ok(())
+    }
+}
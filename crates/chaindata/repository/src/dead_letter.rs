@@ -0,0 +1,190 @@
+use crate::Database;
+use chrono::NaiveDateTime;
+use sqlx::query;
+
+/// What a dead letter was supposed to be decoded into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "dead_letter_kind", rename_all = "lowercase")]
+pub enum DeadLetterKind {
+    Event,
+    Model,
+}
+
+/// A Torii row that could not be decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeadLetter {
+    pub kind: DeadLetterKind,
+    /// Name of the event or model, as given by Torii.
+    pub name: String,
+    /// Missing for data received through gRPC, which then cannot be retried.
+    pub torii_event_id: Option<String>,
+    pub at: NaiveDateTime,
+    /// The raw data, as JSON.
+    pub raw: String,
+    /// The error, followed by its causes.
+    pub error: String,
+}
+
+/// A dead letter waiting to be retried.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredDeadLetter {
+    pub id: i64,
+    pub attempts: u32,
+    pub letter: DeadLetter,
+}
+
+/// Keeps the rows the listeners could not decode, so that they can be retried once the decoders
+/// are fixed instead of stopping the ingestion.
+pub struct Repository {
+    db: Database,
+}
+
+impl Repository {
+    #[must_use]
+    pub fn new(db: impl Into<Database>) -> Self {
+        Self { db: db.into() }
+    }
+
+    /// Stores a dead letter, unless the same row already failed.
+    ///
+    /// Returns whether the dead letter is new.
+    ///
+    /// # Errors
+    /// Returns an error if the dead letter could not be saved.
+    pub async fn save(&self, letter: &DeadLetter) -> Result<bool, sqlx::Error> {
+        let result = query!(
+            r#"
+            INSERT INTO dead_letter (kind, name, torii_event_id, at, raw, error)
+            VALUES ($1, $2, $3, $4, $5::text::jsonb, $6)
+            ON CONFLICT (kind, name, torii_event_id) DO NOTHING
+            "#,
+            letter.kind as DeadLetterKind,
+            letter.name,
+            letter.torii_event_id,
+            letter.at,
+            letter.raw,
+            letter.error
+        )
+        .execute(self.db.writer())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Gets the dead letters of the given kind, oldest first.
+    ///
+    /// # Errors
+    /// Returns an error if the dead letters could not be retrieved.
+    #[allow(clippy::cast_sign_loss)] // Attempts are never negative
+    pub async fn get_all(
+        &self,
+        kind: DeadLetterKind,
+    ) -> Result<Vec<StoredDeadLetter>, sqlx::Error> {
+        let rows = query!(
+            r#"
+            SELECT
+                id, name, torii_event_id, at, raw::text as "raw!", error, attempts
+            FROM dead_letter
+            WHERE kind = $1
+            ORDER BY at, id
+            "#,
+            kind as DeadLetterKind
+        )
+        .fetch_all(self.db.writer())
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| StoredDeadLetter {
+                id: row.id,
+                attempts: row.attempts as u32,
+                letter: DeadLetter {
+                    kind,
+                    name: row.name,
+                    torii_event_id: row.torii_event_id,
+                    at: row.at,
+                    raw: row.raw,
+                    error: row.error,
+                },
+            })
+            .collect())
+    }
+
+    /// Records that retrying the dead letter failed again.
+    ///
+    /// # Errors
+    /// Returns an error if the dead letter could not be updated.
+    pub async fn record_failure(&self, id: i64, error: &str) -> Result<(), sqlx::Error> {
+        query!(
+            r#"
+            UPDATE dead_letter
+            SET error = $2, attempts = attempts + 1, last_attempt_at = NOW()
+            WHERE id = $1
+            "#,
+            id,
+            error
+        )
+        .execute(self.db.writer())
+        .await?;
+
+        Ok(())
+    }
+
+    /// Removes a dead letter, once it was decoded and stored.
+    ///
+    /// # Errors
+    /// Returns an error if the dead letter could not be deleted.
+    pub async fn delete(&self, id: i64) -> Result<(), sqlx::Error> {
+        query!(
+            r#"
+            DELETE FROM dead_letter WHERE id = $1
+            "#,
+            id
+        )
+        .execute(self.db.writer())
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use migrations::MIGRATOR;
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_dead_letters(pool: sqlx::PgPool) -> Result<(), sqlx::Error> {
+        let repo = Repository::new(pool);
+        let letter = DeadLetter {
+            kind: DeadLetterKind::Event,
+            name: "ponzi_land-UnknownEvent".to_string(),
+            torii_event_id: Some("0x1:0x2:0x3".to_string()),
+            at: Utc::now().naive_utc(),
+            raw: r#"{"field":1}"#.to_string(),
+            error: "Unknown variant".to_string(),
+        };
+
+        assert!(repo.save(&letter).await?);
+        // Seen again on the next poll
+        assert!(!repo.save(&letter).await?);
+        assert!(repo.get_all(DeadLetterKind::Model).await?.is_empty());
+
+        let stored = repo.get_all(DeadLetterKind::Event).await?;
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].attempts, 1);
+        assert_eq!(stored[0].letter.raw, r#"{"field": 1}"#);
+        assert_eq!(stored[0].letter.name, letter.name);
+
+        repo.record_failure(stored[0].id, "Still unknown").await?;
+        let stored = repo.get_all(DeadLetterKind::Event).await?;
+        assert_eq!(stored[0].attempts, 2);
+        assert_eq!(stored[0].letter.error, "Still unknown");
+
+        repo.delete(stored[0].id).await?;
+        assert!(repo.get_all(DeadLetterKind::Event).await?.is_empty());
+
+        Ok(())
+    }
+}
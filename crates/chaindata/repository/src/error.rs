@@ -4,4 +4,14 @@ pub enum Error {
     SqlError(#[from] sqlx::Error),
     #[error("Invalid ID: {0}")]
     InvalidId(#[from] chaindata_models::error::Error),
+    #[error("Missing data for event {0:?}")]
+    MissingEventData(chaindata_models::events::EventId),
+    #[error("Already exists: {0:?}")]
+    AlreadyExists(chaindata_models::events::EventId),
+    #[error("The reindex rebuilt {reindexed} rows of {table} out of the {stored} stored, some raw rows are missing")]
+    FewerRows {
+        table: &'static str,
+        reindexed: u64,
+        stored: u64,
+    },
 }
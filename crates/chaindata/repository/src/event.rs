@@ -1,18 +1,102 @@
-use crate::{events::base::EventDataRepository, Database, Error};
-use chaindata_models::events::{Event, EventId, EventType, FetchedEvent};
-use chrono::{DateTime, Utc};
-use sqlx::{query, query_as};
+use crate::{
+    events::base::EventDataRepository, gg_xyz_outbox::Repository as GgXyzOutboxRepository,
+    raw::Repository as RawDataRepository, Database, Error, EventStore, Pagination, RawData,
+};
+use chaindata_models::{
+    events::{Event, EventDataModel, EventId, EventType, FetchedEvent, StoredEvent},
+    shared::Location,
+};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use sqlx::{query, query_as, PgConnection, QueryBuilder};
+use std::collections::HashMap;
+
+/// Filters applied when querying events.
+///
+/// Every filter that is set must match for an event to be returned.
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    /// Only return events of these types (all types if empty).
+    pub event_types: Vec<EventType>,
+    /// Only return events involving this address (as buyer, seller, owner or authorized address).
+    ///
+    /// The address must be formatted the same way as stored, i.e. `{:#x}`.
+    pub address: Option<String>,
+    /// Only return events concerning this location.
+    pub location: Option<Location>,
+    /// Only return events that happened at or after this time.
+    pub from_time: Option<NaiveDateTime>,
+    /// Only return events that happened strictly before this time.
+    pub to_time: Option<NaiveDateTime>,
+    /// Only return events emitted in this block or after.
+    pub from_block: Option<u64>,
+    /// Only return events emitted in this block or before.
+    pub to_block: Option<u64>,
+}
+
+/// Key of the transaction-level advisory lock taken to insert events.
+const EVENT_SEQ_LOCK_KEY: i64 = 0x6576_656e_745f_7365;
 
 pub struct Repository {
     db: Database,
+    gg_xyz_outbox: bool,
 }
 
 impl Repository {
     #[must_use]
-    pub fn new(db: Database) -> Self {
-        Self { db }
+    pub fn new(db: impl Into<Database>) -> Self {
+        Self {
+            db: db.into(),
+            gg_xyz_outbox: false,
+        }
+    }
+
+    /// When enabled, the gg.xyz actions of the new events are queued in the outbox,
+    /// in the same transaction as the events.
+    #[must_use]
+    pub fn with_gg_xyz_outbox(mut self, enabled: bool) -> Self {
+        self.gg_xyz_outbox = enabled;
+        self
+    }
+
+    /// Waits for the other transactions inserting events to end.
+    ///
+    /// Sequence numbers are given at insert, so this makes the events visible in the order of
+    /// their sequence numbers, and a consumer never moves past one that is not committed yet.
+    async fn lock_seq(conn: &mut PgConnection) -> Result<(), Error> {
+        sqlx::query("SELECT pg_advisory_xact_lock($1)")
+            .bind(EVENT_SEQ_LOCK_KEY)
+            .execute(conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Attaches the data stored in the per-type tables to the given events, keeping their order.
+    #[allow(clippy::mutable_key_type)] // The cached string representation is not part of the hash
+    async fn with_data(
+        conn: &mut PgConnection,
+        events: Vec<Event>,
+    ) -> Result<Vec<FetchedEvent>, Error> {
+        let mut data = EventDataRepository::get_all(conn, &events).await?;
+
+        events
+            .into_iter()
+            .map(|event| {
+                let data = data
+                    .remove(&event.id)
+                    .ok_or_else(|| Error::MissingEventData(event.id.clone()))?;
+
+                Ok(FetchedEvent {
+                    id: event.id,
+                    at: event.at,
+                    data,
+                })
+            })
+            .collect()
     }
+}
 
+#[async_trait::async_trait]
+impl EventStore for Repository {
     /// Get an event by its id.
     ///
     /// # Errors
@@ -20,7 +104,7 @@ impl Repository {
     /// - No row was found
     /// - Error connecting to the database
     /// - Wrong format of id
-    pub async fn get_event_by_id(&self, id: EventId) -> Result<Event, Error> {
+    async fn get_event_by_id(&self, id: EventId) -> Result<Event, Error> {
         Ok(query_as!(
             Event,
             r#"
@@ -33,10 +117,182 @@ impl Repository {
         "#,
             id as EventId
         )
-        .fetch_one(&mut *(self.db.acquire().await?))
+        .fetch_one(&mut *(self.db.read().await?))
         .await?)
     }
 
+    /// Get an event by its id, along with its data.
+    ///
+    /// # Errors
+    /// Returns an error if the event could not be fetched. Could be one of the following reasons:
+    /// - Error connecting to the database
+    /// - Wrong format of id
+    /// - The event data is missing
+    async fn get_full_event_by_id(&self, id: EventId) -> Result<Option<FetchedEvent>, Error> {
+        let mut conn = self.db.read().await?;
+
+        let event = query_as!(
+            Event,
+            r#"
+            SELECT
+                id as "id: _",
+                at,
+                event_type as "event_type: _"
+            FROM event
+            WHERE id = $1
+        "#,
+            id as EventId
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(Self::with_data(&mut conn, event.into_iter().collect())
+            .await?
+            .pop())
+    }
+
+    /// Get the events matching the filter, along with their data, ordered by chain position.
+    ///
+    /// # Errors
+    /// Returns an error if the events could not be fetched. Could be one of the following reasons:
+    /// - Error connecting to the database
+    /// - Wrong format of id
+    /// - The data of one of the events is missing
+    async fn get_events(
+        &self,
+        filter: &EventFilter,
+        pagination: &Pagination,
+    ) -> Result<Vec<FetchedEvent>, Error> {
+        let mut conn = self.db.read().await?;
+
+        let mut query = QueryBuilder::new("SELECT id, at, event_type FROM event WHERE TRUE");
+
+        if !filter.event_types.is_empty() {
+            query.push(" AND event_type IN (");
+            let mut separated = query.separated(", ");
+            for event_type in &filter.event_types {
+                separated.push_bind(event_type.clone());
+            }
+            separated.push_unseparated(")");
+        }
+
+        if let Some(from_time) = filter.from_time {
+            query.push(" AND at >= ").push_bind(from_time);
+        }
+
+        if let Some(to_time) = filter.to_time {
+            query.push(" AND at < ").push_bind(to_time);
+        }
+
+        if let Some(from_block) = filter.from_block {
+            query
+                .push(" AND id >= ")
+                .push_bind(EventId::first_of_block(from_block));
+        }
+
+        if let Some(to_block) = filter.to_block {
+            query
+                .push(" AND id < ")
+                .push_bind(EventId::first_of_block(to_block.saturating_add(1)));
+        }
+
+        if let Some(after) = &pagination.after {
+            query.push(" AND id > ").push_bind(after.clone());
+        }
+
+        if let Some(location) = filter.location {
+            query
+                .push(" AND id IN (SELECT id FROM event_auction_finished WHERE location = ")
+                .push_bind(location)
+                .push(" UNION ALL SELECT id FROM event_land_bought WHERE location = ")
+                .push_bind(location)
+                .push(" UNION ALL SELECT id FROM event_land_nuked WHERE location = ")
+                .push_bind(location)
+                .push(" UNION ALL SELECT id FROM event_new_auction WHERE location = ")
+                .push_bind(location)
+                .push(")");
+        }
+
+        if let Some(address) = &filter.address {
+            query
+                .push(" AND id IN (SELECT id FROM event_auction_finished WHERE buyer = ")
+                .push_bind(address.clone())
+                .push(" UNION ALL SELECT id FROM event_land_bought WHERE buyer = ")
+                .push_bind(address.clone())
+                .push(" OR seller = ")
+                .push_bind(address.clone())
+                .push(" UNION ALL SELECT id FROM event_land_nuked WHERE owner = ")
+                .push_bind(address.clone())
+                .push(" UNION ALL SELECT id FROM event_address_authorized WHERE address = ")
+                .push_bind(address.clone())
+                .push(" UNION ALL SELECT id FROM event_address_removed WHERE address = ")
+                .push_bind(address.clone())
+                .push(")");
+        }
+
+        query
+            .push(" ORDER BY id LIMIT ")
+            .push_bind(pagination.sql_limit());
+
+        let events: Vec<Event> = query.build_query_as().fetch_all(&mut *conn).await?;
+
+        Self::with_data(&mut conn, events).await
+    }
+
+    /// Get the events stored after the given sequence number, in the order they were stored.
+    ///
+    /// # Errors
+    /// Returns an error if the events could not be fetched. Could be one of the following reasons:
+    /// - Error connecting to the database
+    /// - Wrong format of id
+    /// - The data of one of the events is missing
+    async fn get_events_after_seq(
+        &self,
+        event_types: &[EventType],
+        after_seq: i64,
+        limit: u32,
+    ) -> Result<Vec<StoredEvent>, Error> {
+        // Used to resume the consumers, so it must not lag behind
+        let mut conn = self.db.writer().acquire().await?;
+
+        let rows = query!(
+            r#"
+            SELECT
+                id as "id: EventId",
+                at,
+                event_type as "event_type: EventType",
+                seq
+            FROM event
+            WHERE seq > $1
+                AND (cardinality($2::event_type[]) = 0 OR event_type = ANY($2))
+            ORDER BY seq
+            LIMIT $3
+        "#,
+            after_seq,
+            event_types as &[EventType],
+            i64::from(limit)
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let seqs: Vec<i64> = rows.iter().map(|row| row.seq).collect();
+        let events = rows
+            .into_iter()
+            .map(|row| Event {
+                id: row.id,
+                at: row.at,
+                event_type: row.event_type,
+            })
+            .collect();
+
+        Ok(Self::with_data(&mut conn, events)
+            .await?
+            .into_iter()
+            .zip(seqs)
+            .map(|(event, seq)| StoredEvent { seq, event })
+            .collect())
+    }
+
     /// Get the last event date.
     ///
     /// # Errors
@@ -44,7 +300,7 @@ impl Repository {
     /// - No row was found
     /// - Error connecting to the database
     /// - Wrong format of id
-    pub async fn get_last_event_date(&self) -> Result<DateTime<Utc>, Error> {
+    async fn get_last_event_date(&self) -> Result<DateTime<Utc>, Error> {
         Ok(query!(
             r#"
             SELECT
@@ -52,7 +308,8 @@ impl Repository {
             FROM event
         "#
         )
-        .fetch_one(&mut *(self.db.acquire().await?))
+        // Used to resume indexing, so it must not lag behind
+        .fetch_one(self.db.writer())
         .await?
         .max
         .map_or(DateTime::UNIX_EPOCH, |date| date.and_utc()))
@@ -62,9 +319,10 @@ impl Repository {
     ///
     /// # Errors
     /// Returns an error if the event could not be saved.
-    pub async fn save_event(&self, event: FetchedEvent) -> Result<EventId, Error> {
+    async fn save_event(&self, event: FetchedEvent) -> Result<EventId, Error> {
         // Start a TX
-        let mut tx = self.db.begin().await?;
+        let mut tx = self.db.writer().begin().await?;
+        Self::lock_seq(&mut tx).await?;
 
         // Generate a new id
         let id = event.id;
@@ -91,10 +349,314 @@ impl Repository {
 
         // Insert the event data
         EventDataRepository::save(&mut *tx, &event_data).await?;
+        if self.gg_xyz_outbox {
+            GgXyzOutboxRepository::enqueue_in(&mut tx, std::slice::from_ref(&event_data)).await?;
+        }
 
         // Commit the TX
         tx.commit().await?;
+        self.db.record_write().await;
 
         Ok(id)
     }
+
+    /// Saves many events at once, in a single transaction.
+    ///
+    /// Events that already exist are ignored, and only the ids of the newly inserted ones are returned.
+    ///
+    /// # Errors
+    /// Returns an error if the events could not be saved.
+    async fn save_events(&self, events: Vec<FetchedEvent>) -> Result<Vec<EventId>, Error> {
+        Ok(self
+            .save_events_with_raw(events, &[])
+            .await?
+            .into_iter()
+            .map(|stored| stored.event.id)
+            .collect())
+    }
+
+    /// Saves many events at once along with their raw rows, in a single transaction.
+    ///
+    /// Events and raw rows that already exist are ignored, and only the newly inserted events
+    /// are returned, in the order they were stored: their sequence numbers follow their ids.
+    ///
+    /// # Errors
+    /// Returns an error if the events or the raw rows could not be saved.
+    async fn save_events_with_raw(
+        &self,
+        events: Vec<FetchedEvent>,
+        raw: &[RawData],
+    ) -> Result<Vec<StoredEvent>, Error> {
+        if events.is_empty() && raw.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut tx = self.db.writer().begin().await?;
+        RawDataRepository::save_many_in(&mut tx, raw).await?;
+        Self::lock_seq(&mut tx).await?;
+
+        let ids: Vec<String> = events.iter().map(|event| event.id.as_string()).collect();
+        let ats: Vec<NaiveDateTime> = events.iter().map(|event| event.at).collect();
+        let types: Vec<EventType> = events
+            .iter()
+            .map(|event| EventType::from(&event.data))
+            .collect();
+
+        // Inserted in chain order, so that the sequence numbers follow it
+        let mut seqs: HashMap<String, i64> = query!(
+            r#"
+            INSERT INTO event (id, at, event_type)
+            SELECT * FROM UNNEST($1::text[], $2::timestamp[], $3::event_type[])
+                AS t(id, at, event_type)
+            ORDER BY id
+            ON CONFLICT (id) DO NOTHING
+            RETURNING id, seq
+        "#,
+            &ids,
+            &ats,
+            &types as &[EventType]
+        )
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(|row| (row.id, row.seq))
+        .collect();
+
+        // Only the data of new events is inserted, duplicates are left untouched
+        let mut inserted: Vec<StoredEvent> = events
+            .into_iter()
+            .filter_map(|mut event| {
+                let seq = seqs.remove(&event.id.as_string())?;
+                event.data.set_id(event.id.clone());
+                Some(StoredEvent { seq, event })
+            })
+            .collect();
+        inserted.sort_by_key(|stored| stored.seq);
+
+        let data: Vec<EventDataModel> = inserted
+            .iter()
+            .map(|stored| stored.event.data.clone())
+            .collect();
+
+        EventDataRepository::save_all(&mut tx, &data).await?;
+        if self.gg_xyz_outbox {
+            GgXyzOutboxRepository::enqueue_in(&mut tx, &data).await?;
+        }
+
+        tx.commit().await?;
+        self.db.record_write().await;
+
+        Ok(inserted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use chaindata_models::{
+        events::{
+            actions::{LandBoughtEventModel, LandNukedEventModel, NewAuctionEventModel},
+            EventDataModel,
+        },
+        shared::U256,
+    };
+    use migrations::MIGRATOR;
+
+    fn bought(id: EventId, at: NaiveDateTime, location: Location) -> FetchedEvent {
+        FetchedEvent {
+            id,
+            at,
+            data: EventDataModel::LandBought(LandBoughtEventModel {
+                id: None,
+                location,
+                buyer: "0xbuyer".to_string(),
+                seller: "0xseller".to_string(),
+                price: U256::from_str("100").unwrap(),
+                token_used: "0xtoken".to_string(),
+            }),
+        }
+    }
+
+    fn nuked(id: EventId, at: NaiveDateTime, location: Location) -> FetchedEvent {
+        FetchedEvent {
+            id,
+            at,
+            data: EventDataModel::LandNuked(LandNukedEventModel {
+                id: None,
+                location,
+                owner: "0xbuyer".to_string(),
+            }),
+        }
+    }
+
+    fn new_auction(id: EventId, at: NaiveDateTime, location: Location) -> FetchedEvent {
+        FetchedEvent {
+            id,
+            at,
+            data: EventDataModel::NewAuction(NewAuctionEventModel {
+                id: None,
+                location,
+                starting_price: U256::from_str("1000").unwrap(),
+                floor_price: U256::from_str("10").unwrap(),
+            }),
+        }
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_get_full_event_by_id(pool: sqlx::PgPool) -> Result<(), Error> {
+        let repo = Repository::new(pool);
+        let now = Utc::now().naive_utc();
+        let location: Location = 1234.into();
+
+        let id = repo
+            .save_event(bought(EventId::new_test(1, 0, 0), now, location))
+            .await?;
+
+        let event = repo
+            .get_full_event_by_id(id.clone())
+            .await?
+            .expect("The event should exist");
+        assert_eq!(event.id, id);
+
+        let EventDataModel::LandBought(data) = event.data else {
+            panic!("Wrong event type: {:?}", event.data);
+        };
+        assert_eq!(data.id, Some(id));
+        assert_eq!(data.location, location);
+        assert_eq!(data.buyer, "0xbuyer");
+        assert_eq!(data.price, U256::from_str("100").unwrap());
+
+        let missing = repo
+            .get_full_event_by_id(EventId::new_test(2, 0, 0))
+            .await?;
+        assert!(missing.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_get_events_with_filters(pool: sqlx::PgPool) -> Result<(), Error> {
+        let repo = Repository::new(pool);
+        let time1 = Utc::now().naive_utc();
+        let time2 = time1 + chrono::Duration::hours(1);
+        let first: Location = 1.into();
+        let second: Location = 2.into();
+
+        // Saved out of order to check the ordering by chain position
+        repo.save_event(nuked(EventId::new_test(3, 0, 0), time2, first))
+            .await?;
+        repo.save_event(bought(EventId::new_test(2, 0, 1), time1, second))
+            .await?;
+        repo.save_event(new_auction(EventId::new_test(2, 0, 0), time1, first))
+            .await?;
+
+        let all = repo
+            .get_events(&EventFilter::default(), &Pagination::default())
+            .await?;
+        let ids: Vec<EventId> = all.iter().map(|event| event.id.clone()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                EventId::new_test(2, 0, 0),
+                EventId::new_test(2, 0, 1),
+                EventId::new_test(3, 0, 0)
+            ]
+        );
+
+        // By event type
+        let filter = EventFilter {
+            event_types: vec![EventType::LandNuked, EventType::NewAuction],
+            ..Default::default()
+        };
+        let events = repo.get_events(&filter, &Pagination::default()).await?;
+        assert_eq!(events.len(), 2);
+        assert!(events
+            .iter()
+            .all(|event| !matches!(event.data, EventDataModel::LandBought(_))));
+
+        // By location
+        let filter = EventFilter {
+            location: Some(first),
+            ..Default::default()
+        };
+        let events = repo.get_events(&filter, &Pagination::default()).await?;
+        assert_eq!(events.len(), 2);
+
+        // By address (buyer of the land bought event, and owner of the nuked land)
+        let filter = EventFilter {
+            address: Some("0xbuyer".to_string()),
+            ..Default::default()
+        };
+        let events = repo.get_events(&filter, &Pagination::default()).await?;
+        assert_eq!(events.len(), 2);
+
+        // By time range
+        let filter = EventFilter {
+            from_time: Some(time2),
+            ..Default::default()
+        };
+        let events = repo.get_events(&filter, &Pagination::default()).await?;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].id, EventId::new_test(3, 0, 0));
+
+        // By block range
+        let filter = EventFilter {
+            from_block: Some(2),
+            to_block: Some(2),
+            ..Default::default()
+        };
+        let events = repo.get_events(&filter, &Pagination::default()).await?;
+        assert_eq!(events.len(), 2);
+
+        // Pagination
+        let page = repo
+            .get_events(&EventFilter::default(), &Pagination::new(None, 2))
+            .await?;
+        assert_eq!(page.len(), 2);
+        let next_page = repo
+            .get_events(
+                &EventFilter::default(),
+                &Pagination::new(Some(page[1].id.clone()), 2),
+            )
+            .await?;
+        assert_eq!(next_page.len(), 1);
+        assert_eq!(next_page[0].id, EventId::new_test(3, 0, 0));
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_save_events(pool: sqlx::PgPool) -> Result<(), Error> {
+        let repo = Repository::new(pool);
+        let at = Utc::now().naive_utc();
+        let location: Location = 1.into();
+
+        // One event already exists, and must be skipped
+        repo.save_event(nuked(EventId::new_test(1, 0, 0), at, location))
+            .await?;
+
+        let inserted = repo
+            .save_events(vec![
+                nuked(EventId::new_test(1, 0, 0), at, location),
+                bought(EventId::new_test(1, 0, 1), at, location),
+                new_auction(EventId::new_test(1, 0, 2), at, location),
+            ])
+            .await?;
+        assert_eq!(
+            inserted,
+            vec![EventId::new_test(1, 0, 1), EventId::new_test(1, 0, 2)]
+        );
+
+        let all = repo
+            .get_events(&EventFilter::default(), &Pagination::default())
+            .await?;
+        assert_eq!(all.len(), 3);
+        assert!(matches!(all[1].data, EventDataModel::LandBought(_)));
+
+        assert!(repo.save_events(Vec::new()).await?.is_empty());
+
+        Ok(())
+    }
 }
@@ -1,5 +1,14 @@
-use chaindata_models::events::EventDataModel;
-use sqlx::Error;
+use std::collections::HashMap;
+
+use chaindata_models::events::{
+    actions::{
+        AuctionFinishedEventModel, LandBoughtEventModel, LandNukedEventModel,
+        LandTransferEventModel, NewAuctionEventModel,
+    },
+    auth::{AddressAuthorizedEventModel, AddressRemovedEventModel, VerifierUpdatedEventModel},
+    Event, EventDataModel, EventId, EventType,
+};
+use sqlx::{postgres::PgRow, Error, FromRow, PgConnection};
 
 use super::event_data::EventModelRepository;
 
@@ -19,6 +28,7 @@ impl EventDataRepository {
             EventDataModel::AuctionFinished(event) => Self::save_event(conn, event),
             EventDataModel::LandBought(event) => Self::save_event(conn, event),
             EventDataModel::LandNuked(event) => Self::save_event(conn, event),
+            EventDataModel::LandTransfer(event) => Self::save_event(conn, event),
             EventDataModel::NewAuction(event) => Self::save_event(conn, event),
             EventDataModel::AddressAuthorized(event) => Self::save_event(conn, event),
             EventDataModel::AddressRemoved(event) => Self::save_event(conn, event),
@@ -26,4 +36,151 @@ impl EventDataRepository {
         }
         .await
     }
+
+    /// Saves the data of many events, grouped by type so that each table is written in batches.
+    ///
+    /// Data that already exists is ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub async fn save_all(conn: &mut PgConnection, events: &[EventDataModel]) -> Result<(), Error> {
+        macro_rules! save_variant {
+            ($variant:ident) => {
+                let models: Vec<_> = events
+                    .iter()
+                    .filter_map(|event| match event {
+                        EventDataModel::$variant(model) => Some(model.clone()),
+                        _ => None,
+                    })
+                    .collect();
+                if !models.is_empty() {
+                    Self::save_many(&mut *conn, &models).await?;
+                }
+            };
+        }
+
+        save_variant!(AuctionFinished);
+        save_variant!(LandBought);
+        save_variant!(LandNuked);
+        save_variant!(LandTransfer);
+        save_variant!(NewAuction);
+        save_variant!(AddressAuthorized);
+        save_variant!(AddressRemoved);
+        save_variant!(VerifierUpdated);
+
+        Ok(())
+    }
+
+    /// Loads the data of the given events from their per-type tables.
+    ///
+    /// Events are grouped by type, so that only one query is done per event type.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    #[allow(clippy::mutable_key_type)] // The cached string representation is not part of the hash
+    pub async fn get_all(
+        conn: &mut PgConnection,
+        events: &[Event],
+    ) -> Result<HashMap<EventId, EventDataModel>, Error> {
+        let ids_of = |event_type: EventType| -> Vec<EventId> {
+            events
+                .iter()
+                .filter(|event| event.event_type == event_type)
+                .map(|event| event.id.clone())
+                .collect()
+        };
+
+        let mut data = Vec::with_capacity(events.len());
+        data.extend(
+            Self::get_all_of::<AuctionFinishedEventModel>(
+                conn,
+                &ids_of(EventType::AuctionFinished),
+                EventDataModel::AuctionFinished,
+            )
+            .await?,
+        );
+        data.extend(
+            Self::get_all_of::<LandBoughtEventModel>(
+                conn,
+                &ids_of(EventType::LandBought),
+                EventDataModel::LandBought,
+            )
+            .await?,
+        );
+        data.extend(
+            Self::get_all_of::<LandNukedEventModel>(
+                conn,
+                &ids_of(EventType::LandNuked),
+                EventDataModel::LandNuked,
+            )
+            .await?,
+        );
+        data.extend(
+            Self::get_all_of::<LandTransferEventModel>(
+                conn,
+                &ids_of(EventType::LandTransfer),
+                EventDataModel::LandTransfer,
+            )
+            .await?,
+        );
+        data.extend(
+            Self::get_all_of::<NewAuctionEventModel>(
+                conn,
+                &ids_of(EventType::NewAuction),
+                EventDataModel::NewAuction,
+            )
+            .await?,
+        );
+        data.extend(
+            Self::get_all_of::<AddressAuthorizedEventModel>(
+                conn,
+                &ids_of(EventType::AddressAuthorized),
+                EventDataModel::AddressAuthorized,
+            )
+            .await?,
+        );
+        data.extend(
+            Self::get_all_of::<AddressRemovedEventModel>(
+                conn,
+                &ids_of(EventType::AddressRemoved),
+                EventDataModel::AddressRemoved,
+            )
+            .await?,
+        );
+        data.extend(
+            Self::get_all_of::<VerifierUpdatedEventModel>(
+                conn,
+                &ids_of(EventType::VerifierUpdated),
+                EventDataModel::VerifierUpdated,
+            )
+            .await?,
+        );
+
+        Ok(data
+            .into_iter()
+            .filter_map(|model| model.id().cloned().map(|id| (id, model)))
+            .collect())
+    }
+
+    async fn get_all_of<Model>(
+        conn: &mut PgConnection,
+        ids: &[EventId],
+        wrap: fn(Model) -> EventDataModel,
+    ) -> Result<Vec<EventDataModel>, Error>
+    where
+        Self: EventModelRepository<Model>,
+        Model: Sized + Unpin + Send + Sync + for<'r> FromRow<'r, PgRow> + 'static,
+    {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        Ok(Self::get_by_ids(&mut *conn, ids)
+            .await?
+            .into_iter()
+            .map(wrap)
+            .collect())
+    }
 }
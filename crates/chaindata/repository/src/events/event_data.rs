@@ -2,12 +2,13 @@
 
 use chaindata_models::events::{
     actions::{
-        AuctionFinishedEventModel, LandBoughtEventModel, LandNukedEventModel, NewAuctionEventModel,
+        AuctionFinishedEventModel, LandBoughtEventModel, LandNukedEventModel,
+        LandTransferEventModel, NewAuctionEventModel,
     },
     auth::{AddressAuthorizedEventModel, AddressRemovedEventModel, VerifierUpdatedEventModel},
     EventId,
 };
-use sqlx::{postgres::PgRow, query_builder::Separated, FromRow, QueryBuilder};
+use sqlx::{postgres::PgRow, query_builder::Separated, FromRow, PgConnection, QueryBuilder};
 
 use super::base::EventDataRepository;
 use sqlx::Error;
@@ -30,12 +31,32 @@ where
         query
             .push(" FROM ")
             .push(Self::TABLE_NAME)
-            .push(" WHERE id = ?")
+            .push(" WHERE id = ")
             .push_bind(id);
 
         query.build_query_as().fetch_optional(conn).await
     }
 
+    async fn get_by_ids<'e, Conn>(conn: Conn, ids: &[EventId]) -> Result<Vec<Model>, Error>
+    where
+        Conn: 'e + sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let ids: Vec<String> = ids.iter().map(EventId::as_string).collect();
+
+        // Build query builder
+        let mut query = QueryBuilder::new("SELECT ");
+        Self::push_parameters(&mut query);
+
+        query
+            .push(" FROM ")
+            .push(Self::TABLE_NAME)
+            .push(" WHERE id = ANY(")
+            .push_bind(ids)
+            .push(")");
+
+        query.build_query_as().fetch_all(conn).await
+    }
+
     fn push_parameters(query: &mut QueryBuilder<'_, sqlx::Postgres>);
     fn push_tuple(args: Separated<'_, '_, sqlx::Postgres, &'static str>, model: &Model);
 
@@ -54,8 +75,28 @@ where
         query.build().execute(conn).await?;
         Ok(())
     }
+
+    /// Inserts the models in batches, ignoring the ones that already exist.
+    async fn save_many(conn: &mut PgConnection, models: &[Model]) -> Result<(), Error> {
+        // Keeps the number of bound parameters far below the postgres limit (65535)
+        for chunk in models.chunks(SAVE_BATCH_SIZE) {
+            let mut query = QueryBuilder::new("INSERT INTO ");
+            query.push(Self::TABLE_NAME).push(" (");
+            Self::push_parameters(&mut query);
+            query.push(") ");
+
+            query.push_values(chunk, Self::push_tuple);
+            query.push(" ON CONFLICT (id) DO NOTHING");
+
+            query.build().execute(&mut *conn).await?;
+        }
+        Ok(())
+    }
 }
 
+/// Number of rows inserted per statement by [`EventModelRepository::save_many`].
+const SAVE_BATCH_SIZE: usize = 1000;
+
 // Implement for each event.
 macro_rules! implement_repository {
     // Main entry point for the macro
@@ -128,6 +169,14 @@ implement_repository!(LandNukedEventModel, "event_land_nuked", {
     owner
 });
 
+implement_repository!(LandTransferEventModel, "event_land_transfer", {
+    id,
+    from_location,
+    to_location,
+    token_address,
+    amount
+});
+
 implement_repository!(NewAuctionEventModel, "event_new_auction", {
     id,
     location,
@@ -0,0 +1,98 @@
+//! Models and events shared by the tests of the repositories and of the services using them.
+//!
+//! They hold the values the tests rarely care about, the others can be changed with the struct
+//! update syntax.
+#![allow(clippy::missing_panics_doc)] // Amounts are written by the tests, as valid numbers
+
+use std::str::FromStr;
+
+use chaindata_models::{
+    events::{
+        actions::{LandBoughtEventModel, LandNukedEventModel},
+        EventDataModel, EventId, FetchedEvent,
+    },
+    models::{LandModel, LandStakeModel, Level},
+    shared::{Location, U256},
+};
+use chrono::NaiveDateTime;
+
+use crate::{RawData, RawDataKind};
+
+/// Token used by the lands and the trades.
+pub const TOKEN: &str = "0xtoken";
+
+/// Land of `owner`, bought at `at` and sold for 100 [`TOKEN`].
+#[must_use]
+pub fn land(
+    id: EventId,
+    at: NaiveDateTime,
+    location: impl Into<Location>,
+    owner: &str,
+) -> LandModel {
+    LandModel {
+        id,
+        at,
+        location: location.into(),
+        bought_at: at,
+        owner: owner.to_string(),
+        sell_price: U256::from_str("100").unwrap(),
+        token_used: TOKEN.to_string(),
+        level: Level::Zero,
+    }
+}
+
+/// Stake of a land, last paid at `at`.
+#[must_use]
+pub fn stake(
+    id: EventId,
+    at: NaiveDateTime,
+    location: impl Into<Location>,
+    amount: &str,
+) -> LandStakeModel {
+    LandStakeModel {
+        id,
+        at,
+        location: location.into(),
+        last_pay_time: at,
+        amount: U256::from_str(amount).unwrap(),
+    }
+}
+
+#[must_use]
+pub fn event(id: EventId, at: NaiveDateTime, data: EventDataModel) -> FetchedEvent {
+    FetchedEvent { id, at, data }
+}
+
+/// Purchase of a land from `seller`, paid in [`TOKEN`].
+#[must_use]
+pub fn bought(location: u64, buyer: &str, seller: &str, price: &str) -> EventDataModel {
+    EventDataModel::LandBought(LandBoughtEventModel {
+        id: None,
+        location: location.into(),
+        buyer: buyer.to_string(),
+        seller: seller.to_string(),
+        price: U256::from_str(price).unwrap(),
+        token_used: TOKEN.to_string(),
+    })
+}
+
+#[must_use]
+pub fn nuked(location: u64, owner: &str) -> EventDataModel {
+    EventDataModel::LandNuked(LandNukedEventModel {
+        id: None,
+        location: location.into(),
+        owner: owner.to_string(),
+    })
+}
+
+/// The row Torii gives for `event(EventId::new_test(block, 0, 0), at, nuked(location, owner))`.
+#[must_use]
+pub fn raw_nuked(block: u64, at: NaiveDateTime, location: u64, owner: &str) -> RawData {
+    RawData {
+        kind: RawDataKind::Event,
+        name: "ponzi_land-LandNukedEvent".to_string(),
+        torii_event_id: format!("{block:#x}:0x0:0x0"),
+        at,
+        data: format!(r#"{{"owner_nuked":"{owner}","land_location":{location}}}"#),
+    }
+}
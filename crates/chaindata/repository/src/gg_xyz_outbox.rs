@@ -0,0 +1,369 @@
+use crate::{Database, Error};
+use chaindata_models::events::{EventDataModel, EventId};
+use chrono::NaiveDateTime;
+use sqlx::{query, query_as, PgConnection};
+
+/// Delivery status of an outbox entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "outbox_status", rename_all = "lowercase")]
+pub enum OutboxStatus {
+    /// Not delivered yet, possibly after failed attempts.
+    Pending,
+    Delivered,
+    /// Given up on after too many attempts.
+    Failed,
+}
+
+/// An action to deliver to gg.xyz.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutboxEntry {
+    /// The id of the event followed by the index of the action in it.
+    pub idempotency_key: String,
+    pub event_id: EventId,
+    pub player_address: String,
+    pub action: String,
+    pub status: OutboxStatus,
+    pub attempts: u32,
+    pub next_attempt_at: NaiveDateTime,
+    pub last_error: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub delivered_at: Option<NaiveDateTime>,
+}
+
+struct OutboxRow {
+    idempotency_key: String,
+    event_id: String,
+    player_address: String,
+    action: String,
+    status: OutboxStatus,
+    attempts: i32,
+    next_attempt_at: NaiveDateTime,
+    last_error: Option<String>,
+    created_at: NaiveDateTime,
+    delivered_at: Option<NaiveDateTime>,
+}
+
+impl TryFrom<OutboxRow> for OutboxEntry {
+    type Error = Error;
+
+    #[allow(clippy::cast_sign_loss)] // Attempts are never negative
+    fn try_from(row: OutboxRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            idempotency_key: row.idempotency_key,
+            event_id: row.event_id.parse()?,
+            player_address: row.player_address,
+            action: row.action,
+            status: row.status,
+            attempts: row.attempts as u32,
+            next_attempt_at: row.next_attempt_at,
+            last_error: row.last_error,
+            created_at: row.created_at,
+            delivered_at: row.delivered_at,
+        })
+    }
+}
+
+/// Number of entries per status.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OutboxStats {
+    pub pending: u64,
+    pub delivered: u64,
+    pub failed: u64,
+    /// Creation time of the oldest pending entry, to spot a stuck delivery.
+    pub oldest_pending: Option<NaiveDateTime>,
+}
+
+/// The actions to submit to gg.xyz for an event, as (player, action) pairs.
+#[must_use]
+pub fn gg_xyz_actions(data: &EventDataModel) -> Vec<(String, &'static str)> {
+    match data {
+        EventDataModel::LandNuked(val) => vec![(val.owner.clone(), "Land nuked")],
+        EventDataModel::AuctionFinished(val) => {
+            vec![(val.buyer.clone(), "Bought from auction")]
+        }
+        EventDataModel::LandBought(val) => vec![
+            (val.buyer.clone(), "Bought from player"),
+            (val.seller.clone(), "Sold land"),
+        ],
+        EventDataModel::AddressAuthorized(val) => {
+            vec![(val.address.clone(), "Joined the Ponzi")]
+        }
+        _ => vec![],
+    }
+}
+
+/// Outbox of the actions to deliver to gg.xyz.
+///
+/// Entries are written along with their event by the event repository, so that no action is
+/// lost if gg.xyz is down, and none is queued twice when events are fetched again.
+pub struct Repository {
+    db: Database,
+}
+
+impl Repository {
+    #[must_use]
+    pub fn new(db: impl Into<Database>) -> Self {
+        Self { db: db.into() }
+    }
+
+    /// Queues the actions of the given events, within the transaction saving them.
+    ///
+    /// The ids of the events must be set.
+    ///
+    /// # Errors
+    /// Returns an error if the entries could not be saved.
+    pub(crate) async fn enqueue_in(
+        conn: &mut PgConnection,
+        events: &[EventDataModel],
+    ) -> Result<(), Error> {
+        let mut keys = Vec::new();
+        let mut event_ids = Vec::new();
+        let mut players = Vec::new();
+        let mut actions = Vec::new();
+
+        for data in events {
+            let Some(id) = data.id() else {
+                continue;
+            };
+            for (index, (player, action)) in gg_xyz_actions(data).into_iter().enumerate() {
+                keys.push(format!("{}:{index}", id.as_string()));
+                event_ids.push(id.as_string());
+                players.push(player);
+                actions.push(action.to_string());
+            }
+        }
+
+        if keys.is_empty() {
+            return Ok(());
+        }
+
+        query!(
+            r#"
+            INSERT INTO gg_xyz_outbox (idempotency_key, event_id, player_address, action)
+            SELECT * FROM UNNEST($1::text[], $2::text[], $3::text[], $4::text[])
+            ON CONFLICT (idempotency_key) DO NOTHING
+            "#,
+            &keys,
+            &event_ids,
+            &players,
+            &actions
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Gets the pending entries due for an attempt, in chain order.
+    ///
+    /// # Errors
+    /// Returns an error if the entries could not be retrieved.
+    pub async fn get_due(&self, limit: u32) -> Result<Vec<OutboxEntry>, Error> {
+        let rows = query_as!(
+            OutboxRow,
+            r#"
+            SELECT
+                idempotency_key, event_id, player_address, action,
+                status as "status!: OutboxStatus", attempts, next_attempt_at, last_error,
+                created_at, delivered_at
+            FROM gg_xyz_outbox
+            WHERE status = 'pending' AND next_attempt_at <= NOW()
+            ORDER BY event_id, idempotency_key
+            LIMIT $1
+            "#,
+            i64::from(limit)
+        )
+        // The worker must not deliver an entry again because the replica lags behind
+        .fetch_all(self.db.writer())
+        .await?;
+
+        rows.into_iter().map(OutboxEntry::try_from).collect()
+    }
+
+    /// Gets the most recent entries, optionally with the given status.
+    ///
+    /// # Errors
+    /// Returns an error if the entries could not be retrieved.
+    pub async fn get_entries(
+        &self,
+        status: Option<OutboxStatus>,
+        limit: u32,
+    ) -> Result<Vec<OutboxEntry>, Error> {
+        let rows = query_as!(
+            OutboxRow,
+            r#"
+            SELECT
+                idempotency_key, event_id, player_address, action,
+                status as "status!: OutboxStatus", attempts, next_attempt_at, last_error,
+                created_at, delivered_at
+            FROM gg_xyz_outbox
+            WHERE $1::outbox_status IS NULL OR status = $1
+            ORDER BY event_id DESC, idempotency_key DESC
+            LIMIT $2
+            "#,
+            status as Option<OutboxStatus>,
+            i64::from(limit)
+        )
+        .fetch_all(&mut *(self.db.read().await?))
+        .await?;
+
+        rows.into_iter().map(OutboxEntry::try_from).collect()
+    }
+
+    /// Counts the entries per status.
+    ///
+    /// # Errors
+    /// Returns an error if the entries could not be counted.
+    #[allow(clippy::cast_sign_loss)] // Counts are never negative
+    pub async fn get_stats(&self) -> Result<OutboxStats, sqlx::Error> {
+        let row = query!(
+            r#"
+            SELECT
+                COUNT(*) FILTER (WHERE status = 'pending') as "pending!",
+                COUNT(*) FILTER (WHERE status = 'delivered') as "delivered!",
+                COUNT(*) FILTER (WHERE status = 'failed') as "failed!",
+                MIN(created_at) FILTER (WHERE status = 'pending') as oldest_pending
+            FROM gg_xyz_outbox
+            "#
+        )
+        .fetch_one(&mut *(self.db.read().await?))
+        .await?;
+
+        Ok(OutboxStats {
+            pending: row.pending as u64,
+            delivered: row.delivered as u64,
+            failed: row.failed as u64,
+            oldest_pending: row.oldest_pending,
+        })
+    }
+
+    /// Records that the entry was delivered.
+    ///
+    /// # Errors
+    /// Returns an error if the entry could not be updated.
+    pub async fn mark_delivered(&self, idempotency_key: &str) -> Result<(), sqlx::Error> {
+        query!(
+            r#"
+            UPDATE gg_xyz_outbox
+            SET status = 'delivered', attempts = attempts + 1, delivered_at = NOW(),
+                last_error = NULL
+            WHERE idempotency_key = $1
+            "#,
+            idempotency_key
+        )
+        .execute(self.db.writer())
+        .await?;
+
+        Ok(())
+    }
+
+    /// Records a failed attempt. The entry is tried again at `retry_at`, or marked as failed if
+    /// there is none.
+    ///
+    /// # Errors
+    /// Returns an error if the entry could not be updated.
+    pub async fn record_failure(
+        &self,
+        idempotency_key: &str,
+        error: &str,
+        retry_at: Option<NaiveDateTime>,
+    ) -> Result<(), sqlx::Error> {
+        query!(
+            r#"
+            UPDATE gg_xyz_outbox
+            SET attempts = attempts + 1, last_error = $2,
+                status = CASE WHEN $3::timestamp IS NULL THEN 'failed' ELSE 'pending' END::outbox_status,
+                next_attempt_at = COALESCE($3, next_attempt_at)
+            WHERE idempotency_key = $1
+            "#,
+            idempotency_key,
+            error,
+            retry_at
+        )
+        .execute(self.db.writer())
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{EventRepository, EventStore};
+    use chaindata_models::{
+        events::{
+            actions::{LandBoughtEventModel, NewAuctionEventModel},
+            FetchedEvent,
+        },
+        shared::U256,
+    };
+    use chrono::{Duration, Utc};
+    use migrations::MIGRATOR;
+
+    fn bought(block: u64) -> FetchedEvent {
+        FetchedEvent {
+            id: EventId::new_test(block, 0, 0),
+            at: Utc::now().naive_utc(),
+            data: EventDataModel::LandBought(LandBoughtEventModel {
+                id: None,
+                location: 1.into(),
+                buyer: "0xbuyer".to_string(),
+                seller: "0xseller".to_string(),
+                price: U256::from_str("10").unwrap(),
+                token_used: "0xtoken".to_string(),
+            }),
+        }
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_outbox(pool: sqlx::PgPool) -> Result<(), Error> {
+        let events = EventRepository::new(pool.clone()).with_gg_xyz_outbox(true);
+        let repo = Repository::new(pool);
+
+        events
+            .save_events(vec![
+                bought(1),
+                FetchedEvent {
+                    id: EventId::new_test(2, 0, 0),
+                    at: Utc::now().naive_utc(),
+                    data: EventDataModel::NewAuction(NewAuctionEventModel {
+                        id: None,
+                        location: 2.into(),
+                        starting_price: U256::from_str("100").unwrap(),
+                        floor_price: U256::from_str("1").unwrap(),
+                    }),
+                },
+            ])
+            .await?;
+        // Fetched again, nothing is queued twice
+        events.save_events(vec![bought(1)]).await?;
+
+        let due = repo.get_due(10).await?;
+        let keys: Vec<&str> = due.iter().map(|e| e.idempotency_key.as_str()).collect();
+        let event_id = EventId::new_test(1, 0, 0).as_string();
+        assert_eq!(keys, vec![format!("{event_id}:0"), format!("{event_id}:1")]);
+        assert_eq!(due[0].player_address, "0xbuyer");
+        assert_eq!(due[1].action, "Sold land");
+
+        repo.mark_delivered(&due[0].idempotency_key).await?;
+        let later = Utc::now().naive_utc() + Duration::minutes(1);
+        repo.record_failure(&due[1].idempotency_key, "Unavailable", Some(later))
+            .await?;
+        assert!(repo.get_due(10).await?.is_empty());
+
+        let pending = repo.get_entries(Some(OutboxStatus::Pending), 10).await?;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].attempts, 1);
+        assert_eq!(pending[0].last_error.as_deref(), Some("Unavailable"));
+
+        repo.record_failure(&due[1].idempotency_key, "Unavailable", None)
+            .await?;
+        let stats = repo.get_stats().await?;
+        assert_eq!((stats.pending, stats.delivered, stats.failed), (0, 1, 1));
+
+        Ok(())
+    }
+}
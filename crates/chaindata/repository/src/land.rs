@@ -1,5 +1,12 @@
-use crate::{Database, Error};
-use chaindata_models::{events::EventId, models::LandModel, shared::Location};
+use crate::{
+    raw::Repository as RawDataRepository, Database, Error, LandStore, Pagination, RawData,
+    TimeRange,
+};
+use chaindata_models::{
+    events::EventId,
+    models::LandModel,
+    shared::{Location, U256},
+};
 use chrono::NaiveDateTime;
 use sqlx::{query, query_as};
 use std::collections::HashMap;
@@ -10,15 +17,21 @@ pub struct Repository {
 
 impl Repository {
     #[must_use]
-    pub fn new(db: Database) -> Self {
-        Self { db }
+    pub fn new(db: impl Into<Database>) -> Self {
+        Self { db: db.into() }
     }
+}
 
-    /// Saves a land model to the database
+#[async_trait::async_trait]
+impl LandStore for Repository {
+    /// Saves a land model to the database, and makes it the current state of its location
+    /// if it is the most recent one.
     /// # Errors
     /// Returns an error if the land could not be saved.
-    pub async fn save(&self, land: LandModel) -> Result<EventId, Error> {
-        Ok(query!(
+    async fn save(&self, land: LandModel) -> Result<EventId, Error> {
+        let mut tx = self.db.writer().begin().await?;
+
+        let id = query!(
             r#"
             INSERT INTO land (
                 id, at, location, bought_at, owner, sell_price, token_used, level
@@ -26,7 +39,7 @@ impl Repository {
             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
             RETURNING id
             "#,
-            land.id as EventId,
+            land.id.clone() as EventId,
             land.at,
             land.location as Location,
             land.bought_at,
@@ -35,22 +48,210 @@ impl Repository {
             land.token_used,
             land.level as _
         )
-        .fetch_one(&mut *(self.db.acquire().await?))
+        .fetch_one(&mut *tx)
         .await?
         .id
-        .parse()?)
+        .parse()?;
+
+        // Versions might not arrive in order, so only replace an older current state.
+        query!(
+            r#"
+            INSERT INTO land_current (
+                location, id, at, bought_at, owner, sell_price, token_used, level
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (location) DO UPDATE SET
+                id = EXCLUDED.id,
+                at = EXCLUDED.at,
+                bought_at = EXCLUDED.bought_at,
+                owner = EXCLUDED.owner,
+                sell_price = EXCLUDED.sell_price,
+                token_used = EXCLUDED.token_used,
+                level = EXCLUDED.level
+            WHERE land_current.id < EXCLUDED.id
+            "#,
+            land.location as Location,
+            land.id as EventId,
+            land.at,
+            land.bought_at,
+            land.owner,
+            land.sell_price as _,
+            land.token_used,
+            land.level as _
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        self.db.record_write().await;
+
+        Ok(id)
+    }
+
+    /// Saves many land models at once, in a single transaction, and updates the current state
+    /// of their locations.
+    ///
+    /// Versions that already exist are ignored. Returns the number of new versions.
+    ///
+    /// # Errors
+    /// Returns an error if the lands could not be saved.
+    async fn save_many(&self, lands: &[LandModel]) -> Result<u64, Error> {
+        self.save_many_with_raw(lands, &[]).await
+    }
+
+    /// Saves many lands along with the raw rows they were decoded from, in a single
+    /// transaction.
+    ///
+    /// Versions and raw rows that already exist are ignored. Returns the number of new versions.
+    ///
+    /// # Errors
+    /// Returns an error if the lands or the raw rows could not be saved.
+    async fn save_many_with_raw(&self, lands: &[LandModel], raw: &[RawData]) -> Result<u64, Error> {
+        if lands.is_empty() && raw.is_empty() {
+            return Ok(0);
+        }
+
+        let ids: Vec<String> = lands.iter().map(|land| land.id.as_string()).collect();
+        let ats: Vec<NaiveDateTime> = lands.iter().map(|land| land.at).collect();
+        let locations: Vec<Location> = lands.iter().map(|land| land.location).collect();
+        let bought_ats: Vec<NaiveDateTime> = lands.iter().map(|land| land.bought_at).collect();
+        let owners: Vec<String> = lands.iter().map(|land| land.owner.clone()).collect();
+        let sell_prices: Vec<U256> = lands.iter().map(|land| land.sell_price).collect();
+        let tokens: Vec<String> = lands.iter().map(|land| land.token_used.clone()).collect();
+        let levels: Vec<i32> = lands.iter().map(|land| land.level as i32).collect();
+
+        let mut tx = self.db.writer().begin().await?;
+        RawDataRepository::save_many_in(&mut tx, raw).await?;
+
+        let inserted = query!(
+            r#"
+            INSERT INTO land (
+                id, at, location, bought_at, owner, sell_price, token_used, level
+            )
+            SELECT * FROM UNNEST(
+                $1::text[], $2::timestamp[], $3::int4[], $4::timestamp[],
+                $5::text[], $6::numeric[], $7::text[], $8::int4[]
+            )
+            ON CONFLICT (id) DO NOTHING
+            "#,
+            &ids,
+            &ats,
+            &locations as &[Location],
+            &bought_ats,
+            &owners,
+            &sell_prices as &[U256],
+            &tokens,
+            &levels
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        // Only the most recent version of each location in the batch can become the current state
+        query!(
+            r#"
+            INSERT INTO land_current (
+                location, id, at, bought_at, owner, sell_price, token_used, level
+            )
+            SELECT DISTINCT ON (location)
+                location, id, at, bought_at, owner, sell_price, token_used, level
+            FROM UNNEST(
+                $1::text[], $2::timestamp[], $3::int4[], $4::timestamp[],
+                $5::text[], $6::numeric[], $7::text[], $8::int4[]
+            ) AS batch(id, at, location, bought_at, owner, sell_price, token_used, level)
+            ORDER BY location, id DESC
+            ON CONFLICT (location) DO UPDATE SET
+                id = EXCLUDED.id,
+                at = EXCLUDED.at,
+                bought_at = EXCLUDED.bought_at,
+                owner = EXCLUDED.owner,
+                sell_price = EXCLUDED.sell_price,
+                token_used = EXCLUDED.token_used,
+                level = EXCLUDED.level
+            WHERE land_current.id < EXCLUDED.id
+            "#,
+            &ids,
+            &ats,
+            &locations as &[Location],
+            &bought_ats,
+            &owners,
+            &sell_prices as &[U256],
+            &tokens,
+            &levels
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        self.db.record_write().await;
+
+        Ok(inserted)
+    }
+
+    /// Gets the current state of the land at a specific location
+    ///
+    /// # Errors
+    /// Returns an error if the land could not be retrieved
+    async fn get_current_at_location(
+        &self,
+        location: Location,
+    ) -> Result<Option<LandModel>, Error> {
+        Ok(query_as!(
+            LandModel,
+            r#"
+            SELECT
+                id as "id: _",
+                at,
+                location as "location: Location",
+                bought_at,
+                owner,
+                sell_price as "sell_price: _",
+                token_used,
+                level as "level: _"
+            FROM land_current
+            WHERE location = $1
+            "#,
+            location as Location
+        )
+        .fetch_optional(&mut *(self.db.read().await?))
+        .await?)
+    }
+
+    /// Gets the current state of all lands
+    ///
+    /// # Errors
+    /// Returns an error if the lands could not be retrieved
+    async fn get_all_current(&self) -> Result<Vec<LandModel>, Error> {
+        Ok(query_as!(
+            LandModel,
+            r#"
+            SELECT
+                id as "id: _",
+                at,
+                location as "location: Location",
+                bought_at,
+                owner,
+                sell_price as "sell_price: _",
+                token_used,
+                level as "level: _"
+            FROM land_current
+            ORDER BY location
+            "#
+        )
+        .fetch_all(&mut *(self.db.read().await?))
+        .await?)
     }
 
     /// Gets the latest land model at a specific location at or before the given timestamp
     /// # Errors
     /// Returns an error if the latest land could not be retrieved
     /// (for example, if the location is invalid, or if there is no land at that location)
-    pub async fn get_latest_at_location(
+    async fn get_latest_at_location(
         &self,
         location: Location,
         at: NaiveDateTime,
-    ) -> Result<Option<LandModel>, sqlx::Error> {
-        query_as!(
+    ) -> Result<Option<LandModel>, Error> {
+        Ok(query_as!(
             LandModel,
             r#"
             SELECT
@@ -70,17 +271,17 @@ impl Repository {
             location as Location,
             at
         )
-        .fetch_optional(&mut *(self.db.acquire().await?))
-        .await
+        .fetch_optional(&mut *(self.db.read().await?))
+        .await?)
     }
 
     /// Gets all lands that exist at a specific point in time
     ///
     /// # Errors
     /// Returns an error if the lands could not be retrieved
-    pub async fn get_all_at_time(&self, at: NaiveDateTime) -> Result<Vec<LandModel>, sqlx::Error> {
+    async fn get_all_at_time(&self, at: NaiveDateTime) -> Result<Vec<LandModel>, Error> {
         // This query gets the most recent version of each land at or before the specified time
-        query_as!(
+        Ok(query_as!(
             LandModel,
             r#"
             WITH latest_lands AS (
@@ -103,16 +304,57 @@ impl Repository {
             "#,
             at
         )
-        .fetch_all(&mut *(self.db.acquire().await?))
-        .await
+        .fetch_all(&mut *(self.db.read().await?))
+        .await?)
+    }
+
+    /// Gets the versions of the land at a specific location within the given time range,
+    /// ordered by chain position.
+    ///
+    /// # Errors
+    /// Returns an error if the lands could not be retrieved
+    async fn get_history(
+        &self,
+        location: Location,
+        range: TimeRange,
+        pagination: &Pagination,
+    ) -> Result<Vec<LandModel>, Error> {
+        Ok(query_as!(
+            LandModel,
+            r#"
+            SELECT
+                id as "id: _",
+                at,
+                location as "location: Location",
+                bought_at,
+                owner,
+                sell_price as "sell_price: _",
+                token_used,
+                level as "level: _"
+            FROM land
+            WHERE location = $1
+                AND ($2::timestamp IS NULL OR at >= $2)
+                AND ($3::timestamp IS NULL OR at < $3)
+                AND ($4::text IS NULL OR id > $4)
+            ORDER BY id
+            LIMIT $5
+            "#,
+            location as Location,
+            range.from,
+            range.to,
+            pagination.after.clone() as Option<EventId>,
+            pagination.sql_limit()
+        )
+        .fetch_all(&mut *(self.db.read().await?))
+        .await?)
     }
 
     /// Gets a land model by ID
     ///
     /// # Errors
     /// Returns an error if the land could not be retrieved
-    pub async fn get_by_id(&self, id: EventId) -> Result<Option<LandModel>, sqlx::Error> {
-        query_as!(
+    async fn get_by_id(&self, id: EventId) -> Result<Option<LandModel>, Error> {
+        Ok(query_as!(
             LandModel,
             r#"
             SELECT
@@ -129,24 +371,25 @@ impl Repository {
             "#,
             id as EventId
         )
-        .fetch_optional(&mut *(self.db.acquire().await?))
-        .await
+        .fetch_optional(&mut *(self.db.read().await?))
+        .await?)
     }
 
     /// Gets the latest timestamp from the land table
     ///
     /// # Errors
     /// Returns an error if the latest timestamp could not be retrieved
-    pub async fn get_latest_timestamp(&self) -> Result<Option<NaiveDateTime>, sqlx::Error> {
-        query!(
+    async fn get_latest_timestamp(&self) -> Result<Option<NaiveDateTime>, Error> {
+        Ok(query!(
             r#"
             SELECT MAX(at) as latest_time
             FROM land
             "#
         )
-        .fetch_one(&mut *(self.db.acquire().await?))
-        .await
-        .map(|row| row.latest_time)
+        // Used to resume indexing, so it must not lag behind
+        .fetch_one(self.db.writer())
+        .await?
+        .latest_time)
     }
 
     /// Gets the total distribution of tokens for all lands
@@ -154,26 +397,20 @@ impl Repository {
     /// # Errors
     /// Returns an error if the database could not be accessed
     #[allow(clippy::cast_sign_loss)] // We are fine
-    pub async fn get_land_distribution(&self) -> Result<HashMap<String, u64>, sqlx::Error> {
-        query!(
+    async fn get_land_distribution(&self) -> Result<HashMap<String, u64>, Error> {
+        Ok(query!(
             r#"
             SELECT token_used, count(*)
-            FROM (
-                SELECT *,
-                       ROW_NUMBER() OVER (PARTITION BY location ORDER BY id DESC) as rn
-                FROM land
-            ) ranked
-            WHERE rn = 1 AND owner <> '0'
+            FROM land_current
+            WHERE owner <> '0'
             GROUP BY token_used
             "#
         )
-        .fetch_all(&mut *(self.db.acquire().await?))
-        .await
-        .map(|rows| {
-            rows.into_iter()
-                .map(|row| (row.token_used, row.count.unwrap_or(0) as u64))
-                .collect()
-        })
+        .fetch_all(&mut *(self.db.read().await?))
+        .await?
+        .into_iter()
+        .map(|row| (row.token_used, row.count.unwrap_or(0) as u64))
+        .collect())
     }
 }
 
@@ -310,4 +547,148 @@ mod tests {
 
         Ok(())
     }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_land_history(pool: sqlx::PgPool) -> Result<(), Error> {
+        let repo = Repository::new(pool);
+
+        let location: Location = 4321.into();
+        let other_location: Location = 4322.into();
+        let time1 = Utc::now().naive_utc();
+
+        let land_at = |id: u32, at: NaiveDateTime, location: Location| LandModel {
+            id: EventId::new_test(0, 0, id),
+            at,
+            location,
+            bought_at: at,
+            owner: format!("0xowner{id}"),
+            sell_price: U256::from_str("100").unwrap(),
+            token_used: "0xtoken".to_string(),
+            level: Level::Zero,
+        };
+
+        for i in 0..3 {
+            repo.save(land_at(
+                i,
+                time1 + chrono::Duration::hours(i.into()),
+                location,
+            ))
+            .await?;
+        }
+        repo.save(land_at(10, time1, other_location)).await?;
+
+        // Whole history, only for the requested location
+        let history = repo
+            .get_history(location, TimeRange::default(), &Pagination::default())
+            .await?;
+        let ids: Vec<EventId> = history.iter().map(|l| l.id.clone()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                EventId::new_test(0, 0, 0),
+                EventId::new_test(0, 0, 1),
+                EventId::new_test(0, 0, 2)
+            ]
+        );
+
+        // Time range (end excluded)
+        let range = TimeRange::new(
+            Some(time1 + chrono::Duration::hours(1)),
+            Some(time1 + chrono::Duration::hours(2)),
+        );
+        let history = repo
+            .get_history(location, range, &Pagination::default())
+            .await?;
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].id, EventId::new_test(0, 0, 1));
+
+        // Pagination
+        let history = repo
+            .get_history(
+                location,
+                TimeRange::default(),
+                &Pagination::new(Some(EventId::new_test(0, 0, 0)), 1),
+            )
+            .await?;
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].id, EventId::new_test(0, 0, 1));
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_land_current_state(pool: sqlx::PgPool) -> Result<(), Error> {
+        let repo = Repository::new(pool);
+
+        let location: Location = 1111.into();
+        let now = Utc::now().naive_utc();
+        let land = |id: u32, owner: &str| LandModel {
+            id: EventId::new_test(0, 0, id),
+            at: now,
+            location,
+            bought_at: now,
+            owner: owner.to_string(),
+            sell_price: U256::from_str("100").unwrap(),
+            token_used: "0xtoken".to_string(),
+            level: Level::Zero,
+        };
+
+        repo.save(land(1, "0xowner1")).await?;
+        repo.save(land(3, "0xowner3")).await?;
+        // An older version arriving late must not replace the current state
+        repo.save(land(2, "0xowner2")).await?;
+
+        let current = repo.get_current_at_location(location).await?.unwrap();
+        assert_eq!(current.id, EventId::new_test(0, 0, 3));
+        assert_eq!(current.owner, "0xowner3");
+
+        let all = repo.get_all_current().await?;
+        assert_eq!(all.len(), 1);
+
+        let distribution = repo.get_land_distribution().await?;
+        assert_eq!(distribution.get("0xtoken"), Some(&1));
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_land_save_many(pool: sqlx::PgPool) -> Result<(), Error> {
+        let repo = Repository::new(pool);
+
+        let now = Utc::now().naive_utc();
+        let land = |id: u32, location: u64, owner: &str| LandModel {
+            id: EventId::new_test(0, 0, id),
+            at: now,
+            location: location.into(),
+            bought_at: now,
+            owner: owner.to_string(),
+            sell_price: U256::from_str("100").unwrap(),
+            token_used: "0xtoken".to_string(),
+            level: Level::First,
+        };
+
+        repo.save(land(0, 1, "0xowner0")).await?;
+
+        let inserted = repo
+            .save_many(&[
+                land(0, 1, "0xowner0"),
+                land(2, 1, "0xowner2"),
+                land(1, 1, "0xowner1"),
+                land(3, 2, "0xowner3"),
+            ])
+            .await?;
+        assert_eq!(inserted, 3);
+
+        let history = repo
+            .get_history(1.into(), TimeRange::default(), &Pagination::default())
+            .await?;
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[2].level, Level::First);
+
+        let current = repo.get_current_at_location(1.into()).await?.unwrap();
+        assert_eq!(current.owner, "0xowner2");
+        assert_eq!(repo.get_all_current().await?.len(), 2);
+
+        Ok(())
+    }
 }
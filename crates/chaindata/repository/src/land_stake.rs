@@ -1,8 +1,15 @@
-use chaindata_models::{events::EventId, models::LandStakeModel, shared::Location};
+use chaindata_models::{
+    events::EventId,
+    models::LandStakeModel,
+    shared::{Location, U256},
+};
 use chrono::NaiveDateTime;
 use sqlx::{query, query_as};
 
-use crate::{Database, Error};
+use crate::{
+    raw::Repository as RawDataRepository, Database, Error, LandStakeStore, Pagination, RawData,
+    TimeRange,
+};
 
 pub struct Repository {
     db: Database,
@@ -10,17 +17,23 @@ pub struct Repository {
 
 impl Repository {
     #[must_use]
-    pub fn new(db: Database) -> Self {
-        Self { db }
+    pub fn new(db: impl Into<Database>) -> Self {
+        Self { db: db.into() }
     }
+}
 
-    /// Saves a land stake model to the database
+#[async_trait::async_trait]
+impl LandStakeStore for Repository {
+    /// Saves a land stake model to the database, and makes it the current stake of its location
+    /// if it is the most recent one.
     ///
     /// # Errors
     ///
     /// Returns an error if the database operation fails.
-    pub async fn save(&self, land_stake: LandStakeModel) -> Result<EventId, Error> {
-        Ok(query!(
+    async fn save(&self, land_stake: LandStakeModel) -> Result<EventId, Error> {
+        let mut tx = self.db.writer().begin().await?;
+
+        let id = query!(
             r#"
             INSERT INTO land_stake (
                 id, at, location, last_pay_time, amount
@@ -28,16 +41,191 @@ impl Repository {
             VALUES ($1, $2, $3, $4, $5)
             RETURNING id
             "#,
-            land_stake.id as EventId,
+            land_stake.id.clone() as EventId,
             land_stake.at,
             land_stake.location as Location,
             land_stake.last_pay_time,
             land_stake.amount as _
         )
-        .fetch_one(&mut *(self.db.acquire().await?))
+        .fetch_one(&mut *tx)
         .await?
         .id
-        .parse()?)
+        .parse()?;
+
+        // Versions might not arrive in order, so only replace an older current stake.
+        query!(
+            r#"
+            INSERT INTO land_stake_current (
+                location, id, at, last_pay_time, amount
+            )
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (location) DO UPDATE SET
+                id = EXCLUDED.id,
+                at = EXCLUDED.at,
+                last_pay_time = EXCLUDED.last_pay_time,
+                amount = EXCLUDED.amount
+            WHERE land_stake_current.id < EXCLUDED.id
+            "#,
+            land_stake.location as Location,
+            land_stake.id as EventId,
+            land_stake.at,
+            land_stake.last_pay_time,
+            land_stake.amount as _
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        self.db.record_write().await;
+
+        Ok(id)
+    }
+
+    /// Saves many land stake models at once, in a single transaction, and updates the current stake
+    /// of their locations.
+    ///
+    /// Versions that already exist are ignored. Returns the number of new versions.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn save_many(&self, land_stakes: &[LandStakeModel]) -> Result<u64, Error> {
+        self.save_many_with_raw(land_stakes, &[]).await
+    }
+
+    /// Saves many land stakes along with the raw rows they were decoded from, in a single
+    /// transaction.
+    ///
+    /// Versions and raw rows that already exist are ignored. Returns the number of new versions.
+    ///
+    /// # Errors
+    /// Returns an error if the land stakes or the raw rows could not be saved.
+    async fn save_many_with_raw(
+        &self,
+        land_stakes: &[LandStakeModel],
+        raw: &[RawData],
+    ) -> Result<u64, Error> {
+        if land_stakes.is_empty() && raw.is_empty() {
+            return Ok(0);
+        }
+
+        let ids: Vec<String> = land_stakes
+            .iter()
+            .map(|stake| stake.id.as_string())
+            .collect();
+        let ats: Vec<NaiveDateTime> = land_stakes.iter().map(|stake| stake.at).collect();
+        let locations: Vec<Location> = land_stakes.iter().map(|stake| stake.location).collect();
+        let last_pay_times: Vec<NaiveDateTime> = land_stakes
+            .iter()
+            .map(|stake| stake.last_pay_time)
+            .collect();
+        let amounts: Vec<U256> = land_stakes.iter().map(|stake| stake.amount).collect();
+
+        let mut tx = self.db.writer().begin().await?;
+        RawDataRepository::save_many_in(&mut tx, raw).await?;
+
+        let inserted = query!(
+            r#"
+            INSERT INTO land_stake (
+                id, at, location, last_pay_time, amount
+            )
+            SELECT * FROM UNNEST(
+                $1::text[], $2::timestamp[], $3::int4[], $4::timestamp[], $5::numeric[]
+            )
+            ON CONFLICT (id) DO NOTHING
+            "#,
+            &ids,
+            &ats,
+            &locations as &[Location],
+            &last_pay_times,
+            &amounts as &[U256]
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        // Only the most recent version of each location in the batch can become the current stake
+        query!(
+            r#"
+            INSERT INTO land_stake_current (
+                location, id, at, last_pay_time, amount
+            )
+            SELECT DISTINCT ON (location)
+                location, id, at, last_pay_time, amount
+            FROM UNNEST(
+                $1::text[], $2::timestamp[], $3::int4[], $4::timestamp[], $5::numeric[]
+            ) AS batch(id, at, location, last_pay_time, amount)
+            ORDER BY location, id DESC
+            ON CONFLICT (location) DO UPDATE SET
+                id = EXCLUDED.id,
+                at = EXCLUDED.at,
+                last_pay_time = EXCLUDED.last_pay_time,
+                amount = EXCLUDED.amount
+            WHERE land_stake_current.id < EXCLUDED.id
+            "#,
+            &ids,
+            &ats,
+            &locations as &[Location],
+            &last_pay_times,
+            &amounts as &[U256]
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        self.db.record_write().await;
+
+        Ok(inserted)
+    }
+
+    /// Gets the current stake of the land at a specific location
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn get_current_at_location(
+        &self,
+        location: Location,
+    ) -> Result<Option<LandStakeModel>, Error> {
+        Ok(query_as!(
+            LandStakeModel,
+            r#"
+            SELECT
+                id as "id: _",
+                at,
+                location as "location: Location",
+                last_pay_time,
+                amount as "amount: _"
+            FROM land_stake_current
+            WHERE location = $1
+            "#,
+            location as Location
+        )
+        .fetch_optional(&mut *(self.db.read().await?))
+        .await?)
+    }
+
+    /// Gets the current stake of all lands
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn get_all_current(&self) -> Result<Vec<LandStakeModel>, Error> {
+        Ok(query_as!(
+            LandStakeModel,
+            r#"
+            SELECT
+                id as "id: _",
+                at,
+                location as "location: Location",
+                last_pay_time,
+                amount as "amount: _"
+            FROM land_stake_current
+            ORDER BY location
+            "#
+        )
+        .fetch_all(&mut *(self.db.read().await?))
+        .await?)
     }
 
     /// Gets the latest land stake model at a specific location at or before the given timestamp
@@ -45,12 +233,12 @@ impl Repository {
     /// # Errors
     ///
     /// Returns an error if the database operation fails.
-    pub async fn get_latest_at_location(
+    async fn get_latest_at_location(
         &self,
         location: Location,
         at: NaiveDateTime,
-    ) -> Result<Option<LandStakeModel>, sqlx::Error> {
-        query_as!(
+    ) -> Result<Option<LandStakeModel>, Error> {
+        Ok(query_as!(
             LandStakeModel,
             r#"
             SELECT
@@ -67,8 +255,8 @@ impl Repository {
             location as Location,
             at
         )
-        .fetch_optional(&mut *(self.db.acquire().await?))
-        .await
+        .fetch_optional(&mut *(self.db.read().await?))
+        .await?)
     }
 
     /// Gets all land stakes that exist at a specific point in time
@@ -76,12 +264,9 @@ impl Repository {
     /// # Errors
     ///
     /// Returns an error if the database operation fails.
-    pub async fn get_all_at_time(
-        &self,
-        at: NaiveDateTime,
-    ) -> Result<Vec<LandStakeModel>, sqlx::Error> {
+    async fn get_all_at_time(&self, at: NaiveDateTime) -> Result<Vec<LandStakeModel>, Error> {
         // This query gets the most recent version of each land stake at or before the specified time
-        query_as!(
+        Ok(query_as!(
             LandStakeModel,
             r#"
             WITH latest_land_stakes AS (
@@ -101,16 +286,54 @@ impl Repository {
             "#,
             at
         )
-        .fetch_all(&mut *(self.db.acquire().await?))
-        .await
+        .fetch_all(&mut *(self.db.read().await?))
+        .await?)
+    }
+
+    /// Gets the versions of the land stake at a specific location within the given time range,
+    /// ordered by chain position.
+    ///
+    /// # Errors
+    /// Returns an error if the database operation fails.
+    async fn get_history(
+        &self,
+        location: Location,
+        range: TimeRange,
+        pagination: &Pagination,
+    ) -> Result<Vec<LandStakeModel>, Error> {
+        Ok(query_as!(
+            LandStakeModel,
+            r#"
+            SELECT
+                id as "id: _",
+                at,
+                location as "location: Location",
+                last_pay_time,
+                amount as "amount: _"
+            FROM land_stake
+            WHERE location = $1
+                AND ($2::timestamp IS NULL OR at >= $2)
+                AND ($3::timestamp IS NULL OR at < $3)
+                AND ($4::text IS NULL OR id > $4)
+            ORDER BY id
+            LIMIT $5
+            "#,
+            location as Location,
+            range.from,
+            range.to,
+            pagination.after.clone() as Option<EventId>,
+            pagination.sql_limit()
+        )
+        .fetch_all(&mut *(self.db.read().await?))
+        .await?)
     }
 
     /// Gets a land stake model by ID
     ///
     /// # Errors
     /// Returns an error if the database operation fails.
-    pub async fn get_by_id(&self, id: EventId) -> Result<Option<LandStakeModel>, sqlx::Error> {
-        query_as!(
+    async fn get_by_id(&self, id: EventId) -> Result<Option<LandStakeModel>, Error> {
+        Ok(query_as!(
             LandStakeModel,
             r#"
             SELECT
@@ -124,24 +347,25 @@ impl Repository {
             "#,
             id as EventId
         )
-        .fetch_optional(&mut *(self.db.acquire().await?))
-        .await
+        .fetch_optional(&mut *(self.db.read().await?))
+        .await?)
     }
 
     /// Gets the latest timestamp from the `land_stake` table
     ///
     /// # Errors
     /// Returns an error if the database operation fails.
-    pub async fn get_latest_timestamp(&self) -> Result<Option<NaiveDateTime>, sqlx::Error> {
-        query!(
+    async fn get_latest_timestamp(&self) -> Result<Option<NaiveDateTime>, Error> {
+        Ok(query!(
             r#"
             SELECT MAX(at) as latest_time
             FROM land_stake
             "#
         )
-        .fetch_one(&mut *(self.db.acquire().await?))
-        .await
-        .map(|row| row.latest_time)
+        // Used to resume indexing, so it must not lag behind
+        .fetch_one(self.db.writer())
+        .await?
+        .latest_time)
     }
 }
 
@@ -269,4 +493,64 @@ mod tests {
 
         Ok(())
     }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_land_stake_history(pool: sqlx::PgPool) -> Result<(), Error> {
+        let repo = Repository::new(pool);
+
+        let location: Location = 4321.into();
+        let time1 = Utc::now().naive_utc();
+
+        for i in 0..3 {
+            let at = time1 + chrono::Duration::hours(i.into());
+            repo.save(LandStakeModel {
+                id: EventId::new_test(0, 0, i),
+                at,
+                location,
+                last_pay_time: at,
+                amount: U256::from_str("100").unwrap(),
+            })
+            .await?;
+        }
+
+        let history = repo
+            .get_history(location, TimeRange::default(), &Pagination::default())
+            .await?;
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].id, EventId::new_test(0, 0, 0));
+
+        let range = TimeRange::new(Some(time1 + chrono::Duration::hours(1)), None);
+        let history = repo
+            .get_history(location, range, &Pagination::new(None, 1))
+            .await?;
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].id, EventId::new_test(0, 0, 1));
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_land_stake_current_state(pool: sqlx::PgPool) -> Result<(), Error> {
+        let repo = Repository::new(pool);
+
+        let location: Location = 1111.into();
+        let now = Utc::now().naive_utc();
+        let stake = |id: u32, amount: &str| LandStakeModel {
+            id: EventId::new_test(0, 0, id),
+            at: now,
+            location,
+            last_pay_time: now,
+            amount: U256::from_str(amount).unwrap(),
+        };
+
+        repo.save(stake(2, "200")).await?;
+        // An older version arriving late must not replace the current stake
+        repo.save(stake(1, "100")).await?;
+
+        let current = repo.get_current_at_location(location).await?.unwrap();
+        assert_eq!(current.amount, U256::from_str("200").unwrap());
+        assert_eq!(repo.get_all_current().await?.len(), 1);
+
+        Ok(())
+    }
 }
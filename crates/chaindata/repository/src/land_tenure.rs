@@ -0,0 +1,386 @@
+use crate::{Database, Error};
+use chaindata_models::{
+    events::EventId,
+    shared::{Location, U256},
+};
+use chrono::{Duration, NaiveDateTime};
+use sqlx::{query, query_as, PgConnection};
+
+/// How a tenure ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "tenure_outcome", rename_all = "lowercase")]
+pub enum TenureOutcome {
+    Sold,
+    Nuked,
+}
+
+/// An ownership of a land, from its purchase until it was bought again or nuked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LandTenure {
+    /// Id of the purchase event, from a player or an auction.
+    pub id: EventId,
+    pub location: Location,
+    pub owner: String,
+    /// Token chosen by the owner, if the land version of the purchase is stored.
+    pub token_used: Option<String>,
+    pub purchase_price: U256,
+    pub from_auction: bool,
+    pub bought_at: NaiveDateTime,
+    /// Stake set in the transaction of the purchase, if stored.
+    pub initial_stake: Option<U256>,
+    /// Id of the event ending the tenure, if the land is no longer owned.
+    pub ended_by: Option<EventId>,
+    pub ended_at: Option<NaiveDateTime>,
+    pub outcome: Option<TenureOutcome>,
+    /// Stake left just before the land was sold.
+    pub final_stake: Option<U256>,
+    /// Taxes paid during the tenure, once it ended: the whole stake if the land was nuked,
+    /// what was taken from it otherwise.
+    pub taxes_paid: Option<U256>,
+}
+
+impl LandTenure {
+    /// How long the land was held, if it is no longer owned.
+    #[must_use]
+    pub fn duration(&self) -> Option<Duration> {
+        self.ended_at.map(|ended_at| ended_at - self.bought_at)
+    }
+}
+
+/// Tenures of the lands, derived from the purchase and nuke events along with the land and stake
+/// versions.
+///
+/// Tenures are rebuilt per location, so refreshing a location again gives the same result.
+pub struct Repository {
+    db: Database,
+}
+
+impl Repository {
+    #[must_use]
+    pub fn new(db: impl Into<Database>) -> Self {
+        Self { db: db.into() }
+    }
+
+    /// Rebuilds the tenures of the given locations.
+    ///
+    /// # Errors
+    /// Returns an error if the tenures could not be computed or saved.
+    pub async fn refresh(&self, locations: &[Location]) -> Result<(), Error> {
+        if locations.is_empty() {
+            return Ok(());
+        }
+
+        let mut tx = self.db.writer().begin().await?;
+        Self::refresh_in(&mut tx, Some(locations)).await?;
+        tx.commit().await?;
+        self.db.record_write().await;
+
+        Ok(())
+    }
+
+    /// Rebuilds the tenures of every location.
+    ///
+    /// # Errors
+    /// Returns an error if the tenures could not be computed or saved.
+    pub async fn refresh_all(&self) -> Result<(), Error> {
+        let mut tx = self.db.writer().begin().await?;
+        Self::refresh_in(&mut tx, None).await?;
+        tx.commit().await?;
+        self.db.record_write().await;
+
+        Ok(())
+    }
+
+    /// Same as [`Repository::refresh`], within an existing transaction, for every location if
+    /// none are given.
+    ///
+    /// # Errors
+    /// Returns an error if the tenures could not be computed or saved.
+    pub(crate) async fn refresh_in(
+        conn: &mut PgConnection,
+        locations: Option<&[Location]>,
+    ) -> Result<(), Error> {
+        let locations = locations.map(<[Location]>::to_vec);
+
+        query!(
+            r#"
+            DELETE FROM land_tenure WHERE $1::int4[] IS NULL OR location = ANY($1)
+            "#,
+            locations.as_deref() as Option<&[Location]>
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        query!(
+            r#"
+            WITH purchase AS (
+                SELECT b.id, e.at, b.location, b.buyer, b.price, false as from_auction
+                FROM event_land_bought b
+                JOIN event e ON e.id = b.id
+                WHERE $1::int4[] IS NULL OR b.location = ANY($1)
+                UNION ALL
+                SELECT f.id, e.at, f.location, f.buyer, f.price, true
+                FROM event_auction_finished f
+                JOIN event e ON e.id = f.id
+                WHERE $1::int4[] IS NULL OR f.location = ANY($1)
+            ),
+            ending AS (
+                SELECT id, location, false as nuked FROM purchase
+                UNION ALL
+                SELECT id, location, true
+                FROM event_land_nuked
+                WHERE $1::int4[] IS NULL OR location = ANY($1)
+            ),
+            tenure AS (
+                SELECT
+                    p.*,
+                    -- Versions are compared by transaction, as their order with the events
+                    -- within a transaction is not relevant
+                    split_part(p.id, ':e_', 1) as tx,
+                    n.id as ended_by,
+                    split_part(n.id, ':e_', 1) as end_tx,
+                    n.nuked
+                FROM purchase p
+                LEFT JOIN LATERAL (
+                    SELECT x.id, x.nuked
+                    FROM ending x
+                    WHERE x.location = p.location AND x.id > p.id
+                    ORDER BY x.id
+                    LIMIT 1
+                ) n ON true
+            )
+            INSERT INTO land_tenure (
+                id, location, owner, token_used, purchase_price, from_auction, bought_at,
+                initial_stake, ended_by, ended_at, outcome, final_stake
+            )
+            SELECT
+                t.id,
+                t.location,
+                t.buyer,
+                (
+                    SELECT l.token_used FROM land l
+                    WHERE l.location = t.location AND l.id > t.tx AND l.id < t.tx || ';'
+                    ORDER BY l.id DESC
+                    LIMIT 1
+                ),
+                t.price,
+                t.from_auction,
+                t.at,
+                (
+                    SELECT s.amount FROM land_stake s
+                    WHERE s.location = t.location AND s.id > t.tx AND s.id < t.tx || ';'
+                    ORDER BY s.id DESC
+                    LIMIT 1
+                ),
+                t.ended_by,
+                e.at,
+                CASE
+                    WHEN t.nuked THEN 'nuked'::tenure_outcome
+                    WHEN NOT t.nuked THEN 'sold'::tenure_outcome
+                END,
+                CASE WHEN NOT t.nuked THEN (
+                    SELECT s.amount FROM land_stake s
+                    WHERE s.location = t.location AND s.id > t.tx AND s.id < t.end_tx
+                    ORDER BY s.id DESC
+                    LIMIT 1
+                ) END
+            FROM tenure t
+            LEFT JOIN event e ON e.id = t.ended_by
+            "#,
+            locations.as_deref() as Option<&[Location]>
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Gets the tenures of an owner, in chain order.
+    ///
+    /// # Errors
+    /// Returns an error if the tenures could not be retrieved.
+    pub async fn get_by_owner(&self, owner: &str) -> Result<Vec<LandTenure>, sqlx::Error> {
+        query_as!(
+            LandTenure,
+            r#"
+            SELECT
+                id as "id: EventId",
+                location as "location: Location",
+                owner,
+                token_used,
+                purchase_price as "purchase_price: U256",
+                from_auction,
+                bought_at,
+                initial_stake as "initial_stake: U256",
+                ended_by as "ended_by: EventId",
+                ended_at,
+                outcome as "outcome: TenureOutcome",
+                final_stake as "final_stake: U256",
+                CASE outcome
+                    WHEN 'nuked' THEN initial_stake
+                    WHEN 'sold' THEN GREATEST(initial_stake - final_stake, 0)
+                END as "taxes_paid: U256"
+            FROM land_tenure
+            WHERE owner = $1
+            ORDER BY id
+            "#,
+            owner
+        )
+        .fetch_all(&mut *(self.db.read().await?))
+        .await
+    }
+
+    /// Gets the tenures of a location, in chain order.
+    ///
+    /// # Errors
+    /// Returns an error if the tenures could not be retrieved.
+    pub async fn get_by_location(
+        &self,
+        location: Location,
+    ) -> Result<Vec<LandTenure>, sqlx::Error> {
+        query_as!(
+            LandTenure,
+            r#"
+            SELECT
+                id as "id: EventId",
+                location as "location: Location",
+                owner,
+                token_used,
+                purchase_price as "purchase_price: U256",
+                from_auction,
+                bought_at,
+                initial_stake as "initial_stake: U256",
+                ended_by as "ended_by: EventId",
+                ended_at,
+                outcome as "outcome: TenureOutcome",
+                final_stake as "final_stake: U256",
+                CASE outcome
+                    WHEN 'nuked' THEN initial_stake
+                    WHEN 'sold' THEN GREATEST(initial_stake - final_stake, 0)
+                END as "taxes_paid: U256"
+            FROM land_tenure
+            WHERE location = $1
+            ORDER BY id
+            "#,
+            location as Location
+        )
+        .fetch_all(&mut *(self.db.read().await?))
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{
+        fixtures::{bought, event, land, nuked, stake},
+        EventRepository, EventStore, LandRepository, LandStakeRepository, LandStakeStore,
+        LandStore,
+    };
+    use chaindata_models::{
+        events::{actions::AuctionFinishedEventModel, EventDataModel},
+        models::LandModel,
+    };
+    use chrono::{SubsecRound, Utc};
+    use migrations::MIGRATOR;
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_land_tenures(pool: sqlx::PgPool) -> Result<(), Error> {
+        let events = EventRepository::new(pool.clone());
+        let lands = LandRepository::new(pool.clone());
+        let stakes = LandStakeRepository::new(pool.clone());
+        let repo = Repository::new(pool);
+
+        // Stored with microseconds
+        let time1 = Utc::now().naive_utc().trunc_subsecs(6);
+        let time2 = time1 + Duration::hours(1);
+        let time3 = time2 + Duration::hours(2);
+
+        // 0xa wins the auction, 0xb buys from 0xa, then 0xb gets nuked
+        events
+            .save_event(event(
+                EventId::new_test(1, 1, 0),
+                time1,
+                EventDataModel::AuctionFinished(AuctionFinishedEventModel {
+                    id: None,
+                    location: 1.into(),
+                    buyer: "0xa".to_string(),
+                    price: U256::from_str("1000").unwrap(),
+                }),
+            ))
+            .await?;
+        lands
+            .save(LandModel {
+                token_used: "0xtoken1".to_string(),
+                ..land(EventId::new_test(1, 1, 1), time1, 1, "0xa")
+            })
+            .await?;
+        stakes
+            .save(stake(EventId::new_test(1, 1, 2), time1, 1, "50"))
+            .await?;
+        stakes
+            .save(stake(EventId::new_test(1, 9, 0), time1, 1, "20"))
+            .await?;
+        events
+            .save_event(event(
+                EventId::new_test(2, 1, 1),
+                time2,
+                bought(1, "0xb", "0xa", "200"),
+            ))
+            .await?;
+        // The land version of 0xb comes before the event in the transaction, its stake later
+        lands
+            .save(LandModel {
+                token_used: "0xtoken2".to_string(),
+                ..land(EventId::new_test(2, 1, 0), time2, 1, "0xb")
+            })
+            .await?;
+        repo.refresh(&[1.into()]).await?;
+
+        let tenures = repo.get_by_location(1.into()).await?;
+        assert_eq!(tenures.len(), 2);
+        assert_eq!(tenures[0].owner, "0xa");
+        assert!(tenures[0].from_auction);
+        assert_eq!(tenures[0].token_used.as_deref(), Some("0xtoken1"));
+        assert_eq!(tenures[0].outcome, Some(TenureOutcome::Sold));
+        assert_eq!(tenures[0].ended_by, Some(EventId::new_test(2, 1, 1)));
+        assert_eq!(tenures[0].final_stake, U256::from_str("20").ok());
+        assert_eq!(tenures[0].taxes_paid, U256::from_str("30").ok());
+        assert_eq!(tenures[0].duration(), Some(Duration::hours(1)));
+        assert_eq!(tenures[1].owner, "0xb");
+        assert_eq!(tenures[1].initial_stake, None);
+        assert_eq!(tenures[1].outcome, None);
+        assert_eq!(tenures[1].taxes_paid, None);
+
+        stakes
+            .save(stake(EventId::new_test(2, 1, 2), time2, 1, "80"))
+            .await?;
+        // Versions stored after the events are picked up when the location is refreshed
+        repo.refresh(&[1.into()]).await?;
+        assert_eq!(
+            repo.get_by_owner("0xb").await?[0].initial_stake,
+            U256::from_str("80").ok()
+        );
+        events
+            .save_event(event(EventId::new_test(3, 1, 0), time3, nuked(1, "0xb")))
+            .await?;
+        // Refreshing again gives the same tenures
+        repo.refresh_all().await?;
+        repo.refresh_all().await?;
+
+        let tenures = repo.get_by_owner("0xb").await?;
+        assert_eq!(tenures.len(), 1);
+        assert!(!tenures[0].from_auction);
+        assert_eq!(tenures[0].token_used.as_deref(), Some("0xtoken2"));
+        assert_eq!(tenures[0].initial_stake, U256::from_str("80").ok());
+        assert_eq!(tenures[0].outcome, Some(TenureOutcome::Nuked));
+        assert_eq!(tenures[0].final_stake, None);
+        assert_eq!(tenures[0].taxes_paid, U256::from_str("80").ok());
+        assert_eq!(tenures[0].duration(), Some(Duration::hours(2)));
+        assert_eq!(repo.get_by_location(1.into()).await?.len(), 2);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,550 @@
+use crate::{Database, Error};
+use chrono::{Duration, NaiveDateTime};
+use sqlx::query;
+
+/// Score computed for every address, from which the categories are ranked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "leaderboard_metric", rename_all = "snake_case")]
+pub enum Metric {
+    /// Number of lands bought from auctions.
+    AuctionPurchases,
+    /// Number of lands bought from other players.
+    PlayerPurchases,
+    /// Taxes paid on the lands that were sold or nuked, relative to the price paid for them.
+    TaxRatio,
+    /// Taxes received from the neighbouring lands, in the main token.
+    TaxesReceived,
+    /// Balance of the token whose holders are ranked.
+    TokenBalance,
+}
+
+/// Period of activity a score is computed on, ending when it is computed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "leaderboard_window", rename_all = "lowercase")]
+pub enum Window {
+    Day,
+    Week,
+    Month,
+    All,
+}
+
+impl Window {
+    pub const ALL: [Window; 4] = [Window::Day, Window::Week, Window::Month, Window::All];
+
+    /// Start of the window, if it does not span the whole history.
+    #[must_use]
+    pub fn start(self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        match self {
+            Window::Day => Some(now - Duration::days(1)),
+            Window::Week => Some(now - Duration::weeks(1)),
+            Window::Month => Some(now - Duration::days(30)),
+            Window::All => None,
+        }
+    }
+}
+
+/// A ranking of the leaderboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    /// Most taxes received from the neighbouring lands.
+    TopTaxLord,
+    /// Most lands bought from auctions.
+    LandBaronAuction,
+    /// Most lands bought from other players.
+    LandBaronPlayer,
+    /// Lowest taxes paid relative to the price paid for the lands.
+    TaxDodger,
+    /// Highest taxes paid relative to the price paid for the lands.
+    BiggestLoser,
+    /// Largest balances of the token whose holders are ranked.
+    TopHolders,
+}
+
+impl Category {
+    #[must_use]
+    pub fn metric(self) -> Metric {
+        match self {
+            Category::TopTaxLord => Metric::TaxesReceived,
+            Category::LandBaronAuction => Metric::AuctionPurchases,
+            Category::LandBaronPlayer => Metric::PlayerPurchases,
+            Category::TaxDodger | Category::BiggestLoser => Metric::TaxRatio,
+            Category::TopHolders => Metric::TokenBalance,
+        }
+    }
+
+    /// Whether the lowest score ranks first.
+    #[must_use]
+    pub fn ascending(self) -> bool {
+        matches!(self, Category::TaxDodger)
+    }
+
+    /// Number of most active addresses the ranking is restricted to, if any.
+    ///
+    /// Ratios are only meaningful for addresses that bought enough lands.
+    #[must_use]
+    pub fn candidates(self) -> Option<u32> {
+        match self {
+            Category::TaxDodger | Category::BiggestLoser => Some(60),
+            Category::TopTaxLord
+            | Category::LandBaronAuction
+            | Category::LandBaronPlayer
+            | Category::TopHolders => None,
+        }
+    }
+
+    /// Number of addresses shown by default.
+    #[must_use]
+    pub fn default_limit(self) -> u32 {
+        match self {
+            Category::LandBaronAuction => 3,
+            Category::LandBaronPlayer => 2,
+            Category::TopTaxLord
+            | Category::TaxDodger
+            | Category::BiggestLoser
+            | Category::TopHolders => 5,
+        }
+    }
+}
+
+/// Filters applied when ranking, on top of the blacklist applied when computing.
+#[derive(Debug, Clone, Default)]
+pub struct LeaderboardFilter {
+    /// Minimum number of transactions of the addresses.
+    pub min_transactions: u64,
+    /// Addresses not to rank.
+    pub exclude: Vec<String>,
+}
+
+/// A ranked address.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeaderboardEntry {
+    /// Rank in the category, starting at 1.
+    pub rank: u64,
+    pub address: String,
+    pub score: f64,
+    /// Lands bought and sold over the whole history, auctions included.
+    pub transactions: u64,
+    pub computed_at: NaiveDateTime,
+}
+
+/// Periodic snapshots of the leaderboard scores, computed from the events, the land tenures, the
+/// tax ledger and the token balances.
+///
+/// Taxes paid are the ones of the tenures that ended. Amounts are converted to the main token at
+/// the price stored when they were paid, and left out when no price was stored before.
+pub struct Repository {
+    db: Database,
+}
+
+impl Repository {
+    #[must_use]
+    pub fn new(db: impl Into<Database>) -> Self {
+        Self { db: db.into() }
+    }
+
+    /// Recomputes the scores of every metric on the window ending at `now`, leaving out the
+    /// blacklisted addresses.
+    ///
+    /// Balances are the current ones of `holders_token` whatever the window, and none are ranked
+    /// without it.
+    ///
+    /// Returns the number of scores stored.
+    ///
+    /// # Errors
+    /// Returns an error if the scores could not be computed or saved.
+    pub async fn refresh(
+        &self,
+        window: Window,
+        now: NaiveDateTime,
+        blacklist: &[String],
+        main_token: &str,
+        holders_token: Option<&str>,
+    ) -> Result<u64, Error> {
+        let mut tx = self.db.writer().begin().await?;
+
+        query!(
+            "DELETE FROM leaderboard_snapshot WHERE time_window = $1",
+            window as Window
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let inserted = query!(
+            r#"
+            WITH purchase AS (
+                SELECT b.id, e.at, b.location, b.buyer, b.price, 'player' as kind
+                FROM event_land_bought b
+                JOIN event e ON e.id = b.id
+                UNION ALL
+                SELECT f.id, e.at, f.location, f.buyer, f.price, 'auction'
+                FROM event_auction_finished f
+                JOIN event e ON e.id = f.id
+            ),
+            transactions AS (
+                SELECT address, COUNT(*) as count
+                FROM (
+                    SELECT buyer as address FROM purchase
+                    UNION ALL
+                    SELECT seller FROM event_land_bought
+                ) t
+                GROUP BY address
+            ),
+            paid AS (
+                -- Ownerships started in the window and since ended. Auctions are paid in the
+                -- main token, stakes in the token chosen by the owner.
+                SELECT
+                    t.owner,
+                    main_token_value(
+                        COALESCE(b.token_used, $5), t.purchase_price, t.bought_at, $5
+                    ) as purchase_value,
+                    main_token_value(
+                        t.token_used,
+                        CASE t.outcome
+                            WHEN 'nuked' THEN t.initial_stake
+                            WHEN 'sold' THEN GREATEST(t.initial_stake - t.final_stake, 0)
+                        END,
+                        t.ended_at,
+                        $5
+                    ) as taxes_value
+                FROM land_tenure t
+                LEFT JOIN event_land_bought b ON b.id = t.id
+                WHERE t.outcome IS NOT NULL AND ($2::timestamp IS NULL OR t.bought_at >= $2)
+            ),
+            score AS (
+                SELECT 'auction_purchases'::leaderboard_metric as metric, buyer as address,
+                    COUNT(*)::float8 as score
+                FROM purchase
+                WHERE kind = 'auction' AND ($2::timestamp IS NULL OR at >= $2)
+                GROUP BY buyer
+                UNION ALL
+                SELECT 'player_purchases', buyer, COUNT(*)::float8
+                FROM purchase
+                WHERE kind = 'player' AND ($2::timestamp IS NULL OR at >= $2)
+                GROUP BY buyer
+                UNION ALL
+                SELECT 'tax_ratio', owner, SUM(taxes_value) / SUM(purchase_value)
+                FROM paid
+                WHERE taxes_value IS NOT NULL AND purchase_value IS NOT NULL
+                GROUP BY owner
+                HAVING SUM(purchase_value) > 0
+                UNION ALL
+                SELECT 'taxes_received', recipient_owner,
+                    SUM(main_token_value(token, amount, at, $5))
+                FROM tax_ledger
+                WHERE recipient_owner IS NOT NULL AND ($2::timestamp IS NULL OR at >= $2)
+                GROUP BY recipient_owner
+                HAVING COUNT(main_token_value(token, amount, at, $5)) > 0
+                UNION ALL
+                SELECT 'token_balance', account, balance::float8
+                FROM token_balance
+                WHERE token = $6 AND balance > 0
+            )
+            INSERT INTO leaderboard_snapshot
+                (metric, time_window, address, score, transactions, computed_at)
+            SELECT s.metric, $1, s.address, s.score, COALESCE(t.count, 0), $3
+            FROM score s
+            LEFT JOIN transactions t ON t.address = s.address
+            WHERE s.address <> ALL($4)
+            "#,
+            window as Window,
+            window.start(now),
+            now,
+            blacklist,
+            main_token,
+            holders_token
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        tx.commit().await?;
+        self.db.record_write().await;
+
+        Ok(inserted)
+    }
+
+    /// Ranks the addresses of a category from the latest snapshot of the window.
+    ///
+    /// # Errors
+    /// Returns an error if the snapshot could not be retrieved.
+    #[allow(clippy::cast_sign_loss)] // Ranks and counts are never negative
+    pub async fn get(
+        &self,
+        category: Category,
+        window: Window,
+        filter: &LeaderboardFilter,
+        limit: u32,
+    ) -> Result<Vec<LeaderboardEntry>, sqlx::Error> {
+        let rows = query!(
+            r#"
+            WITH candidate AS (
+                SELECT address, score, transactions, computed_at
+                FROM leaderboard_snapshot
+                WHERE metric = $1 AND time_window = $2
+                    AND transactions >= $3 AND address <> ALL($4)
+                ORDER BY transactions DESC, address
+                LIMIT $5
+            )
+            SELECT
+                ROW_NUMBER() OVER (
+                    ORDER BY
+                        CASE WHEN $6 THEN score END ASC,
+                        CASE WHEN NOT $6 THEN score END DESC,
+                        address
+                ) as "rank!",
+                address,
+                score,
+                transactions,
+                computed_at
+            FROM candidate
+            ORDER BY 1
+            LIMIT $7
+            "#,
+            category.metric() as Metric,
+            window as Window,
+            i64::try_from(filter.min_transactions).unwrap_or(i64::MAX),
+            &filter.exclude,
+            category.candidates().map(i64::from),
+            category.ascending(),
+            i64::from(limit)
+        )
+        .fetch_all(&mut *(self.db.read().await?))
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| LeaderboardEntry {
+                rank: row.rank as u64,
+                address: row.address,
+                score: row.score,
+                transactions: row.transactions as u64,
+                computed_at: row.computed_at,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{
+        fixtures::{bought, event, land, nuked, stake},
+        EventRepository, EventStore, LandRepository, LandStakeRepository, LandStakeStore,
+        LandStore, LandTenureRepository, TaxLedgerRepository, TokenPrice, TokenPriceRepository,
+        TokenRepository,
+    };
+    use chaindata_models::{
+        events::{
+            actions::{AuctionFinishedEventModel, LandTransferEventModel},
+            EventDataModel, EventId,
+        },
+        models::TokenBalanceModel,
+        shared::U256,
+    };
+    use chrono::{SubsecRound, Utc};
+    use migrations::MIGRATOR;
+
+    fn balance(account: &str, amount: &str) -> TokenBalanceModel {
+        TokenBalanceModel {
+            token: "0xbtc".to_string(),
+            account: account.to_string(),
+            balance: U256::from_str(amount).unwrap(),
+        }
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_leaderboard(pool: sqlx::PgPool) -> Result<(), Error> {
+        let events = EventRepository::new(pool.clone());
+        let stakes = LandStakeRepository::new(pool.clone());
+        let lands = LandRepository::new(pool.clone());
+        let prices = TokenPriceRepository::new(pool.clone());
+        let repo = Repository::new(pool);
+
+        // Stored with microseconds
+        let now = Utc::now().naive_utc().trunc_subsecs(6);
+        let old = now - Duration::days(2);
+
+        // 0xa buys 1 from 0xc, stakes 50, has 20 left when 0xb buys it: 30 paid for 100
+        events
+            .save_event(event(
+                EventId::new_test(1, 1, 0),
+                old,
+                bought(1, "0xa", "0xc", "100"),
+            ))
+            .await?;
+        stakes
+            .save(stake(EventId::new_test(1, 1, 1), old, 1, "50"))
+            .await?;
+        stakes
+            .save(stake(EventId::new_test(1, 5, 0), old, 1, "20"))
+            .await?;
+        events
+            .save_event(event(
+                EventId::new_test(2, 2, 0),
+                now,
+                bought(1, "0xb", "0xa", "200"),
+            ))
+            .await?;
+        stakes
+            .save(stake(EventId::new_test(2, 2, 1), now, 1, "80"))
+            .await?;
+
+        // 0xb wins the auction of 2, stakes 100 and gets nuked: 100 paid for 1000
+        events
+            .save_event(event(
+                EventId::new_test(3, 3, 0),
+                now,
+                EventDataModel::AuctionFinished(AuctionFinishedEventModel {
+                    id: None,
+                    location: 2.into(),
+                    buyer: "0xb".to_string(),
+                    price: U256::from_str("1000").unwrap(),
+                }),
+            ))
+            .await?;
+        stakes
+            .save(stake(EventId::new_test(3, 3, 1), now, 2, "100"))
+            .await?;
+        events
+            .save_event(event(EventId::new_test(4, 4, 0), now, nuked(2, "0xb")))
+            .await?;
+
+        // Stakes are in the token chosen by the owners, worth half a main token
+        lands
+            .save_many(&[
+                land(EventId::new_test(1, 1, 1), old, 1, "0xa"),
+                land(EventId::new_test(2, 2, 1), now, 1, "0xb"),
+                land(EventId::new_test(3, 3, 1), now, 2, "0xb"),
+            ])
+            .await?;
+        prices
+            .save(&[TokenPrice {
+                token: "0xtoken".to_string(),
+                at: old - Duration::hours(1),
+                ratio: 2.,
+                pool_token0: "0xmain".to_string(),
+                pool_token1: "0xtoken".to_string(),
+                pool_fee: U256::from_str("0").unwrap(),
+                pool_tick_spacing: 1000,
+                pool_extension: "0x0".to_string(),
+            }])
+            .await?;
+
+        // 0xb receives 10 tokens of taxes on 1, from 2
+        events
+            .save_event(event(
+                EventId::new_test(2, 5, 0),
+                now,
+                EventDataModel::LandTransfer(LandTransferEventModel {
+                    id: None,
+                    from_location: 2.into(),
+                    to_location: 1.into(),
+                    token_address: "0xtoken".to_string(),
+                    amount: U256::from_str("10").unwrap(),
+                }),
+            ))
+            .await?;
+
+        TokenRepository::new(repo.db.clone())
+            .save_balances(&[
+                balance("0xa", "5"),
+                balance("0xb", "7"),
+                balance("0xc", "100"),
+            ])
+            .await?;
+
+        LandTenureRepository::new(repo.db.clone())
+            .refresh_all()
+            .await?;
+        TaxLedgerRepository::new(repo.db.clone())
+            .record_missing()
+            .await?;
+
+        let blacklist = vec!["0xc".to_string()];
+        for window in Window::ALL {
+            repo.refresh(window, now, &blacklist, "0xmain", Some("0xbtc"))
+                .await?;
+        }
+        // Recomputing replaces the previous snapshot
+        repo.refresh(Window::All, now, &blacklist, "0xmain", Some("0xbtc"))
+            .await?;
+
+        let ranked = |entries: Vec<LeaderboardEntry>| {
+            entries
+                .into_iter()
+                .map(|entry| (entry.rank, entry.address, entry.score))
+                .collect::<Vec<_>>()
+        };
+        let all = LeaderboardFilter::default();
+
+        // 0xb paid 50 main tokens of taxes for an auction of 1000
+        assert_eq!(
+            ranked(repo.get(Category::TaxDodger, Window::All, &all, 5).await?),
+            vec![(1, "0xb".to_string(), 0.05), (2, "0xa".to_string(), 0.3)]
+        );
+        assert_eq!(
+            ranked(
+                repo.get(Category::BiggestLoser, Window::All, &all, 5)
+                    .await?
+            ),
+            vec![(1, "0xa".to_string(), 0.3), (2, "0xb".to_string(), 0.05)]
+        );
+        assert_eq!(
+            ranked(repo.get(Category::TopTaxLord, Window::Day, &all, 5).await?),
+            vec![(1, "0xb".to_string(), 5.0)]
+        );
+        assert_eq!(
+            ranked(repo.get(Category::TopHolders, Window::Day, &all, 5).await?),
+            vec![(1, "0xb".to_string(), 7.0), (2, "0xa".to_string(), 5.0)]
+        );
+        assert_eq!(
+            ranked(
+                repo.get(Category::LandBaronPlayer, Window::All, &all, 5)
+                    .await?
+            ),
+            vec![(1, "0xa".to_string(), 1.0), (2, "0xb".to_string(), 1.0)]
+        );
+
+        // The purchase of 0xa is out of the window
+        assert_eq!(
+            ranked(
+                repo.get(Category::LandBaronPlayer, Window::Day, &all, 5)
+                    .await?
+            ),
+            vec![(1, "0xb".to_string(), 1.0)]
+        );
+
+        let auction = repo
+            .get(Category::LandBaronAuction, Window::All, &all, 5)
+            .await?;
+        assert_eq!(auction.len(), 1);
+        assert_eq!(auction[0].address, "0xb");
+        assert_eq!(auction[0].transactions, 2);
+        assert_eq!(auction[0].computed_at, now);
+
+        let filter = LeaderboardFilter {
+            min_transactions: 0,
+            exclude: vec!["0xb".to_string()],
+        };
+        assert_eq!(
+            ranked(
+                repo.get(Category::TaxDodger, Window::All, &filter, 5)
+                    .await?
+            ),
+            vec![(1, "0xa".to_string(), 0.3)]
+        );
+
+        let filter = LeaderboardFilter {
+            min_transactions: 3,
+            exclude: Vec::new(),
+        };
+        assert!(repo
+            .get(Category::TaxDodger, Window::All, &filter, 5)
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
+}
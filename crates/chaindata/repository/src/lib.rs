@@ -1,12 +1,61 @@
+pub mod cursor;
+pub mod dead_letter;
 pub mod event;
 pub mod events;
+#[cfg(any(test, feature = "test-fixtures"))]
+pub mod fixtures;
+pub mod gg_xyz_outbox;
 pub mod land;
 pub mod land_stake;
+pub mod land_tenure;
+pub mod leaderboard;
+pub mod lock;
+pub mod market;
+pub mod memory;
+pub mod nuke_forecast;
+pub mod player;
+pub mod raw;
+pub mod reindex;
+pub mod reorg;
+pub mod tax_ledger;
+pub mod token;
+pub mod token_price;
+pub mod traits;
+pub mod webhook;
 
+mod database;
 mod error;
+mod pagination;
 
-pub type Database = sqlx::PgPool;
+pub use cursor::Repository as CursorRepository;
+pub use database::Database;
+pub use dead_letter::{
+    DeadLetter, DeadLetterKind, Repository as DeadLetterRepository, StoredDeadLetter,
+};
 pub use error::Error;
-pub use event::Repository as EventRepository;
+pub use event::{EventFilter, Repository as EventRepository};
+pub use gg_xyz_outbox::{
+    gg_xyz_actions, OutboxEntry, OutboxStats, OutboxStatus, Repository as GgXyzOutboxRepository,
+};
 pub use land::Repository as LandRepository;
 pub use land_stake::Repository as LandStakeRepository;
+pub use land_tenure::{LandTenure, Repository as LandTenureRepository, TenureOutcome};
+pub use leaderboard::{
+    Category as LeaderboardCategory, LeaderboardEntry, LeaderboardFilter,
+    Repository as LeaderboardRepository, Window as LeaderboardWindow,
+};
+pub use lock::WriterLock;
+pub use market::{Bucket, MarketAggregate, Repository as MarketRepository};
+pub use nuke_forecast::{NukeForecast, Repository as NukeForecastRepository};
+pub use pagination::{Pagination, TimeRange};
+pub use player::{OwnedLand, PlayerStats, Repository as PlayerRepository};
+pub use raw::{RawData, RawDataKind, Repository as RawDataRepository};
+pub use reindex::Repository as ReindexRepository;
+pub use reorg::{Repository as ReorgRepository, Rollback, TrackedBlock};
+pub use tax_ledger::{Repository as TaxLedgerRepository, TaxTotals, TokenTaxes};
+pub use token::{ReceivedTotal, Repository as TokenRepository, TokenHolder};
+pub use token_price::{Candle, Repository as TokenPriceRepository, TokenPrice};
+pub use traits::{CursorStore, EventStore, LandStakeStore, LandStore};
+pub use webhook::{
+    DueDelivery, NewWebhook, Repository as WebhookRepository, Webhook, WebhookDelivery,
+};
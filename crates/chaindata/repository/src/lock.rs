@@ -0,0 +1,44 @@
+use crate::Database;
+use sqlx::{query, query_scalar, PgConnection};
+
+/// Key of the advisory lock held by the processes writing the chain data.
+const WRITER_LOCK_KEY: i64 = 0x706f_6e7a_6c61_6e64;
+
+/// Exclusive right to write the chain data, held until dropped.
+///
+/// The indexer holds it while running, so that a reindex never swaps the tables it writes to.
+/// It is an advisory lock of the session of a dedicated connection, which postgres releases
+/// when the connection is closed, including when the process dies.
+pub struct WriterLock {
+    _conn: PgConnection,
+}
+
+impl WriterLock {
+    /// Takes the lock, waiting for its current holder to release it.
+    ///
+    /// # Errors
+    /// Returns an error if the database could not be accessed.
+    pub async fn acquire(db: &Database) -> Result<Self, sqlx::Error> {
+        let mut conn = db.writer().acquire().await?.detach();
+        query("SELECT pg_advisory_lock($1)")
+            .bind(WRITER_LOCK_KEY)
+            .execute(&mut conn)
+            .await?;
+
+        Ok(Self { _conn: conn })
+    }
+
+    /// Takes the lock if nobody holds it.
+    ///
+    /// # Errors
+    /// Returns an error if the database could not be accessed.
+    pub async fn try_acquire(db: &Database) -> Result<Option<Self>, sqlx::Error> {
+        let mut conn = db.writer().acquire().await?.detach();
+        let locked: bool = query_scalar("SELECT pg_try_advisory_lock($1)")
+            .bind(WRITER_LOCK_KEY)
+            .fetch_one(&mut conn)
+            .await?;
+
+        Ok(locked.then_some(Self { _conn: conn }))
+    }
+}
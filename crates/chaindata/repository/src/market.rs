@@ -0,0 +1,344 @@
+use crate::{Database, Error, TimeRange};
+use chaindata_models::shared::U256;
+use chrono::NaiveDateTime;
+use sqlx::{query, PgConnection};
+
+/// Size of the time buckets of the market aggregates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "market_bucket", rename_all = "lowercase")]
+pub enum Bucket {
+    Hour,
+    Day,
+}
+
+impl Bucket {
+    pub const ALL: [Bucket; 2] = [Bucket::Hour, Bucket::Day];
+}
+
+/// Market activity of a token during a time bucket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarketAggregate {
+    pub bucket: Bucket,
+    /// Start of the bucket (inclusive).
+    pub bucket_start: NaiveDateTime,
+    pub token: String,
+    /// Number of lands bought from other players.
+    pub trade_count: u64,
+    /// Sum of the prices of the trades.
+    pub volume: U256,
+    /// Price statistics of the trades, if there was any.
+    pub min_price: Option<U256>,
+    pub max_price: Option<U256>,
+    pub median_price: Option<U256>,
+    pub auctions_started: u64,
+    pub auctions_finished: u64,
+    /// Number of distinct addresses that bought a land, from a player or an auction.
+    pub unique_buyers: u64,
+}
+
+/// Hourly and daily market aggregates per token, computed from the stored events.
+///
+/// Trades are priced in the token they used. Auctions carry no token in their events,
+/// so they are attributed to the auction token given when refreshing.
+pub struct Repository {
+    db: Database,
+}
+
+impl Repository {
+    #[must_use]
+    pub fn new(db: impl Into<Database>) -> Self {
+        Self { db: db.into() }
+    }
+
+    /// Recomputes all the buckets containing a time between `from` and `to` (both inclusive),
+    /// from the events currently stored.
+    ///
+    /// # Errors
+    /// Returns an error if the aggregates could not be computed or saved.
+    pub async fn refresh(
+        &self,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+        auction_token: &str,
+    ) -> Result<(), Error> {
+        let mut tx = self.db.writer().begin().await?;
+        Self::refresh_in(&mut tx, from, to, auction_token).await?;
+        tx.commit().await?;
+        self.db.record_write().await;
+
+        Ok(())
+    }
+
+    /// Same as [`Repository::refresh`], within an existing transaction.
+    ///
+    /// # Errors
+    /// Returns an error if the aggregates could not be computed or saved.
+    pub(crate) async fn refresh_in(
+        conn: &mut PgConnection,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+        auction_token: &str,
+    ) -> Result<(), Error> {
+        for bucket in Bucket::ALL {
+            query!(
+                r#"
+                DELETE FROM market_aggregate
+                WHERE bucket = $1
+                    AND bucket_start >= date_trunc($1::market_bucket::text, $2::timestamp)
+                    AND bucket_start <= date_trunc($1::market_bucket::text, $3::timestamp)
+                "#,
+                bucket as Bucket,
+                from,
+                to
+            )
+            .execute(&mut *conn)
+            .await?;
+
+            query!(
+                r#"
+                WITH bounds AS (
+                    SELECT
+                        date_trunc($1::market_bucket::text, $2::timestamp) as start,
+                        date_trunc($1::market_bucket::text, $3::timestamp)
+                            + ('1 ' || $1::market_bucket::text)::interval as "end"
+                ),
+                activity AS (
+                    SELECT e.at, b.token_used as token, b.price, b.buyer, 'trade' as kind
+                    FROM event e
+                    JOIN event_land_bought b ON b.id = e.id
+                    UNION ALL
+                    SELECT e.at, $4, NULL, f.buyer, 'auction_finished'
+                    FROM event e
+                    JOIN event_auction_finished f ON f.id = e.id
+                    UNION ALL
+                    SELECT e.at, $4, NULL, NULL, 'auction_started'
+                    FROM event e
+                    JOIN event_new_auction n ON n.id = e.id
+                )
+                INSERT INTO market_aggregate (
+                    bucket, bucket_start, token, trade_count, volume,
+                    min_price, max_price, median_price,
+                    auctions_started, auctions_finished, unique_buyers
+                )
+                SELECT
+                    $1::market_bucket,
+                    date_trunc($1::market_bucket::text, a.at),
+                    a.token,
+                    COUNT(*) FILTER (WHERE a.kind = 'trade'),
+                    COALESCE(SUM(a.price), 0),
+                    MIN(a.price),
+                    MAX(a.price),
+                    percentile_disc(0.5) WITHIN GROUP (ORDER BY a.price),
+                    COUNT(*) FILTER (WHERE a.kind = 'auction_started'),
+                    COUNT(*) FILTER (WHERE a.kind = 'auction_finished'),
+                    COUNT(DISTINCT a.buyer)
+                FROM activity a, bounds
+                WHERE a.at >= bounds.start AND a.at < bounds."end"
+                GROUP BY 2, 3
+                "#,
+                bucket as Bucket,
+                from,
+                to,
+                auction_token
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Gets the start of the latest bucket of the given size, if any was aggregated.
+    ///
+    /// # Errors
+    /// Returns an error if the bucket could not be retrieved.
+    pub async fn get_last_bucket_start(
+        &self,
+        bucket: Bucket,
+    ) -> Result<Option<NaiveDateTime>, sqlx::Error> {
+        Ok(query!(
+            r#"
+            SELECT MAX(bucket_start) as last_start
+            FROM market_aggregate
+            WHERE bucket = $1
+            "#,
+            bucket as Bucket
+        )
+        .fetch_one(&mut *(self.db.read().await?))
+        .await?
+        .last_start)
+    }
+
+    /// Gets the aggregates of the given bucket size starting within the time range,
+    /// ordered by bucket start then token.
+    ///
+    /// If `token` is set, only the aggregates of this token are returned.
+    ///
+    /// # Errors
+    /// Returns an error if the aggregates could not be retrieved.
+    #[allow(clippy::cast_sign_loss)] // Counts are never negative
+    pub async fn get_aggregates(
+        &self,
+        bucket: Bucket,
+        token: Option<&str>,
+        range: TimeRange,
+    ) -> Result<Vec<MarketAggregate>, sqlx::Error> {
+        let rows = query!(
+            r#"
+            SELECT
+                bucket as "bucket!: Bucket",
+                bucket_start,
+                token,
+                trade_count,
+                volume as "volume!: U256",
+                min_price as "min_price?: U256",
+                max_price as "max_price?: U256",
+                median_price as "median_price?: U256",
+                auctions_started,
+                auctions_finished,
+                unique_buyers
+            FROM market_aggregate
+            WHERE bucket = $1
+                AND ($2::text IS NULL OR token = $2)
+                AND ($3::timestamp IS NULL OR bucket_start >= $3)
+                AND ($4::timestamp IS NULL OR bucket_start < $4)
+            ORDER BY bucket_start, token
+            "#,
+            bucket as Bucket,
+            token,
+            range.from,
+            range.to
+        )
+        .fetch_all(&mut *(self.db.read().await?))
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| MarketAggregate {
+                bucket: row.bucket,
+                bucket_start: row.bucket_start,
+                token: row.token,
+                trade_count: row.trade_count as u64,
+                volume: row.volume,
+                min_price: row.min_price,
+                max_price: row.max_price,
+                median_price: row.median_price,
+                auctions_started: row.auctions_started as u64,
+                auctions_finished: row.auctions_finished as u64,
+                unique_buyers: row.unique_buyers as u64,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{EventRepository, EventStore};
+    use chaindata_models::events::{
+        actions::{AuctionFinishedEventModel, LandBoughtEventModel, NewAuctionEventModel},
+        EventDataModel, EventId, FetchedEvent,
+    };
+    use chrono::{Duration, NaiveDate};
+    use migrations::MIGRATOR;
+
+    fn bought(id: u32, at: NaiveDateTime, buyer: &str, price: &str, token: &str) -> FetchedEvent {
+        FetchedEvent {
+            id: EventId::new_test(0, 0, id),
+            at,
+            data: EventDataModel::LandBought(LandBoughtEventModel {
+                id: None,
+                location: 1.into(),
+                buyer: buyer.to_string(),
+                seller: "0xseller".to_string(),
+                price: U256::from_str(price).unwrap(),
+                token_used: token.to_string(),
+            }),
+        }
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_market_aggregates(pool: sqlx::PgPool) -> Result<(), Error> {
+        let events = EventRepository::new(pool.clone());
+        let repo = Repository::new(pool);
+
+        let day = NaiveDate::from_ymd_opt(2025, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        let hour1 = day + Duration::minutes(10);
+        let hour2 = day + Duration::hours(1) + Duration::minutes(10);
+
+        assert_eq!(repo.get_last_bucket_start(Bucket::Day).await?, None);
+
+        events
+            .save_events(vec![
+                bought(0, hour1, "0xa", "10", "0xtoken"),
+                bought(1, hour1, "0xa", "30", "0xtoken"),
+                bought(2, hour2, "0xb", "20", "0xtoken"),
+                bought(3, hour2, "0xb", "5", "0xother"),
+                FetchedEvent {
+                    id: EventId::new_test(0, 0, 4),
+                    at: hour1,
+                    data: EventDataModel::NewAuction(NewAuctionEventModel {
+                        id: None,
+                        location: 2.into(),
+                        starting_price: U256::from_str("100").unwrap(),
+                        floor_price: U256::from_str("1").unwrap(),
+                    }),
+                },
+                FetchedEvent {
+                    id: EventId::new_test(0, 0, 5),
+                    at: hour2,
+                    data: EventDataModel::AuctionFinished(AuctionFinishedEventModel {
+                        id: None,
+                        location: 2.into(),
+                        buyer: "0xc".to_string(),
+                        price: U256::from_str("50").unwrap(),
+                    }),
+                },
+            ])
+            .await?;
+
+        repo.refresh(hour1, hour2, "0xtoken").await?;
+
+        let hourly = repo
+            .get_aggregates(Bucket::Hour, Some("0xtoken"), TimeRange::default())
+            .await?;
+        assert_eq!(hourly.len(), 2);
+        assert_eq!(
+            repo.get_last_bucket_start(Bucket::Hour).await?,
+            Some(day + Duration::hours(1))
+        );
+        assert_eq!(repo.get_last_bucket_start(Bucket::Day).await?, Some(day));
+        assert_eq!(hourly[0].bucket_start, day);
+        assert_eq!(hourly[0].trade_count, 2);
+        assert_eq!(hourly[0].volume, U256::from_str("40").unwrap());
+        assert_eq!(hourly[0].min_price, Some(U256::from_str("10").unwrap()));
+        assert_eq!(hourly[0].max_price, Some(U256::from_str("30").unwrap()));
+        assert_eq!(hourly[0].auctions_started, 1);
+        assert_eq!(hourly[0].unique_buyers, 1);
+        assert_eq!(hourly[1].auctions_finished, 1);
+        assert_eq!(hourly[1].unique_buyers, 2);
+
+        let daily = repo
+            .get_aggregates(Bucket::Day, None, TimeRange::default())
+            .await?;
+        assert_eq!(daily.len(), 2);
+        let token = daily.iter().find(|a| a.token == "0xtoken").unwrap();
+        assert_eq!(token.trade_count, 3);
+        assert_eq!(token.median_price, Some(U256::from_str("20").unwrap()));
+        assert_eq!(token.unique_buyers, 3);
+
+        // Refreshing again gives the same result
+        repo.refresh(hour2, hour2, "0xtoken").await?;
+        let daily_again = repo
+            .get_aggregates(Bucket::Day, None, TimeRange::default())
+            .await?;
+        assert_eq!(daily, daily_again);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,635 @@
+//! In-memory implementation of the storage traits, with the same time-travel semantics as the
+//! postgres repositories.
+//!
+//! Meant for tests of the logic built on top of the repositories, which don't need a database.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use chaindata_models::{
+    events::{Event, EventDataModel, EventId, EventType, FetchedEvent, StoredEvent},
+    models::{LandModel, LandStakeModel},
+    shared::Location,
+};
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use crate::{
+    CursorStore, Error, EventFilter, EventStore, LandStakeStore, LandStore, Pagination, RawData,
+    TimeRange,
+};
+
+#[derive(Default)]
+struct State {
+    // Keyed by the string representation of the ids, which keeps the chain ordering
+    events: BTreeMap<String, FetchedEvent>,
+    /// Keys of the events in the order they were stored, the sequence number being the index
+    /// plus one
+    sequence: Vec<String>,
+    lands: BTreeMap<String, LandModel>,
+    land_stakes: BTreeMap<String, LandStakeModel>,
+    cursors: HashMap<String, i64>,
+}
+
+/// Stores events, lands, land stakes and consumer cursors in memory.
+///
+/// Clones share the same storage, so the same instance can be used for all the traits.
+#[derive(Clone, Default)]
+pub struct MemoryRepository {
+    state: Arc<RwLock<State>>,
+}
+
+impl MemoryRepository {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, State> {
+        self.state
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, State> {
+        self.state
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// A version of a model, stored by location.
+trait Versioned: Clone {
+    fn id(&self) -> &EventId;
+    fn at(&self) -> NaiveDateTime;
+    fn location(&self) -> Location;
+}
+
+impl Versioned for LandModel {
+    fn id(&self) -> &EventId {
+        &self.id
+    }
+
+    fn at(&self) -> NaiveDateTime {
+        self.at
+    }
+
+    fn location(&self) -> Location {
+        self.location
+    }
+}
+
+impl Versioned for LandStakeModel {
+    fn id(&self) -> &EventId {
+        &self.id
+    }
+
+    fn at(&self) -> NaiveDateTime {
+        self.at
+    }
+
+    fn location(&self) -> Location {
+        self.location
+    }
+}
+
+/// Inserts the version, unless one with the same id already exists.
+fn insert<T: Versioned>(versions: &mut BTreeMap<String, T>, model: T) -> bool {
+    let key = model.id().as_string();
+    if versions.contains_key(&key) {
+        return false;
+    }
+    versions.insert(key, model);
+    true
+}
+
+/// Keeps the latest version of each location, in location order.
+///
+/// Versions are compared by `key`, and iterated in chain order.
+fn latest_by_location<'a, T, K, I>(versions: I, key: impl Fn(&T) -> K) -> Vec<T>
+where
+    T: Versioned + 'a,
+    K: PartialOrd,
+    I: Iterator<Item = &'a T>,
+{
+    let mut latest: BTreeMap<u64, &T> = BTreeMap::new();
+    for version in versions {
+        let location = (*version.location()).0;
+        match latest.get(&location) {
+            Some(current) if key(current) > key(version) => {}
+            _ => {
+                latest.insert(location, version);
+            }
+        }
+    }
+    latest.into_values().cloned().collect()
+}
+
+fn current<T: Versioned>(versions: &BTreeMap<String, T>) -> Vec<T> {
+    // Iterating in chain order, the last version of each location wins
+    latest_by_location(versions.values(), |_| ())
+}
+
+fn at_time<T: Versioned>(versions: &BTreeMap<String, T>, at: NaiveDateTime) -> Vec<T> {
+    latest_by_location(
+        versions.values().filter(|version| version.at() <= at),
+        Versioned::at,
+    )
+}
+
+fn latest_at_location<T: Versioned>(
+    versions: &BTreeMap<String, T>,
+    location: Location,
+    at: NaiveDateTime,
+) -> Option<T> {
+    at_time(versions, at)
+        .into_iter()
+        .find(|version| version.location() == location)
+}
+
+fn history<T: Versioned>(
+    versions: &BTreeMap<String, T>,
+    location: Location,
+    range: TimeRange,
+    pagination: &Pagination,
+) -> Vec<T> {
+    let after = pagination.after.as_ref().map(EventId::as_string);
+    versions
+        .iter()
+        .filter(|(id, _)| after.as_ref().is_none_or(|after| *id > after))
+        .map(|(_, version)| version)
+        .filter(|version| version.location() == location)
+        .filter(|version| range.from.is_none_or(|from| version.at() >= from))
+        .filter(|version| range.to.is_none_or(|to| version.at() < to))
+        .take(usize::try_from(pagination.sql_limit()).unwrap_or(usize::MAX))
+        .cloned()
+        .collect()
+}
+
+fn latest_timestamp<T: Versioned>(versions: &BTreeMap<String, T>) -> Option<NaiveDateTime> {
+    versions.values().map(Versioned::at).max()
+}
+
+/// Checks if the event matches the filter (except the pagination).
+fn matches(filter: &EventFilter, event: &FetchedEvent) -> bool {
+    if !filter.event_types.is_empty() && !filter.event_types.contains(&EventType::from(&event.data))
+    {
+        return false;
+    }
+
+    if filter.from_time.is_some_and(|from| event.at < from)
+        || filter.to_time.is_some_and(|to| event.at >= to)
+    {
+        return false;
+    }
+
+    if filter
+        .from_block
+        .is_some_and(|block| event.id < EventId::first_of_block(block))
+        || filter
+            .to_block
+            .is_some_and(|block| event.id >= EventId::first_of_block(block.saturating_add(1)))
+    {
+        return false;
+    }
+
+    if let Some(location) = filter.location {
+        let event_location = match &event.data {
+            EventDataModel::AuctionFinished(data) => Some(data.location),
+            EventDataModel::LandBought(data) => Some(data.location),
+            EventDataModel::LandNuked(data) => Some(data.location),
+            EventDataModel::NewAuction(data) => Some(data.location),
+            _ => None,
+        };
+        if event_location != Some(location) {
+            return false;
+        }
+    }
+
+    if let Some(address) = &filter.address {
+        let involved = match &event.data {
+            EventDataModel::AuctionFinished(data) => data.buyer == *address,
+            EventDataModel::LandBought(data) => data.buyer == *address || data.seller == *address,
+            EventDataModel::LandNuked(data) => data.owner == *address,
+            EventDataModel::AddressAuthorized(data) => data.address == *address,
+            EventDataModel::AddressRemoved(data) => data.address == *address,
+            _ => false,
+        };
+        if !involved {
+            return false;
+        }
+    }
+
+    true
+}
+
+#[async_trait::async_trait]
+impl EventStore for MemoryRepository {
+    async fn get_event_by_id(&self, id: EventId) -> Result<Event, Error> {
+        self.read()
+            .events
+            .get(&id.as_string())
+            .map(|event| Event {
+                id: event.id.clone(),
+                at: event.at,
+                event_type: EventType::from(&event.data),
+            })
+            .ok_or(Error::SqlError(sqlx::Error::RowNotFound))
+    }
+
+    async fn get_full_event_by_id(&self, id: EventId) -> Result<Option<FetchedEvent>, Error> {
+        Ok(self.read().events.get(&id.as_string()).cloned())
+    }
+
+    async fn get_events(
+        &self,
+        filter: &EventFilter,
+        pagination: &Pagination,
+    ) -> Result<Vec<FetchedEvent>, Error> {
+        let after = pagination.after.as_ref().map(EventId::as_string);
+
+        Ok(self
+            .read()
+            .events
+            .iter()
+            .filter(|(id, _)| after.as_ref().is_none_or(|after| *id > after))
+            .map(|(_, event)| event)
+            .filter(|event| matches(filter, event))
+            .take(usize::try_from(pagination.sql_limit()).unwrap_or(usize::MAX))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_events_after_seq(
+        &self,
+        event_types: &[EventType],
+        after_seq: i64,
+        limit: u32,
+    ) -> Result<Vec<StoredEvent>, Error> {
+        let state = self.read();
+        let skipped = usize::try_from(after_seq.max(0)).unwrap_or(usize::MAX);
+
+        Ok(state
+            .sequence
+            .iter()
+            .zip(1..)
+            .skip(skipped)
+            .map(|(key, seq)| (&state.events[key], seq))
+            .filter(|(event, _)| {
+                event_types.is_empty() || event_types.contains(&EventType::from(&event.data))
+            })
+            .take(usize::try_from(limit).unwrap_or(usize::MAX))
+            .map(|(event, seq)| StoredEvent {
+                seq,
+                event: event.clone(),
+            })
+            .collect())
+    }
+
+    async fn get_last_event_date(&self) -> Result<DateTime<Utc>, Error> {
+        Ok(self
+            .read()
+            .events
+            .values()
+            .map(|event| event.at)
+            .max()
+            .map_or(DateTime::UNIX_EPOCH, |date| date.and_utc()))
+    }
+
+    async fn save_event(&self, event: FetchedEvent) -> Result<EventId, Error> {
+        let id = event.id.clone();
+        if self.save_events(vec![event]).await?.is_empty() {
+            return Err(Error::AlreadyExists(id));
+        }
+        Ok(id)
+    }
+
+    async fn save_events(&self, events: Vec<FetchedEvent>) -> Result<Vec<EventId>, Error> {
+        Ok(self
+            .save_events_with_raw(events, &[])
+            .await?
+            .into_iter()
+            .map(|stored| stored.event.id)
+            .collect())
+    }
+
+    /// The raw rows are not kept in memory, only the events are saved.
+    async fn save_events_with_raw(
+        &self,
+        mut events: Vec<FetchedEvent>,
+        _raw: &[RawData],
+    ) -> Result<Vec<StoredEvent>, Error> {
+        let mut state = self.write();
+        let mut inserted = Vec::new();
+
+        events.sort_by(|a, b| a.id.cmp(&b.id));
+        for mut event in events {
+            let key = event.id.as_string();
+            if state.events.contains_key(&key) {
+                continue;
+            }
+
+            event.data.set_id(event.id.clone());
+            state.sequence.push(key.clone());
+            inserted.push(StoredEvent {
+                seq: i64::try_from(state.sequence.len()).unwrap_or(i64::MAX),
+                event: event.clone(),
+            });
+            state.events.insert(key, event);
+        }
+
+        Ok(inserted)
+    }
+}
+
+#[async_trait::async_trait]
+impl LandStore for MemoryRepository {
+    async fn save(&self, land: LandModel) -> Result<EventId, Error> {
+        let id = land.id.clone();
+        if !insert(&mut self.write().lands, land) {
+            return Err(Error::AlreadyExists(id));
+        }
+        Ok(id)
+    }
+
+    async fn save_many(&self, lands: &[LandModel]) -> Result<u64, Error> {
+        let mut state = self.write();
+        Ok(lands
+            .iter()
+            .filter(|land| insert(&mut state.lands, (*land).clone()))
+            .count() as u64)
+    }
+
+    /// The raw rows are not kept in memory, only the versions are saved.
+    async fn save_many_with_raw(
+        &self,
+        lands: &[LandModel],
+        _raw: &[RawData],
+    ) -> Result<u64, Error> {
+        LandStore::save_many(self, lands).await
+    }
+
+    async fn get_current_at_location(
+        &self,
+        location: Location,
+    ) -> Result<Option<LandModel>, Error> {
+        Ok(current(&self.read().lands)
+            .into_iter()
+            .find(|land| land.location == location))
+    }
+
+    async fn get_all_current(&self) -> Result<Vec<LandModel>, Error> {
+        Ok(current(&self.read().lands))
+    }
+
+    async fn get_latest_at_location(
+        &self,
+        location: Location,
+        at: NaiveDateTime,
+    ) -> Result<Option<LandModel>, Error> {
+        Ok(latest_at_location(&self.read().lands, location, at))
+    }
+
+    async fn get_all_at_time(&self, at: NaiveDateTime) -> Result<Vec<LandModel>, Error> {
+        Ok(at_time(&self.read().lands, at))
+    }
+
+    async fn get_history(
+        &self,
+        location: Location,
+        range: TimeRange,
+        pagination: &Pagination,
+    ) -> Result<Vec<LandModel>, Error> {
+        Ok(history(&self.read().lands, location, range, pagination))
+    }
+
+    async fn get_by_id(&self, id: EventId) -> Result<Option<LandModel>, Error> {
+        Ok(self.read().lands.get(&id.as_string()).cloned())
+    }
+
+    async fn get_latest_timestamp(&self) -> Result<Option<NaiveDateTime>, Error> {
+        Ok(latest_timestamp(&self.read().lands))
+    }
+
+    async fn get_land_distribution(&self) -> Result<HashMap<String, u64>, Error> {
+        let mut distribution = HashMap::new();
+        for land in current(&self.read().lands) {
+            if land.owner != "0" {
+                *distribution.entry(land.token_used).or_default() += 1;
+            }
+        }
+        Ok(distribution)
+    }
+}
+
+#[async_trait::async_trait]
+impl LandStakeStore for MemoryRepository {
+    async fn save(&self, land_stake: LandStakeModel) -> Result<EventId, Error> {
+        let id = land_stake.id.clone();
+        if !insert(&mut self.write().land_stakes, land_stake) {
+            return Err(Error::AlreadyExists(id));
+        }
+        Ok(id)
+    }
+
+    async fn save_many(&self, land_stakes: &[LandStakeModel]) -> Result<u64, Error> {
+        let mut state = self.write();
+        Ok(land_stakes
+            .iter()
+            .filter(|stake| insert(&mut state.land_stakes, (*stake).clone()))
+            .count() as u64)
+    }
+
+    /// The raw rows are not kept in memory, only the versions are saved.
+    async fn save_many_with_raw(
+        &self,
+        land_stakes: &[LandStakeModel],
+        _raw: &[RawData],
+    ) -> Result<u64, Error> {
+        LandStakeStore::save_many(self, land_stakes).await
+    }
+
+    async fn get_current_at_location(
+        &self,
+        location: Location,
+    ) -> Result<Option<LandStakeModel>, Error> {
+        Ok(current(&self.read().land_stakes)
+            .into_iter()
+            .find(|stake| stake.location == location))
+    }
+
+    async fn get_all_current(&self) -> Result<Vec<LandStakeModel>, Error> {
+        Ok(current(&self.read().land_stakes))
+    }
+
+    async fn get_latest_at_location(
+        &self,
+        location: Location,
+        at: NaiveDateTime,
+    ) -> Result<Option<LandStakeModel>, Error> {
+        Ok(latest_at_location(&self.read().land_stakes, location, at))
+    }
+
+    async fn get_all_at_time(&self, at: NaiveDateTime) -> Result<Vec<LandStakeModel>, Error> {
+        Ok(at_time(&self.read().land_stakes, at))
+    }
+
+    async fn get_history(
+        &self,
+        location: Location,
+        range: TimeRange,
+        pagination: &Pagination,
+    ) -> Result<Vec<LandStakeModel>, Error> {
+        Ok(history(
+            &self.read().land_stakes,
+            location,
+            range,
+            pagination,
+        ))
+    }
+
+    async fn get_by_id(&self, id: EventId) -> Result<Option<LandStakeModel>, Error> {
+        Ok(self.read().land_stakes.get(&id.as_string()).cloned())
+    }
+
+    async fn get_latest_timestamp(&self) -> Result<Option<NaiveDateTime>, Error> {
+        Ok(latest_timestamp(&self.read().land_stakes))
+    }
+}
+
+#[async_trait::async_trait]
+impl CursorStore for MemoryRepository {
+    async fn get_cursor(&self, consumer_id: &str) -> Result<Option<i64>, Error> {
+        Ok(self.read().cursors.get(consumer_id).copied())
+    }
+
+    async fn save_cursor(&self, consumer_id: &str, seq: i64) -> Result<(), Error> {
+        self.write().cursors.insert(consumer_id.to_string(), seq);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{event, land, nuked};
+    use chrono::Duration;
+
+    #[tokio::test]
+    async fn test_land_time_travel() -> Result<(), Error> {
+        let repo = MemoryRepository::new();
+        let time1 = Utc::now().naive_utc();
+        let time2 = time1 + Duration::hours(1);
+
+        LandStore::save(
+            &repo,
+            land(EventId::new_test(0, 0, 0), time1, 1, "0xowner0"),
+        )
+        .await?;
+        LandStore::save(
+            &repo,
+            land(EventId::new_test(0, 0, 2), time2, 1, "0xowner2"),
+        )
+        .await?;
+        // Late arrival of an older version
+        LandStore::save(
+            &repo,
+            land(EventId::new_test(0, 0, 1), time1, 1, "0xowner1"),
+        )
+        .await?;
+        LandStore::save(
+            &repo,
+            land(EventId::new_test(0, 0, 3), time1, 2, "0xowner3"),
+        )
+        .await?;
+
+        assert!(matches!(
+            LandStore::save(
+                &repo,
+                land(EventId::new_test(0, 0, 3), time1, 2, "0xowner3")
+            )
+            .await,
+            Err(Error::AlreadyExists(_))
+        ));
+
+        let current = LandStore::get_current_at_location(&repo, 1.into())
+            .await?
+            .unwrap();
+        assert_eq!(current.owner, "0xowner2");
+
+        let before = LandStore::get_latest_at_location(&repo, 1.into(), time1)
+            .await?
+            .unwrap();
+        // Among versions of the same time, the last one on chain wins
+        assert_eq!(before.id, EventId::new_test(0, 0, 1));
+        assert!(
+            LandStore::get_latest_at_location(&repo, 1.into(), time1 - Duration::hours(1))
+                .await?
+                .is_none()
+        );
+
+        assert_eq!(LandStore::get_all_at_time(&repo, time1).await?.len(), 2);
+        assert_eq!(LandStore::get_all_current(&repo).await?.len(), 2);
+        assert_eq!(LandStore::get_latest_timestamp(&repo).await?, Some(time2));
+
+        let history = LandStore::get_history(
+            &repo,
+            1.into(),
+            TimeRange::default(),
+            &Pagination::new(Some(EventId::new_test(0, 0, 0)), 10),
+        )
+        .await?;
+        let ids: Vec<EventId> = history.into_iter().map(|land| land.id).collect();
+        assert_eq!(
+            ids,
+            vec![EventId::new_test(0, 0, 1), EventId::new_test(0, 0, 2)]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_events_filter() -> Result<(), Error> {
+        let repo = MemoryRepository::new();
+        let at = Utc::now().naive_utc();
+
+        let inserted = repo
+            .save_events(vec![
+                event(EventId::new_test(2, 0, 0), at, nuked(1, "0xa")),
+                event(EventId::new_test(1, 0, 0), at, nuked(2, "0xb")),
+            ])
+            .await?;
+        assert_eq!(inserted.len(), 2);
+        assert!(repo
+            .save_event(event(EventId::new_test(1, 0, 0), at, nuked(2, "0xb")))
+            .await
+            .is_err());
+
+        let all = repo
+            .get_events(&EventFilter::default(), &Pagination::default())
+            .await?;
+        assert_eq!(all[0].id, EventId::new_test(1, 0, 0));
+
+        let filter = EventFilter {
+            address: Some("0xa".to_string()),
+            ..Default::default()
+        };
+        let events = repo.get_events(&filter, &Pagination::default()).await?;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data.id(), Some(&EventId::new_test(2, 0, 0)));
+
+        let filter = EventFilter {
+            to_block: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(
+            repo.get_events(&filter, &Pagination::default())
+                .await?
+                .len(),
+            1
+        );
+
+        Ok(())
+    }
+}
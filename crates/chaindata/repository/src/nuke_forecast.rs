@@ -0,0 +1,257 @@
+use crate::{Database, Error};
+use chaindata_models::shared::{Location, U256};
+use chrono::NaiveDateTime;
+use sqlx::{query, query_as};
+
+/// Width of the map, in lands.
+const GRID_WIDTH: i32 = 64;
+/// Percentage of the sell price paid in taxes per hour, split between the possible neighbors.
+const TAX_RATE: i32 = 2;
+/// Speed of the game time compared to the real time.
+const TIME_SPEED: i32 = 5;
+/// Duration the tax rate applies to, in seconds.
+const BASE_TIME: i32 = 3600;
+/// Nuke times further away are capped, as they would not fit in a timestamp.
+const MAX_SECONDS: f64 = 100. * 365. * 24. * 3600.;
+
+/// When an owned land can be nuked, if its stake, price and neighbors stay the same.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NukeForecast {
+    pub location: Location,
+    pub owner: String,
+    pub token_used: String,
+    pub stake: U256,
+    /// Occupied neighbors, paid by the land.
+    pub neighbors: i32,
+    /// Taxes paid to each neighbor per hour.
+    pub tax_per_neighbor: U256,
+    /// `None` when the land pays no taxes.
+    pub nuke_at: Option<NaiveDateTime>,
+    pub computed_at: NaiveDateTime,
+}
+
+/// Forecasts of the nukes, recomputed periodically from the current lands and stakes.
+///
+/// The time to nuke follows the contract: the stake divided by the taxes paid to all the occupied
+/// neighbors, counted from the last time the taxes were paid.
+pub struct Repository {
+    db: Database,
+}
+
+impl Repository {
+    #[must_use]
+    pub fn new(db: impl Into<Database>) -> Self {
+        Self { db: db.into() }
+    }
+
+    /// Recomputes the forecast of every owned land, replacing the previous ones.
+    ///
+    /// Returns the number of lands forecast.
+    ///
+    /// # Errors
+    /// Returns an error if the forecasts could not be computed or saved.
+    pub async fn refresh(&self, now: NaiveDateTime) -> Result<u64, Error> {
+        let mut tx = self.db.writer().begin().await?;
+
+        query!("DELETE FROM nuke_forecast")
+            .execute(&mut *tx)
+            .await?;
+
+        let inserted = query!(
+            r#"
+            WITH owned AS (
+                SELECT
+                    l.location, l.owner, l.token_used, l.sell_price, l.level,
+                    s.amount as stake, s.last_pay_time
+                FROM land_current l
+                JOIN land_stake_current s ON s.location = l.location
+                WHERE l.owner <> '0'
+            ),
+            neighbor AS (
+                -- Neighbors within the map, and the ones of them that are owned
+                SELECT o.location, COUNT(*) as possible, COUNT(n.location) as occupied
+                FROM owned o
+                CROSS JOIN (
+                    VALUES (-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)
+                ) d(dx, dy)
+                LEFT JOIN land_current n
+                    ON n.location = o.location + d.dx * $2 + d.dy AND n.owner <> '0'
+                WHERE o.location / $2 + d.dx BETWEEN 0 AND $2 - 1
+                    AND o.location % $2 + d.dy BETWEEN 0 AND $2 - 1
+                GROUP BY o.location
+            ),
+            rate AS (
+                SELECT
+                    o.*,
+                    n.occupied,
+                    -- Integer divisions, as in the contract, with the discount of the level
+                    div(
+                        div(o.sell_price * $3::int4 * $4::int4, n.possible * 100)
+                            * (100 - CASE o.level WHEN 1 THEN 10 WHEN 2 THEN 15 ELSE 0 END),
+                        100
+                    ) as tax_per_neighbor
+                FROM owned o
+                JOIN neighbor n ON n.location = o.location
+            )
+            INSERT INTO nuke_forecast
+                (location, owner, token_used, stake, neighbors, tax_per_neighbor, nuke_at, computed_at)
+            SELECT
+                location,
+                owner,
+                token_used,
+                stake,
+                occupied,
+                tax_per_neighbor,
+                CASE WHEN tax_per_neighbor * occupied > 0 THEN
+                    last_pay_time + make_interval(secs => LEAST(
+                        div(stake * $5::int4, tax_per_neighbor * occupied)::float8,
+                        $6
+                    ))
+                END,
+                $1
+            FROM rate
+            "#,
+            now,
+            GRID_WIDTH,
+            TAX_RATE,
+            TIME_SPEED,
+            BASE_TIME,
+            MAX_SECONDS
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        tx.commit().await?;
+        self.db.record_write().await;
+
+        Ok(inserted)
+    }
+
+    /// Gets the lands that can be nuked before the given time, the soonest first.
+    ///
+    /// Lands that can already be nuked are included.
+    ///
+    /// # Errors
+    /// Returns an error if the forecasts could not be retrieved.
+    pub async fn get_at_risk(
+        &self,
+        before: NaiveDateTime,
+        owner: Option<&str>,
+    ) -> Result<Vec<NukeForecast>, sqlx::Error> {
+        query_as!(
+            NukeForecast,
+            r#"
+            SELECT
+                location as "location: Location",
+                owner,
+                token_used,
+                stake as "stake: U256",
+                neighbors,
+                tax_per_neighbor as "tax_per_neighbor: U256",
+                nuke_at,
+                computed_at
+            FROM nuke_forecast
+            WHERE nuke_at <= $1 AND ($2::text IS NULL OR owner = $2)
+            ORDER BY nuke_at, location
+            "#,
+            before,
+            owner
+        )
+        .fetch_all(&mut *(self.db.read().await?))
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{fixtures, LandRepository, LandStakeRepository, LandStakeStore, LandStore};
+    use chaindata_models::{
+        events::EventId,
+        models::{LandModel, LandStakeModel, Level},
+    };
+    use chrono::{Duration, SubsecRound, Utc};
+    use migrations::MIGRATOR;
+
+    fn land(location: Location, owner: &str, sell_price: &str, level: Level) -> LandModel {
+        let id = EventId::new_test(1, (*location).0, 0);
+        LandModel {
+            sell_price: U256::from_str(sell_price).unwrap(),
+            level,
+            ..fixtures::land(id, Utc::now().naive_utc(), location, owner)
+        }
+    }
+
+    fn stake(location: Location, amount: &str, last_pay_time: NaiveDateTime) -> LandStakeModel {
+        let id = EventId::new_test(1, (*location).0, 1);
+        fixtures::stake(id, last_pay_time, location, amount)
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_nuke_forecast(pool: sqlx::PgPool) -> Result<(), Error> {
+        let lands = LandRepository::new(pool.clone());
+        let stakes = LandStakeRepository::new(pool.clone());
+        let repo = Repository::new(pool);
+
+        let now = Utc::now().naive_utc().trunc_subsecs(6);
+        let center = Location::coords(10, 10);
+        let corner = Location::coords(0, 0);
+
+        // 8 possible neighbors: 8000 * 2% * 5 / 8 = 100 per neighbor per hour
+        lands.save(land(center, "0xa", "8000", Level::Zero)).await?;
+        stakes.save(stake(center, "300", now)).await?;
+        // 3 possible neighbors, with a 10% discount: 3000 * 2% * 5 / 3 * 90% = 90
+        lands
+            .save(land(corner, "0xb", "3000", Level::First))
+            .await?;
+        stakes.save(stake(corner, "1000", now)).await?;
+
+        // Three neighbors for the center, one of them unowned
+        for location in [Location::coords(9, 9), Location::coords(11, 10)] {
+            lands.save(land(location, "0xc", "0", Level::Zero)).await?;
+        }
+        lands
+            .save(land(Location::coords(10, 11), "0", "0", Level::Zero))
+            .await?;
+
+        // Only the staked lands pay taxes
+        assert_eq!(repo.refresh(now).await?, 2);
+
+        // 300 / (100 * 2) hours
+        let at_risk = repo.get_at_risk(now + Duration::hours(2), None).await?;
+        assert_eq!(at_risk.len(), 1);
+        assert_eq!(at_risk[0].location, center);
+        assert_eq!(at_risk[0].neighbors, 2);
+        assert_eq!(at_risk[0].tax_per_neighbor, U256::from_str("100").unwrap());
+        assert_eq!(
+            at_risk[0].nuke_at,
+            Some(now + Duration::hours(1) + Duration::minutes(30))
+        );
+        assert!(repo
+            .get_at_risk(now + Duration::hours(1), None)
+            .await?
+            .is_empty());
+
+        // The corner has no neighbor, and the neighbors of the center have no stake
+        lands
+            .save(land(Location::coords(0, 1), "0xc", "0", Level::Zero))
+            .await?;
+        repo.refresh(now).await?;
+        let at_risk = repo.get_at_risk(now + Duration::days(365), None).await?;
+        assert_eq!(at_risk.len(), 2);
+        assert_eq!(at_risk[1].location, corner);
+        assert_eq!(at_risk[1].tax_per_neighbor, U256::from_str("90").unwrap());
+        // 1000 * 3600 / 90 seconds
+        assert_eq!(at_risk[1].nuke_at, Some(now + Duration::seconds(40000)));
+
+        let at_risk = repo
+            .get_at_risk(now + Duration::days(365), Some("0xb"))
+            .await?;
+        assert_eq!(at_risk.len(), 1);
+
+        Ok(())
+    }
+}
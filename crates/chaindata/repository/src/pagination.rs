@@ -0,0 +1,49 @@
+use chaindata_models::events::EventId;
+use chrono::NaiveDateTime;
+
+/// Keyset pagination over rows ordered by their chain position (their id).
+#[derive(Debug, Clone)]
+pub struct Pagination {
+    /// Only return rows strictly after this id.
+    pub after: Option<EventId>,
+    /// Maximum number of rows to return (capped to [`Pagination::MAX_LIMIT`]).
+    pub limit: u32,
+}
+
+impl Pagination {
+    pub const DEFAULT_LIMIT: u32 = 100;
+    pub const MAX_LIMIT: u32 = 1000;
+
+    #[must_use]
+    pub fn new(after: Option<EventId>, limit: u32) -> Self {
+        Self { after, limit }
+    }
+
+    /// The limit to use in queries, capped to [`Pagination::MAX_LIMIT`].
+    #[must_use]
+    pub fn sql_limit(&self) -> i64 {
+        i64::from(self.limit.min(Self::MAX_LIMIT))
+    }
+}
+
+impl Default for Pagination {
+    fn default() -> Self {
+        Self::new(None, Self::DEFAULT_LIMIT)
+    }
+}
+
+/// A time range, including its start and excluding its end.
+///
+/// Both bounds are optional, an unbounded range matching everything.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeRange {
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+}
+
+impl TimeRange {
+    #[must_use]
+    pub fn new(from: Option<NaiveDateTime>, to: Option<NaiveDateTime>) -> Self {
+        Self { from, to }
+    }
+}
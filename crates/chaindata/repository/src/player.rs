@@ -0,0 +1,298 @@
+use crate::{Database, Error};
+use chaindata_models::{
+    events::EventId,
+    models::{LandModel, LandStakeModel, Level},
+    shared::{Location, U256},
+};
+use chrono::NaiveDateTime;
+use sqlx::{query, query_as};
+use std::collections::HashMap;
+
+/// A land currently owned by a player, along with its latest stake.
+#[derive(Debug, Clone)]
+pub struct OwnedLand {
+    pub land: LandModel,
+    /// The latest stake of the land, if it was ever staked.
+    pub stake: Option<LandStakeModel>,
+}
+
+/// Counters of the actions of a player over the whole history.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PlayerStats {
+    /// Number of lands bought by the player.
+    pub purchases: u64,
+    /// Number of lands bought from the player.
+    pub sales: u64,
+    /// Number of lands of the player that were nuked.
+    pub nukes: u64,
+}
+
+/// Read-only queries about what a given address owns and did.
+///
+/// Addresses must be formatted the same way as stored, i.e. `{:#x}`.
+pub struct Repository {
+    db: Database,
+}
+
+impl Repository {
+    #[must_use]
+    pub fn new(db: impl Into<Database>) -> Self {
+        Self { db: db.into() }
+    }
+
+    /// Gets the lands currently owned by the address, with their latest stake.
+    ///
+    /// # Errors
+    /// Returns an error if the lands could not be retrieved
+    pub async fn get_owned_lands(&self, address: &str) -> Result<Vec<OwnedLand>, sqlx::Error> {
+        let rows = query!(
+            r#"
+            SELECT
+                l.id as "id!: EventId",
+                l.at as "at!",
+                l.location as "location!: Location",
+                l.bought_at as "bought_at!",
+                l.owner as "owner!",
+                l.sell_price as "sell_price!: U256",
+                l.token_used as "token_used!",
+                l.level as "level!: Level",
+                s.id as "stake_id?: EventId",
+                s.at as "stake_at?",
+                s.last_pay_time as "last_pay_time?",
+                s.amount as "amount?: U256"
+            FROM land_current l
+            LEFT JOIN land_stake_current s ON s.location = l.location
+            WHERE l.owner = $1
+            ORDER BY l.location
+            "#,
+            address
+        )
+        .fetch_all(&mut *(self.db.read().await?))
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let stake = match (row.stake_id, row.stake_at, row.last_pay_time, row.amount) {
+                    (Some(id), Some(at), Some(last_pay_time), Some(amount)) => {
+                        Some(LandStakeModel {
+                            id,
+                            at,
+                            location: row.location,
+                            last_pay_time,
+                            amount,
+                        })
+                    }
+                    _ => None,
+                };
+
+                OwnedLand {
+                    land: LandModel {
+                        id: row.id,
+                        at: row.at,
+                        location: row.location,
+                        bought_at: row.bought_at,
+                        owner: row.owner,
+                        sell_price: row.sell_price,
+                        token_used: row.token_used,
+                        level: row.level,
+                    },
+                    stake,
+                }
+            })
+            .collect())
+    }
+
+    /// Gets the lands owned by the address at a specific point in time
+    ///
+    /// # Errors
+    /// Returns an error if the lands could not be retrieved
+    pub async fn get_owned_lands_at(
+        &self,
+        address: &str,
+        at: NaiveDateTime,
+    ) -> Result<Vec<LandModel>, sqlx::Error> {
+        query_as!(
+            LandModel,
+            r#"
+            WITH latest_lands AS (
+                SELECT DISTINCT ON (location)
+                    id, at, location, bought_at, owner, sell_price, token_used, level
+                FROM land
+                WHERE at <= $2
+                ORDER BY location, id DESC
+            )
+            SELECT
+                id as "id!: _",
+                at as "at!",
+                location as "location!: Location",
+                bought_at as "bought_at!",
+                owner as "owner!",
+                sell_price as "sell_price!: _",
+                token_used as "token_used!",
+                level as "level!: _"
+            FROM latest_lands
+            WHERE owner = $1
+            ORDER BY location
+            "#,
+            address,
+            at
+        )
+        .fetch_all(&mut *(self.db.read().await?))
+        .await
+    }
+
+    /// Gets the total amount currently staked on the lands of the address, per token.
+    ///
+    /// # Errors
+    /// Returns an error if the stakes could not be retrieved
+    pub async fn get_stake_per_token(
+        &self,
+        address: &str,
+    ) -> Result<HashMap<String, U256>, sqlx::Error> {
+        query!(
+            r#"
+            SELECT l.token_used as "token_used!", SUM(s.amount)::numeric as "total!"
+            FROM land_current l
+            JOIN land_stake_current s ON s.location = l.location
+            WHERE l.owner = $1
+            GROUP BY l.token_used
+            "#,
+            address
+        )
+        .fetch_all(&mut *(self.db.read().await?))
+        .await
+        .map(|rows| {
+            rows.into_iter()
+                .map(|row| (row.token_used, U256::from(row.total)))
+                .collect()
+        })
+    }
+
+    /// Counts the purchases, sales and nukes suffered by the address.
+    ///
+    /// # Errors
+    /// Returns an error if the events could not be counted
+    #[allow(clippy::cast_sign_loss)] // Counts are never negative
+    pub async fn get_stats(&self, address: &str) -> Result<PlayerStats, Error> {
+        let row = query!(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM event_land_bought WHERE buyer = $1) as "purchases!",
+                (SELECT COUNT(*) FROM event_land_bought WHERE seller = $1) as "sales!",
+                (SELECT COUNT(*) FROM event_land_nuked WHERE owner = $1) as "nukes!"
+            "#,
+            address
+        )
+        .fetch_one(&mut *(self.db.read().await?))
+        .await?;
+
+        Ok(PlayerStats {
+            purchases: row.purchases as u64,
+            sales: row.sales as u64,
+            nukes: row.nukes as u64,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{
+        fixtures::{bought, event, land, nuked, stake},
+        EventRepository, EventStore, LandRepository, LandStakeRepository, LandStakeStore,
+        LandStore,
+    };
+    use chrono::Utc;
+    use migrations::MIGRATOR;
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_player_lands(pool: sqlx::PgPool) -> Result<(), Error> {
+        let lands = LandRepository::new(pool.clone());
+        let stakes = LandStakeRepository::new(pool.clone());
+        let repo = Repository::new(pool);
+
+        let time1 = Utc::now().naive_utc();
+        let time2 = time1 + chrono::Duration::hours(1);
+
+        let land = |id, at, location: u64, owner, token: &str| LandModel {
+            token_used: token.to_string(),
+            ..land(EventId::new_test(0, 0, id), at, location, owner)
+        };
+        let stake = |id, at, location: u64, amount| {
+            stake(EventId::new_test(0, 0, id), at, location, amount)
+        };
+
+        // 0xa owns 1 and 2 at first, then sells 2 to 0xb
+        lands.save(land(0, time1, 1, "0xa", "0xtoken1")).await?;
+        lands.save(land(1, time1, 2, "0xa", "0xtoken2")).await?;
+        lands.save(land(2, time1, 3, "0xa", "0xtoken1")).await?;
+        lands.save(land(3, time2, 2, "0xb", "0xtoken2")).await?;
+
+        stakes.save(stake(4, time1, 1, "10")).await?;
+        stakes.save(stake(5, time2, 1, "30")).await?;
+        stakes.save(stake(6, time1, 3, "5")).await?;
+
+        let owned = repo.get_owned_lands("0xa").await?;
+        let locations: Vec<Location> = owned.iter().map(|o| o.land.location).collect();
+        assert_eq!(locations, vec![Location::new(1), Location::new(3)]);
+        assert_eq!(
+            owned[0].stake.as_ref().map(|s| s.amount),
+            Some(U256::from_str("30").unwrap())
+        );
+
+        let owned_before = repo.get_owned_lands_at("0xa", time1).await?;
+        assert_eq!(owned_before.len(), 3);
+
+        let per_token = repo.get_stake_per_token("0xa").await?;
+        assert_eq!(per_token.len(), 1);
+        assert_eq!(per_token["0xtoken1"], U256::from_str("35").unwrap());
+
+        assert!(repo.get_owned_lands("0xc").await?.is_empty());
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_player_stats(pool: sqlx::PgPool) -> Result<(), Error> {
+        let events = EventRepository::new(pool.clone());
+        let repo = Repository::new(pool);
+        let at = Utc::now().naive_utc();
+
+        let bought = |id, buyer, seller| {
+            event(
+                EventId::new_test(0, 0, id),
+                at,
+                bought(1, buyer, seller, "100"),
+            )
+        };
+
+        events.save_event(bought(0, "0xa", "0xb")).await?;
+        events.save_event(bought(1, "0xa", "0xc")).await?;
+        events.save_event(bought(2, "0xb", "0xa")).await?;
+        events
+            .save_event(event(EventId::new_test(0, 0, 3), at, nuked(1, "0xb")))
+            .await?;
+
+        assert_eq!(
+            repo.get_stats("0xa").await?,
+            PlayerStats {
+                purchases: 2,
+                sales: 1,
+                nukes: 0,
+            }
+        );
+        assert_eq!(
+            repo.get_stats("0xb").await?,
+            PlayerStats {
+                purchases: 1,
+                sales: 1,
+                nukes: 1,
+            }
+        );
+
+        Ok(())
+    }
+}
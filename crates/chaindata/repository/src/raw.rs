@@ -0,0 +1,207 @@
+use crate::Database;
+use chaindata_models::events::EventId;
+use chrono::NaiveDateTime;
+use sqlx::{query, PgConnection};
+
+/// What a raw row is decoded into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "raw_data_kind", rename_all = "lowercase")]
+pub enum RawDataKind {
+    Event,
+    Model,
+}
+
+/// A row as received from Torii.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawData {
+    pub kind: RawDataKind,
+    /// Name of the event or model, as given by Torii.
+    pub name: String,
+    pub torii_event_id: String,
+    pub at: NaiveDateTime,
+    /// The data, as JSON.
+    pub data: String,
+}
+
+/// Keeps the rows received from Torii, to rebuild the derived tables from them.
+pub struct Repository {
+    db: Database,
+}
+
+impl Repository {
+    #[must_use]
+    pub fn new(db: impl Into<Database>) -> Self {
+        Self { db: db.into() }
+    }
+
+    /// Stores raw rows, skipping the ones already stored.
+    ///
+    /// The block of each row is read from its Torii event id, so that a rollback can remove
+    /// the rows of the reorganized blocks.
+    ///
+    /// # Errors
+    /// Returns an error if the rows could not be saved.
+    pub async fn save_many(&self, rows: &[RawData]) -> Result<u64, sqlx::Error> {
+        Self::save_many_in(&mut *(self.db.writer().acquire().await?), rows).await
+    }
+
+    /// Same as [`Repository::save_many`], within an existing transaction.
+    ///
+    /// # Errors
+    /// Returns an error if the rows could not be saved.
+    pub(crate) async fn save_many_in(
+        conn: &mut PgConnection,
+        rows: &[RawData],
+    ) -> Result<u64, sqlx::Error> {
+        if rows.is_empty() {
+            return Ok(0);
+        }
+
+        let mut kinds = Vec::with_capacity(rows.len());
+        let mut names = Vec::with_capacity(rows.len());
+        let mut event_ids = Vec::with_capacity(rows.len());
+        let mut ats = Vec::with_capacity(rows.len());
+        let mut data = Vec::with_capacity(rows.len());
+        let mut blocks = Vec::with_capacity(rows.len());
+        for row in rows {
+            kinds.push(row.kind);
+            names.push(row.name.clone());
+            event_ids.push(row.torii_event_id.clone());
+            ats.push(row.at);
+            data.push(row.data.clone());
+            blocks.push(
+                EventId::parse_from_torii(&row.torii_event_id)
+                    .ok()
+                    .and_then(|id| id.block_number())
+                    .and_then(|block| i64::try_from(block).ok()),
+            );
+        }
+
+        let result = query!(
+            r#"
+            INSERT INTO raw_torii_data (kind, name, torii_event_id, at, data, block)
+            SELECT kind, name, torii_event_id, at, data::jsonb, block
+            FROM UNNEST(
+                $1::raw_data_kind[], $2::text[], $3::text[], $4::timestamp[], $5::text[],
+                $6::int8[]
+            ) AS t(kind, name, torii_event_id, at, data, block)
+            ON CONFLICT (kind, name, torii_event_id) DO NOTHING
+            "#,
+            &kinds as &[RawDataKind],
+            &names,
+            &event_ids,
+            &ats,
+            &data,
+            &blocks as &[Option<i64>]
+        )
+        .execute(conn)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Gets a page of raw rows of the given kind, in the order they were received, with the
+    /// position to pass as `after` to get the next page.
+    ///
+    /// # Errors
+    /// Returns an error if the rows could not be retrieved.
+    pub async fn get_page(
+        &self,
+        kind: RawDataKind,
+        after: i64,
+        limit: u32,
+    ) -> Result<(Vec<RawData>, i64), sqlx::Error> {
+        let rows = query!(
+            r#"
+            SELECT id, name, torii_event_id, at, data::text as "data!"
+            FROM raw_torii_data
+            WHERE kind = $1 AND id > $2
+            ORDER BY id
+            LIMIT $3
+            "#,
+            kind as RawDataKind,
+            after,
+            i64::from(limit)
+        )
+        .fetch_all(self.db.writer())
+        .await?;
+
+        let next = rows.last().map_or(after, |row| row.id);
+        let rows = rows
+            .into_iter()
+            .map(|row| RawData {
+                kind,
+                name: row.name,
+                torii_event_id: row.torii_event_id,
+                at: row.at,
+                data: row.data,
+            })
+            .collect();
+
+        Ok((rows, next))
+    }
+
+    /// Counts the stored raw rows.
+    ///
+    /// # Errors
+    /// Returns an error if the rows could not be counted.
+    #[allow(clippy::cast_sign_loss)] // Counts are never negative
+    pub async fn count(&self) -> Result<u64, sqlx::Error> {
+        let count = query!(r#"SELECT COUNT(*) as "count!" FROM raw_torii_data"#)
+            .fetch_one(self.db.writer())
+            .await?
+            .count;
+
+        Ok(count as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use migrations::MIGRATOR;
+
+    fn raw(kind: RawDataKind, name: &str, event_id: &str) -> RawData {
+        RawData {
+            kind,
+            name: name.to_string(),
+            torii_event_id: event_id.to_string(),
+            at: Utc::now().naive_utc(),
+            data: r#"{"location":1}"#.to_string(),
+        }
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_raw_data(pool: sqlx::PgPool) -> Result<(), sqlx::Error> {
+        let repo = Repository::new(pool);
+
+        let rows = vec![
+            raw(
+                RawDataKind::Event,
+                "ponzi_land-LandNukedEvent",
+                "0x1:0x0:0x0",
+            ),
+            // Both models changed in the same event
+            raw(RawDataKind::Model, "ponzi_land-Land", "0x1:0x0:0x1"),
+            raw(RawDataKind::Model, "ponzi_land-LandStake", "0x1:0x0:0x1"),
+        ];
+        assert_eq!(repo.save_many(&rows).await?, 3);
+        // Fetched again after a restart
+        assert_eq!(repo.save_many(&rows).await?, 0);
+        assert_eq!(repo.count().await?, 3);
+
+        let (page, next) = repo.get_page(RawDataKind::Model, 0, 1).await?;
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].name, "ponzi_land-Land");
+        assert_eq!(page[0].data, r#"{"location": 1}"#);
+
+        let (page, next) = repo.get_page(RawDataKind::Model, next, 10).await?;
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].name, "ponzi_land-LandStake");
+        let (page, _) = repo.get_page(RawDataKind::Model, next, 10).await?;
+        assert!(page.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,297 @@
+use std::time::Duration;
+
+use crate::{
+    events::base::EventDataRepository, gg_xyz_outbox::Repository as GgXyzOutboxRepository,
+    Database, Error,
+};
+use chaindata_models::events::{Event, EventId, EventType};
+use chrono::NaiveDateTime;
+use sqlx::{postgres::PgPoolOptions, Executor};
+
+/// Schema in which the derived tables are rebuilt before being swapped in.
+const SHADOW_SCHEMA: &str = "reindex";
+
+/// Tables rebuilt from the raw Torii data.
+const DERIVED_TABLES: &[&str] = &[
+    "event",
+    "event_auction_finished",
+    "event_address_authorized",
+    "event_address_removed",
+    "event_land_bought",
+    "event_new_auction",
+    "event_land_nuked",
+    "event_land_transfer",
+    "event_verifier_updated",
+    "land",
+    "land_stake",
+    "land_current",
+    "land_stake_current",
+    "land_tenure",
+    "market_aggregate",
+    "tax_ledger",
+];
+
+/// Tables decoded from the raw rows, which a reindex only shrinks when forced to.
+const DECODED_TABLES: &[&str] = &["event", "land", "land_stake"];
+
+/// Rebuilds the derived tables in a shadow schema, and swaps them in once complete.
+pub struct Repository {
+    db: Database,
+    gg_xyz_outbox: bool,
+}
+
+impl Repository {
+    #[must_use]
+    pub fn new(db: impl Into<Database>) -> Self {
+        Self {
+            db: db.into(),
+            gg_xyz_outbox: false,
+        }
+    }
+
+    /// When enabled, the gg.xyz actions of the events that were not stored before the reindex
+    /// are queued in the outbox, in the same transaction as the swap.
+    #[must_use]
+    pub fn with_gg_xyz_outbox(mut self, enabled: bool) -> Self {
+        self.gg_xyz_outbox = enabled;
+        self
+    }
+
+    /// Creates empty copies of the derived tables in the shadow schema, replacing any left by an
+    /// interrupted reindex, and returns a database writing into them.
+    ///
+    /// The repositories built on the returned database use the shadow tables, as they come first
+    /// in its search path, and the public schema for everything else.
+    ///
+    /// # Errors
+    /// Returns an error if the shadow tables could not be created.
+    pub async fn create_shadow(&self) -> Result<Database, sqlx::Error> {
+        let mut tx = self.db.writer().begin().await?;
+
+        tx.execute(format!("DROP SCHEMA IF EXISTS {SHADOW_SCHEMA} CASCADE").as_str())
+            .await?;
+        tx.execute(format!("CREATE SCHEMA {SHADOW_SCHEMA}").as_str())
+            .await?;
+        for table in DERIVED_TABLES {
+            tx.execute(
+                format!("CREATE TABLE {SHADOW_SCHEMA}.{table} (LIKE public.{table} INCLUDING ALL)")
+                    .as_str(),
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        let options = (*self.db.writer().connect_options())
+            .clone()
+            .options([("search_path", format!("{SHADOW_SCHEMA},public"))]);
+        let pool = PgPoolOptions::new()
+            .max_connections(4)
+            .acquire_timeout(Duration::from_secs(30))
+            .connect_with(options)
+            .await?;
+
+        Ok(Database::new(pool))
+    }
+
+    /// Replaces the content of the derived tables with the one of the shadow tables, in a single
+    /// transaction, then drops the shadow schema.
+    ///
+    /// Events are updated in place rather than replaced, so that what references them (outbox,
+    /// webhook deliveries) is only removed along with the events that disappeared. The events
+    /// that were not stored before are handled as if just received: their gg.xyz actions are
+    /// queued when enabled, and their sequence numbers, given while filling the shadow tables,
+    /// come after every cursor, so the consumers handle them on their next start.
+    ///
+    /// Returns the ids of the events that were not stored before.
+    ///
+    /// # Errors
+    /// Returns [`Error::FewerRows`] if the shadow tables hold fewer events, lands or land stakes
+    /// than the ones in use, which happens when the raw data misses rows, unless
+    /// `allow_fewer_rows` is set.
+    /// Returns an error if the tables could not be swapped. In both cases they are left untouched.
+    #[allow(clippy::cast_sign_loss)] // Counts are never negative
+    #[allow(clippy::mutable_key_type)] // The cached string representation is not part of the hash
+    pub async fn swap(&self, allow_fewer_rows: bool) -> Result<Vec<EventId>, Error> {
+        let mut tx = self.db.writer().begin().await?;
+
+        for table in DECODED_TABLES {
+            let (reindexed, stored): (i64, i64) = sqlx::query_as(
+                format!(
+                    "SELECT (SELECT COUNT(*) FROM {SHADOW_SCHEMA}.{table}), \
+                     (SELECT COUNT(*) FROM public.{table})"
+                )
+                .as_str(),
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            if reindexed < stored && !allow_fewer_rows {
+                return Err(Error::FewerRows {
+                    table,
+                    reindexed: reindexed as u64,
+                    stored: stored as u64,
+                });
+            }
+        }
+
+        let mut new_events = Vec::new();
+        for table in DERIVED_TABLES {
+            if *table == "event" {
+                tx.execute(
+                    format!(
+                        "DELETE FROM public.event e WHERE NOT EXISTS \
+                         (SELECT 1 FROM {SHADOW_SCHEMA}.event r WHERE r.id = e.id)"
+                    )
+                    .as_str(),
+                )
+                .await?;
+                // Rows that did not exist have no previous version, hence no `xmax`
+                let rows: Vec<(String, NaiveDateTime, EventType, bool)> = sqlx::query_as(
+                    format!(
+                        "INSERT INTO public.event SELECT * FROM {SHADOW_SCHEMA}.event \
+                         ON CONFLICT (id) DO UPDATE \
+                         SET at = EXCLUDED.at, event_type = EXCLUDED.event_type \
+                         RETURNING id, at, event_type, (xmax = 0)"
+                    )
+                    .as_str(),
+                )
+                .fetch_all(&mut *tx)
+                .await?;
+                for (id, at, event_type, inserted) in rows {
+                    if inserted {
+                        new_events.push(Event {
+                            id: id.parse()?,
+                            at,
+                            event_type,
+                        });
+                    }
+                }
+            } else {
+                tx.execute(format!("TRUNCATE public.{table}").as_str())
+                    .await?;
+                tx.execute(
+                    format!("INSERT INTO public.{table} SELECT * FROM {SHADOW_SCHEMA}.{table}")
+                        .as_str(),
+                )
+                .await?;
+            }
+        }
+        new_events.sort_by(|a, b| a.id.cmp(&b.id));
+
+        if self.gg_xyz_outbox && !new_events.is_empty() {
+            let mut data = EventDataRepository::get_all(&mut tx, &new_events).await?;
+            let data: Vec<_> = new_events
+                .iter()
+                .filter_map(|event| data.remove(&event.id))
+                .collect();
+            GgXyzOutboxRepository::enqueue_in(&mut tx, &data).await?;
+        }
+
+        tx.execute(format!("DROP SCHEMA {SHADOW_SCHEMA} CASCADE").as_str())
+            .await?;
+
+        tx.commit().await?;
+        self.db.record_write().await;
+
+        Ok(new_events.into_iter().map(|event| event.id).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        fixtures::{event, land, nuked},
+        CursorRepository, CursorStore, EventFilter, EventRepository, EventStore, LandRepository,
+        LandStore, Pagination,
+    };
+    use chaindata_models::events::{EventDataModel, EventId};
+    use chrono::Utc;
+    use migrations::MIGRATOR;
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_swap(pool: sqlx::PgPool) -> Result<(), crate::Error> {
+        let events = EventRepository::new(pool.clone());
+        let cursors = CursorRepository::new(pool.clone());
+        let lands = LandRepository::new(pool.clone());
+        let repo = Repository::new(pool);
+        let now = Utc::now().naive_utc();
+        let nuked = |block, owner| event(EventId::new_test(block, 0, 0), now, nuked(1, owner));
+
+        // Decoded with a buggy decoder, and an event that should not exist
+        let stored = events
+            .save_events_with_raw(vec![nuked(1, "0xwrong"), nuked(2, "0x2")], &[])
+            .await?;
+        cursors.save_cursor("consumer", stored[1].seq).await?;
+
+        // Run twice, as if interrupted after the shadow tables were filled
+        for _ in 0..2 {
+            let shadow = repo.create_shadow().await?;
+            EventRepository::new(shadow.clone())
+                .save_events(vec![nuked(1, "0x1")])
+                .await?;
+            shadow.writer().close().await;
+
+            // Not visible before the swap
+            let all = events
+                .get_events(&EventFilter::default(), &Pagination::default())
+                .await?;
+            assert_eq!(all.len(), 2);
+        }
+
+        // Losing events must be forced
+        assert!(matches!(
+            repo.swap(false).await,
+            Err(crate::Error::FewerRows {
+                table: "event",
+                reindexed: 1,
+                stored: 2
+            })
+        ));
+        assert!(repo.swap(true).await?.is_empty());
+
+        let all = events
+            .get_events(&EventFilter::default(), &Pagination::default())
+            .await?;
+        assert_eq!(all.len(), 1);
+        let EventDataModel::LandNuked(data) = &all[0].data else {
+            panic!("the event is a land nuked event");
+        };
+        assert_eq!(data.owner, "0x1");
+
+        // Events that now decode are handled again by the consumers
+        let shadow = repo.create_shadow().await?;
+        EventRepository::new(shadow.clone())
+            .save_events(vec![nuked(1, "0x1"), nuked(2, "0x2")])
+            .await?;
+        shadow.writer().close().await;
+
+        assert_eq!(repo.swap(false).await?, vec![EventId::new_test(2, 0, 0)]);
+        let cursor = cursors.get_cursor("consumer").await?.unwrap_or_default();
+        let pending = events.get_events_after_seq(&[], cursor, 10).await?;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].event.id, EventId::new_test(2, 0, 0));
+
+        // Losing land versions must be forced too
+        lands
+            .save(land(EventId::new_test(1, 0, 1), now, 1, "0x1"))
+            .await?;
+        let shadow = repo.create_shadow().await?;
+        EventRepository::new(shadow.clone())
+            .save_events(vec![nuked(1, "0x1"), nuked(2, "0x2")])
+            .await?;
+        shadow.writer().close().await;
+
+        assert!(matches!(
+            repo.swap(false).await,
+            Err(crate::Error::FewerRows {
+                table: "land",
+                reindexed: 0,
+                stored: 1
+            })
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,290 @@
+use crate::{
+    land_tenure::Repository as LandTenureRepository, market::Repository as MarketRepository,
+    Database, Error,
+};
+use chaindata_models::{events::EventId, shared::Location};
+use sqlx::{query, PgConnection};
+
+/// A block we stored events from, with its hash at the time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackedBlock {
+    pub number: u64,
+    /// The hash of the block, formatted as `{:#x}`.
+    pub hash: String,
+}
+
+/// What was removed by a rollback.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Rollback {
+    pub events: u64,
+    pub lands: u64,
+    pub land_stakes: u64,
+}
+
+/// Tracks the hashes of the recent blocks, and rolls back what was stored from reorganized ones.
+pub struct Repository {
+    db: Database,
+}
+
+impl Repository {
+    #[must_use]
+    pub fn new(db: impl Into<Database>) -> Self {
+        Self { db: db.into() }
+    }
+
+    /// Gets the tracked blocks, most recent first.
+    ///
+    /// # Errors
+    /// Returns an error if the blocks could not be retrieved.
+    #[allow(clippy::cast_sign_loss)] // Block numbers are never negative
+    pub async fn get_tracked_blocks(&self) -> Result<Vec<TrackedBlock>, sqlx::Error> {
+        let rows = query!(
+            r#"
+            SELECT number, hash FROM block ORDER BY number DESC
+            "#
+        )
+        // Compared to the chain right after ingestion, so it must not lag behind
+        .fetch_all(self.db.writer())
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| TrackedBlock {
+                number: row.number as u64,
+                hash: row.hash,
+            })
+            .collect())
+    }
+
+    /// Starts tracking blocks. Blocks already tracked keep their first known hash.
+    ///
+    /// # Errors
+    /// Returns an error if the blocks could not be saved.
+    #[allow(clippy::cast_possible_wrap)] // Block numbers fit in an i64
+    pub async fn track_blocks(&self, blocks: &[TrackedBlock]) -> Result<(), sqlx::Error> {
+        let numbers: Vec<i64> = blocks.iter().map(|block| block.number as i64).collect();
+        let hashes: Vec<String> = blocks.iter().map(|block| block.hash.clone()).collect();
+
+        query!(
+            r#"
+            INSERT INTO block (number, hash)
+            SELECT * FROM UNNEST($1::int8[], $2::text[])
+            ON CONFLICT (number) DO NOTHING
+            "#,
+            &numbers,
+            &hashes
+        )
+        .execute(self.db.writer())
+        .await?;
+
+        Ok(())
+    }
+
+    /// Stops tracking the blocks older than the given one.
+    ///
+    /// # Errors
+    /// Returns an error if the blocks could not be deleted.
+    #[allow(clippy::cast_possible_wrap)] // Block numbers fit in an i64
+    pub async fn prune_blocks(&self, below: u64) -> Result<u64, sqlx::Error> {
+        Ok(query!(
+            r#"
+            DELETE FROM block WHERE number < $1
+            "#,
+            below as i64
+        )
+        .execute(self.db.writer())
+        .await?
+        .rows_affected())
+    }
+
+    /// Removes everything stored from the given block onwards, in a single transaction:
+    /// events, land and stake versions, the raw rows they were decoded from, and what is derived
+    /// from them (current state, tenures, tax ledger, market aggregates and tracked blocks).
+    ///
+    /// The listeners then naturally fetch the new version of the chain, as they resume from
+    /// the last stored data, and the consumers handle it as it gets new sequence numbers.
+    ///
+    /// # Errors
+    /// Returns an error if the rollback failed, in which case nothing was removed.
+    #[allow(clippy::cast_possible_wrap)] // Block numbers fit in an i64
+    pub async fn rollback_from(&self, block: u64, auction_token: &str) -> Result<Rollback, Error> {
+        let fork = EventId::first_of_block(block);
+        let mut tx = self.db.writer().begin().await?;
+
+        let range = query!(
+            r#"
+            SELECT MIN(at) as from_time, MAX(at) as to_time FROM event WHERE id >= $1
+            "#,
+            fork.clone() as EventId
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let events = query!(
+            r#"
+            WITH
+                auction_finished AS (DELETE FROM event_auction_finished WHERE id >= $1),
+                address_authorized AS (DELETE FROM event_address_authorized WHERE id >= $1),
+                address_removed AS (DELETE FROM event_address_removed WHERE id >= $1),
+                land_bought AS (DELETE FROM event_land_bought WHERE id >= $1),
+                new_auction AS (DELETE FROM event_new_auction WHERE id >= $1),
+                land_nuked AS (DELETE FROM event_land_nuked WHERE id >= $1),
+                land_transfer AS (DELETE FROM event_land_transfer WHERE id >= $1),
+                verifier_updated AS (DELETE FROM event_verifier_updated WHERE id >= $1)
+            DELETE FROM event WHERE id >= $1
+            "#,
+            fork.clone() as EventId
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        let lands = query!(
+            r#"
+            DELETE FROM land WHERE id >= $1
+            "#,
+            fork.clone() as EventId
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        let land_stakes = query!(
+            r#"
+            DELETE FROM land_stake WHERE id >= $1
+            "#,
+            fork.clone() as EventId
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        Self::rollback_current_state(&mut tx, &fork).await?;
+        Self::rollback_tenures(&mut tx, &fork).await?;
+
+        // Owners are read from earlier versions, so the entries kept are still valid
+        query!(
+            r#"
+            DELETE FROM tax_ledger WHERE id >= $1
+            "#,
+            fork.clone() as EventId
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if let (Some(from), Some(to)) = (range.from_time, range.to_time) {
+            MarketRepository::refresh_in(&mut tx, from, to, auction_token).await?;
+        }
+
+        // Otherwise a reindex would bring the rolled back events back
+        query!(
+            r#"
+            DELETE FROM raw_torii_data WHERE block >= $1
+            "#,
+            block as i64
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        query!(
+            r#"
+            DELETE FROM block WHERE number >= $1
+            "#,
+            block as i64
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        self.db.record_write().await;
+
+        Ok(Rollback {
+            events,
+            lands,
+            land_stakes,
+        })
+    }
+
+    /// Resets the current state of the locations whose current version is rolled back
+    /// to their last remaining version.
+    async fn rollback_current_state(conn: &mut PgConnection, fork: &EventId) -> Result<(), Error> {
+        let locations: Vec<Location> = query!(
+            r#"
+            DELETE FROM land_current WHERE id >= $1 RETURNING location as "location!: Location"
+            "#,
+            fork.clone() as EventId
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|row| row.location)
+        .collect();
+
+        query!(
+            r#"
+            INSERT INTO land_current (
+                location, id, at, bought_at, owner, sell_price, token_used, level
+            )
+            SELECT DISTINCT ON (location)
+                location, id, at, bought_at, owner, sell_price, token_used, level
+            FROM land
+            WHERE location = ANY($1::int4[])
+            ORDER BY location, id DESC
+            "#,
+            &locations as &[Location]
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        let locations: Vec<Location> = query!(
+            r#"
+            DELETE FROM land_stake_current WHERE id >= $1
+            RETURNING location as "location!: Location"
+            "#,
+            fork.clone() as EventId
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|row| row.location)
+        .collect();
+
+        query!(
+            r#"
+            INSERT INTO land_stake_current (location, id, at, last_pay_time, amount)
+            SELECT DISTINCT ON (location)
+                location, id, at, last_pay_time, amount
+            FROM land_stake
+            WHERE location = ANY($1::int4[])
+            ORDER BY location, id DESC
+            "#,
+            &locations as &[Location]
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Rebuilds the tenures started or ended by a rolled back event from what remains.
+    async fn rollback_tenures(conn: &mut PgConnection, fork: &EventId) -> Result<(), Error> {
+        let locations: Vec<Location> = query!(
+            r#"
+            DELETE FROM land_tenure WHERE id >= $1 OR ended_by >= $1
+            RETURNING location as "location!: Location"
+            "#,
+            fork.clone() as EventId
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|row| row.location)
+        .collect();
+
+        if !locations.is_empty() {
+            LandTenureRepository::refresh_in(conn, Some(&locations)).await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,473 @@
+use crate::{Database, Error, TimeRange};
+use chaindata_models::{
+    events::EventId,
+    shared::{Location, U256},
+};
+use sqlx::{query, query_as, PgConnection};
+
+/// Taxes paid and received in a token, by a player or a land.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaxTotals {
+    pub token: String,
+    pub paid: U256,
+    pub received: U256,
+    /// Number of transfers counted in either direction.
+    pub transfers: i64,
+    /// Received minus paid in the main token, each transfer valued at the price of the token
+    /// when it happened.
+    pub net_in_main_token: f64,
+    /// Number of transfers left out of the net amount, as no price of the token was stored
+    /// before them.
+    pub unpriced_transfers: i64,
+}
+
+/// Taxes paid by all the lands in a token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenTaxes {
+    pub token: String,
+    pub amount: U256,
+    pub transfers: i64,
+}
+
+/// Ledger of the taxes paid between neighbouring lands, derived from the transfer events.
+///
+/// Each transfer is recorded once, with the owners of both lands read from the land versions
+/// active before its transaction. Recording a transfer again gives the same row.
+pub struct Repository {
+    db: Database,
+}
+
+impl Repository {
+    #[must_use]
+    pub fn new(db: impl Into<Database>) -> Self {
+        Self { db: db.into() }
+    }
+
+    /// Records the given transfer events in the ledger.
+    ///
+    /// # Errors
+    /// Returns an error if the entries could not be computed or saved.
+    pub async fn record(&self, ids: &[EventId]) -> Result<(), Error> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        let mut tx = self.db.writer().begin().await?;
+        Self::record_in(&mut tx, Some(ids)).await?;
+        tx.commit().await?;
+        self.db.record_write().await;
+
+        Ok(())
+    }
+
+    /// Records the transfer events missing from the ledger, and resolves the owners still unknown.
+    ///
+    /// # Errors
+    /// Returns an error if the entries could not be computed or saved.
+    pub async fn record_missing(&self) -> Result<(), Error> {
+        let mut tx = self.db.writer().begin().await?;
+        let ids: Vec<EventId> = query!(
+            r#"
+            SELECT t.id as "id: EventId"
+            FROM event_land_transfer t
+            LEFT JOIN tax_ledger l ON l.id = t.id
+            WHERE l.id IS NULL OR l.payer_owner IS NULL OR l.recipient_owner IS NULL
+            "#
+        )
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(|row| row.id)
+        .collect();
+
+        Self::record_in(&mut tx, Some(&ids)).await?;
+        tx.commit().await?;
+        self.db.record_write().await;
+
+        Ok(())
+    }
+
+    /// Resolves again the owners of every entry of the given locations, as land versions can be
+    /// stored after the events, and a late one may change who owned a land when a transfer
+    /// happened.
+    ///
+    /// # Errors
+    /// Returns an error if the entries could not be computed or saved.
+    pub async fn resolve_owners(&self, locations: &[Location]) -> Result<(), Error> {
+        let ids: Vec<EventId> = query!(
+            r#"
+            SELECT id as "id: EventId"
+            FROM tax_ledger
+            WHERE payer_location = ANY($1::int4[]) OR recipient_location = ANY($1::int4[])
+            "#,
+            locations as &[Location]
+        )
+        // Compared to what was just written
+        .fetch_all(self.db.writer())
+        .await?
+        .into_iter()
+        .map(|row| row.id)
+        .collect();
+
+        self.record(&ids).await
+    }
+
+    /// Same as [`Repository::record`], within an existing transaction, for every transfer if
+    /// none are given.
+    ///
+    /// # Errors
+    /// Returns an error if the entries could not be computed or saved.
+    pub(crate) async fn record_in(
+        conn: &mut PgConnection,
+        ids: Option<&[EventId]>,
+    ) -> Result<(), Error> {
+        let ids: Option<Vec<String>> = ids.map(|ids| ids.iter().map(EventId::as_string).collect());
+
+        query!(
+            r#"
+            INSERT INTO tax_ledger (
+                id, at, token, amount, payer_location, payer_owner,
+                recipient_location, recipient_owner
+            )
+            SELECT
+                t.id,
+                e.at,
+                t.token_address,
+                t.amount,
+                t.from_location,
+                (
+                    SELECT l.owner FROM land l
+                    WHERE l.location = t.from_location AND l.id < split_part(t.id, ':e_', 1)
+                    ORDER BY l.id DESC
+                    LIMIT 1
+                ),
+                t.to_location,
+                (
+                    SELECT l.owner FROM land l
+                    WHERE l.location = t.to_location AND l.id < split_part(t.id, ':e_', 1)
+                    ORDER BY l.id DESC
+                    LIMIT 1
+                )
+            FROM event_land_transfer t
+            JOIN event e ON e.id = t.id
+            WHERE $1::text[] IS NULL OR t.id = ANY($1)
+            ON CONFLICT (id) DO UPDATE SET
+                payer_owner = EXCLUDED.payer_owner,
+                recipient_owner = EXCLUDED.recipient_owner
+            "#,
+            ids.as_deref()
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Gets the taxes paid and received by a player over a time range, per token.
+    ///
+    /// Transfers are valued in `main_token` with the prices stored by the
+    /// [`crate::TokenPriceRepository`].
+    ///
+    /// # Errors
+    /// Returns an error if the totals could not be retrieved.
+    pub async fn get_totals_by_owner(
+        &self,
+        owner: &str,
+        main_token: &str,
+        range: TimeRange,
+    ) -> Result<Vec<TaxTotals>, sqlx::Error> {
+        query_as!(
+            TaxTotals,
+            r#"
+            SELECT
+                l.token,
+                COALESCE(SUM(l.amount) FILTER (WHERE l.payer_owner = $1), 0) as "paid!: U256",
+                COALESCE(SUM(l.amount) FILTER (WHERE l.recipient_owner = $1), 0)
+                    as "received!: U256",
+                COUNT(*) as "transfers!",
+                COALESCE(
+                    SUM(
+                        CASE WHEN l.recipient_owner = $1 THEN v.value ELSE 0 END
+                        - CASE WHEN l.payer_owner = $1 THEN v.value ELSE 0 END
+                    ),
+                    0
+                ) as "net_in_main_token!",
+                COUNT(*) FILTER (WHERE v.value IS NULL) as "unpriced_transfers!"
+            FROM tax_ledger l
+            CROSS JOIN LATERAL (
+                SELECT main_token_value(l.token, l.amount, l.at, $2) as value
+            ) v
+            WHERE (l.payer_owner = $1 OR l.recipient_owner = $1)
+                AND ($3::timestamp IS NULL OR l.at >= $3)
+                AND ($4::timestamp IS NULL OR l.at < $4)
+            GROUP BY l.token
+            ORDER BY l.token
+            "#,
+            owner,
+            main_token,
+            range.from,
+            range.to
+        )
+        .fetch_all(&mut *(self.db.read().await?))
+        .await
+    }
+
+    /// Gets the taxes paid and received by a land over a time range, per token, whoever owned it.
+    ///
+    /// Transfers are valued in `main_token` with the prices stored by the
+    /// [`crate::TokenPriceRepository`].
+    ///
+    /// # Errors
+    /// Returns an error if the totals could not be retrieved.
+    pub async fn get_totals_by_location(
+        &self,
+        location: Location,
+        main_token: &str,
+        range: TimeRange,
+    ) -> Result<Vec<TaxTotals>, sqlx::Error> {
+        query_as!(
+            TaxTotals,
+            r#"
+            SELECT
+                l.token,
+                COALESCE(SUM(l.amount) FILTER (WHERE l.payer_location = $1), 0)
+                    as "paid!: U256",
+                COALESCE(SUM(l.amount) FILTER (WHERE l.recipient_location = $1), 0)
+                    as "received!: U256",
+                COUNT(*) as "transfers!",
+                COALESCE(
+                    SUM(
+                        CASE WHEN l.recipient_location = $1 THEN v.value ELSE 0 END
+                        - CASE WHEN l.payer_location = $1 THEN v.value ELSE 0 END
+                    ),
+                    0
+                ) as "net_in_main_token!",
+                COUNT(*) FILTER (WHERE v.value IS NULL) as "unpriced_transfers!"
+            FROM tax_ledger l
+            CROSS JOIN LATERAL (
+                SELECT main_token_value(l.token, l.amount, l.at, $2) as value
+            ) v
+            WHERE (l.payer_location = $1 OR l.recipient_location = $1)
+                AND ($3::timestamp IS NULL OR l.at >= $3)
+                AND ($4::timestamp IS NULL OR l.at < $4)
+            GROUP BY l.token
+            ORDER BY l.token
+            "#,
+            location as Location,
+            main_token,
+            range.from,
+            range.to
+        )
+        .fetch_all(&mut *(self.db.read().await?))
+        .await
+    }
+
+    /// Gets the taxes paid by all the lands over a time range, per token.
+    ///
+    /// # Errors
+    /// Returns an error if the totals could not be retrieved.
+    pub async fn get_totals_by_token(
+        &self,
+        range: TimeRange,
+    ) -> Result<Vec<TokenTaxes>, sqlx::Error> {
+        query_as!(
+            TokenTaxes,
+            r#"
+            SELECT
+                token,
+                SUM(amount) as "amount!: U256",
+                COUNT(*) as "transfers!"
+            FROM tax_ledger
+            WHERE ($1::timestamp IS NULL OR at >= $1)
+                AND ($2::timestamp IS NULL OR at < $2)
+            GROUP BY token
+            ORDER BY token
+            "#,
+            range.from,
+            range.to
+        )
+        .fetch_all(&mut *(self.db.read().await?))
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{
+        fixtures::land, EventRepository, EventStore, LandRepository, LandStore, TokenPrice,
+        TokenPriceRepository,
+    };
+    use chaindata_models::events::{actions::LandTransferEventModel, EventDataModel, FetchedEvent};
+    use chrono::{Duration, NaiveDateTime, SubsecRound, Utc};
+    use migrations::MIGRATOR;
+
+    fn transfer(id: EventId, at: NaiveDateTime, from: u64, to: u64, amount: &str) -> FetchedEvent {
+        FetchedEvent {
+            id: id.clone(),
+            at,
+            data: EventDataModel::LandTransfer(LandTransferEventModel {
+                id: Some(id),
+                from_location: from.into(),
+                to_location: to.into(),
+                token_address: "0x1".to_string(),
+                amount: U256::from_str(amount).unwrap(),
+            }),
+        }
+    }
+
+    fn price(at: NaiveDateTime, ratio: f64) -> TokenPrice {
+        TokenPrice {
+            token: "0x1".to_string(),
+            at,
+            ratio,
+            pool_token0: "0x1".to_string(),
+            pool_token1: "0xmain".to_string(),
+            pool_fee: U256::from_str("0").unwrap(),
+            pool_tick_spacing: 1000,
+            pool_extension: "0x0".to_string(),
+        }
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_tax_ledger(pool: sqlx::PgPool) {
+        let repo = Repository::new(pool.clone());
+        let events = EventRepository::new(pool.clone());
+        let lands = LandRepository::new(pool.clone());
+        let prices = TokenPriceRepository::new(pool);
+        let at = Utc::now().naive_utc().trunc_subsecs(6);
+
+        lands
+            .save_many(&[
+                land(EventId::new_test(1, 0, 0), at, 1, "0xa"),
+                land(EventId::new_test(1, 1, 0), at, 2, "0xb"),
+                // Bought in the transaction of the second transfer, which the previous owner paid
+                land(EventId::new_test(3, 0, 1), at, 1, "0xc"),
+            ])
+            .await
+            .unwrap();
+        events
+            .save_events(vec![
+                transfer(EventId::new_test(2, 0, 0), at, 1, 2, "10"),
+                transfer(
+                    EventId::new_test(3, 0, 0),
+                    at + Duration::hours(1),
+                    1,
+                    2,
+                    "20",
+                ),
+                transfer(
+                    EventId::new_test(4, 0, 0),
+                    at + Duration::hours(2),
+                    2,
+                    1,
+                    "5",
+                ),
+                // Its payer has no version stored yet
+                transfer(
+                    EventId::new_test(5, 0, 0),
+                    at + Duration::hours(3),
+                    3,
+                    2,
+                    "7",
+                ),
+            ])
+            .await
+            .unwrap();
+        repo.record_missing().await.unwrap();
+
+        let totals = repo
+            .get_totals_by_owner("0xa", "0x1", TimeRange::default())
+            .await
+            .unwrap();
+        assert_eq!(totals.len(), 1);
+        assert_eq!(totals[0].paid, U256::from_str("30").unwrap());
+        assert_eq!(totals[0].received, U256::from_str("0").unwrap());
+        assert_eq!(totals[0].transfers, 2);
+        assert!((totals[0].net_in_main_token + 30.).abs() < f64::EPSILON);
+        assert_eq!(totals[0].unpriced_transfers, 0);
+
+        // Each transfer is valued at the last price stored before it
+        prices
+            .save(&[
+                price(at + Duration::minutes(30), 2.),
+                price(at + Duration::minutes(90), 4.),
+            ])
+            .await
+            .unwrap();
+        let totals = repo
+            .get_totals_by_owner("0xa", "0xmain", TimeRange::default())
+            .await
+            .unwrap();
+        assert!((totals[0].net_in_main_token + 10.).abs() < f64::EPSILON);
+        assert_eq!(totals[0].unpriced_transfers, 1);
+        let totals = repo
+            .get_totals_by_location(1.into(), "0xmain", TimeRange::default())
+            .await
+            .unwrap();
+        assert!((totals[0].net_in_main_token + 10. - 5. / 4.).abs() < f64::EPSILON);
+
+        let totals = repo
+            .get_totals_by_owner("0xc", "0x1", TimeRange::default())
+            .await
+            .unwrap();
+        assert_eq!(totals[0].paid, U256::from_str("0").unwrap());
+        assert_eq!(totals[0].received, U256::from_str("5").unwrap());
+
+        let range = TimeRange::new(Some(at + Duration::minutes(30)), None);
+        let totals = repo.get_totals_by_owner("0xb", "0x1", range).await.unwrap();
+        assert_eq!(totals[0].paid, U256::from_str("5").unwrap());
+        assert_eq!(totals[0].received, U256::from_str("27").unwrap());
+        assert_eq!(totals[0].transfers, 3);
+
+        let totals = repo
+            .get_totals_by_location(1.into(), "0x1", TimeRange::default())
+            .await
+            .unwrap();
+        assert_eq!(totals[0].paid, U256::from_str("30").unwrap());
+        assert_eq!(totals[0].received, U256::from_str("5").unwrap());
+
+        let by_token = repo
+            .get_totals_by_token(TimeRange::default())
+            .await
+            .unwrap();
+        assert_eq!(by_token[0].amount, U256::from_str("42").unwrap());
+        assert_eq!(by_token[0].transfers, 4);
+
+        // The owner is resolved once the version is stored
+        lands
+            .save_many(&[land(EventId::new_test(1, 2, 0), at, 3, "0xd")])
+            .await
+            .unwrap();
+        repo.resolve_owners(&[3.into()]).await.unwrap();
+        let totals = repo
+            .get_totals_by_owner("0xd", "0x1", TimeRange::default())
+            .await
+            .unwrap();
+        assert_eq!(totals[0].paid, U256::from_str("7").unwrap());
+
+        // A late version changes the owner of the land at the time of the transfers after it
+        lands
+            .save_many(&[land(EventId::new_test(3, 5, 0), at, 1, "0xe")])
+            .await
+            .unwrap();
+        repo.resolve_owners(&[1.into()]).await.unwrap();
+        let totals = repo
+            .get_totals_by_owner("0xe", "0x1", TimeRange::default())
+            .await
+            .unwrap();
+        assert_eq!(totals[0].received, U256::from_str("5").unwrap());
+        let totals = repo
+            .get_totals_by_owner("0xc", "0x1", TimeRange::default())
+            .await
+            .unwrap();
+        assert!(totals.is_empty());
+        let totals = repo
+            .get_totals_by_owner("0xa", "0x1", TimeRange::default())
+            .await
+            .unwrap();
+        assert_eq!(totals[0].paid, U256::from_str("30").unwrap());
+    }
+}
@@ -0,0 +1,288 @@
+use crate::{Database, Error, TimeRange};
+use chaindata_models::{
+    models::{TokenBalanceModel, TokenTransferModel},
+    shared::U256,
+};
+use chrono::NaiveDateTime;
+use sqlx::{query, query_as};
+
+/// An account holding a token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenHolder {
+    pub account: String,
+    pub balance: U256,
+}
+
+/// What an account received from a sender in a token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceivedTotal {
+    pub account: String,
+    pub token: String,
+    pub amount: U256,
+    pub transfers: i64,
+}
+
+/// ERC20 balances and transfers, mirrored from torii.
+pub struct Repository {
+    db: Database,
+}
+
+impl Repository {
+    #[must_use]
+    pub fn new(db: impl Into<Database>) -> Self {
+        Self { db: db.into() }
+    }
+
+    /// Saves the given balances, replacing the ones already stored for the same accounts.
+    ///
+    /// Returns the number of balances written.
+    ///
+    /// # Errors
+    /// Returns an error if the balances could not be saved.
+    pub async fn save_balances(&self, balances: &[TokenBalanceModel]) -> Result<u64, Error> {
+        if balances.is_empty() {
+            return Ok(0);
+        }
+
+        let tokens: Vec<String> = balances.iter().map(|b| b.token.clone()).collect();
+        let accounts: Vec<String> = balances.iter().map(|b| b.account.clone()).collect();
+        let amounts: Vec<U256> = balances.iter().map(|b| b.balance).collect();
+
+        let written = query!(
+            r#"
+            INSERT INTO token_balance (token, account, balance)
+            -- A balance can only be written once per statement
+            SELECT DISTINCT ON (token, account) token, account, balance
+            FROM UNNEST($1::text[], $2::text[], $3::numeric[]) as b(token, account, balance)
+            ON CONFLICT (token, account) DO UPDATE SET balance = EXCLUDED.balance
+            "#,
+            &tokens,
+            &accounts,
+            &amounts as &[U256]
+        )
+        .execute(self.db.writer())
+        .await?
+        .rows_affected();
+        self.db.record_write().await;
+
+        Ok(written)
+    }
+
+    /// Saves the given transfers, ignoring the ones already stored.
+    ///
+    /// Returns the number of transfers inserted.
+    ///
+    /// # Errors
+    /// Returns an error if the transfers could not be saved.
+    pub async fn save_transfers(&self, transfers: &[TokenTransferModel]) -> Result<u64, Error> {
+        if transfers.is_empty() {
+            return Ok(0);
+        }
+
+        let ids: Vec<String> = transfers.iter().map(|t| t.id.clone()).collect();
+        let ats: Vec<NaiveDateTime> = transfers.iter().map(|t| t.at).collect();
+        let tokens: Vec<String> = transfers.iter().map(|t| t.token.clone()).collect();
+        let froms: Vec<String> = transfers.iter().map(|t| t.from_address.clone()).collect();
+        let tos: Vec<String> = transfers.iter().map(|t| t.to_address.clone()).collect();
+        let amounts: Vec<U256> = transfers.iter().map(|t| t.amount).collect();
+
+        let inserted = query!(
+            r#"
+            INSERT INTO token_transfer (id, at, token, from_address, to_address, amount)
+            SELECT * FROM UNNEST(
+                $1::text[], $2::timestamp[], $3::text[], $4::text[], $5::text[], $6::numeric[]
+            )
+            ON CONFLICT (id) DO NOTHING
+            "#,
+            &ids,
+            &ats,
+            &tokens,
+            &froms,
+            &tos,
+            &amounts as &[U256]
+        )
+        .execute(self.db.writer())
+        .await?
+        .rows_affected();
+        self.db.record_write().await;
+
+        Ok(inserted)
+    }
+
+    /// Gets the time of the most recent transfer stored, to resume the mirroring from it.
+    ///
+    /// # Errors
+    /// Returns an error if the time could not be retrieved.
+    pub async fn get_last_transfer_time(&self) -> Result<Option<NaiveDateTime>, sqlx::Error> {
+        Ok(query!(
+            r#"
+            SELECT MAX(at) as at FROM token_transfer
+            "#
+        )
+        // Compared to what was just written
+        .fetch_one(self.db.writer())
+        .await?
+        .at)
+    }
+
+    /// Gets the accounts holding the most of a token, leaving out the excluded ones.
+    ///
+    /// # Errors
+    /// Returns an error if the holders could not be retrieved.
+    pub async fn get_top_holders(
+        &self,
+        token: &str,
+        exclude: &[String],
+        limit: u32,
+    ) -> Result<Vec<TokenHolder>, sqlx::Error> {
+        query_as!(
+            TokenHolder,
+            r#"
+            SELECT account, balance as "balance: U256"
+            FROM token_balance
+            WHERE token = $1 AND balance > 0 AND account <> ALL($2::text[])
+            ORDER BY balance DESC, account
+            LIMIT $3
+            "#,
+            token,
+            exclude,
+            i64::from(limit)
+        )
+        .fetch_all(&mut *(self.db.read().await?))
+        .await
+    }
+
+    /// Gets what each account received from a sender over a time range, per token.
+    ///
+    /// # Errors
+    /// Returns an error if the totals could not be retrieved.
+    pub async fn get_received_from(
+        &self,
+        sender: &str,
+        range: TimeRange,
+    ) -> Result<Vec<ReceivedTotal>, sqlx::Error> {
+        query_as!(
+            ReceivedTotal,
+            r#"
+            SELECT
+                to_address as account,
+                token,
+                SUM(amount) as "amount!: U256",
+                COUNT(*) as "transfers!"
+            FROM token_transfer
+            WHERE from_address = $1
+                AND ($2::timestamp IS NULL OR at >= $2)
+                AND ($3::timestamp IS NULL OR at < $3)
+            GROUP BY to_address, token
+            ORDER BY to_address, token
+            "#,
+            sender,
+            range.from,
+            range.to
+        )
+        .fetch_all(&mut *(self.db.read().await?))
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use chrono::{Duration, SubsecRound, Utc};
+    use migrations::MIGRATOR;
+
+    fn balance(token: &str, account: &str, amount: &str) -> TokenBalanceModel {
+        TokenBalanceModel {
+            token: token.to_string(),
+            account: account.to_string(),
+            balance: U256::from_str(amount).unwrap(),
+        }
+    }
+
+    fn transfer(
+        id: &str,
+        at: NaiveDateTime,
+        from: &str,
+        to: &str,
+        amount: &str,
+    ) -> TokenTransferModel {
+        TokenTransferModel {
+            id: id.to_string(),
+            at,
+            token: "0x1".to_string(),
+            from_address: from.to_string(),
+            to_address: to.to_string(),
+            amount: U256::from_str(amount).unwrap(),
+        }
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_token_mirror(pool: sqlx::PgPool) {
+        let repo = Repository::new(pool);
+
+        repo.save_balances(&[
+            balance("0x1", "0xa", "10"),
+            balance("0x1", "0xb", "30"),
+            balance("0x1", "0xc", "20"),
+            balance("0x1", "0xd", "0"),
+            balance("0x2", "0xa", "50"),
+        ])
+        .await
+        .unwrap();
+        // Balances are replaced
+        repo.save_balances(&[balance("0x1", "0xa", "40")])
+            .await
+            .unwrap();
+
+        let holders = repo
+            .get_top_holders("0x1", &["0xb".to_string()], 10)
+            .await
+            .unwrap();
+        let accounts: Vec<&str> = holders.iter().map(|h| h.account.as_str()).collect();
+        assert_eq!(accounts, vec!["0xa", "0xc"]);
+        assert_eq!(holders[0].balance, U256::from_str("40").unwrap());
+
+        let at = Utc::now().naive_utc().trunc_subsecs(6);
+        assert_eq!(repo.get_last_transfer_time().await.unwrap(), None);
+        let inserted = repo
+            .save_transfers(&[
+                transfer("1", at, "0xgame", "0xa", "5"),
+                transfer("2", at + Duration::hours(1), "0xgame", "0xa", "7"),
+                transfer("3", at + Duration::hours(2), "0xgame", "0xb", "1"),
+                transfer("4", at + Duration::hours(3), "0xa", "0xgame", "100"),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(inserted, 4);
+        let inserted = repo
+            .save_transfers(&[transfer(
+                "4",
+                at + Duration::hours(3),
+                "0xa",
+                "0xgame",
+                "100",
+            )])
+            .await
+            .unwrap();
+        assert_eq!(inserted, 0);
+        assert_eq!(
+            repo.get_last_transfer_time().await.unwrap(),
+            Some(at + Duration::hours(3))
+        );
+
+        let received = repo
+            .get_received_from("0xgame", TimeRange::default())
+            .await
+            .unwrap();
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[0].account, "0xa");
+        assert_eq!(received[0].amount, U256::from_str("12").unwrap());
+        assert_eq!(received[0].transfers, 2);
+
+        let range = TimeRange::new(Some(at + Duration::minutes(30)), None);
+        let received = repo.get_received_from("0xgame", range).await.unwrap();
+        assert_eq!(received[0].amount, U256::from_str("7").unwrap());
+    }
+}
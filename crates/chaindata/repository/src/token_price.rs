@@ -0,0 +1,298 @@
+use crate::{Database, Error, TimeRange};
+use chaindata_models::shared::U256;
+use chrono::NaiveDateTime;
+use sqlx::{query, query_as};
+
+/// Price of a token sampled from the Ekubo pools.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenPrice {
+    pub token: String,
+    pub at: NaiveDateTime,
+    /// Amount of the token per main token.
+    pub ratio: f64,
+    /// Key of the pool holding the token, the last one of the price path.
+    pub pool_token0: String,
+    pub pool_token1: String,
+    pub pool_fee: U256,
+    pub pool_tick_spacing: i64,
+    pub pool_extension: String,
+}
+
+/// Prices of a token over a time bucket.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candle {
+    /// Start of the bucket (inclusive).
+    pub start: NaiveDateTime,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    /// Number of prices sampled during the bucket.
+    pub samples: i64,
+}
+
+/// History of the prices of the tokens, one sample per price update.
+pub struct Repository {
+    db: Database,
+}
+
+impl Repository {
+    #[must_use]
+    pub fn new(db: impl Into<Database>) -> Self {
+        Self { db: db.into() }
+    }
+
+    /// Saves the given prices, ignoring the ones already stored for the same token and time.
+    ///
+    /// Returns the number of prices inserted.
+    ///
+    /// # Errors
+    /// Returns an error if the prices could not be saved.
+    pub async fn save(&self, prices: &[TokenPrice]) -> Result<u64, Error> {
+        if prices.is_empty() {
+            return Ok(0);
+        }
+
+        let tokens: Vec<String> = prices.iter().map(|p| p.token.clone()).collect();
+        let ats: Vec<NaiveDateTime> = prices.iter().map(|p| p.at).collect();
+        let ratios: Vec<f64> = prices.iter().map(|p| p.ratio).collect();
+        let pool_token0s: Vec<String> = prices.iter().map(|p| p.pool_token0.clone()).collect();
+        let pool_token1s: Vec<String> = prices.iter().map(|p| p.pool_token1.clone()).collect();
+        let fees: Vec<U256> = prices.iter().map(|p| p.pool_fee).collect();
+        let tick_spacings: Vec<i64> = prices.iter().map(|p| p.pool_tick_spacing).collect();
+        let extensions: Vec<String> = prices.iter().map(|p| p.pool_extension.clone()).collect();
+
+        let inserted = query!(
+            r#"
+            INSERT INTO token_price (
+                token, at, ratio,
+                pool_token0, pool_token1, pool_fee, pool_tick_spacing, pool_extension
+            )
+            SELECT * FROM UNNEST(
+                $1::text[], $2::timestamp[], $3::float8[],
+                $4::text[], $5::text[], $6::numeric[], $7::int8[], $8::text[]
+            )
+            ON CONFLICT (token, at) DO NOTHING
+            "#,
+            &tokens,
+            &ats,
+            &ratios,
+            &pool_token0s,
+            &pool_token1s,
+            &fees as &[U256],
+            &tick_spacings,
+            &extensions
+        )
+        .execute(self.db.writer())
+        .await?
+        .rows_affected();
+        self.db.record_write().await;
+
+        Ok(inserted)
+    }
+
+    /// Gets the price of a token at the given time, the last one sampled before it.
+    ///
+    /// # Errors
+    /// Returns an error if the price could not be retrieved.
+    pub async fn get_at(
+        &self,
+        token: &str,
+        at: NaiveDateTime,
+    ) -> Result<Option<TokenPrice>, sqlx::Error> {
+        query_as!(
+            TokenPrice,
+            r#"
+            SELECT
+                token,
+                at,
+                ratio,
+                pool_token0,
+                pool_token1,
+                pool_fee as "pool_fee: U256",
+                pool_tick_spacing,
+                pool_extension
+            FROM token_price
+            WHERE token = $1 AND at <= $2
+            ORDER BY at DESC
+            LIMIT 1
+            "#,
+            token,
+            at
+        )
+        .fetch_optional(&mut *(self.db.read().await?))
+        .await
+    }
+
+    /// Gets the prices of a token within the time range, grouped in buckets of the given number
+    /// of seconds and ordered by bucket start.
+    ///
+    /// Buckets are aligned on the Unix epoch, and the ones without any price are left out.
+    ///
+    /// # Errors
+    /// Returns an error if the candles could not be retrieved, or if the bucket size is not
+    /// positive.
+    pub async fn get_candles(
+        &self,
+        token: &str,
+        bucket_seconds: i64,
+        range: TimeRange,
+    ) -> Result<Vec<Candle>, sqlx::Error> {
+        query_as!(
+            Candle,
+            r#"
+            SELECT
+                date_bin(
+                    $2::int8 * INTERVAL '1 second', at, TIMESTAMP '1970-01-01'
+                ) as "start!",
+                (array_agg(ratio ORDER BY at))[1] as "open!",
+                MAX(ratio) as "high!",
+                MIN(ratio) as "low!",
+                (array_agg(ratio ORDER BY at DESC))[1] as "close!",
+                COUNT(*) as "samples!"
+            FROM token_price
+            WHERE token = $1
+                AND ($3::timestamp IS NULL OR at >= $3)
+                AND ($4::timestamp IS NULL OR at < $4)
+            GROUP BY 1
+            ORDER BY 1
+            "#,
+            token,
+            bucket_seconds,
+            range.from,
+            range.to
+        )
+        .fetch_all(&mut *(self.db.read().await?))
+        .await
+    }
+
+    /// Gets the average price of a token between two times, each price weighted by how long it
+    /// lasted.
+    ///
+    /// The price sampled last before `from` counts from `from`. Without it, the average starts
+    /// at the first price of the range. Returns `None` when no price covers the range.
+    ///
+    /// # Errors
+    /// Returns an error if the average could not be computed.
+    pub async fn get_twap(
+        &self,
+        token: &str,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+    ) -> Result<Option<f64>, sqlx::Error> {
+        Ok(query!(
+            r#"
+            WITH samples AS (
+                SELECT at, ratio
+                FROM token_price
+                WHERE token = $1 AND at > $2 AND at < $3
+                UNION ALL
+                (
+                    SELECT $2, ratio
+                    FROM token_price
+                    WHERE token = $1 AND at <= $2
+                    ORDER BY at DESC
+                    LIMIT 1
+                )
+            ),
+            spans AS (
+                SELECT
+                    ratio,
+                    EXTRACT(EPOCH FROM (COALESCE(LEAD(at) OVER (ORDER BY at), $3) - at))::float8
+                        as seconds
+                FROM samples
+            )
+            SELECT SUM(ratio * seconds) / NULLIF(SUM(seconds), 0) as twap
+            FROM spans
+            "#,
+            token,
+            from,
+            to
+        )
+        .fetch_one(&mut *(self.db.read().await?))
+        .await?
+        .twap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use chrono::{Duration, NaiveDate};
+    use migrations::MIGRATOR;
+
+    fn price(token: &str, at: NaiveDateTime, ratio: f64) -> TokenPrice {
+        TokenPrice {
+            token: token.to_string(),
+            at,
+            ratio,
+            pool_token0: "0x1".to_string(),
+            pool_token1: token.to_string(),
+            pool_fee: U256::from_str("170141183460469235273462165868118016").unwrap(),
+            pool_tick_spacing: 1000,
+            pool_extension: "0x0".to_string(),
+        }
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_token_prices(pool: sqlx::PgPool) -> Result<(), Error> {
+        let repo = Repository::new(pool);
+
+        let start = NaiveDate::from_ymd_opt(2025, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        let at = |minutes| start + Duration::minutes(minutes);
+
+        let prices = vec![
+            price("0xtoken", at(0), 2.),
+            price("0xtoken", at(30), 4.),
+            price("0xtoken", at(45), 1.),
+            price("0xtoken", at(70), 3.),
+            price("0xother", at(10), 100.),
+        ];
+        assert_eq!(repo.save(&prices).await?, 5);
+        // Samples are only stored once
+        assert_eq!(repo.save(&prices[..1]).await?, 0);
+
+        let sample = repo.get_at("0xtoken", at(40)).await?.unwrap();
+        assert_eq!(sample, prices[1]);
+        assert!(repo.get_at("0xtoken", at(-1)).await?.is_none());
+
+        let candles = repo
+            .get_candles("0xtoken", 3600, TimeRange::default())
+            .await?;
+        assert_eq!(candles.len(), 2);
+        assert_eq!(
+            candles[0],
+            Candle {
+                start,
+                open: 2.,
+                high: 4.,
+                low: 1.,
+                close: 1.,
+                samples: 3,
+            }
+        );
+        assert_eq!(candles[1].start, at(60));
+        assert!((candles[1].close - 3.).abs() < f64::EPSILON);
+
+        let candles = repo
+            .get_candles("0xtoken", 3600, TimeRange::new(Some(at(30)), Some(at(60))))
+            .await?;
+        assert_eq!(candles.len(), 1);
+        assert!((candles[0].open - 4.).abs() < f64::EPSILON);
+
+        // 10 minutes at 2, 15 at 4 and 15 at 1
+        let twap = repo.get_twap("0xtoken", at(20), at(60)).await?.unwrap();
+        assert!((twap - 95. / 40.).abs() < 1e-9);
+        // Starting at the first price
+        let twap = repo.get_twap("0xother", at(0), at(20)).await?.unwrap();
+        assert!((twap - 100.).abs() < 1e-9);
+        assert!(repo.get_twap("0xother", at(0), at(5)).await?.is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,280 @@
+//! Storage interfaces of the chain data.
+//!
+//! They are implemented over postgres by the repositories of this crate, and in memory by
+//! [`crate::memory::MemoryRepository`] to test the logic built on top of them without a database.
+
+use std::collections::HashMap;
+
+use chaindata_models::{
+    events::{Event, EventId, EventType, FetchedEvent, StoredEvent},
+    models::{LandModel, LandStakeModel},
+    shared::Location,
+};
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use crate::{Error, EventFilter, Pagination, RawData, TimeRange};
+
+/// Storage of the on-chain events.
+#[async_trait::async_trait]
+pub trait EventStore: Send + Sync {
+    /// Get an event by its id.
+    ///
+    /// # Errors
+    /// Returns an error if the event does not exist, or if the storage could not be accessed.
+    async fn get_event_by_id(&self, id: EventId) -> Result<Event, Error>;
+
+    /// Get an event by its id, along with its data.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed, or if the event data is missing.
+    async fn get_full_event_by_id(&self, id: EventId) -> Result<Option<FetchedEvent>, Error>;
+
+    /// Get the events matching the filter, along with their data, ordered by chain position.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed, or if the data of an event is missing.
+    async fn get_events(
+        &self,
+        filter: &EventFilter,
+        pagination: &Pagination,
+    ) -> Result<Vec<FetchedEvent>, Error>;
+
+    /// Get the events stored after the given sequence number, in the order they were stored.
+    ///
+    /// Only the events of the given types are returned, or all of them if there is none.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed, or if the data of an event is missing.
+    async fn get_events_after_seq(
+        &self,
+        event_types: &[EventType],
+        after_seq: i64,
+        limit: u32,
+    ) -> Result<Vec<StoredEvent>, Error>;
+
+    /// Get the date of the last event, or the unix epoch if there is none.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed.
+    async fn get_last_event_date(&self) -> Result<DateTime<Utc>, Error>;
+
+    /// Saves an event.
+    ///
+    /// # Errors
+    /// Returns an error if the event already exists, or if the storage could not be accessed.
+    async fn save_event(&self, event: FetchedEvent) -> Result<EventId, Error>;
+
+    /// Saves many events at once, ignoring the ones that already exist.
+    ///
+    /// Returns the ids of the newly inserted events.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed.
+    async fn save_events(&self, events: Vec<FetchedEvent>) -> Result<Vec<EventId>, Error>;
+
+    /// Saves many events at once along with the raw rows they were decoded from, so that the
+    /// events are never stored without the rows needed to rebuild them.
+    ///
+    /// Returns the newly inserted events, in the order they were stored, which is their chain
+    /// order within the call.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed, in which case nothing is saved.
+    async fn save_events_with_raw(
+        &self,
+        events: Vec<FetchedEvent>,
+        raw: &[RawData],
+    ) -> Result<Vec<StoredEvent>, Error>;
+}
+
+/// Storage of the versions of the lands, and of their current state.
+#[async_trait::async_trait]
+pub trait LandStore: Send + Sync {
+    /// Saves a version of a land, and makes it the current state of its location if it is
+    /// the most recent one.
+    ///
+    /// # Errors
+    /// Returns an error if the version already exists, or if the storage could not be accessed.
+    async fn save(&self, land: LandModel) -> Result<EventId, Error>;
+
+    /// Saves many versions at once, ignoring the ones that already exist.
+    ///
+    /// Returns the number of new versions.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed.
+    async fn save_many(&self, lands: &[LandModel]) -> Result<u64, Error>;
+
+    /// Saves many versions at once along with the raw rows they were decoded from, so that the
+    /// versions are never stored without the rows needed to rebuild them.
+    ///
+    /// Returns the number of new versions.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed, in which case nothing is saved.
+    async fn save_many_with_raw(&self, lands: &[LandModel], raw: &[RawData]) -> Result<u64, Error>;
+
+    /// Gets the current state of the land at a specific location.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed.
+    async fn get_current_at_location(
+        &self,
+        location: Location,
+    ) -> Result<Option<LandModel>, Error>;
+
+    /// Gets the current state of all lands, ordered by location.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed.
+    async fn get_all_current(&self) -> Result<Vec<LandModel>, Error>;
+
+    /// Gets the latest version of the land at a specific location at or before the given time.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed.
+    async fn get_latest_at_location(
+        &self,
+        location: Location,
+        at: NaiveDateTime,
+    ) -> Result<Option<LandModel>, Error>;
+
+    /// Gets the latest version of every land at or before the given time.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed.
+    async fn get_all_at_time(&self, at: NaiveDateTime) -> Result<Vec<LandModel>, Error>;
+
+    /// Gets the versions of the land at a specific location within the given time range,
+    /// ordered by chain position.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed.
+    async fn get_history(
+        &self,
+        location: Location,
+        range: TimeRange,
+        pagination: &Pagination,
+    ) -> Result<Vec<LandModel>, Error>;
+
+    /// Gets a version of a land by its id.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed.
+    async fn get_by_id(&self, id: EventId) -> Result<Option<LandModel>, Error>;
+
+    /// Gets the time of the most recent version.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed.
+    async fn get_latest_timestamp(&self) -> Result<Option<NaiveDateTime>, Error>;
+
+    /// Gets the number of owned lands per token.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed.
+    async fn get_land_distribution(&self) -> Result<HashMap<String, u64>, Error>;
+}
+
+/// Storage of the versions of the land stakes, and of their current state.
+#[async_trait::async_trait]
+pub trait LandStakeStore: Send + Sync {
+    /// Saves a version of a land stake, and makes it the current stake of its location if it is
+    /// the most recent one.
+    ///
+    /// # Errors
+    /// Returns an error if the version already exists, or if the storage could not be accessed.
+    async fn save(&self, land_stake: LandStakeModel) -> Result<EventId, Error>;
+
+    /// Saves many versions at once, ignoring the ones that already exist.
+    ///
+    /// Returns the number of new versions.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed.
+    async fn save_many(&self, land_stakes: &[LandStakeModel]) -> Result<u64, Error>;
+
+    /// Saves many versions at once along with the raw rows they were decoded from, so that the
+    /// versions are never stored without the rows needed to rebuild them.
+    ///
+    /// Returns the number of new versions.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed, in which case nothing is saved.
+    async fn save_many_with_raw(
+        &self,
+        land_stakes: &[LandStakeModel],
+        raw: &[RawData],
+    ) -> Result<u64, Error>;
+
+    /// Gets the current stake of the land at a specific location.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed.
+    async fn get_current_at_location(
+        &self,
+        location: Location,
+    ) -> Result<Option<LandStakeModel>, Error>;
+
+    /// Gets the current stake of all lands, ordered by location.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed.
+    async fn get_all_current(&self) -> Result<Vec<LandStakeModel>, Error>;
+
+    /// Gets the latest stake at a specific location at or before the given time.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed.
+    async fn get_latest_at_location(
+        &self,
+        location: Location,
+        at: NaiveDateTime,
+    ) -> Result<Option<LandStakeModel>, Error>;
+
+    /// Gets the latest stake of every land at or before the given time.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed.
+    async fn get_all_at_time(&self, at: NaiveDateTime) -> Result<Vec<LandStakeModel>, Error>;
+
+    /// Gets the versions of the stake at a specific location within the given time range,
+    /// ordered by chain position.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed.
+    async fn get_history(
+        &self,
+        location: Location,
+        range: TimeRange,
+        pagination: &Pagination,
+    ) -> Result<Vec<LandStakeModel>, Error>;
+
+    /// Gets a version of a land stake by its id.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed.
+    async fn get_by_id(&self, id: EventId) -> Result<Option<LandStakeModel>, Error>;
+
+    /// Gets the time of the most recent version.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed.
+    async fn get_latest_timestamp(&self) -> Result<Option<NaiveDateTime>, Error>;
+}
+
+/// Storage of the position of each consumer of the event dispatcher.
+#[async_trait::async_trait]
+pub trait CursorStore: Send + Sync {
+    /// Gets the sequence number of the last event handled by the consumer, if any.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed.
+    async fn get_cursor(&self, consumer_id: &str) -> Result<Option<i64>, Error>;
+
+    /// Records that the consumer handled every event stored up to the given sequence number.
+    ///
+    /// # Errors
+    /// Returns an error if the storage could not be accessed.
+    async fn save_cursor(&self, consumer_id: &str, seq: i64) -> Result<(), Error>;
+}
@@ -0,0 +1,409 @@
+use crate::{Database, Error, OutboxStatus};
+use chaindata_models::{
+    events::{EventId, EventType, FetchedEvent},
+    shared::Location,
+};
+use chrono::NaiveDateTime;
+use sqlx::{query, query_as};
+
+/// A subscriber to the game events.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Webhook {
+    pub id: i64,
+    pub name: String,
+    pub url: String,
+    pub secret: String,
+    /// Empty to receive every type of event.
+    pub event_types: Vec<EventType>,
+    /// Empty to receive events regardless of the players involved.
+    pub addresses: Vec<String>,
+    /// Empty to receive events regardless of the land.
+    pub locations: Vec<Location>,
+    pub enabled: bool,
+}
+
+impl Webhook {
+    /// Whether the event passes the filters of the webhook.
+    #[must_use]
+    pub fn matches(&self, event: &FetchedEvent) -> bool {
+        let event_type = EventType::from(&event.data);
+        let addresses = event.data.addresses();
+        let location = event.data.location();
+
+        (self.event_types.is_empty() || self.event_types.contains(&event_type))
+            && (self.addresses.is_empty()
+                || addresses
+                    .iter()
+                    .any(|address| self.addresses.iter().any(|a| a == address)))
+            && (self.locations.is_empty()
+                || location.is_some_and(|location| self.locations.contains(&location)))
+    }
+}
+
+/// A webhook to create, or to update if one with the same name exists.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewWebhook {
+    pub name: String,
+    pub url: String,
+    pub secret: String,
+    pub event_types: Vec<EventType>,
+    pub addresses: Vec<String>,
+    pub locations: Vec<Location>,
+}
+
+/// A delivery waiting for an attempt, with what is needed to send it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DueDelivery {
+    pub id: i64,
+    pub url: String,
+    pub secret: String,
+    /// The body to send, as JSON.
+    pub payload: String,
+    pub attempts: u32,
+}
+
+/// An entry of the delivery log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook: String,
+    pub event_id: EventId,
+    pub status: OutboxStatus,
+    pub attempts: u32,
+    pub next_attempt_at: NaiveDateTime,
+    pub last_error: Option<String>,
+    /// HTTP status of the last response, if the subscriber answered.
+    pub response_status: Option<u16>,
+    pub created_at: NaiveDateTime,
+    pub delivered_at: Option<NaiveDateTime>,
+}
+
+struct DeliveryRow {
+    id: i64,
+    webhook: String,
+    event_id: String,
+    status: OutboxStatus,
+    attempts: i32,
+    next_attempt_at: NaiveDateTime,
+    last_error: Option<String>,
+    response_status: Option<i32>,
+    created_at: NaiveDateTime,
+    delivered_at: Option<NaiveDateTime>,
+}
+
+impl TryFrom<DeliveryRow> for WebhookDelivery {
+    type Error = Error;
+
+    // Attempts are never negative, and HTTP statuses fit in a u16
+    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+    fn try_from(row: DeliveryRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.id,
+            webhook: row.webhook,
+            event_id: row.event_id.parse()?,
+            status: row.status,
+            attempts: row.attempts as u32,
+            next_attempt_at: row.next_attempt_at,
+            last_error: row.last_error,
+            response_status: row.response_status.map(|status| status as u16),
+            created_at: row.created_at,
+            delivered_at: row.delivered_at,
+        })
+    }
+}
+
+/// Webhook subscriptions, and the log of their deliveries.
+pub struct Repository {
+    db: Database,
+}
+
+impl Repository {
+    #[must_use]
+    pub fn new(db: impl Into<Database>) -> Self {
+        Self { db: db.into() }
+    }
+
+    /// Creates a webhook, or updates the one with the same name, and returns its id.
+    ///
+    /// Webhooks updated this way are enabled again.
+    ///
+    /// # Errors
+    /// Returns an error if the webhook could not be saved.
+    pub async fn upsert(&self, webhook: &NewWebhook) -> Result<i64, sqlx::Error> {
+        let id = query!(
+            r#"
+            INSERT INTO webhook (name, url, secret, event_types, addresses, locations)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (name) DO UPDATE
+            SET url = EXCLUDED.url, secret = EXCLUDED.secret,
+                event_types = EXCLUDED.event_types, addresses = EXCLUDED.addresses,
+                locations = EXCLUDED.locations, enabled = TRUE
+            RETURNING id
+            "#,
+            webhook.name,
+            webhook.url,
+            webhook.secret,
+            &webhook.event_types as &[EventType],
+            &webhook.addresses,
+            &webhook.locations as &[Location]
+        )
+        .fetch_one(self.db.writer())
+        .await?
+        .id;
+
+        self.db.record_write().await;
+
+        Ok(id)
+    }
+
+    /// Gets the enabled webhooks.
+    ///
+    /// # Errors
+    /// Returns an error if the webhooks could not be retrieved.
+    pub async fn get_enabled(&self) -> Result<Vec<Webhook>, sqlx::Error> {
+        query_as!(
+            Webhook,
+            r#"
+            SELECT
+                id, name, url, secret,
+                event_types as "event_types!: Vec<EventType>",
+                addresses,
+                locations as "locations!: Vec<Location>",
+                enabled
+            FROM webhook
+            WHERE enabled
+            ORDER BY id
+            "#
+        )
+        .fetch_all(&mut *(self.db.read().await?))
+        .await
+    }
+
+    /// Queues the delivery of an event to a webhook, unless it already was.
+    ///
+    /// Returns whether the delivery is new.
+    ///
+    /// # Errors
+    /// Returns an error if the delivery could not be saved.
+    pub async fn enqueue(
+        &self,
+        webhook_id: i64,
+        event_id: &EventId,
+        payload: &str,
+    ) -> Result<bool, sqlx::Error> {
+        let result = query!(
+            r#"
+            INSERT INTO webhook_delivery (webhook_id, event_id, payload)
+            VALUES ($1, $2, $3::text::jsonb)
+            ON CONFLICT (webhook_id, event_id) DO NOTHING
+            "#,
+            webhook_id,
+            event_id.clone() as EventId,
+            payload
+        )
+        .execute(self.db.writer())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Gets the pending deliveries due for an attempt, in chain order.
+    ///
+    /// # Errors
+    /// Returns an error if the deliveries could not be retrieved.
+    #[allow(clippy::cast_sign_loss)] // Attempts are never negative
+    pub async fn get_due(&self, limit: u32) -> Result<Vec<DueDelivery>, sqlx::Error> {
+        let rows = query!(
+            r#"
+            SELECT d.id, w.url, w.secret, d.payload::text as "payload!", d.attempts
+            FROM webhook_delivery d
+            JOIN webhook w ON w.id = d.webhook_id
+            WHERE d.status = 'pending' AND d.next_attempt_at <= NOW() AND w.enabled
+            ORDER BY d.event_id, d.id
+            LIMIT $1
+            "#,
+            i64::from(limit)
+        )
+        // The worker must not deliver an event again because the replica lags behind
+        .fetch_all(self.db.writer())
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| DueDelivery {
+                id: row.id,
+                url: row.url,
+                secret: row.secret,
+                payload: row.payload,
+                attempts: row.attempts as u32,
+            })
+            .collect())
+    }
+
+    /// Gets the most recent deliveries, optionally with the given status.
+    ///
+    /// # Errors
+    /// Returns an error if the deliveries could not be retrieved.
+    pub async fn get_deliveries(
+        &self,
+        status: Option<OutboxStatus>,
+        limit: u32,
+    ) -> Result<Vec<WebhookDelivery>, Error> {
+        let rows = query_as!(
+            DeliveryRow,
+            r#"
+            SELECT
+                d.id, w.name as webhook, d.event_id,
+                d.status as "status!: OutboxStatus", d.attempts, d.next_attempt_at,
+                d.last_error, d.response_status, d.created_at, d.delivered_at
+            FROM webhook_delivery d
+            JOIN webhook w ON w.id = d.webhook_id
+            WHERE $1::outbox_status IS NULL OR d.status = $1
+            ORDER BY d.id DESC
+            LIMIT $2
+            "#,
+            status as Option<OutboxStatus>,
+            i64::from(limit)
+        )
+        .fetch_all(&mut *(self.db.read().await?))
+        .await?;
+
+        rows.into_iter().map(WebhookDelivery::try_from).collect()
+    }
+
+    /// Records that the delivery was accepted by the subscriber.
+    ///
+    /// # Errors
+    /// Returns an error if the delivery could not be updated.
+    pub async fn mark_delivered(&self, id: i64, response_status: u16) -> Result<(), sqlx::Error> {
+        query!(
+            r#"
+            UPDATE webhook_delivery
+            SET status = 'delivered', attempts = attempts + 1, delivered_at = NOW(),
+                response_status = $2, last_error = NULL
+            WHERE id = $1
+            "#,
+            id,
+            i32::from(response_status)
+        )
+        .execute(self.db.writer())
+        .await?;
+
+        Ok(())
+    }
+
+    /// Records a failed attempt. The delivery is tried again at `retry_at`, or marked as failed if
+    /// there is none.
+    ///
+    /// # Errors
+    /// Returns an error if the delivery could not be updated.
+    pub async fn record_failure(
+        &self,
+        id: i64,
+        error: &str,
+        response_status: Option<u16>,
+        retry_at: Option<NaiveDateTime>,
+    ) -> Result<(), sqlx::Error> {
+        query!(
+            r#"
+            UPDATE webhook_delivery
+            SET attempts = attempts + 1, last_error = $2, response_status = $3,
+                status = CASE WHEN $4::timestamp IS NULL THEN 'failed' ELSE 'pending' END::outbox_status,
+                next_attempt_at = COALESCE($4, next_attempt_at)
+            WHERE id = $1
+            "#,
+            id,
+            error,
+            response_status.map(i32::from),
+            retry_at
+        )
+        .execute(self.db.writer())
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{EventRepository, EventStore};
+    use chaindata_models::{
+        events::{actions::LandBoughtEventModel, EventDataModel},
+        shared::U256,
+    };
+    use chrono::{Duration, Utc};
+    use migrations::MIGRATOR;
+
+    fn bought(block: u64, location: u64, buyer: &str) -> FetchedEvent {
+        FetchedEvent {
+            id: EventId::new_test(block, 0, 0),
+            at: Utc::now().naive_utc(),
+            data: EventDataModel::LandBought(LandBoughtEventModel {
+                id: None,
+                location: location.into(),
+                buyer: buyer.to_string(),
+                seller: "0xseller".to_string(),
+                price: U256::from_str("10").unwrap(),
+                token_used: "0xtoken".to_string(),
+            }),
+        }
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_webhooks(pool: sqlx::PgPool) -> Result<(), Error> {
+        let events = EventRepository::new(pool.clone());
+        let repo = Repository::new(pool);
+
+        let mut webhook = NewWebhook {
+            name: "discord".to_string(),
+            url: "http://localhost/hook".to_string(),
+            secret: "secret".to_string(),
+            event_types: vec![EventType::LandBought],
+            addresses: vec![],
+            locations: vec![Location::new(1)],
+        };
+        let id = repo.upsert(&webhook).await?;
+        // Updated from the configuration on the next start
+        webhook.addresses = vec!["0xbuyer".to_string()];
+        assert_eq!(repo.upsert(&webhook).await?, id);
+
+        let webhooks = repo.get_enabled().await?;
+        assert_eq!(webhooks.len(), 1);
+        assert_eq!(webhooks[0].addresses, webhook.addresses);
+        assert!(webhooks[0].matches(&bought(1, 1, "0xbuyer")));
+        assert!(!webhooks[0].matches(&bought(1, 2, "0xbuyer")));
+        assert!(!webhooks[0].matches(&bought(1, 1, "0xother")));
+
+        let event = bought(1, 1, "0xbuyer");
+        events.save_events(vec![event.clone()]).await?;
+        assert!(repo.enqueue(id, &event.id, r#"{"schema":1}"#).await?);
+        assert!(!repo.enqueue(id, &event.id, r#"{"schema":1}"#).await?);
+
+        let due = repo.get_due(10).await?;
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].payload, r#"{"schema": 1}"#);
+        assert_eq!(due[0].secret, "secret");
+
+        let later = Utc::now().naive_utc() + Duration::minutes(1);
+        repo.record_failure(due[0].id, "Bad gateway", Some(502), Some(later))
+            .await?;
+        assert!(repo.get_due(10).await?.is_empty());
+
+        let log = repo.get_deliveries(Some(OutboxStatus::Pending), 10).await?;
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].webhook, "discord");
+        assert_eq!(log[0].response_status, Some(502));
+        assert_eq!(log[0].attempts, 1);
+
+        repo.mark_delivered(due[0].id, 204).await?;
+        let log = repo.get_deliveries(None, 10).await?;
+        assert_eq!(log[0].status, OutboxStatus::Delivered);
+        assert_eq!(log[0].event_id, event.id);
+        assert!(log[0].last_error.is_none());
+
+        Ok(())
+    }
+}
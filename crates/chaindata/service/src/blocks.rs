@@ -0,0 +1,45 @@
+use starknet::{
+    core::types::{BlockId, Felt, MaybePendingBlockWithTxHashes, StarknetError},
+    providers::{jsonrpc::HttpTransport, JsonRpcClient, Provider, ProviderError},
+};
+
+use crate::error::Error;
+
+/// Gives the hashes of the blocks of the canonical chain, to detect reorganizations.
+#[async_trait::async_trait]
+pub trait BlockSource: Send + Sync {
+    /// Gets the hash of the block with the given number, if it exists yet.
+    ///
+    /// # Errors
+    /// Returns an error if the chain could not be reached.
+    async fn block_hash(&self, number: u64) -> Result<Option<Felt>, Error>;
+}
+
+/// Reads the block hashes from a starknet RPC node.
+pub struct RpcBlockSource {
+    client: JsonRpcClient<HttpTransport>,
+}
+
+impl RpcBlockSource {
+    #[must_use]
+    pub fn new(client: JsonRpcClient<HttpTransport>) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait::async_trait]
+impl BlockSource for RpcBlockSource {
+    async fn block_hash(&self, number: u64) -> Result<Option<Felt>, Error> {
+        match self
+            .client
+            .get_block_with_tx_hashes(BlockId::Number(number))
+            .await
+        {
+            Ok(MaybePendingBlockWithTxHashes::Block(block)) => Ok(Some(block.block_hash)),
+            // A pending block has no hash yet
+            Ok(MaybePendingBlockWithTxHashes::PendingBlock(_))
+            | Err(ProviderError::StarknetError(StarknetError::BlockNotFound)) => Ok(None),
+            Err(err) => Err(Error::BlockSourceError(err.to_string())),
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use chaindata_models::events::{EventType, FetchedEvent};
+use chaindata_repository::LandTenureRepository;
+
+use crate::error::Error;
+
+use super::EventConsumer;
+
+/// Keeps the tenures of the lands up to date with the purchases and nukes.
+pub struct LandTenureConsumer {
+    repository: Arc<LandTenureRepository>,
+}
+
+impl LandTenureConsumer {
+    #[must_use]
+    pub fn new(repository: Arc<LandTenureRepository>) -> Self {
+        Self { repository }
+    }
+}
+
+#[async_trait::async_trait]
+impl EventConsumer for LandTenureConsumer {
+    fn id(&self) -> &'static str {
+        "land_tenure"
+    }
+
+    fn event_types(&self) -> Vec<EventType> {
+        vec![
+            EventType::LandBought,
+            EventType::AuctionFinished,
+            EventType::LandNuked,
+        ]
+    }
+
+    async fn catch_up(&self) -> Result<(), Error> {
+        self.repository.refresh_all().await?;
+        Ok(())
+    }
+
+    async fn handle(&self, event: &FetchedEvent) -> Result<(), Error> {
+        // Tenures are rebuilt per location, so events stored out of order are still placed right
+        if let Some(location) = event.data.location() {
+            self.repository.refresh(&[location]).await?;
+        }
+        Ok(())
+    }
+}
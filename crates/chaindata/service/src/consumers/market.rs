@@ -0,0 +1,81 @@
+use std::{slice, sync::Arc};
+
+use chaindata_models::events::{EventType, FetchedEvent};
+use chaindata_repository::{Bucket, MarketRepository};
+use chrono::{DateTime, Duration, Utc};
+
+use crate::error::Error;
+
+use super::EventConsumer;
+
+/// Keeps the market aggregates up to date with the trades and auctions.
+pub struct MarketConsumer {
+    repository: Arc<MarketRepository>,
+    /// Token in which auctions are paid, to attribute them in the aggregates.
+    auction_token: String,
+}
+
+impl MarketConsumer {
+    #[must_use]
+    pub fn new(repository: Arc<MarketRepository>, auction_token: String) -> Self {
+        Self {
+            repository,
+            auction_token,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EventConsumer for MarketConsumer {
+    fn id(&self) -> &'static str {
+        "market"
+    }
+
+    fn event_types(&self) -> Vec<EventType> {
+        vec![
+            EventType::LandBought,
+            EventType::AuctionFinished,
+            EventType::NewAuction,
+        ]
+    }
+
+    async fn catch_up(&self) -> Result<(), Error> {
+        // Buckets before the last aggregated one are complete, unless events arrived late
+        let from = self
+            .repository
+            .get_last_bucket_start(Bucket::Day)
+            .await?
+            .unwrap_or(DateTime::UNIX_EPOCH.naive_utc());
+
+        self.repository
+            .refresh(from, Utc::now().naive_utc(), &self.auction_token)
+            .await?;
+        Ok(())
+    }
+
+    async fn handle(&self, event: &FetchedEvent) -> Result<(), Error> {
+        self.handle_batch(slice::from_ref(event)).await
+    }
+
+    async fn handle_batch(&self, events: &[FetchedEvent]) -> Result<(), Error> {
+        // Events might arrive late, so the buckets are recomputed rather than incremented.
+        // Close events share their buckets, which are recomputed once for all of them.
+        let mut times: Vec<_> = events.iter().map(|event| event.at).collect();
+        times.sort_unstable();
+
+        let mut ranges = Vec::new();
+        for at in times {
+            match ranges.last_mut() {
+                Some((_, to)) if at - *to < Duration::days(1) => *to = at,
+                _ => ranges.push((at, at)),
+            }
+        }
+
+        for (from, to) in ranges {
+            self.repository
+                .refresh(from, to, &self.auction_token)
+                .await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+use chaindata_models::events::{EventType, FetchedEvent};
+
+use crate::error::Error;
+
+pub mod land_tenure;
+pub mod market;
+pub mod tax_ledger;
+
+/// A consumer of the stored events, fed by the [`crate::dispatcher::EventDispatcher`].
+///
+/// Events are delivered in the order they were stored, by batches of the ones stored together,
+/// and only once they are stored. A failing batch is retried as a whole, so handling an event
+/// again must give the same result. Each consumer runs in its own task, so one failing or
+/// lagging never affects the others.
+///
+/// Consumers are registered with [`crate::ChainDataService::register`].
+#[async_trait::async_trait]
+pub trait EventConsumer: Send + Sync + 'static {
+    /// Identifies the consumer, to persist its cursor. Must never change.
+    fn id(&self) -> &'static str;
+
+    /// The types of events to deliver to the consumer, or every type if empty.
+    fn event_types(&self) -> Vec<EventType> {
+        Vec::new()
+    }
+
+    /// Builds the state of the consumer from the events stored before its first start, as it
+    /// only gets the ones stored after it.
+    ///
+    /// Called on every start until the consumer handles its first event, so running it again
+    /// must give the same result.
+    ///
+    /// # Errors
+    /// Returns an error if the state could not be built, in which case the consumer is restarted.
+    async fn catch_up(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Handles an event.
+    ///
+    /// # Errors
+    /// Returns an error if the event could not be handled, in which case it is retried.
+    async fn handle(&self, event: &FetchedEvent) -> Result<(), Error>;
+
+    /// Handles events stored together, in order.
+    ///
+    /// They are handled one by one by default, consumers whose work covers several events at
+    /// once can do it once per batch instead.
+    ///
+    /// # Errors
+    /// Returns an error if an event could not be handled, in which case the batch is retried.
+    async fn handle_batch(&self, events: &[FetchedEvent]) -> Result<(), Error> {
+        for event in events {
+            self.handle(event).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+use std::sync::Arc;
+
+use chaindata_models::events::{EventType, FetchedEvent};
+use chaindata_repository::TaxLedgerRepository;
+
+use crate::error::Error;
+
+use super::EventConsumer;
+
+/// Records the tax transfers in the ledger.
+pub struct TaxLedgerConsumer {
+    repository: Arc<TaxLedgerRepository>,
+}
+
+impl TaxLedgerConsumer {
+    #[must_use]
+    pub fn new(repository: Arc<TaxLedgerRepository>) -> Self {
+        Self { repository }
+    }
+}
+
+#[async_trait::async_trait]
+impl EventConsumer for TaxLedgerConsumer {
+    fn id(&self) -> &'static str {
+        "tax_ledger"
+    }
+
+    fn event_types(&self) -> Vec<EventType> {
+        vec![EventType::LandTransfer]
+    }
+
+    async fn catch_up(&self) -> Result<(), Error> {
+        self.repository.record_missing().await?;
+        Ok(())
+    }
+
+    async fn handle(&self, event: &FetchedEvent) -> Result<(), Error> {
+        self.repository
+            .record(std::slice::from_ref(&event.id))
+            .await?;
+        Ok(())
+    }
+}
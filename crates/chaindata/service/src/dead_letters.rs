@@ -0,0 +1,188 @@
+use chaindata_repository::{
+    DeadLetter, DeadLetterKind, DeadLetterRepository, EventStore, LandStakeStore, LandStore,
+    LandTenureRepository, StoredDeadLetter, TaxLedgerRepository,
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    decode::{decode_event, decode_model, raw_from_dead_letter, DecodedModel},
+    error::Error,
+    tasks::unique_locations,
+};
+
+/// Outcome of a retry of the dead letters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetryReport {
+    /// Dead letters decoded and stored, which were removed.
+    pub decoded: u64,
+    /// Dead letters that still could not be decoded.
+    pub failed: u64,
+    /// Dead letters that cannot be retried, as they were not received as JSON.
+    pub skipped: u64,
+}
+
+/// Stores a row the listeners could not decode, so that they can go on with the next ones.
+pub(crate) async fn store_dead_letter(dead_letters: &DeadLetterRepository, letter: &DeadLetter) {
+    error!(
+        "Could not decode {} {:?}, storing it as a dead letter: {}",
+        letter.name, letter.torii_event_id, letter.error
+    );
+
+    if let Err(err) = dead_letters.save(letter).await {
+        error!("Failed to save dead letter: {}", err);
+    }
+}
+
+/// Decodes the dead letters again, typically after a decoder fix, and stores what now decodes.
+///
+/// Recovered events are stored after the cursors of the consumers, which handle them on their
+/// next start, so the retry must not run along the chain data service. The tenures and tax
+/// ledger owners of the locations of the recovered models are refreshed.
+///
+/// # Errors
+/// Returns an error if the database could not be accessed.
+pub async fn retry_dead_letters(
+    dead_letters: &DeadLetterRepository,
+    event_repository: &dyn EventStore,
+    land_repository: &dyn LandStore,
+    land_stake_repository: &dyn LandStakeStore,
+    tenure_repository: &LandTenureRepository,
+    tax_ledger_repository: &TaxLedgerRepository,
+) -> Result<RetryReport, Error> {
+    let mut report = RetryReport::default();
+    let mut locations = Vec::new();
+
+    for stored in dead_letters.get_all(DeadLetterKind::Event).await? {
+        let Some(raw) = raw_from_dead_letter(&stored.letter) else {
+            report.skipped += 1;
+            continue;
+        };
+
+        match decode_event(raw) {
+            Ok(event) => {
+                event_repository.save_events(vec![event]).await?;
+                recovered(dead_letters, &stored, &mut report).await?;
+            }
+            Err(letter) => failed(dead_letters, &stored, &letter.error, &mut report).await?,
+        }
+    }
+
+    for stored in dead_letters.get_all(DeadLetterKind::Model).await? {
+        let Some(raw) = raw_from_dead_letter(&stored.letter) else {
+            report.skipped += 1;
+            continue;
+        };
+
+        match decode_model(raw) {
+            Ok(model) => {
+                match model {
+                    Some(DecodedModel::Land(land)) => {
+                        locations.push(land.location);
+                        land_repository.save_many(&[land]).await?;
+                    }
+                    Some(DecodedModel::LandStake(land_stake)) => {
+                        locations.push(land_stake.location);
+                        land_stake_repository.save_many(&[land_stake]).await?;
+                    }
+                    None => {}
+                }
+                recovered(dead_letters, &stored, &mut report).await?;
+            }
+            Err(letter) => failed(dead_letters, &stored, &letter.error, &mut report).await?,
+        }
+    }
+
+    // Tenures and the tax ledger read the versions, which were missing until now
+    let locations = unique_locations(locations);
+    tenure_repository.refresh(&locations).await?;
+    tax_ledger_repository.resolve_owners(&locations).await?;
+
+    info!(
+        "Retried dead letters: {} decoded, {} still failing, {} skipped",
+        report.decoded, report.failed, report.skipped
+    );
+    Ok(report)
+}
+
+async fn recovered(
+    dead_letters: &DeadLetterRepository,
+    stored: &StoredDeadLetter,
+    report: &mut RetryReport,
+) -> Result<(), Error> {
+    dead_letters.delete(stored.id).await?;
+    report.decoded += 1;
+    Ok(())
+}
+
+async fn failed(
+    dead_letters: &DeadLetterRepository,
+    stored: &StoredDeadLetter,
+    error: &str,
+    report: &mut RetryReport,
+) -> Result<(), Error> {
+    warn!(
+        "Dead letter {} ({}) still fails to decode: {error}",
+        stored.id, stored.letter.name
+    );
+    dead_letters.record_failure(stored.id, error).await?;
+    report.failed += 1;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chaindata_models::events::EventId;
+    use chaindata_repository::{
+        fixtures::{event, nuked, raw_nuked},
+        CursorRepository, CursorStore, EventRepository, LandRepository, LandStakeRepository,
+    };
+    use chrono::Utc;
+    use migrations::MIGRATOR;
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_recovered_events_reach_the_consumers(pool: sqlx::PgPool) -> Result<(), Error> {
+        let events = EventRepository::new(pool.clone());
+        let cursors = CursorRepository::new(pool.clone());
+        let dead_letters = DeadLetterRepository::new(pool.clone());
+        let now = Utc::now().naive_utc();
+
+        // The event of block 2 was handled, while the one of block 1 failed to decode
+        let stored = events
+            .save_events_with_raw(
+                vec![event(EventId::new_test(2, 0, 0), now, nuked(1, "0x2"))],
+                &[],
+            )
+            .await?;
+        cursors.save_cursor("consumer", stored[0].seq).await?;
+        let failed = raw_nuked(1, now, 1, "0x1");
+        dead_letters
+            .save(&DeadLetter {
+                kind: DeadLetterKind::Event,
+                name: failed.name,
+                torii_event_id: Some(failed.torii_event_id),
+                at: failed.at,
+                raw: failed.data,
+                error: "Could not convert the data".to_string(),
+            })
+            .await?;
+
+        let report = retry_dead_letters(
+            &dead_letters,
+            &events,
+            &LandRepository::new(pool.clone()),
+            &LandStakeRepository::new(pool.clone()),
+            &LandTenureRepository::new(pool.clone()),
+            &TaxLedgerRepository::new(pool),
+        )
+        .await?;
+        assert_eq!(report.decoded, 1);
+
+        // Stored after the cursor, although it comes first on chain
+        let pending = events.get_events_after_seq(&[], stored[0].seq, 10).await?;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].event.id, EventId::new_test(1, 0, 0));
+
+        Ok(())
+    }
+}
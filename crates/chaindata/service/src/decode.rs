@@ -0,0 +1,204 @@
+use chaindata_models::{
+    events::{EventId, FetchedEvent},
+    models::{LandModel, LandStakeModel},
+};
+use chaindata_repository::{DeadLetter, DeadLetterKind, RawData, RawDataKind};
+use chrono::Utc;
+use ponziland_models::{events::EventData, models::Model};
+use serde_json::Value;
+use torii_ingester::{error::ToriiConversionError, RawToriiData};
+
+/// Why a Torii row could not be decoded.
+#[derive(thiserror::Error, Debug)]
+pub enum DecodeError {
+    #[error("Could not convert the data")]
+    Conversion(#[from] ToriiConversionError),
+    #[error("Invalid event id {0}")]
+    InvalidEventId(String, #[source] chaindata_models::error::Error),
+    #[error("Missing event id")]
+    MissingEventId,
+}
+
+/// A decoded model, in the form it is stored.
+pub enum DecodedModel {
+    Land(LandModel),
+    LandStake(LandStakeModel),
+}
+
+/// Formats the error followed by all its causes, as `error: cause: cause...`.
+#[must_use]
+pub fn error_chain(err: &dyn std::error::Error) -> String {
+    let mut chain = err.to_string();
+    let mut source = err.source();
+    while let Some(cause) = source {
+        chain.push_str(": ");
+        chain.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    chain
+}
+
+fn parse_event_id(event_id: &str) -> Result<EventId, DecodeError> {
+    EventId::parse_from_torii(event_id)
+        .map_err(|err| DecodeError::InvalidEventId(event_id.to_string(), err))
+}
+
+fn dead_letter(kind: DeadLetterKind, raw: RawToriiData, err: &DecodeError) -> DeadLetter {
+    let name = raw.name().to_string();
+    let (torii_event_id, at, raw) = match raw {
+        RawToriiData::Json {
+            data, at, event_id, ..
+        } => (Some(event_id), at.naive_utc(), data),
+        // Only kept for inspection, as there is no JSON to retry it from
+        RawToriiData::Grpc(data) => (
+            None,
+            Utc::now().naive_utc(),
+            Value::String(format!("{data:?}")),
+        ),
+    };
+
+    DeadLetter {
+        kind,
+        name,
+        torii_event_id,
+        at,
+        raw: raw.to_string(),
+        error: error_chain(err),
+    }
+}
+
+fn try_decode_event(raw: RawToriiData) -> Result<FetchedEvent, DecodeError> {
+    Ok(match raw {
+        RawToriiData::Grpc(data) => FetchedEvent {
+            id: EventId::new_test(0, 0, 0),
+            at: Utc::now().naive_utc(),
+            data: EventData::try_from(data)?.into(),
+        },
+        RawToriiData::Json {
+            name,
+            data,
+            at,
+            event_id,
+        } => FetchedEvent {
+            id: parse_event_id(&event_id)?,
+            at: at.naive_utc(),
+            data: EventData::from_json(&name, data)?.into(),
+        },
+    })
+}
+
+#[allow(clippy::match_wildcard_for_single_variants)]
+fn try_decode_model(raw: RawToriiData) -> Result<Option<DecodedModel>, DecodeError> {
+    let model = Model::parse(raw)?;
+    let at = model.timestamp.unwrap_or(Utc::now()).naive_utc();
+    let id = || {
+        parse_event_id(
+            model
+                .event_id
+                .as_deref()
+                .ok_or(DecodeError::MissingEventId)?,
+        )
+    };
+
+    Ok(match &model.model {
+        Model::Land(land) => Some(DecodedModel::Land(LandModel::from_at(land, id()?, at))),
+        Model::LandStake(land_stake) => Some(DecodedModel::LandStake(LandStakeModel::from_at(
+            land_stake,
+            id()?,
+            at,
+        ))),
+        _ => {
+            //TODO: Implement this later
+            None
+        }
+    })
+}
+
+/// Decodes an event fetched from Torii.
+///
+/// # Errors
+/// Returns the dead letter to store if the event could not be decoded.
+pub fn decode_event(raw: RawToriiData) -> Result<FetchedEvent, DeadLetter> {
+    try_decode_event(raw.clone()).map_err(|err| dead_letter(DeadLetterKind::Event, raw, &err))
+}
+
+/// Decodes a model fetched from Torii, if it is supported.
+///
+/// # Errors
+/// Returns the dead letter to store if the model could not be decoded.
+pub fn decode_model(raw: RawToriiData) -> Result<Option<DecodedModel>, DeadLetter> {
+    try_decode_model(raw.clone()).map_err(|err| dead_letter(DeadLetterKind::Model, raw, &err))
+}
+
+/// Rebuilds the Torii row a dead letter was made from, if it can be retried.
+#[must_use]
+pub fn raw_from_dead_letter(letter: &DeadLetter) -> Option<RawToriiData> {
+    Some(RawToriiData::Json {
+        name: letter.name.clone(),
+        data: serde_json::from_str(&letter.raw).ok()?,
+        at: letter.at.and_utc(),
+        event_id: letter.torii_event_id.clone()?,
+    })
+}
+
+/// Keeps a Torii row as received, if it can be decoded again later.
+///
+/// Rows received through gRPC are not kept, as there is no JSON to decode them from.
+#[must_use]
+pub fn raw_data(kind: RawDataKind, raw: &RawToriiData) -> Option<RawData> {
+    match raw {
+        RawToriiData::Json {
+            name,
+            data,
+            at,
+            event_id,
+        } => Some(RawData {
+            kind,
+            name: name.clone(),
+            torii_event_id: event_id.clone(),
+            at: at.naive_utc(),
+            data: data.to_string(),
+        }),
+        RawToriiData::Grpc(_) => None,
+    }
+}
+
+/// Rebuilds the Torii row a raw row was stored from.
+#[must_use]
+pub fn raw_from_stored(raw: &RawData) -> Option<RawToriiData> {
+    Some(RawToriiData::Json {
+        name: raw.name.clone(),
+        data: serde_json::from_str(&raw.data).ok()?,
+        at: raw.at.and_utc(),
+        event_id: raw.torii_event_id.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_dead_letter_roundtrip() {
+        let raw = RawToriiData::Json {
+            name: "ponzi_land-LandNukedEvent".to_string(),
+            data: json!({ "owner_nuked": "0x1" }),
+            at: Utc::now(),
+            event_id: "0x1:0x2:0x3".to_string(),
+        };
+
+        let letter = decode_event(raw).expect_err("the location is missing");
+        assert_eq!(letter.kind, DeadLetterKind::Event);
+        assert_eq!(letter.torii_event_id.as_deref(), Some("0x1:0x2:0x3"));
+        assert!(letter.error.starts_with("Could not convert the data: "));
+
+        let RawToriiData::Json { name, data, .. } =
+            raw_from_dead_letter(&letter).expect("the dead letter can be retried")
+        else {
+            panic!("dead letters are retried as JSON");
+        };
+        assert_eq!(name, "ponzi_land-LandNukedEvent");
+        assert_eq!(data, json!({ "owner_nuked": "0x1" }));
+    }
+}
@@ -0,0 +1,83 @@
+use std::sync::{Arc, PoisonError, RwLock};
+
+use chaindata_models::events::{EventType, StoredEvent};
+use chaindata_repository::{CursorStore, EventStore};
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tracing::error;
+
+use crate::{consumers::EventConsumer, tasks::consumer::ConsumerTask};
+
+/// The channel feeding a consumer.
+struct Channel {
+    consumer_id: &'static str,
+    /// Types of the events the consumer wants, or empty for all of them.
+    event_types: Vec<EventType>,
+    sender: UnboundedSender<StoredEvent>,
+}
+
+/// Fans the stored events out to the registered consumers.
+///
+/// Each consumer has its own unbounded channel, so a slow consumer never holds back the others,
+/// and its own cursor, so it can replay what it missed after a restart.
+pub struct EventDispatcher {
+    event_repository: Arc<dyn EventStore>,
+    cursor_repository: Arc<dyn CursorStore>,
+    channels: RwLock<Vec<Channel>>,
+}
+
+impl EventDispatcher {
+    #[must_use]
+    pub fn new(
+        event_repository: Arc<dyn EventStore>,
+        cursor_repository: Arc<dyn CursorStore>,
+    ) -> Self {
+        Self {
+            event_repository,
+            cursor_repository,
+            channels: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Registers a consumer, and returns the task feeding it.
+    ///
+    /// Events dispatched before the task is started are kept until it processes them.
+    pub fn register(&self, consumer: impl EventConsumer) -> ConsumerTask {
+        let (sender, rx) = mpsc::unbounded_channel();
+        self.channels
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Channel {
+                consumer_id: consumer.id(),
+                event_types: consumer.event_types(),
+                sender,
+            });
+
+        ConsumerTask::new(
+            Arc::new(consumer),
+            self.event_repository.clone(),
+            self.cursor_repository.clone(),
+            rx,
+        )
+    }
+
+    /// Sends stored events to every consumer interested in them.
+    ///
+    /// Events must be given in the order they were stored.
+    pub fn dispatch(&self, events: &[StoredEvent]) {
+        let channels = self.channels.read().unwrap_or_else(PoisonError::into_inner);
+        for channel in channels.iter() {
+            let wanted = events.iter().filter(|event| {
+                channel.event_types.is_empty()
+                    || channel
+                        .event_types
+                        .contains(&EventType::from(&event.event.data))
+            });
+            for event in wanted {
+                if channel.sender.send(event.clone()).is_err() {
+                    error!("Channel of consumer {} is closed", channel.consumer_id);
+                    break;
+                }
+            }
+        }
+    }
+}
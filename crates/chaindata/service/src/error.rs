@@ -4,4 +4,16 @@ use torii_ingester::torii_client;
 pub enum Error {
     #[error("Error while connecting to the database")]
     ToriiConnectionError(#[from] torii_client::Error),
+    #[error("Error while accessing the repositories")]
+    RepositoryError(#[from] chaindata_repository::Error),
+    #[error("Error while querying the database")]
+    DatabaseError(#[from] sqlx::Error),
+    #[error("Error while calling gg.xyz")]
+    GgXyzError(#[from] reqwest::Error),
+    #[error("Error while reading blocks: {0}")]
+    BlockSourceError(String),
+    #[error("Invalid webhook configuration: {0}")]
+    WebhookConfigError(String),
+    #[error("Consumer {0} registered after the service was started")]
+    AlreadyStarted(&'static str),
 }
@@ -35,14 +35,21 @@ impl GGApi {
 
     /// Sends an action to the GG.xyz API
     ///
+    /// Requests with the same idempotency key are only taken into account once by the API.
+    ///
     /// # Errors
     /// Returns an error if the request fails or the response from the remote server is not a 200
-    pub async fn send_actions(&self, req: PostRequest) -> Result<(), reqwest::Error> {
+    pub async fn send_actions(
+        &self,
+        idempotency_key: &str,
+        req: PostRequest,
+    ) -> Result<(), reqwest::Error> {
         // Set the path, but keep the rest from the base path.
         let response = self
             .client
             .post(self.path.clone())
             .header("secret", &self.token)
+            .header("Idempotency-Key", idempotency_key)
             .json(&req)
             .send()
             .await?;
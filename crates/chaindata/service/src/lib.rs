@@ -1,23 +1,58 @@
+pub mod blocks;
+pub mod consumers;
+pub mod dead_letters;
+pub mod decode;
+pub mod dispatcher;
 pub mod error;
 pub mod gg_xyz_api;
+pub mod reindex;
 pub mod tasks;
+pub mod webhooks;
 
-use chaindata_repository::{Database, EventRepository, LandRepository, LandStakeRepository};
+use blocks::RpcBlockSource;
+use chaindata_repository::{
+    CursorRepository, Database, DeadLetterRepository, EventRepository, GgXyzOutboxRepository,
+    LandRepository, LandStakeRepository, LandTenureRepository, MarketRepository, NewWebhook,
+    ReorgRepository, TaxLedgerRepository, TokenRepository, WebhookRepository,
+};
+use consumers::{
+    land_tenure::LandTenureConsumer, market::MarketConsumer, tax_ledger::TaxLedgerConsumer,
+    EventConsumer,
+};
+use dispatcher::EventDispatcher;
 use gg_xyz_api::GGApi;
 use reqwest::Url;
 use serde::{Deserialize, Serialize};
-use starknet::core::types::Felt;
-use std::sync::Arc;
+use starknet::{
+    core::types::Felt,
+    providers::{jsonrpc::HttpTransport, JsonRpcClient},
+};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 use tasks::{
-    event_listener::EventListenerTask, model_listener::ModelListenerTask, Task, TaskWrapper,
+    consumer::ConsumerTask, event_listener::EventListenerTask, gg_xyz_outbox::GgXyzOutboxTask,
+    model_listener::ModelListenerTask, reorg::ReorgTask, supervisor::TaskHealth,
+    token_mirror::TokenMirrorTask, webhook_delivery::WebhookDeliveryTask, Task, TaskWrapper,
 };
 use torii_ingester::{ToriiClient, ToriiConfiguration};
+use webhooks::{WebhookConfig, WebhookConsumer};
 
 /// `ChainDataService` is a service that handles the importation and syncing of new events and data
 /// to the database for further processing.
 pub struct ChainDataService {
     event_listener_task: TaskWrapper<EventListenerTask>,
     model_listener_task: TaskWrapper<ModelListenerTask>,
+    reorg_task: TaskWrapper<ReorgTask>,
+    token_mirror_task: TaskWrapper<TokenMirrorTask>,
+    gg_xyz_outbox_task: Option<TaskWrapper<GgXyzOutboxTask>>,
+    webhook_delivery_task: TaskWrapper<WebhookDeliveryTask>,
+    dispatcher: Arc<EventDispatcher>,
+    consumers: Mutex<Consumers>,
+}
+
+/// Tasks of the registered consumers, which can only be added until the service is started.
+struct Consumers {
+    tasks: Vec<TaskWrapper<ConsumerTask>>,
+    started: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -27,6 +62,12 @@ pub struct ChainDataServiceConfiguration {
     pub gg_xyz_enabled: bool,
     pub gg_xyz_api_key: String,
     pub gg_xyz_api_url: Url,
+    /// Token in which auctions are paid.
+    pub auction_token: Felt,
+    /// Starknet RPC node used to detect chain reorganizations.
+    pub rpc_url: Url,
+    /// Webhooks to create or update on startup, besides the ones only defined in the database.
+    pub webhooks: Vec<WebhookConfig>,
 }
 
 impl ChainDataService {
@@ -45,35 +86,154 @@ impl ChainDataService {
 
         let client = Arc::new(ToriiClient::new(&torii_config).await?);
 
-        let event_repository = Arc::new(EventRepository::new(database.clone()));
+        let event_repository = Arc::new(
+            EventRepository::new(database.clone()).with_gg_xyz_outbox(config.gg_xyz_enabled),
+        );
         let land_repository = Arc::new(LandRepository::new(database.clone()));
         let land_stake_repository = Arc::new(LandStakeRepository::new(database.clone()));
-        let gg_xyz_api = Arc::new(GGApi::new(&config.gg_xyz_api_url, config.gg_xyz_api_key));
+        let market_repository = Arc::new(MarketRepository::new(database.clone()));
+        let cursor_repository = Arc::new(CursorRepository::new(database.clone()));
+        let dead_letter_repository = Arc::new(DeadLetterRepository::new(database.clone()));
+        let reorg_repository = Arc::new(ReorgRepository::new(database.clone()));
+        let tenure_repository = Arc::new(LandTenureRepository::new(database.clone()));
+        let tax_ledger_repository = Arc::new(TaxLedgerRepository::new(database.clone()));
+        let token_repository = Arc::new(TokenRepository::new(database.clone()));
+        let block_source = Arc::new(RpcBlockSource::new(JsonRpcClient::new(HttpTransport::new(
+            config.rpc_url.clone(),
+        ))));
+        let auction_token = format!("{:#x}", config.auction_token);
+
+        let webhook_repository = Arc::new(WebhookRepository::new(database.clone()));
+        for webhook in config.webhooks {
+            webhook_repository
+                .upsert(&NewWebhook::try_from(webhook)?)
+                .await?;
+        }
 
-        Ok(Arc::new(Self {
+        let dispatcher = Arc::new(EventDispatcher::new(
+            event_repository.clone(),
+            cursor_repository,
+        ));
+        let consumer_tasks = vec![dispatcher
+            .register(WebhookConsumer::new(webhook_repository.clone()))
+            .wrap()];
+
+        // Actions are queued along with the events, and delivered from the outbox
+        let gg_xyz_outbox_task = config.gg_xyz_enabled.then(|| {
+            let gg_xyz_api = Arc::new(GGApi::new(&config.gg_xyz_api_url, config.gg_xyz_api_key));
+            let outbox = Arc::new(GgXyzOutboxRepository::new(database.clone()));
+            GgXyzOutboxTask::new(outbox, gg_xyz_api).wrap()
+        });
+
+        let service = Arc::new(Self {
             event_listener_task: EventListenerTask::new(
                 client.clone(),
+                event_repository.clone(),
+                dispatcher.clone(),
+                dead_letter_repository.clone(),
+            )
+            .wrap(),
+            reorg_task: ReorgTask::new(
+                block_source,
                 event_repository,
-                Some(gg_xyz_api).filter(|_| config.gg_xyz_enabled),
+                reorg_repository,
+                auction_token.clone(),
             )
             .wrap(),
+            token_mirror_task: TokenMirrorTask::new(client.clone(), token_repository).wrap(),
             model_listener_task: ModelListenerTask::new(
                 client.clone(),
                 land_repository,
                 land_stake_repository,
+                dead_letter_repository,
+                tenure_repository.clone(),
+                tax_ledger_repository.clone(),
             )
             .wrap(),
-        }))
+            gg_xyz_outbox_task,
+            webhook_delivery_task: WebhookDeliveryTask::new(webhook_repository).wrap(),
+            dispatcher,
+            consumers: Mutex::new(Consumers {
+                tasks: consumer_tasks,
+                started: false,
+            }),
+        });
+
+        // Features derived from the events, each catching up on its own
+        service.register(MarketConsumer::new(market_repository, auction_token))?;
+        service.register(LandTenureConsumer::new(tenure_repository))?;
+        service.register(TaxLedgerConsumer::new(tax_ledger_repository))?;
+
+        Ok(service)
+    }
+
+    /// Registers a consumer of the stored events, such as a derived feature or a notifier.
+    ///
+    /// Consumers must be registered before the service is started, and their ids must be
+    /// unique. A new consumer gets the events stored after its first start.
+    ///
+    /// # Errors
+    /// Returns an error if the service is already started, as the consumer would never run.
+    pub fn register(&self, consumer: impl EventConsumer) -> Result<(), error::Error> {
+        let mut consumers = self.consumers();
+        if consumers.started {
+            return Err(error::Error::AlreadyStarted(consumer.id()));
+        }
+
+        let task = self.dispatcher.register(consumer).wrap();
+        consumers.tasks.push(task);
+        Ok(())
+    }
+
+    fn consumers(&self) -> MutexGuard<'_, Consumers> {
+        self.consumers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Gets the health of every task of the service.
+    #[must_use]
+    pub fn health(&self) -> Vec<TaskHealth> {
+        let mut health = vec![
+            self.event_listener_task.health(),
+            self.model_listener_task.health(),
+            self.reorg_task.health(),
+            self.token_mirror_task.health(),
+        ];
+        health.extend(self.gg_xyz_outbox_task.iter().map(TaskWrapper::health));
+        health.push(self.webhook_delivery_task.health());
+        health.extend(self.consumers().tasks.iter().map(TaskWrapper::health));
+        health
     }
 
     pub fn stop(self: &Arc<Self>) {
         self.event_listener_task.stop();
         self.model_listener_task.stop();
+        self.reorg_task.stop();
+        self.token_mirror_task.stop();
+        if let Some(task) = &self.gg_xyz_outbox_task {
+            task.stop();
+        }
+        self.webhook_delivery_task.stop();
+        for task in &self.consumers().tasks {
+            task.stop();
+        }
     }
 
     pub fn start(self: &Arc<Self>) {
         // Start all in parallel
         self.event_listener_task.start();
         self.model_listener_task.start();
+        self.reorg_task.start();
+        self.token_mirror_task.start();
+        if let Some(task) = &self.gg_xyz_outbox_task {
+            task.start();
+        }
+        self.webhook_delivery_task.start();
+        let mut consumers = self.consumers();
+        consumers.started = true;
+        for task in &consumers.tasks {
+            task.start();
+        }
     }
 }
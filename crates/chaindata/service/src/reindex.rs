@@ -0,0 +1,194 @@
+use chaindata_models::models::{LandModel, LandStakeModel};
+use chaindata_repository::{
+    DeadLetterRepository, EventRepository, EventStore, LandRepository, LandStakeRepository,
+    LandStakeStore, LandStore, LandTenureRepository, MarketRepository, RawData, RawDataKind,
+    RawDataRepository, ReindexRepository, TaxLedgerRepository,
+};
+use futures_util::{Stream, StreamExt};
+use starknet::core::types::Felt;
+use torii_ingester::{torii_client, RawToriiData, ToriiClient, ToriiConfiguration};
+use tracing::info;
+
+use crate::{
+    dead_letters::store_dead_letter,
+    decode::{decode_event, decode_model, raw_data, raw_from_stored, DecodedModel},
+    error::Error,
+};
+
+/// Number of raw rows decoded and saved at once.
+const PAGE_SIZE: u32 = 1000;
+
+/// Outcome of a reindex.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReindexReport {
+    pub events: u64,
+    /// Events that were not stored before, which the consumers handle again.
+    pub new_events: u64,
+    pub lands: u64,
+    pub land_stakes: u64,
+    /// Rows that could not be decoded, which were stored as dead letters.
+    pub failed: u64,
+}
+
+/// Fetches every event and model from Torii into the raw storage, for the rows received before
+/// it existed.
+///
+/// Returns the number of rows that were not stored yet.
+///
+/// # Errors
+/// Returns an error if Torii or the database could not be accessed.
+pub async fn fetch_raw_data(
+    torii_url: &str,
+    world_address: Felt,
+    raw_repository: &RawDataRepository,
+) -> Result<u64, Error> {
+    let client = ToriiClient::new(&ToriiConfiguration {
+        base_url: torii_url.to_string(),
+        world_address,
+    })
+    .await?;
+
+    let events = store_stream(client.get_all_events()?, RawDataKind::Event, raw_repository).await?;
+    let models = store_stream(
+        client.get_all_entities()?,
+        RawDataKind::Model,
+        raw_repository,
+    )
+    .await?;
+
+    info!("Fetched {events} new raw events and {models} new raw models");
+    Ok(events + models)
+}
+
+async fn store_stream(
+    stream: impl Stream<Item = Result<RawToriiData, torii_client::Error>>,
+    kind: RawDataKind,
+    raw_repository: &RawDataRepository,
+) -> Result<u64, Error> {
+    let mut stream = std::pin::pin!(stream);
+    let mut stored = 0;
+    let mut page = Vec::new();
+
+    while let Some(raw) = stream.next().await {
+        page.extend(raw_data(kind, &raw?));
+        if page.len() >= PAGE_SIZE as usize {
+            stored += raw_repository.save_many(&std::mem::take(&mut page)).await?;
+        }
+    }
+    stored += raw_repository.save_many(&page).await?;
+
+    Ok(stored)
+}
+
+/// Rebuilds the derived tables from the raw storage with the current decoders, then swaps them in
+/// at once. Running it again gives the same tables.
+///
+/// The events that were not stored before, such as the ones that failed to decode, are handled
+/// as if just received: they are stored after the cursors of the consumers, which handle them
+/// on their next start, and their gg.xyz actions are queued if the repository is set up to.
+///
+/// # Errors
+/// Returns an error if the database could not be accessed, or if the rebuilt tables hold fewer
+/// events, lands or land stakes than the ones in use and `force` is not set, in which case the
+/// tables in use are left untouched.
+pub async fn reindex(
+    raw_repository: &RawDataRepository,
+    reindex_repository: &ReindexRepository,
+    dead_letters: &DeadLetterRepository,
+    auction_token: &str,
+    force: bool,
+) -> Result<ReindexReport, Error> {
+    let mut report = ReindexReport::default();
+    let shadow = reindex_repository.create_shadow().await?;
+
+    let event_repository = EventRepository::new(shadow.clone());
+    let mut range = None;
+    let mut after = 0;
+    loop {
+        let (rows, next) = raw_repository
+            .get_page(RawDataKind::Event, after, PAGE_SIZE)
+            .await?;
+        if rows.is_empty() {
+            break;
+        }
+        after = next;
+
+        let mut events = Vec::with_capacity(rows.len());
+        for raw in rows.iter().filter_map(raw_from_stored) {
+            match decode_event(raw) {
+                Ok(event) => events.push(event),
+                Err(letter) => {
+                    report.failed += 1;
+                    store_dead_letter(dead_letters, &letter).await;
+                }
+            }
+        }
+
+        for event in &events {
+            let (from, to) = range.get_or_insert((event.at, event.at));
+            *from = event.at.min(*from);
+            *to = event.at.max(*to);
+        }
+        report.events += event_repository.save_events(events).await?.len() as u64;
+    }
+
+    // The aggregates are derived from the events, so they are rebuilt the same way
+    if let Some((from, to)) = range {
+        MarketRepository::new(shadow.clone())
+            .refresh(from, to, auction_token)
+            .await?;
+    }
+
+    let land_repository = LandRepository::new(shadow.clone());
+    let land_stake_repository = LandStakeRepository::new(shadow.clone());
+    let mut after = 0;
+    loop {
+        let (rows, next) = raw_repository
+            .get_page(RawDataKind::Model, after, PAGE_SIZE)
+            .await?;
+        if rows.is_empty() {
+            break;
+        }
+        after = next;
+
+        let (lands, land_stakes) = decode_models(&rows, dead_letters, &mut report).await;
+        report.lands += land_repository.save_many(&lands).await?;
+        report.land_stakes += land_stake_repository.save_many(&land_stakes).await?;
+    }
+
+    // Tenures and the tax ledger are derived from both the events and the models
+    LandTenureRepository::new(shadow.clone())
+        .refresh_all()
+        .await?;
+    TaxLedgerRepository::new(shadow.clone())
+        .record_missing()
+        .await?;
+
+    shadow.writer().close().await;
+    report.new_events = reindex_repository.swap(force).await?.len() as u64;
+
+    Ok(report)
+}
+
+async fn decode_models(
+    rows: &[RawData],
+    dead_letters: &DeadLetterRepository,
+    report: &mut ReindexReport,
+) -> (Vec<LandModel>, Vec<LandStakeModel>) {
+    let mut lands = Vec::new();
+    let mut land_stakes = Vec::new();
+
+    for raw in rows.iter().filter_map(raw_from_stored) {
+        match decode_model(raw) {
+            Ok(Some(DecodedModel::Land(land))) => lands.push(land),
+            Ok(Some(DecodedModel::LandStake(land_stake))) => land_stakes.push(land_stake),
+            Ok(None) => {}
+            Err(letter) => {
+                report.failed += 1;
+                store_dead_letter(dead_letters, &letter).await;
+            }
+        }
+    }
+
+    (lands, land_stakes)
+}
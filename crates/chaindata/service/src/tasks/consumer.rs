@@ -0,0 +1,488 @@
+use std::{sync::Arc, time::Duration};
+
+use chaindata_models::events::{FetchedEvent, StoredEvent};
+use chaindata_repository::{CursorStore, EventStore, Pagination};
+use tokio::{
+    select,
+    sync::{mpsc::UnboundedReceiver, oneshot, Mutex},
+};
+use tracing::{error, info, warn};
+
+use crate::consumers::EventConsumer;
+
+use super::Task;
+
+/// Number of failed attempts at an event logged as warnings, before they are logged as errors.
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Delay before the first retry, doubled on each attempt.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest delay between two attempts at an event.
+const MAX_RETRY_DELAY: Duration = Duration::from_mins(5);
+
+/// Maximum number of events handled at once.
+const MAX_BATCH_SIZE: usize = Pagination::MAX_LIMIT as usize;
+
+/// `ConsumerTask` feeds a consumer with the events of its channel, in order, after replaying
+/// the ones stored since its cursor.
+///
+/// Events are followed in the order they were stored, given by their sequence number, so that
+/// the ones stored late (out of chain order, after a reorg or a reindex) are not skipped.
+///
+/// A consumer without a cursor only gets the events dispatched after its first start, after
+/// catching up with the ones stored before.
+pub struct ConsumerTask {
+    consumer: Arc<dyn EventConsumer>,
+    event_repository: Arc<dyn EventStore>,
+    cursor_repository: Arc<dyn CursorStore>,
+    receiver: Mutex<UnboundedReceiver<StoredEvent>>,
+}
+
+impl ConsumerTask {
+    #[must_use]
+    pub fn new(
+        consumer: Arc<dyn EventConsumer>,
+        event_repository: Arc<dyn EventStore>,
+        cursor_repository: Arc<dyn CursorStore>,
+        receiver: UnboundedReceiver<StoredEvent>,
+    ) -> Self {
+        Self {
+            consumer,
+            event_repository,
+            cursor_repository,
+            receiver: Mutex::new(receiver),
+        }
+    }
+
+    /// Handles a batch of events until it succeeds, then moves the cursor past it.
+    ///
+    /// A failing batch stays pending, as moving past it would lose it for the consumer: it is
+    /// retried with a growing delay, and the following events wait for it.
+    ///
+    /// Returns `false` if the task was stopped before the batch could be handled.
+    async fn process(
+        &self,
+        batch: Vec<StoredEvent>,
+        cursor: &mut Option<i64>,
+        stop: &mut oneshot::Receiver<()>,
+    ) -> bool {
+        let id = self.consumer.id();
+        let Some(last_seq) = batch.last().map(|stored| stored.seq) else {
+            return true;
+        };
+        let events: Vec<FetchedEvent> = batch.into_iter().map(|stored| stored.event).collect();
+        let first = &events[0].id;
+
+        let mut delay = RETRY_DELAY;
+        let mut attempt = 1;
+        while let Err(err) = self.consumer.handle_batch(&events).await {
+            if attempt < MAX_ATTEMPTS {
+                warn!(
+                    "Consumer {id} failed to handle {} events from {first:?} (attempt {attempt}): \
+                     {err}",
+                    events.len()
+                );
+            } else {
+                error!(
+                    "Consumer {id} still fails to handle {} events from {first:?} \
+                     (attempt {attempt}), retrying in {delay:?}: {err}",
+                    events.len()
+                );
+            }
+
+            select! {
+                () = tokio::time::sleep(delay) => {},
+                _ = &mut *stop => {
+                    info!("Received stop signal, consumer {id} leaves the events from {first:?} pending");
+                    return false;
+                }
+            }
+            delay = (delay * 2).min(MAX_RETRY_DELAY);
+            attempt += 1;
+        }
+
+        if let Err(err) = self.cursor_repository.save_cursor(id, last_seq).await {
+            error!("Failed to save the cursor of consumer {id}: {err}");
+        }
+        *cursor = Some(last_seq);
+
+        true
+    }
+
+    /// Handles the events stored after the cursor, until there is none left.
+    ///
+    /// Returns `false` if the task was stopped before the end.
+    async fn replay(
+        &self,
+        cursor: &mut Option<i64>,
+        stop: &mut oneshot::Receiver<()>,
+    ) -> Result<bool, chaindata_repository::Error> {
+        let event_types = self.consumer.event_types();
+        let mut count = 0;
+
+        while let Some(after) = *cursor {
+            let events = self
+                .event_repository
+                .get_events_after_seq(&event_types, after, Pagination::MAX_LIMIT)
+                .await?;
+
+            if events.is_empty() {
+                break;
+            }
+
+            count += events.len();
+            if !self.process(events, cursor, stop).await {
+                return Ok(false);
+            }
+        }
+
+        if count > 0 {
+            info!("Consumer {} replayed {count} events", self.consumer.id());
+        }
+
+        Ok(true)
+    }
+}
+
+#[async_trait::async_trait]
+impl Task for ConsumerTask {
+    const NAME: &'static str = "ConsumerTask";
+
+    fn name(&self) -> String {
+        format!("{}({})", Self::NAME, self.consumer.id())
+    }
+
+    async fn do_task(self: Arc<Self>, mut rx: oneshot::Receiver<()>) {
+        let id = self.consumer.id();
+        info!("Starting consumer {id}");
+
+        // Only one instance may read the channel
+        let mut receiver = self.receiver.lock().await;
+
+        let mut cursor = match self.cursor_repository.get_cursor(id).await {
+            Ok(cursor) => cursor,
+            Err(err) => {
+                error!("Failed to get the cursor of consumer {id}: {err}");
+                return;
+            }
+        };
+
+        if cursor.is_none() {
+            if let Err(err) = self.consumer.catch_up().await {
+                error!("Consumer {id} failed to catch up with the stored events: {err}");
+                return;
+            }
+        }
+
+        match self.replay(&mut cursor, &mut rx).await {
+            Ok(true) => {}
+            Ok(false) => return,
+            Err(err) => {
+                error!("Failed to replay the events of consumer {id}: {err}");
+                return;
+            }
+        }
+
+        loop {
+            select! {
+                message = receiver.recv() => {
+                    let Some(event) = message else {
+                        info!("Channel of consumer {id} closed, stopping");
+                        return;
+                    };
+
+                    // The events dispatched together are handled at once
+                    let mut batch = vec![event];
+                    while batch.len() < MAX_BATCH_SIZE {
+                        let Ok(event) = receiver.try_recv() else {
+                            break;
+                        };
+                        batch.push(event);
+                    }
+
+                    // Already handled while replaying
+                    batch.retain(|event| cursor.is_none_or(|cursor| event.seq > cursor));
+
+                    if !self.process(batch, &mut cursor, &mut rx).await {
+                        return;
+                    }
+                },
+                stop_result = &mut rx => {
+                    match stop_result {
+                        Ok(()) => info!("Received stop signal, shutting down consumer {id}"),
+                        Err(e) => info!("Stop channel closed unexpectedly: {}", e),
+                    }
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::Mutex as StdMutex};
+
+    use super::*;
+    use crate::{dispatcher::EventDispatcher, error::Error};
+    use chaindata_models::{
+        events::{actions::NewAuctionEventModel, EventDataModel, EventId, EventType, FetchedEvent},
+        shared::U256,
+    };
+    use chaindata_repository::{
+        fixtures::{self, nuked},
+        memory::MemoryRepository,
+    };
+    use chrono::Utc;
+
+    /// Records the ids of the events it handles.
+    #[derive(Clone, Default)]
+    struct RecordingConsumer {
+        event_types: Vec<EventType>,
+        handled: Arc<StdMutex<Vec<EventId>>>,
+        /// Sizes of the batches it was given.
+        batches: Arc<StdMutex<Vec<usize>>>,
+        /// Number of times handling the next events fails.
+        failures: Arc<StdMutex<u32>>,
+        /// Number of times it caught up with the stored events.
+        catch_ups: Arc<StdMutex<u32>>,
+    }
+
+    #[async_trait::async_trait]
+    impl EventConsumer for RecordingConsumer {
+        fn id(&self) -> &'static str {
+            "recording"
+        }
+
+        fn event_types(&self) -> Vec<EventType> {
+            self.event_types.clone()
+        }
+
+        async fn catch_up(&self) -> Result<(), Error> {
+            *self.catch_ups.lock().unwrap() += 1;
+            Ok(())
+        }
+
+        async fn handle(&self, event: &FetchedEvent) -> Result<(), Error> {
+            let mut failures = self.failures.lock().unwrap();
+            if *failures > 0 {
+                *failures -= 1;
+                return Err(Error::BlockSourceError("unavailable".to_string()));
+            }
+
+            self.handled.lock().unwrap().push(event.id.clone());
+            Ok(())
+        }
+
+        async fn handle_batch(&self, events: &[FetchedEvent]) -> Result<(), Error> {
+            self.batches.lock().unwrap().push(events.len());
+            for event in events {
+                self.handle(event).await?;
+            }
+            Ok(())
+        }
+    }
+
+    fn event(block: u64) -> FetchedEvent {
+        let id = EventId::new_test(block, 0, 0);
+        fixtures::event(id, Utc::now().naive_utc(), nuked(1, "0x1"))
+    }
+
+    fn auction(block: u64) -> FetchedEvent {
+        FetchedEvent {
+            id: EventId::new_test(block, 0, 0),
+            at: Utc::now().naive_utc(),
+            data: EventDataModel::NewAuction(NewAuctionEventModel {
+                id: None,
+                location: 1.into(),
+                starting_price: U256::from_str("100").unwrap(),
+                floor_price: U256::from_str("1").unwrap(),
+            }),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_then_live() {
+        let repo = Arc::new(MemoryRepository::new());
+        let consumer = RecordingConsumer::default();
+        let dispatcher = EventDispatcher::new(repo.clone(), repo.clone());
+        let task = Arc::new(dispatcher.register(consumer.clone()));
+
+        // Handled before the restart, then missed while stopped
+        let stored = repo
+            .save_events_with_raw(vec![event(1), event(2), event(3)], &[])
+            .await
+            .unwrap();
+        repo.save_cursor("recording", stored[0].seq).await.unwrap();
+
+        // Dispatched while replaying, so already handled when read from the channel
+        dispatcher.dispatch(&stored[2..]);
+
+        let stored = repo
+            .save_events_with_raw(vec![event(4)], &[])
+            .await
+            .unwrap();
+        dispatcher.dispatch(&stored);
+
+        let (stop, rx) = oneshot::channel();
+        let handle = tokio::spawn(task.do_task(rx));
+
+        while consumer.handled.lock().unwrap().len() < 3 {
+            tokio::task::yield_now().await;
+        }
+        stop.send(()).unwrap();
+        handle.await.unwrap();
+
+        assert_eq!(
+            *consumer.handled.lock().unwrap(),
+            vec![
+                EventId::new_test(2, 0, 0),
+                EventId::new_test(3, 0, 0),
+                EventId::new_test(4, 0, 0),
+            ]
+        );
+        assert_eq!(repo.get_cursor("recording").await.unwrap(), Some(4));
+        // It already had a cursor
+        assert_eq!(*consumer.catch_ups.lock().unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_event_types() {
+        let repo = Arc::new(MemoryRepository::new());
+        let consumer = RecordingConsumer {
+            event_types: vec![EventType::NewAuction],
+            ..Default::default()
+        };
+        let dispatcher = EventDispatcher::new(repo.clone(), repo.clone());
+        let task = Arc::new(dispatcher.register(consumer.clone()));
+
+        repo.save_events(vec![event(1), auction(2)]).await.unwrap();
+        repo.save_cursor("recording", 0).await.unwrap();
+        let stored = repo
+            .save_events_with_raw(vec![event(3), auction(4)], &[])
+            .await
+            .unwrap();
+        dispatcher.dispatch(&stored);
+
+        let (stop, rx) = oneshot::channel();
+        let handle = tokio::spawn(task.do_task(rx));
+
+        while consumer.handled.lock().unwrap().len() < 2 {
+            tokio::task::yield_now().await;
+        }
+        stop.send(()).unwrap();
+        handle.await.unwrap();
+
+        assert_eq!(
+            *consumer.handled.lock().unwrap(),
+            vec![EventId::new_test(2, 0, 0), EventId::new_test(4, 0, 0)]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failing_event_stays_pending() {
+        let repo = Arc::new(MemoryRepository::new());
+        let consumer = RecordingConsumer {
+            failures: Arc::new(StdMutex::new(MAX_ATTEMPTS + 2)),
+            ..Default::default()
+        };
+        let dispatcher = EventDispatcher::new(repo.clone(), repo.clone());
+        let task = Arc::new(dispatcher.register(consumer.clone()));
+
+        let stored = repo
+            .save_events_with_raw(vec![event(1), event(2)], &[])
+            .await
+            .unwrap();
+        dispatcher.dispatch(&stored);
+
+        let (stop, rx) = oneshot::channel();
+        let handle = tokio::spawn(task.clone().do_task(rx));
+
+        // Still failing on the first event, which the cursor must not move past
+        tokio::time::sleep(RETRY_DELAY * 2).await;
+        assert!(consumer.handled.lock().unwrap().is_empty());
+        assert_eq!(repo.get_cursor("recording").await.unwrap(), None);
+
+        while consumer.handled.lock().unwrap().len() < 2 {
+            tokio::time::sleep(RETRY_DELAY).await;
+        }
+        stop.send(()).unwrap();
+        handle.await.unwrap();
+
+        assert_eq!(
+            *consumer.handled.lock().unwrap(),
+            vec![EventId::new_test(1, 0, 0), EventId::new_test(2, 0, 0)]
+        );
+        assert_eq!(repo.get_cursor("recording").await.unwrap(), Some(2));
+        assert_eq!(*consumer.catch_ups.lock().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_late_event() {
+        let repo = Arc::new(MemoryRepository::new());
+        let consumer = RecordingConsumer::default();
+        let dispatcher = EventDispatcher::new(repo.clone(), repo.clone());
+        let task = Arc::new(dispatcher.register(consumer.clone()));
+
+        let stored = repo
+            .save_events_with_raw(vec![event(1), event(3)], &[])
+            .await
+            .unwrap();
+        repo.save_cursor("recording", stored[1].seq).await.unwrap();
+
+        // Stored after a later event was handled, from a page that came late
+        let stored = repo
+            .save_events_with_raw(vec![event(2)], &[])
+            .await
+            .unwrap();
+        dispatcher.dispatch(&stored);
+
+        let (stop, rx) = oneshot::channel();
+        let handle = tokio::spawn(task.do_task(rx));
+
+        while consumer.handled.lock().unwrap().is_empty() {
+            tokio::task::yield_now().await;
+        }
+        stop.send(()).unwrap();
+        handle.await.unwrap();
+
+        assert_eq!(
+            *consumer.handled.lock().unwrap(),
+            vec![EventId::new_test(2, 0, 0)]
+        );
+        assert_eq!(repo.get_cursor("recording").await.unwrap(), Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_batches() {
+        let repo = Arc::new(MemoryRepository::new());
+        let consumer = RecordingConsumer::default();
+        let dispatcher = EventDispatcher::new(repo.clone(), repo.clone());
+        let task = Arc::new(dispatcher.register(consumer.clone()));
+
+        repo.save_cursor("recording", 0).await.unwrap();
+        let stored = repo
+            .save_events_with_raw(vec![event(1), event(2), event(3)], &[])
+            .await
+            .unwrap();
+        dispatcher.dispatch(&stored);
+
+        let (stop, rx) = oneshot::channel();
+        let handle = tokio::spawn(task.do_task(rx));
+
+        while consumer.handled.lock().unwrap().len() < 3 {
+            tokio::task::yield_now().await;
+        }
+        // Let it read the dispatched events, already replayed
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        stop.send(()).unwrap();
+        handle.await.unwrap();
+
+        // Replayed at once, and nothing left to handle from the channel
+        assert_eq!(*consumer.batches.lock().unwrap(), vec![3]);
+        assert_eq!(repo.get_cursor("recording").await.unwrap(), Some(3));
+    }
+}
@@ -0,0 +1,122 @@
+//! Retries and polling shared by the tasks delivering queued messages to external services.
+
+use std::future::Future;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use tokio::{select, sync::oneshot};
+use tracing::{error, info, warn};
+
+use crate::error::Error;
+
+/// Maximum number of messages delivered at once.
+pub(crate) const BATCH_SIZE: u32 = 100;
+
+/// Delay between two polls of the queue, once it is empty.
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How the failed deliveries are retried.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Backoff {
+    /// Number of attempts after which a message is marked as failed.
+    pub max_attempts: u32,
+    /// Delay before the second attempt, doubled after each failure.
+    pub initial_seconds: i64,
+    /// Maximum delay between two attempts.
+    pub max_seconds: i64,
+}
+
+impl Backoff {
+    /// When to try again after the given number of failed attempts, if the message is not given
+    /// up on.
+    pub(crate) fn retry_delay(&self, attempts: u32) -> Option<Duration> {
+        if attempts >= self.max_attempts {
+            return None;
+        }
+
+        let seconds = self
+            .initial_seconds
+            .saturating_mul(1 << (attempts - 1).min(20))
+            .min(self.max_seconds);
+        Some(Duration::seconds(seconds))
+    }
+
+    /// Logs a failed attempt at delivering `what`, and returns when to try again if it is not
+    /// given up on.
+    pub(crate) fn retry_at(&self, what: &str, attempts: u32, error: &str) -> Option<NaiveDateTime> {
+        let retry_at = self
+            .retry_delay(attempts)
+            .map(|delay| Utc::now().naive_utc() + delay);
+
+        if retry_at.is_some() {
+            warn!("Failed to deliver {what} (attempt {attempts}): {error}");
+        } else {
+            error!("Giving up on {what} after {attempts} attempts: {error}");
+        }
+
+        retry_at
+    }
+}
+
+/// Delivers the messages that are due until stopped, right away while there is a backlog.
+///
+/// `deliver_due` returns how many messages it tried.
+pub(crate) async fn deliver_until_stopped<F, Fut>(
+    name: &str,
+    deliver_due: F,
+    mut rx: oneshot::Receiver<()>,
+) where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<usize, Error>>,
+{
+    loop {
+        let batch_size = match deliver_due().await {
+            Ok(count) => count,
+            Err(err) => {
+                error!("Failed to deliver {name}: {}", err);
+                0
+            }
+        };
+
+        let delay = if batch_size >= BATCH_SIZE as usize {
+            std::time::Duration::ZERO
+        } else {
+            POLL_INTERVAL
+        };
+
+        select! {
+            () = tokio::time::sleep(delay) => {},
+            stop_result = &mut rx => {
+                match stop_result {
+                    Ok(()) => info!("Received stop signal, shutting down {name} delivery"),
+                    Err(e) => info!("Stop channel closed unexpectedly: {}", e),
+                }
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay() {
+        let backoff = Backoff {
+            max_attempts: 10,
+            initial_seconds: 5,
+            max_seconds: 3600,
+        };
+
+        assert_eq!(backoff.retry_delay(1), Some(Duration::seconds(5)));
+        assert_eq!(backoff.retry_delay(3), Some(Duration::seconds(20)));
+        assert_eq!(backoff.retry_delay(9), Some(Duration::seconds(1280)));
+        assert_eq!(backoff.retry_delay(10), None);
+
+        let capped = Backoff {
+            max_seconds: 60,
+            ..backoff
+        };
+        assert_eq!(capped.retry_delay(9), Some(Duration::seconds(60)));
+    }
+}
@@ -1,124 +1,73 @@
 use std::sync::Arc;
 
-use chaindata_models::events::{EventDataModel, EventId, FetchedEvent};
-use chaindata_repository::event::Repository as EventRepository;
-use chrono::Utc;
-use ponziland_models::events::EventData;
-use sqlx::error::DatabaseError;
+use chaindata_models::events::FetchedEvent;
+use chaindata_repository::{DeadLetterRepository, EventStore, RawData, RawDataKind};
 use tokio::select;
 use tokio_stream::StreamExt;
-use torii_ingester::{RawToriiData, ToriiClient};
+use torii_ingester::ToriiClient;
 use tracing::{debug, error, info};
 
-use crate::gg_xyz_api::{GGApi, PostRequest};
+use crate::{
+    dead_letters::store_dead_letter,
+    decode::{decode_event, raw_data},
+    dispatcher::EventDispatcher,
+};
 
 use super::Task;
 
+/// Maximum number of events saved at once.
+const PAGE_SIZE: usize = 500;
+
 /// `EventListenerTask` is a task that subscribes to the events of the on-chain indexer (torii),
 /// and pushes them to the local database.
 pub struct EventListenerTask {
     client: Arc<ToriiClient>,
-    event_repository: Arc<EventRepository>,
-    gg_api: Option<Arc<GGApi>>,
+    event_repository: Arc<dyn EventStore>,
+    dispatcher: Arc<EventDispatcher>,
+    dead_letter_repository: Arc<DeadLetterRepository>,
 }
 
 impl EventListenerTask {
+    #[must_use]
     pub fn new(
         client: Arc<ToriiClient>,
-        event_repository: Arc<EventRepository>,
-        gg_api: Option<Arc<GGApi>>,
+        event_repository: Arc<dyn EventStore>,
+        dispatcher: Arc<EventDispatcher>,
+        dead_letter_repository: Arc<DeadLetterRepository>,
     ) -> Self {
         Self {
             client,
             event_repository,
-            gg_api,
+            dispatcher,
+            dead_letter_repository,
         }
     }
 
-    async fn process_event(&self, event: RawToriiData) {
-        // Parse and save the event
-        let event = match event {
-            RawToriiData::Grpc(data) => {
-                debug!("Processing GRPC event");
-
-                FetchedEvent {
-                    id: EventId::new_test(0, 0, 0),
-                    at: Utc::now().naive_utc(),
-                    data: EventData::try_from(data)
-                        .expect("An error occurred while deserializing model")
-                        .into(),
-                }
-            }
-            RawToriiData::Json {
-                name,
-                data,
-                at,
-                event_id,
-            } => {
-                debug!("Processing JSON event");
-
-                FetchedEvent {
-                    id: EventId::parse_from_torii(&event_id).unwrap(),
-                    at: at.naive_utc(),
-                    data: EventData::from_json(&name, data.clone())
-                        .unwrap_or_else(|_| {
-                            panic!(
-                                "An error occurred while deserializing model for event {name}: {data:#?}"
-                            )
-                        })
-                        .into(),
-                }
-            }
-        };
-
-        if let Err(chaindata_repository::Error::SqlError(err)) =
-            self.event_repository.save_event(event.clone()).await
+    /// Saves a page of events along with their raw data, and dispatches the new ones to the
+    /// consumers, which derive the market aggregates, tenures and tax ledger from them.
+    async fn save_page(&self, events: Vec<FetchedEvent>, raw: &[RawData]) {
+        // Raw rows are kept even for the rows that could not be decoded, to decode them again
+        // after a fix, and saved along with the events so that a reindex never misses one
+        let count = events.len();
+        let inserted = match self
+            .event_repository
+            .save_events_with_raw(events, raw)
+            .await
         {
-            if !err
-                .as_database_error()
-                .is_some_and(DatabaseError::is_unique_violation)
-            {
-                error!("Failed to save event: {}", err);
+            Ok(inserted) => inserted,
+            Err(err) => {
+                error!("Failed to save events: {}", err);
+                return;
             }
-
-            // It is a duplicate, so ignore it
-            return;
-        }
-        info!("Successfully saved event!");
-
-        if let Some(gg_api) = &self.gg_api {
-            // If the event is used to submit something to gg, send it.
-            let res: Option<Vec<(String, &'static str)>> = match event.data.clone() {
-                EventDataModel::LandNuked(val) => Some(vec![(val.owner, "Land nuked")]),
-                EventDataModel::AuctionFinished(val) => {
-                    Some(vec![(val.buyer, "Bought from auction")])
-                }
-                EventDataModel::LandBought(val) => Some(vec![
-                    (val.buyer, "Bought from player"),
-                    (val.seller, "Sold land"),
-                ]),
-                EventDataModel::AddressAuthorized(val) => {
-                    Some(vec![(val.address, "Joined the Ponzi")])
-                }
-                _ => None,
-            };
-
-            if let Some(values) = res {
-                for (user, message) in values {
-                    // Send the message to gg (don't really care about the response)
-                    info!("Submitting action {message} for {user}");
-                    if let Err(err) = gg_api
-                        .send_actions(PostRequest {
-                            address: user,
-                            actions: vec![message.to_string()],
-                        })
-                        .await
-                    {
-                        error!("Error while sending message to gg: {}", err);
-                    }
-                }
-            }
-        }
+        };
+        info!(
+            "Successfully saved {} events ({} duplicates)",
+            inserted.len(),
+            count - inserted.len()
+        );
+
+        // Duplicates were already dispatched when they were first saved
+        self.dispatcher.dispatch(&inserted);
     }
 }
 
@@ -151,11 +100,35 @@ impl Task for EventListenerTask {
                 .get_all_events_after(safe_last_check)
                 .expect("Error while fetching events");
 
-            // Process events as they go
+            // Process events by pages, to save them in bulk
             let mut event_count = 0;
+            let mut page = Vec::with_capacity(PAGE_SIZE);
+            let mut raw_page = Vec::with_capacity(PAGE_SIZE);
             while let Some(event) = events_stream.next().await {
-                self.process_event(event).await;
+                let event = match event {
+                    Ok(event) => event,
+                    Err(err) => {
+                        // The rest of the events is fetched again on the next poll
+                        error!("Failed to fetch events: {}", err);
+                        page.clear();
+                        raw_page.clear();
+                        break;
+                    }
+                };
+                raw_page.extend(raw_data(RawDataKind::Event, &event));
+                match decode_event(event) {
+                    Ok(event) => page.push(event),
+                    Err(letter) => store_dead_letter(&self.dead_letter_repository, &letter).await,
+                }
                 event_count += 1;
+
+                if raw_page.len() >= PAGE_SIZE {
+                    self.save_page(std::mem::take(&mut page), &std::mem::take(&mut raw_page))
+                        .await;
+                }
+            }
+            if !raw_page.is_empty() || !page.is_empty() {
+                self.save_page(page, &raw_page).await;
             }
 
             if event_count > 0 {
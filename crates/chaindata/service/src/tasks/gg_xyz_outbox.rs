@@ -0,0 +1,188 @@
+use std::sync::Arc;
+
+use chaindata_repository::{GgXyzOutboxRepository, OutboxEntry};
+use tracing::{debug, info};
+
+use crate::{
+    decode::error_chain,
+    error::Error,
+    gg_xyz_api::{GGApi, PostRequest},
+};
+
+use super::{
+    delivery::{deliver_until_stopped, Backoff, BATCH_SIZE},
+    Task,
+};
+
+const BACKOFF: Backoff = Backoff {
+    max_attempts: 10,
+    initial_seconds: 5,
+    max_seconds: 3600,
+};
+
+/// `GgXyzOutboxTask` delivers the actions queued in the outbox to gg.xyz, retrying with a backoff
+/// while the API is unavailable.
+pub struct GgXyzOutboxTask {
+    outbox: Arc<GgXyzOutboxRepository>,
+    api: Arc<GGApi>,
+}
+
+impl GgXyzOutboxTask {
+    #[must_use]
+    pub fn new(outbox: Arc<GgXyzOutboxRepository>, api: Arc<GGApi>) -> Self {
+        Self { outbox, api }
+    }
+
+    async fn deliver(&self, entry: &OutboxEntry) -> Result<(), Error> {
+        let result = self
+            .api
+            .send_actions(
+                &entry.idempotency_key,
+                PostRequest {
+                    address: entry.player_address.clone(),
+                    actions: vec![entry.action.clone()],
+                },
+            )
+            .await;
+
+        match result {
+            Ok(()) => {
+                debug!(
+                    "Delivered action {} for {}",
+                    entry.action, entry.player_address
+                );
+                self.outbox.mark_delivered(&entry.idempotency_key).await?;
+            }
+            Err(err) => {
+                let error = error_chain(&err);
+                let retry_at = BACKOFF.retry_at(&entry.idempotency_key, entry.attempts + 1, &error);
+
+                self.outbox
+                    .record_failure(&entry.idempotency_key, &error, retry_at)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Tries to deliver the entries that are due, and returns how many were tried.
+    ///
+    /// # Errors
+    /// Returns an error if the outbox could not be accessed.
+    pub async fn deliver_due(&self) -> Result<usize, Error> {
+        let entries = self.outbox.get_due(BATCH_SIZE).await?;
+
+        for entry in &entries {
+            self.deliver(entry).await?;
+        }
+
+        Ok(entries.len())
+    }
+}
+
+#[async_trait::async_trait]
+impl Task for GgXyzOutboxTask {
+    const NAME: &'static str = "GgXyzOutboxTask";
+
+    async fn do_task(self: Arc<Self>, rx: tokio::sync::oneshot::Receiver<()>) {
+        info!("Starting GgXyzOutboxTask with 5-second polling interval");
+
+        deliver_until_stopped("gg.xyz actions", || self.deliver_due(), rx).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use chaindata_models::{
+        events::{actions::LandBoughtEventModel, EventDataModel, EventId, FetchedEvent},
+        shared::U256,
+    };
+    use chaindata_repository::{EventRepository, EventStore, OutboxStatus};
+    use chrono::Utc;
+    use migrations::MIGRATOR;
+    use reqwest::Url;
+
+    const PATH: &str = "/api/v2/action-dispatcher/dispatch/public";
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_deliver_with_retry(pool: sqlx::PgPool) -> Result<(), Error> {
+        let mut server = mockito::Server::new_async().await;
+        let events = EventRepository::new(pool.clone()).with_gg_xyz_outbox(true);
+        let outbox = Arc::new(GgXyzOutboxRepository::new(pool.clone()));
+        let api = Arc::new(GGApi::new(
+            &Url::parse(&server.url()).unwrap(),
+            "secret".to_string(),
+        ));
+        let task = GgXyzOutboxTask::new(outbox.clone(), api);
+
+        let id = EventId::new_test(1, 0, 0);
+        events
+            .save_events(vec![FetchedEvent {
+                id: id.clone(),
+                at: Utc::now().naive_utc(),
+                data: EventDataModel::LandBought(LandBoughtEventModel {
+                    id: None,
+                    location: 1.into(),
+                    buyer: "0xbuyer".to_string(),
+                    seller: "0xseller".to_string(),
+                    price: U256::from_str("10").unwrap(),
+                    token_used: "0xtoken".to_string(),
+                }),
+            }])
+            .await?;
+        let buyer_key = format!("{}:0", id.as_string());
+        let seller_key = format!("{}:1", id.as_string());
+
+        let buyer = server
+            .mock("POST", PATH)
+            .match_header("secret", "secret")
+            .match_header("idempotency-key", buyer_key.as_str())
+            .match_body(mockito::Matcher::PartialJsonString(
+                r#"{"playerAddress":"0xbuyer","actions":["Bought from player"]}"#.to_string(),
+            ))
+            .with_status(200)
+            .create_async()
+            .await;
+        let unavailable = server
+            .mock("POST", PATH)
+            .match_header("idempotency-key", seller_key.as_str())
+            .with_status(503)
+            .create_async()
+            .await;
+
+        assert_eq!(task.deliver_due().await?, 2);
+        buyer.assert_async().await;
+        unavailable.assert_async().await;
+
+        // The seller action waits for its next attempt
+        assert_eq!(task.deliver_due().await?, 0);
+        let pending = outbox.get_entries(Some(OutboxStatus::Pending), 10).await?;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].idempotency_key, seller_key);
+        assert_eq!(pending[0].attempts, 1);
+        assert!(pending[0].last_error.is_some());
+
+        // Once gg.xyz is back
+        sqlx::query("UPDATE gg_xyz_outbox SET next_attempt_at = NOW()")
+            .execute(&pool)
+            .await?;
+        unavailable.remove_async().await;
+        let seller = server
+            .mock("POST", PATH)
+            .match_header("idempotency-key", seller_key.as_str())
+            .with_status(200)
+            .create_async()
+            .await;
+
+        assert_eq!(task.deliver_due().await?, 1);
+        seller.assert_async().await;
+        let stats = outbox.get_stats().await?;
+        assert_eq!((stats.pending, stats.delivered, stats.failed), (0, 2, 0));
+
+        Ok(())
+    }
+}
@@ -1,11 +1,33 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex, PoisonError},
+};
 
-use tokio::sync::oneshot;
+use chaindata_models::shared::Location;
+use tokio::{sync::oneshot, task::JoinHandle};
 
 use tracing::{debug, error, info};
 
+pub mod consumer;
+mod delivery;
 pub mod event_listener;
+pub mod gg_xyz_outbox;
 pub mod model_listener;
+pub mod reorg;
+pub mod supervisor;
+pub mod token_mirror;
+pub mod webhook_delivery;
+
+use supervisor::{RestartPolicy, SharedHealth, TaskHealth};
+
+/// The given locations without duplicates, in the order they first appear.
+pub(crate) fn unique_locations(locations: impl IntoIterator<Item = Location>) -> Vec<Location> {
+    let mut seen = HashSet::new();
+    locations
+        .into_iter()
+        .filter(|location| seen.insert(*location))
+        .collect()
+}
 
 // TODO(Red): Migrate this to a dedicated crate, as we could add more informations later.
 
@@ -17,13 +39,23 @@ pub trait Task: Sized + Send + Sync + 'static {
 
     async fn do_task(self: Arc<Self>, stop_channel: oneshot::Receiver<()>);
 
+    /// Name of this instance of the task, in logs and health reports.
+    fn name(&self) -> String {
+        Self::NAME.to_string()
+    }
+
     fn wrap(self) -> TaskWrapper<Self> {
         TaskWrapper::new(self)
     }
 }
 
+/// Runs a task under supervision: it is restarted with a backoff if it panics or exits
+/// without being stopped, and its health is recorded.
 pub struct TaskWrapper<T: Task> {
     stop_handle: Mutex<Option<oneshot::Sender<()>>>,
+    join_handle: Mutex<Option<JoinHandle<()>>>,
+    health: SharedHealth,
+    policy: RestartPolicy,
     task: Arc<T>,
 }
 
@@ -31,29 +63,57 @@ impl<T: Task + 'static> TaskWrapper<T> {
     pub fn new(task: T) -> Self {
         Self {
             stop_handle: Mutex::new(None),
+            join_handle: Mutex::new(None),
+            health: Arc::new(Mutex::new(TaskHealth::new(task.name()))),
+            policy: RestartPolicy::default(),
             task: Arc::new(task),
         }
     }
 
+    #[must_use]
+    pub fn with_policy(mut self, policy: RestartPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Gets the current health of the task.
+    pub fn health(&self) -> TaskHealth {
+        self.health
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
     pub fn start(&self) {
         let (tx, rx) = oneshot::channel();
 
+        let Ok(mut join_handle) = self.join_handle.lock() else {
+            info!("Failed to acquire lock for starting {}", T::NAME);
+            return;
+        };
+        // The supervisor only finishes once stopped or given up on, in which case it can be restarted
+        if join_handle
+            .as_ref()
+            .is_some_and(|handle| !handle.is_finished())
+        {
+            info!("{} is already started", T::NAME);
+            return;
+        }
+
         // Store the sender in the mutex
         if let Ok(mut guard) = self.stop_handle.lock() {
-            if guard.is_some() {
-                info!("{} is already started", T::NAME);
-                return;
-            }
             *guard = Some(tx);
         } else {
             info!("Failed to acquire lock for starting {}", T::NAME);
             return;
         }
-        let task = self.task.clone();
 
-        tokio::spawn(async {
-            T::do_task(task, rx).await;
-        });
+        *join_handle = Some(tokio::spawn(supervisor::supervise(
+            self.task.clone(),
+            self.policy,
+            self.health.clone(),
+            rx,
+        )));
     }
 
     pub fn stop(&self) {
@@ -61,7 +121,7 @@ impl<T: Task + 'static> TaskWrapper<T> {
         if let Ok(mut guard) = self.stop_handle.lock() {
             // Take the sender out of the Option (replacing it with None)
             if let Some(sender) = guard.take() {
-                // Send the stop signal, ignoring errors if the receiver was dropped
+                // Send the stop signal, ignoring errors if the supervisor already gave up
                 let _ = sender.send(());
                 debug!("Stop signal sent to {}", T::NAME);
             } else {
@@ -1,19 +1,26 @@
 use std::{cmp::max, sync::Arc};
 
-use chaindata_models::{
-    events::EventId,
-    models::{LandModel, LandStakeModel},
+use chaindata_models::models::{LandModel, LandStakeModel};
+use chaindata_repository::{
+    DeadLetterRepository, LandStakeStore, LandStore, LandTenureRepository, RawData, RawDataKind,
+    TaxLedgerRepository,
 };
-use chaindata_repository::{LandRepository, LandStakeRepository};
 use chrono::{DateTime, Utc};
-use ponziland_models::models::Model;
-use sqlx::error::DatabaseError;
 use tokio::select;
 use tokio_stream::StreamExt;
 use torii_ingester::{RawToriiData, ToriiClient};
 use tracing::{debug, error, info};
 
-use super::Task;
+use crate::{
+    dead_letters::store_dead_letter,
+    decode::{decode_model, raw_data, DecodedModel},
+    error::Error,
+};
+
+use super::{unique_locations, Task};
+
+/// Maximum number of models saved at once.
+const PAGE_SIZE: usize = 500;
 
 /// `ModelsListenerTask` is a task that subscribes to some models of the on-chain indexer (torii),
 /// and pushes them to the local database.
@@ -24,26 +31,36 @@ use super::Task;
 /// - Auctions (soon, TODO)
 pub struct ModelListenerTask {
     client: Arc<ToriiClient>,
-    land_repository: Arc<LandRepository>,
-    land_stake_repository: Arc<LandStakeRepository>,
+    land_repository: Arc<dyn LandStore>,
+    land_stake_repository: Arc<dyn LandStakeStore>,
+    dead_letter_repository: Arc<DeadLetterRepository>,
+    tenure_repository: Arc<LandTenureRepository>,
+    tax_ledger_repository: Arc<TaxLedgerRepository>,
 }
 
 impl ModelListenerTask {
+    #[must_use]
     pub fn new(
         client: Arc<ToriiClient>,
-        land_repository: Arc<LandRepository>,
-        land_stake_repository: Arc<LandStakeRepository>,
+        land_repository: Arc<dyn LandStore>,
+        land_stake_repository: Arc<dyn LandStakeStore>,
+        dead_letter_repository: Arc<DeadLetterRepository>,
+        tenure_repository: Arc<LandTenureRepository>,
+        tax_ledger_repository: Arc<TaxLedgerRepository>,
     ) -> Self {
         Self {
             client,
             land_repository,
             land_stake_repository,
+            dead_letter_repository,
+            tenure_repository,
+            tax_ledger_repository,
         }
     }
 
     /// Gets the most recent update time across all model tables.
     /// This is used to determine where to start when catching up with model updates.
-    async fn get_last_update_time(&self) -> Result<DateTime<Utc>, sqlx::Error> {
+    async fn get_last_update_time(&self) -> Result<DateTime<Utc>, Error> {
         // If we did not start indexing, start from the beginning
         let fallback_time = DateTime::UNIX_EPOCH.naive_utc();
 
@@ -62,46 +79,79 @@ impl ModelListenerTask {
         Ok(max(land_latest, land_stake_latest).and_utc())
     }
 
-    #[allow(clippy::match_wildcard_for_single_variants)]
-    async fn process_model(&self, model_data: RawToriiData) {
-        let model = Model::parse(model_data).expect("Error while parsing model data");
-        let result = match model.model {
-            Model::Land(land) => {
-                self.land_repository
-                    .save(LandModel::from_at(
-                        &land,
-                        EventId::parse_from_torii(&model.event_id.unwrap()).unwrap(),
-                        model.timestamp.unwrap_or(Utc::now()).naive_utc(),
-                    ))
-                    .await
-            }
-            Model::LandStake(land_stake) => {
-                self.land_stake_repository
-                    .save(LandStakeModel::from_at(
-                        &land_stake,
-                        EventId::parse_from_torii(&model.event_id.unwrap()).unwrap(),
-                        model.timestamp.unwrap_or(Utc::now()).naive_utc(),
-                    ))
-                    .await
-            }
-            _ => {
-                //TODO: Implement this later
+    /// Buffers the model into the right page, if it is supported.
+    async fn process_model(&self, model_data: RawToriiData, page: &mut ModelPage) {
+        let raw = raw_data(RawDataKind::Model, &model_data);
+        match decode_model(model_data) {
+            Ok(Some(DecodedModel::LandStake(land_stake))) => {
+                page.land_stakes.push(land_stake);
+                page.land_stake_raw.extend(raw);
                 return;
             }
-        };
-
-        if let Err(chaindata_repository::Error::SqlError(err)) = result {
-            if !err
-                .as_database_error()
-                .is_some_and(DatabaseError::is_unique_violation)
-            {
-                error!("Failed to save event: {}", err);
-            }
+            Ok(Some(DecodedModel::Land(land))) => page.lands.push(land),
+            Ok(None) => {}
+            Err(letter) => store_dead_letter(&self.dead_letter_repository, &letter).await,
+        }
+        page.raw.extend(raw);
+    }
+
+    /// Saves the buffered models in bulk, and empties the page.
+    async fn save_page(&self, page: &mut ModelPage) {
+        // Raw rows are kept even for the rows that could not be decoded, to decode them again
+        // after a fix, and saved along with the versions so that a reindex never misses one
+        match self
+            .land_repository
+            .save_many_with_raw(&page.lands, &page.raw)
+            .await
+        {
+            Ok(inserted) => info!("Successfully saved {inserted} lands"),
+            Err(err) => error!("Failed to save lands: {}", err),
+        }
+        match self
+            .land_stake_repository
+            .save_many_with_raw(&page.land_stakes, &page.land_stake_raw)
+            .await
+        {
+            Ok(inserted) => info!("Successfully saved {inserted} land stakes"),
+            Err(err) => error!("Failed to save land stakes: {}", err),
+        }
 
-            // It is a duplicate, so ignore it
-            return;
+        // Tenures and the tax ledger read the versions, which can be stored after the events
+        let locations = unique_locations(
+            page.lands
+                .iter()
+                .map(|land| land.location)
+                .chain(page.land_stakes.iter().map(|stake| stake.location)),
+        );
+        if let Err(err) = self.tenure_repository.refresh(&locations).await {
+            error!("Failed to refresh land tenures: {}", err);
+        }
+        if let Err(err) = self.tax_ledger_repository.resolve_owners(&locations).await {
+            error!("Failed to resolve tax owners: {}", err);
         }
-        info!("Successfully saved event!");
+
+        page.lands.clear();
+        page.land_stakes.clear();
+        page.raw.clear();
+        page.land_stake_raw.clear();
+    }
+}
+
+/// Models waiting to be saved, by type.
+#[derive(Default)]
+struct ModelPage {
+    lands: Vec<LandModel>,
+    land_stakes: Vec<LandStakeModel>,
+    /// Raw rows saved along with the lands, including the ones that were not decoded.
+    raw: Vec<RawData>,
+    /// Raw rows saved along with the land stakes.
+    land_stake_raw: Vec<RawData>,
+}
+
+impl ModelPage {
+    /// Number of rows in the page, including the ones that were not decoded.
+    fn len(&self) -> usize {
+        (self.lands.len() + self.land_stakes.len()).max(self.raw.len() + self.land_stake_raw.len())
     }
 }
 
@@ -129,11 +179,28 @@ impl Task for ModelListenerTask {
                 .get_all_entities_after(last_check)
                 .expect("Error while fetching entities");
 
-            // Process models as they go
+            // Process models by pages, to save them in bulk
             let mut model_count = 0;
+            let mut page = ModelPage::default();
             while let Some(model) = models_stream.next().await {
-                self.process_model(model).await;
+                let model = match model {
+                    Ok(model) => model,
+                    Err(err) => {
+                        // The rest of the models is fetched again on the next poll
+                        error!("Failed to fetch models: {}", err);
+                        page = ModelPage::default();
+                        break;
+                    }
+                };
+                self.process_model(model, &mut page).await;
                 model_count += 1;
+
+                if page.len() >= PAGE_SIZE {
+                    self.save_page(&mut page).await;
+                }
+            }
+            if page.len() > 0 {
+                self.save_page(&mut page).await;
             }
 
             if model_count > 0 {
@@ -0,0 +1,388 @@
+use std::{collections::HashSet, sync::Arc};
+
+use chaindata_repository::{
+    EventFilter, EventStore, Pagination, ReorgRepository, Rollback, TrackedBlock,
+};
+use chrono::Utc;
+use tokio::select;
+use tracing::{debug, error, info, warn};
+
+use crate::{blocks::BlockSource, error::Error};
+
+use super::Task;
+
+/// Number of most recent blocks whose hashes are kept.
+const TRACKED_BLOCKS: u64 = 64;
+
+/// Blocks of events older than this (in minutes) are considered final, and are not tracked.
+const TRACKING_PERIOD_MINUTES: i64 = 30;
+
+/// `ReorgTask` watches the recent blocks we stored events from, and rolls back what was stored
+/// from the blocks that were reorganized.
+///
+/// The hash of a block is recorded when its events are first seen. If the chain later gives
+/// another hash for it, everything from this block onwards is removed, and the listeners
+/// fetch the new version of the chain on their next poll.
+pub struct ReorgTask {
+    block_source: Arc<dyn BlockSource>,
+    event_repository: Arc<dyn EventStore>,
+    reorg_repository: Arc<ReorgRepository>,
+    /// Token in which auctions are paid, to recompute the market aggregates.
+    auction_token: String,
+}
+
+impl ReorgTask {
+    #[must_use]
+    pub fn new(
+        block_source: Arc<dyn BlockSource>,
+        event_repository: Arc<dyn EventStore>,
+        reorg_repository: Arc<ReorgRepository>,
+        auction_token: String,
+    ) -> Self {
+        Self {
+            block_source,
+            event_repository,
+            reorg_repository,
+            auction_token,
+        }
+    }
+
+    /// Rolls back the reorganized blocks if there are any, and tracks the new ones otherwise.
+    ///
+    /// # Errors
+    /// Returns an error if the chain or the database could not be reached.
+    pub async fn check(&self) -> Result<Option<Rollback>, Error> {
+        let tracked = self.reorg_repository.get_tracked_blocks().await?;
+
+        if let Some(fork) = self.find_fork(&tracked).await? {
+            let rollback = self
+                .reorg_repository
+                .rollback_from(fork, &self.auction_token)
+                .await?;
+            warn!(
+                "Chain reorganized from block {fork}: rolled back {} events, {} lands and {} stakes",
+                rollback.events, rollback.lands, rollback.land_stakes
+            );
+
+            return Ok(Some(rollback));
+        }
+
+        self.track_new_blocks(&tracked).await?;
+        Ok(None)
+    }
+
+    /// Finds the oldest block whose hash changed since it was tracked, above the newest
+    /// one that did not.
+    ///
+    /// Blocks the node does not know yet are skipped, only a different hash being a fork.
+    ///
+    /// `tracked` must be ordered from the most recent block.
+    async fn find_fork(&self, tracked: &[TrackedBlock]) -> Result<Option<u64>, Error> {
+        let mut fork = None;
+
+        for block in tracked {
+            let Some(hash) = self.block_source.block_hash(block.number).await? else {
+                // The node has not caught up with the block yet, which is not a fork
+                continue;
+            };
+            if format!("{hash:#x}") == block.hash {
+                break;
+            }
+            fork = Some(block.number);
+        }
+
+        Ok(fork)
+    }
+
+    /// Records the hashes of the blocks of the recent events that are not tracked yet.
+    async fn track_new_blocks(&self, tracked: &[TrackedBlock]) -> Result<(), Error> {
+        let known: HashSet<u64> = tracked.iter().map(|block| block.number).collect();
+        let filter = EventFilter {
+            from_time: Some(
+                Utc::now().naive_utc() - chrono::Duration::minutes(TRACKING_PERIOD_MINUTES),
+            ),
+            ..Default::default()
+        };
+
+        let mut numbers = Vec::new();
+        let mut pagination = Pagination::new(None, Pagination::MAX_LIMIT);
+        loop {
+            let events = self
+                .event_repository
+                .get_events(&filter, &pagination)
+                .await?;
+            let Some(last) = events.last() else {
+                break;
+            };
+            pagination.after = Some(last.id.clone());
+
+            numbers.extend(
+                events
+                    .iter()
+                    .filter_map(|event| event.id.block_number())
+                    .filter(|number| !known.contains(number)),
+            );
+        }
+        numbers.sort_unstable();
+        numbers.dedup();
+
+        let mut blocks = Vec::with_capacity(numbers.len());
+        for number in numbers {
+            // The block might not be visible to the node yet, it will be tracked on the next check
+            if let Some(hash) = self.block_source.block_hash(number).await? {
+                blocks.push(TrackedBlock {
+                    number,
+                    hash: format!("{hash:#x}"),
+                });
+            }
+        }
+
+        if !blocks.is_empty() {
+            debug!("Tracking {} new blocks", blocks.len());
+            self.reorg_repository.track_blocks(&blocks).await?;
+        }
+
+        let newest = blocks.iter().chain(tracked).map(|block| block.number).max();
+        if let Some(newest) = newest {
+            self.reorg_repository
+                .prune_blocks(newest.saturating_sub(TRACKED_BLOCKS - 1))
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Task for ReorgTask {
+    const NAME: &'static str = "ReorgTask";
+
+    async fn do_task(self: Arc<Self>, mut rx: tokio::sync::oneshot::Receiver<()>) {
+        info!("Starting ReorgTask with 10-second polling interval");
+
+        loop {
+            if let Err(err) = self.check().await {
+                error!("Failed to check for chain reorganizations: {}", err);
+            }
+
+            select! {
+                () = tokio::time::sleep(std::time::Duration::from_secs(10)) => {},
+                stop_result = &mut rx => {
+                    match stop_result {
+                        Ok(()) => info!("Received stop signal, shutting down reorg detection"),
+                        Err(e) => info!("Stop channel closed unexpectedly: {}", e),
+                    }
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, str::FromStr, sync::Mutex};
+
+    use super::*;
+    use crate::reindex::reindex;
+    use chaindata_models::{
+        events::EventId,
+        shared::{Location, U256},
+    };
+    use chaindata_repository::{
+        fixtures::{bought, event, land, nuked, raw_nuked, stake},
+        Bucket, CursorRepository, CursorStore, DeadLetterRepository, EventRepository,
+        LandRepository, LandStakeRepository, LandStakeStore, LandStore, MarketRepository,
+        RawDataRepository, ReindexRepository, TimeRange,
+    };
+    use chrono::Utc;
+    use migrations::MIGRATOR;
+    use starknet::core::types::Felt;
+
+    /// Gives the hashes it was told, as a node following either fork would.
+    #[derive(Default)]
+    struct FakeBlockSource {
+        hashes: Mutex<HashMap<u64, Felt>>,
+    }
+
+    impl FakeBlockSource {
+        fn set_chain(&self, blocks: &[u64], fork: u64) {
+            let mut hashes = self.hashes.lock().unwrap();
+            for &block in blocks {
+                hashes.insert(block, Felt::from(fork * 1000 + block));
+            }
+        }
+
+        /// Forgets the given blocks, as a node lagging behind would.
+        fn forget(&self, blocks: &[u64]) {
+            let mut hashes = self.hashes.lock().unwrap();
+            for block in blocks {
+                hashes.remove(block);
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl BlockSource for FakeBlockSource {
+        async fn block_hash(&self, number: u64) -> Result<Option<Felt>, Error> {
+            Ok(self.hashes.lock().unwrap().get(&number).copied())
+        }
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_rollback_reorganized_blocks(pool: sqlx::PgPool) -> Result<(), Error> {
+        let events = Arc::new(EventRepository::new(pool.clone()));
+        let lands = LandRepository::new(pool.clone());
+        let land_stakes = LandStakeRepository::new(pool.clone());
+        let market = MarketRepository::new(pool.clone());
+        let cursors = CursorRepository::new(pool.clone());
+        let reorgs = Arc::new(ReorgRepository::new(pool));
+        let source = Arc::new(FakeBlockSource::default());
+        let task = ReorgTask::new(
+            source.clone(),
+            events.clone(),
+            reorgs.clone(),
+            "0xtoken".to_string(),
+        );
+
+        let now = Utc::now().naive_utc();
+        // Each block has a buyer and a stake of its own
+        let bought = |block: u64, price| {
+            let buyer = format!("0x{block}");
+            event(
+                EventId::new_test(block, 0, 0),
+                now,
+                bought(1, &buyer, "0xseller", price),
+            )
+        };
+        let stored = events
+            .save_events_with_raw(vec![bought(1, "10"), bought(2, "20"), bought(3, "30")], &[])
+            .await?;
+        for block in 1..=3 {
+            let id = EventId::new_test(block, 0, 1);
+            lands
+                .save(land(id.clone(), now, 1, &format!("0x{block}")))
+                .await?;
+            land_stakes
+                .save(stake(id, now, 1, &block.to_string()))
+                .await?;
+        }
+        market.refresh(now, now, "0xtoken").await?;
+        cursors.save_cursor("consumer", stored[2].seq).await?;
+
+        // Nothing to roll back, the blocks get tracked
+        source.set_chain(&[1, 2, 3], 0);
+        assert_eq!(task.check().await?, None);
+        assert_eq!(reorgs.get_tracked_blocks().await?.len(), 3);
+
+        // A lagging node does not know the recent blocks yet
+        source.forget(&[2, 3]);
+        assert_eq!(task.check().await?, None);
+        assert_eq!(reorgs.get_tracked_blocks().await?.len(), 3);
+
+        // Blocks 2 and 3 are replaced
+        source.set_chain(&[2, 3], 1);
+        let rollback = task.check().await?.expect("the reorg should be detected");
+        assert_eq!(
+            rollback,
+            Rollback {
+                events: 2,
+                lands: 2,
+                land_stakes: 2,
+            }
+        );
+
+        let current = lands.get_current_at_location(Location::new(1)).await?;
+        assert_eq!(current.map(|land| land.owner), Some("0x1".to_string()));
+        let current = land_stakes
+            .get_current_at_location(Location::new(1))
+            .await?;
+        assert_eq!(
+            current.map(|stake| stake.amount),
+            Some(U256::from_str("1").unwrap())
+        );
+
+        let aggregates = market
+            .get_aggregates(Bucket::Hour, Some("0xtoken"), TimeRange::default())
+            .await?;
+        assert_eq!(aggregates[0].trade_count, 1);
+        assert_eq!(
+            reorgs.get_tracked_blocks().await?,
+            vec![TrackedBlock {
+                number: 1,
+                hash: format!("{:#x}", Felt::from(1u64)),
+            }]
+        );
+
+        // The listeners sync the new fork, which gets tracked in turn
+        events.save_events(vec![bought(2, "25")]).await?;
+        assert_eq!(task.check().await?, None);
+        assert_eq!(reorgs.get_tracked_blocks().await?.len(), 2);
+
+        // The consumers handle the new fork, stored after their cursor
+        let pending = events
+            .get_events_after_seq(&[], stored[2].seq, Pagination::MAX_LIMIT)
+            .await?;
+        assert_eq!(
+            pending
+                .iter()
+                .map(|stored| stored.event.id.clone())
+                .collect::<Vec<_>>(),
+            vec![EventId::new_test(2, 0, 0)]
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_reindex_after_rollback(pool: sqlx::PgPool) -> Result<(), Error> {
+        let events = Arc::new(EventRepository::new(pool.clone()));
+        let raw = RawDataRepository::new(pool.clone());
+        let reorgs = Arc::new(ReorgRepository::new(pool.clone()));
+        let source = Arc::new(FakeBlockSource::default());
+        let task = ReorgTask::new(
+            source.clone(),
+            events.clone(),
+            reorgs.clone(),
+            "0xtoken".to_string(),
+        );
+
+        let now = Utc::now().naive_utc();
+        let blocks = [1, 2, 3];
+        events
+            .save_events_with_raw(
+                blocks
+                    .iter()
+                    .map(|&block| event(EventId::new_test(block, 0, 0), now, nuked(1, "0x1")))
+                    .collect(),
+                &blocks
+                    .iter()
+                    .map(|&block| raw_nuked(block, now, 1, "0x1"))
+                    .collect::<Vec<_>>(),
+            )
+            .await?;
+
+        source.set_chain(&blocks, 0);
+        assert_eq!(task.check().await?, None);
+
+        // Blocks 2 and 3 are replaced, their raw rows go along with their events
+        source.set_chain(&[2, 3], 1);
+        task.check().await?.expect("the reorg should be detected");
+        assert_eq!(raw.count().await?, 1);
+
+        // So the rolled back events are not rebuilt
+        let report = reindex(
+            &raw,
+            &ReindexRepository::new(pool.clone()),
+            &DeadLetterRepository::new(pool),
+            "0xtoken",
+            false,
+        )
+        .await?;
+        assert_eq!(report.events, 1);
+        assert_eq!(report.new_events, 0);
+        assert_eq!(report.failed, 0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,252 @@
+use std::{
+    any::Any,
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::{select, sync::oneshot, task::JoinError, time::Instant};
+use tracing::{error, info, warn};
+
+use super::Task;
+
+/// How a supervised task is restarted when it panics or exits on its own.
+#[derive(Debug, Clone, Copy)]
+pub struct RestartPolicy {
+    /// Delay before the first restart, doubled after each consecutive failure.
+    pub initial_backoff: Duration,
+    /// Maximum delay between two restarts.
+    ///
+    /// A task that ran for longer than this before failing is considered to have recovered,
+    /// and is restarted after the initial delay again.
+    pub max_backoff: Duration,
+    /// Number of restarts after which the task is given up on, if any.
+    pub max_restarts: Option<u32>,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_mins(5),
+            max_restarts: None,
+        }
+    }
+}
+
+/// What a supervised task is currently doing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    /// Not started yet, or stopped on request.
+    Stopped,
+    Running,
+    /// Failed, and waiting before being restarted.
+    BackingOff,
+    /// Failed too many times, and will not be restarted.
+    Failed,
+}
+
+/// Health of a supervised task, for health endpoints and metrics.
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskHealth {
+    pub name: String,
+    pub state: TaskState,
+    /// Number of times the task was restarted since it was first started.
+    pub restart_count: u32,
+    /// The reason of the last failure, if the task ever failed.
+    pub last_error: Option<String>,
+    pub last_failure_at: Option<DateTime<Utc>>,
+}
+
+impl TaskHealth {
+    #[must_use]
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            state: TaskState::Stopped,
+            restart_count: 0,
+            last_error: None,
+            last_failure_at: None,
+        }
+    }
+
+    /// Whether the task is doing its work, or will be again.
+    #[must_use]
+    pub fn is_healthy(&self) -> bool {
+        self.state != TaskState::Failed
+    }
+}
+
+/// Shared health of a task, updated by its supervisor.
+pub(crate) type SharedHealth = Arc<Mutex<TaskHealth>>;
+
+fn update(health: &SharedHealth, f: impl FnOnce(&mut TaskHealth)) {
+    // The health is only ever written here, so a poisoned lock still holds a consistent value
+    f(&mut health.lock().unwrap_or_else(PoisonError::into_inner));
+}
+
+/// Extracts the message of a panic, which is usually either a `&str` or a `String`.
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+fn failure_reason(result: Result<(), JoinError>) -> String {
+    match result {
+        Ok(()) => "exited without being stopped".to_string(),
+        Err(err) if err.is_panic() => format!("panicked: {}", panic_message(&*err.into_panic())),
+        Err(err) => err.to_string(),
+    }
+}
+
+/// Runs the task until `stop` is signaled, restarting it according to the policy whenever it
+/// panics or exits on its own.
+pub(crate) async fn supervise<T: Task>(
+    task: Arc<T>,
+    policy: RestartPolicy,
+    health: SharedHealth,
+    mut stop: oneshot::Receiver<()>,
+) {
+    let name = task.name();
+    let mut backoff = policy.initial_backoff;
+
+    loop {
+        update(&health, |health| health.state = TaskState::Running);
+
+        let (run_stop, run_rx) = oneshot::channel();
+        let started_at = Instant::now();
+        let mut run = tokio::spawn(T::do_task(task.clone(), run_rx));
+
+        let reason = select! {
+            result = &mut run => failure_reason(result),
+            _ = &mut stop => {
+                // The task may already be gone, in which case there is nothing to stop
+                let _ = run_stop.send(());
+                if let Err(err) = run.await {
+                    error!("{name} failed while stopping: {}", failure_reason(Err(err)));
+                }
+                update(&health, |health| health.state = TaskState::Stopped);
+                return;
+            }
+        };
+
+        if started_at.elapsed() >= policy.max_backoff {
+            backoff = policy.initial_backoff;
+        }
+
+        let mut given_up = false;
+        update(&health, |health| {
+            health.last_error = Some(reason.clone());
+            health.last_failure_at = Some(Utc::now());
+            given_up = policy
+                .max_restarts
+                .is_some_and(|max| health.restart_count >= max);
+            health.state = if given_up {
+                TaskState::Failed
+            } else {
+                TaskState::BackingOff
+            };
+        });
+
+        if given_up {
+            error!("{name} {reason}, giving up after too many restarts");
+            return;
+        }
+        warn!("{name} {reason}, restarting in {backoff:?}");
+
+        select! {
+            () = tokio::time::sleep(backoff) => {},
+            _ = &mut stop => {
+                update(&health, |health| health.state = TaskState::Stopped);
+                return;
+            }
+        }
+
+        backoff = (backoff * 2).min(policy.max_backoff);
+        update(&health, |health| health.restart_count += 1);
+        info!("Restarting {name}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+    use crate::tasks::TaskWrapper;
+
+    /// Panics on its first runs, then runs until stopped.
+    struct FlakyTask {
+        runs: AtomicU32,
+        failures: u32,
+    }
+
+    #[async_trait::async_trait]
+    impl Task for FlakyTask {
+        const NAME: &'static str = "FlakyTask";
+
+        async fn do_task(self: Arc<Self>, rx: oneshot::Receiver<()>) {
+            let run = self.runs.fetch_add(1, Ordering::SeqCst);
+            assert!(run >= self.failures, "run {run} failed");
+
+            let _ = rx.await;
+        }
+    }
+
+    async fn wait_for(wrapper: &TaskWrapper<FlakyTask>, state: TaskState) -> TaskHealth {
+        loop {
+            let health = wrapper.health();
+            if health.state == state {
+                return health;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_restart_then_stop() {
+        let wrapper = TaskWrapper::new(FlakyTask {
+            runs: AtomicU32::new(0),
+            failures: 2,
+        });
+        assert_eq!(wrapper.health().state, TaskState::Stopped);
+
+        wrapper.start();
+        // Running again after two panics
+        loop {
+            let health = wait_for(&wrapper, TaskState::Running).await;
+            if health.restart_count == 2 {
+                assert_eq!(health.last_error.as_deref(), Some("panicked: run 1 failed"));
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        wrapper.stop();
+        wait_for(&wrapper, TaskState::Stopped).await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_give_up() {
+        let wrapper = TaskWrapper::new(FlakyTask {
+            runs: AtomicU32::new(0),
+            failures: u32::MAX,
+        })
+        .with_policy(RestartPolicy {
+            max_restarts: Some(3),
+            ..Default::default()
+        });
+
+        wrapper.start();
+        let health = wait_for(&wrapper, TaskState::Failed).await;
+        assert_eq!(health.restart_count, 3);
+        assert!(!health.is_healthy());
+    }
+}
@@ -0,0 +1,129 @@
+use std::sync::Arc;
+
+use chaindata_models::models::{TokenBalanceModel, TokenTransferModel};
+use chaindata_repository::TokenRepository;
+use chrono::DateTime;
+use tokio::select;
+use tokio_stream::StreamExt;
+use torii_ingester::ToriiClient;
+use tracing::{error, info};
+
+use crate::error::Error;
+
+use super::Task;
+
+/// Maximum number of rows saved at once.
+const PAGE_SIZE: usize = 500;
+
+/// Number of polls between two copies of the balances, as they are fetched in full.
+const BALANCE_POLLS: u32 = 5;
+
+/// `TokenMirrorTask` copies the ERC20 balances and transfers indexed by torii to the local
+/// database.
+///
+/// Transfers are fetched incrementally every minute, while the balances are copied in full
+/// every few minutes.
+pub struct TokenMirrorTask {
+    client: Arc<ToriiClient>,
+    token_repository: Arc<TokenRepository>,
+}
+
+impl TokenMirrorTask {
+    #[must_use]
+    pub fn new(client: Arc<ToriiClient>, token_repository: Arc<TokenRepository>) -> Self {
+        Self {
+            client,
+            token_repository,
+        }
+    }
+
+    /// Copies the transfers executed since the last one stored, and returns how many were new.
+    ///
+    /// # Errors
+    /// Returns an error if torii or the database could not be accessed.
+    pub async fn mirror_transfers(&self) -> Result<u64, Error> {
+        let last = self
+            .token_repository
+            .get_last_transfer_time()
+            .await?
+            .unwrap_or(DateTime::UNIX_EPOCH.naive_utc());
+
+        // Transfers are only dated to the second, so the last second is fetched again
+        let after = last.and_utc() - chrono::Duration::seconds(1);
+        let mut transfers = std::pin::pin!(self.client.get_token_transfers_after(after)?);
+
+        let mut inserted = 0;
+        let mut page = Vec::with_capacity(PAGE_SIZE);
+        while let Some(transfer) = transfers.next().await {
+            page.push(TokenTransferModel::from(transfer?));
+            if page.len() >= PAGE_SIZE {
+                inserted += self
+                    .token_repository
+                    .save_transfers(&std::mem::take(&mut page))
+                    .await?;
+            }
+        }
+        inserted += self.token_repository.save_transfers(&page).await?;
+
+        Ok(inserted)
+    }
+
+    /// Copies every balance, and returns how many were written.
+    ///
+    /// # Errors
+    /// Returns an error if torii or the database could not be accessed.
+    pub async fn mirror_balances(&self) -> Result<u64, Error> {
+        let mut balances = std::pin::pin!(self.client.get_token_balances()?);
+
+        let mut written = 0;
+        let mut page = Vec::with_capacity(PAGE_SIZE);
+        while let Some(balance) = balances.next().await {
+            page.push(TokenBalanceModel::from(balance?));
+            if page.len() >= PAGE_SIZE {
+                written += self
+                    .token_repository
+                    .save_balances(&std::mem::take(&mut page))
+                    .await?;
+            }
+        }
+        written += self.token_repository.save_balances(&page).await?;
+
+        Ok(written)
+    }
+}
+
+#[async_trait::async_trait]
+impl Task for TokenMirrorTask {
+    const NAME: &'static str = "TokenMirrorTask";
+
+    async fn do_task(self: Arc<Self>, mut rx: tokio::sync::oneshot::Receiver<()>) {
+        info!("Starting TokenMirrorTask with 60-second polling interval");
+
+        let mut polls = 0;
+        loop {
+            match self.mirror_transfers().await {
+                Ok(inserted) => info!("Mirrored {inserted} new token transfers"),
+                Err(err) => error!("Failed to mirror token transfers: {}", err),
+            }
+
+            if polls % BALANCE_POLLS == 0 {
+                match self.mirror_balances().await {
+                    Ok(written) => info!("Mirrored {written} token balances"),
+                    Err(err) => error!("Failed to mirror token balances: {}", err),
+                }
+            }
+            polls += 1;
+
+            select! {
+                () = tokio::time::sleep(std::time::Duration::from_mins(1)) => {},
+                stop_result = &mut rx => {
+                    match stop_result {
+                        Ok(()) => info!("Received stop signal, shutting down token mirroring"),
+                        Err(e) => info!("Stop channel closed unexpectedly: {}", e),
+                    }
+                    return;
+                }
+            }
+        }
+    }
+}
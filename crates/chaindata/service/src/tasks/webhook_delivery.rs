@@ -0,0 +1,223 @@
+use std::sync::Arc;
+
+use chaindata_repository::{DueDelivery, WebhookRepository};
+use chrono::Utc;
+use reqwest::header::CONTENT_TYPE;
+use tracing::{debug, info};
+
+use crate::{
+    decode::error_chain,
+    error::Error,
+    webhooks::{sign, DELIVERY_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER},
+};
+
+use super::{
+    delivery::{deliver_until_stopped, Backoff, BATCH_SIZE},
+    Task,
+};
+
+const BACKOFF: Backoff = Backoff {
+    max_attempts: 12,
+    initial_seconds: 10,
+    max_seconds: 6 * 3600,
+};
+
+/// Time given to a subscriber to answer.
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// `WebhookDeliveryTask` sends the queued deliveries to the webhooks, signed with their secret,
+/// retrying with a backoff until the subscriber accepts them.
+pub struct WebhookDeliveryTask {
+    repository: Arc<WebhookRepository>,
+    client: reqwest::Client,
+}
+
+impl WebhookDeliveryTask {
+    /// # Panics
+    /// Panics if the HTTP client cannot be created.
+    #[must_use]
+    pub fn new(repository: Arc<WebhookRepository>) -> Self {
+        Self {
+            repository,
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .expect("Could not create the webhook HTTP client"),
+        }
+    }
+
+    /// Sends a delivery, and returns the status of the response.
+    async fn send(&self, delivery: &DueDelivery) -> Result<reqwest::StatusCode, reqwest::Error> {
+        let timestamp = Utc::now().timestamp();
+
+        let response = self
+            .client
+            .post(&delivery.url)
+            .header(CONTENT_TYPE, "application/json")
+            .header(DELIVERY_HEADER, delivery.id)
+            .header(TIMESTAMP_HEADER, timestamp)
+            .header(
+                SIGNATURE_HEADER,
+                sign(&delivery.secret, timestamp, &delivery.payload),
+            )
+            .body(delivery.payload.clone())
+            .send()
+            .await?;
+
+        Ok(response.status())
+    }
+
+    async fn deliver(&self, delivery: &DueDelivery) -> Result<(), Error> {
+        let (error, response_status) = match self.send(delivery).await {
+            Ok(status) if status.is_success() => {
+                debug!("Delivered {} to {}", delivery.id, delivery.url);
+                self.repository
+                    .mark_delivered(delivery.id, status.as_u16())
+                    .await?;
+                return Ok(());
+            }
+            Ok(status) => (
+                format!("Subscriber answered {status}"),
+                Some(status.as_u16()),
+            ),
+            Err(err) => (error_chain(&err), None),
+        };
+
+        let what = format!("{} to {}", delivery.id, delivery.url);
+        let retry_at = BACKOFF.retry_at(&what, delivery.attempts + 1, &error);
+
+        self.repository
+            .record_failure(delivery.id, &error, response_status, retry_at)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Tries to send the deliveries that are due, and returns how many were tried.
+    ///
+    /// # Errors
+    /// Returns an error if the deliveries could not be accessed.
+    pub async fn deliver_due(&self) -> Result<usize, Error> {
+        let deliveries = self.repository.get_due(BATCH_SIZE).await?;
+
+        for delivery in &deliveries {
+            self.deliver(delivery).await?;
+        }
+
+        Ok(deliveries.len())
+    }
+}
+
+#[async_trait::async_trait]
+impl Task for WebhookDeliveryTask {
+    const NAME: &'static str = "WebhookDeliveryTask";
+
+    async fn do_task(self: Arc<Self>, rx: tokio::sync::oneshot::Receiver<()>) {
+        info!("Starting WebhookDeliveryTask with 5-second polling interval");
+
+        deliver_until_stopped("webhooks", || self.deliver_due(), rx).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use chaindata_models::{
+        events::{
+            actions::{LandNukedEventModel, NewAuctionEventModel},
+            EventDataModel, EventId, FetchedEvent,
+        },
+        shared::U256,
+    };
+    use chaindata_repository::{EventRepository, EventStore, NewWebhook, OutboxStatus};
+    use migrations::MIGRATOR;
+
+    use crate::{consumers::EventConsumer, webhooks::WebhookConsumer};
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_deliver_with_retry(pool: sqlx::PgPool) -> Result<(), Error> {
+        let mut server = mockito::Server::new_async().await;
+        let events = EventRepository::new(pool.clone());
+        let repository = Arc::new(WebhookRepository::new(pool.clone()));
+        let consumer = WebhookConsumer::new(repository.clone());
+        let task = WebhookDeliveryTask::new(repository.clone());
+
+        repository
+            .upsert(&NewWebhook {
+                name: "relay".to_string(),
+                url: format!("{}/hook", server.url()),
+                secret: "secret".to_string(),
+                event_types: vec![],
+                addresses: vec!["0xowner".to_string()],
+                locations: vec![],
+            })
+            .await?;
+
+        let nuked = FetchedEvent {
+            id: EventId::new_test(1, 0, 0),
+            at: Utc::now().naive_utc(),
+            data: EventDataModel::LandNuked(LandNukedEventModel {
+                id: None,
+                location: 1.into(),
+                owner: "0xowner".to_string(),
+            }),
+        };
+        let other = FetchedEvent {
+            id: EventId::new_test(2, 0, 0),
+            at: Utc::now().naive_utc(),
+            data: EventDataModel::NewAuction(NewAuctionEventModel {
+                id: None,
+                location: 1.into(),
+                starting_price: U256::from_str("100").unwrap(),
+                floor_price: U256::from_str("1").unwrap(),
+            }),
+        };
+        events
+            .save_events(vec![nuked.clone(), other.clone()])
+            .await?;
+        consumer.handle(&nuked).await?;
+        // Filtered out, as it involves no player
+        consumer.handle(&other).await?;
+
+        let unavailable = server
+            .mock("POST", "/hook")
+            .match_header(SIGNATURE_HEADER, mockito::Matcher::Regex("^sha256=".into()))
+            .with_status(503)
+            .create_async()
+            .await;
+
+        assert_eq!(task.deliver_due().await?, 1);
+        unavailable.assert_async().await;
+        assert_eq!(task.deliver_due().await?, 0);
+
+        let log = repository
+            .get_deliveries(Some(OutboxStatus::Pending), 10)
+            .await?;
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].response_status, Some(503));
+
+        // Once the subscriber is back
+        sqlx::query("UPDATE webhook_delivery SET next_attempt_at = NOW()")
+            .execute(&pool)
+            .await?;
+        unavailable.remove_async().await;
+        let delivered = server
+            .mock("POST", "/hook")
+            .match_body(mockito::Matcher::PartialJsonString(
+                r#"{"schema":1,"type":"LandNuked","data":{"owner":"0xowner"}}"#.to_string(),
+            ))
+            .with_status(204)
+            .create_async()
+            .await;
+
+        assert_eq!(task.deliver_due().await?, 1);
+        delivered.assert_async().await;
+        let log = repository.get_deliveries(None, 10).await?;
+        assert_eq!(log[0].status, OutboxStatus::Delivered);
+        assert_eq!(log[0].attempts, 2);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,247 @@
+use std::sync::Arc;
+
+use chaindata_models::events::{EventDataModel, EventType, FetchedEvent};
+use chaindata_repository::{NewWebhook, Webhook, WebhookRepository};
+use hmac::{Hmac, Mac};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use starknet::core::types::Felt;
+use tokio::{sync::Mutex, time::Instant};
+use tracing::debug;
+
+use crate::{consumers::EventConsumer, error::Error};
+
+/// Version of the payload format, sent as `schema` so that subscribers can detect changes.
+pub const PAYLOAD_SCHEMA: u32 = 1;
+
+/// Header carrying the signature of the delivery, as `sha256=<hex>`.
+pub const SIGNATURE_HEADER: &str = "X-Ponziland-Signature";
+
+/// Header carrying the signing time, as a unix timestamp.
+pub const TIMESTAMP_HEADER: &str = "X-Ponziland-Timestamp";
+
+/// Header carrying the id of the delivery, the same for every attempt.
+pub const DELIVERY_HEADER: &str = "X-Ponziland-Delivery";
+
+/// How long the enabled webhooks are cached before being loaded again, so that webhooks added
+/// to the database are used without a restart.
+const WEBHOOKS_TTL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// A webhook defined in the configuration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    /// Identifies the webhook, so that it is updated when the configuration changes.
+    pub name: String,
+    pub url: Url,
+    pub secret: String,
+    #[serde(default)]
+    pub event_types: Vec<EventType>,
+    #[serde(default)]
+    pub addresses: Vec<String>,
+    #[serde(default)]
+    pub locations: Vec<u64>,
+}
+
+impl TryFrom<WebhookConfig> for NewWebhook {
+    type Error = Error;
+
+    fn try_from(config: WebhookConfig) -> Result<Self, Self::Error> {
+        // Addresses are stored without leading zeros, as they are formatted from felts
+        let addresses = config
+            .addresses
+            .iter()
+            .map(|address| {
+                Felt::from_hex(address)
+                    .map(|felt| format!("{felt:#x}"))
+                    .map_err(|_| {
+                        Error::WebhookConfigError(format!(
+                            "invalid address {address} for webhook {}",
+                            config.name
+                        ))
+                    })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            name: config.name,
+            url: config.url.to_string(),
+            secret: config.secret,
+            event_types: config.event_types,
+            addresses,
+            locations: config.locations.into_iter().map(Into::into).collect(),
+        })
+    }
+}
+
+/// Builds the body sent to the webhooks for an event.
+///
+/// ```json
+/// { "schema": 1, "id": "...", "type": "LandBought", "at": "2025-01-01T00:00:00+00:00", "data": { ... } }
+/// ```
+///
+/// Amounts are given as decimal strings, as they do not fit in JSON numbers.
+#[must_use]
+pub fn payload(event: &FetchedEvent) -> Value {
+    let data = match &event.data {
+        EventDataModel::AuctionFinished(model) => json!({
+            "location": model.location,
+            "buyer": model.buyer,
+            "price": model.price.to_string(),
+        }),
+        EventDataModel::LandBought(model) => json!({
+            "location": model.location,
+            "buyer": model.buyer,
+            "seller": model.seller,
+            "price": model.price.to_string(),
+            "token_used": model.token_used,
+        }),
+        EventDataModel::LandNuked(model) => json!({
+            "location": model.location,
+            "owner": model.owner,
+        }),
+        EventDataModel::LandTransfer(model) => json!({
+            "from_location": model.from_location,
+            "to_location": model.to_location,
+            "token_address": model.token_address,
+            "amount": model.amount.to_string(),
+        }),
+        EventDataModel::NewAuction(model) => json!({
+            "location": model.location,
+            "starting_price": model.starting_price.to_string(),
+            "floor_price": model.floor_price.to_string(),
+        }),
+        EventDataModel::AddressAuthorized(model) => json!({ "address": model.address }),
+        EventDataModel::AddressRemoved(model) => json!({ "address": model.address }),
+        EventDataModel::VerifierUpdated(model) => json!({
+            "new_verifier": model.new_verifier,
+            "old_verifier": model.old_verifier,
+        }),
+    };
+
+    json!({
+        "schema": PAYLOAD_SCHEMA,
+        "id": event.id.as_string(),
+        "type": EventType::from(&event.data),
+        "at": event.at.and_utc().to_rfc3339(),
+        "data": data,
+    })
+}
+
+/// Signs a delivery with the secret of its webhook.
+///
+/// The signature is the hex encoded HMAC-SHA256 of `{timestamp}.{body}`, so that subscribers can
+/// reject replayed deliveries.
+#[must_use]
+#[allow(clippy::missing_panics_doc)] // HMAC accepts keys of any size
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("valid key");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Queues the deliveries of the stored events to the webhooks they match.
+///
+/// Deliveries are sent by the [`crate::tasks::webhook_delivery::WebhookDeliveryTask`].
+pub struct WebhookConsumer {
+    repository: Arc<WebhookRepository>,
+    webhooks: Mutex<Option<(Instant, Arc<Vec<Webhook>>)>>,
+}
+
+impl WebhookConsumer {
+    #[must_use]
+    pub fn new(repository: Arc<WebhookRepository>) -> Self {
+        Self {
+            repository,
+            webhooks: Mutex::new(None),
+        }
+    }
+
+    /// Gets the enabled webhooks, loading them again once the cached ones are too old.
+    async fn enabled_webhooks(&self) -> Result<Arc<Vec<Webhook>>, Error> {
+        let mut cached = self.webhooks.lock().await;
+        if let Some((loaded_at, webhooks)) = cached.as_ref() {
+            if loaded_at.elapsed() < WEBHOOKS_TTL {
+                return Ok(webhooks.clone());
+            }
+        }
+
+        let webhooks = Arc::new(self.repository.get_enabled().await?);
+        *cached = Some((Instant::now(), webhooks.clone()));
+        Ok(webhooks)
+    }
+}
+
+#[async_trait::async_trait]
+impl EventConsumer for WebhookConsumer {
+    fn id(&self) -> &'static str {
+        "webhooks"
+    }
+
+    async fn handle(&self, event: &FetchedEvent) -> Result<(), Error> {
+        let webhooks = self.enabled_webhooks().await?;
+
+        let mut body = None;
+        for webhook in webhooks.iter().filter(|webhook| webhook.matches(event)) {
+            let body = body.get_or_insert_with(|| payload(event).to_string());
+            if self.repository.enqueue(webhook.id, &event.id, body).await? {
+                debug!("Queued event {:?} for webhook {}", event.id, webhook.name);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chaindata_models::events::{actions::LandNukedEventModel, EventId};
+    use chrono::DateTime;
+
+    #[test]
+    fn test_payload_and_signature() {
+        let event = FetchedEvent {
+            id: EventId::new_test(1, 2, 3),
+            at: DateTime::UNIX_EPOCH.naive_utc(),
+            data: EventDataModel::LandNuked(LandNukedEventModel {
+                id: None,
+                location: 7.into(),
+                owner: "0x1".to_string(),
+            }),
+        };
+
+        let payload = payload(&event);
+        assert_eq!(payload["schema"], json!(1));
+        assert_eq!(payload["type"], json!("LandNuked"));
+        assert_eq!(payload["at"], json!("1970-01-01T00:00:00+00:00"));
+        assert_eq!(payload["data"], json!({ "location": 7, "owner": "0x1" }));
+
+        // Computed with `printf '1700000000.{}' | openssl dgst -sha256 -hmac secret`
+        assert_eq!(
+            sign("secret", 1_700_000_000, "{}"),
+            "sha256=b8569b78799ff9e3cbff0fc2d63a33a2b57f3282abd07c37ae5e8e7d79a5f163"
+        );
+    }
+
+    fn config(addresses: &[&str]) -> WebhookConfig {
+        WebhookConfig {
+            name: "bot".to_string(),
+            url: Url::parse("http://localhost/hook").unwrap(),
+            secret: "secret".to_string(),
+            event_types: vec![],
+            addresses: addresses.iter().map(ToString::to_string).collect(),
+            locations: vec![3],
+        }
+    }
+
+    #[test]
+    fn test_config_addresses() {
+        let webhook = NewWebhook::try_from(config(&["0x0000ABC"])).unwrap();
+        assert_eq!(webhook.addresses, vec!["0xabc".to_string()]);
+        assert!(NewWebhook::try_from(config(&["player"])).is_err());
+    }
+}
@@ -2,6 +2,7 @@ use starknet::core::codec::Error as CodecError;
 use thiserror::Error;
 
 pub mod pool_price;
+pub mod pool_state;
 
 #[derive(Debug, Error)]
 pub enum Error {
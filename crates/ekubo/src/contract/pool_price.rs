@@ -34,6 +34,27 @@ pub struct I129 {
     pub sign: bool,
 }
 
+impl From<I129> for i128 {
+    #[allow(clippy::cast_possible_wrap)] // Ticks and liquidity deltas fit in 127 bits
+    fn from(value: I129) -> Self {
+        let magnitude = value.value as i128;
+        if value.sign {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+}
+
+impl From<i128> for I129 {
+    fn from(value: i128) -> Self {
+        Self {
+            value: value.unsigned_abs(),
+            sign: value < 0,
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Debug, Decode, Encode)]
 pub struct LiquidityResponse {
     pub sqrt_ratio: U256,
@@ -0,0 +1,141 @@
+use super::{
+    pool_price::{PoolKey, I129},
+    Error,
+};
+use starknet::{
+    core::{
+        codec::{Decode, Encode},
+        types::{BlockId, BlockTag, Felt, FunctionCall},
+    },
+    macros::selector,
+};
+
+/// Number of bitmap words searched for an initialized tick before giving up on a call.
+const SKIP_AHEAD: u128 = 5;
+
+#[derive(Clone, Copy, PartialEq, Debug, Decode)]
+pub struct InitializedTickResponse {
+    pub tick: I129,
+    /// Whether the tick is initialized, or only the end of the searched words.
+    pub initialized: bool,
+}
+
+/// Read the liquidity active at the current price of a pool.
+///
+/// # Errors
+/// Returns an error if the RPC call fails or if the response cannot be decoded.
+#[cfg_attr(feature = "tracing", tracing::instrument)]
+pub async fn read_pool_liquidity<
+    T: starknet::providers::Provider + Send + Sync + std::fmt::Debug,
+>(
+    rpc_client: T,
+    contract_address: Felt,
+    pool: &PoolKey,
+) -> Result<u128, Error> {
+    let response = call(
+        rpc_client,
+        contract_address,
+        selector!("get_pool_liquidity"),
+        pool,
+        &[],
+    )
+    .await?;
+
+    Ok(u128::decode(response.iter().collect::<Vec<&Felt>>())?)
+}
+
+/// Read the change of liquidity when crossing a tick upwards.
+///
+/// # Errors
+/// Returns an error if the RPC call fails or if the response cannot be decoded.
+#[cfg_attr(feature = "tracing", tracing::instrument)]
+pub async fn read_tick_liquidity_delta<
+    T: starknet::providers::Provider + Send + Sync + std::fmt::Debug,
+>(
+    rpc_client: T,
+    contract_address: Felt,
+    pool: &PoolKey,
+    tick: I129,
+) -> Result<I129, Error> {
+    let mut arguments = Vec::new();
+    encode(&tick, &mut arguments)?;
+
+    let response = call(
+        rpc_client,
+        contract_address,
+        selector!("get_pool_tick_liquidity_delta"),
+        pool,
+        &arguments,
+    )
+    .await?;
+
+    Ok(I129::decode(response.iter().collect::<Vec<&Felt>>())?)
+}
+
+/// Read the closest initialized tick above `from`, or at or below it when `ascending` is false.
+///
+/// # Errors
+/// Returns an error if the RPC call fails or if the response cannot be decoded.
+#[cfg_attr(feature = "tracing", tracing::instrument)]
+pub async fn read_initialized_tick<
+    T: starknet::providers::Provider + Send + Sync + std::fmt::Debug,
+>(
+    rpc_client: T,
+    contract_address: Felt,
+    pool: &PoolKey,
+    from: I129,
+    ascending: bool,
+) -> Result<InitializedTickResponse, Error> {
+    let mut arguments = Vec::new();
+    encode(&from, &mut arguments)?;
+    encode(&SKIP_AHEAD, &mut arguments)?;
+
+    let entry_point_selector = if ascending {
+        selector!("next_initialized_tick")
+    } else {
+        selector!("prev_initialized_tick")
+    };
+    let response = call(
+        rpc_client,
+        contract_address,
+        entry_point_selector,
+        pool,
+        &arguments,
+    )
+    .await?;
+
+    Ok(InitializedTickResponse::decode(
+        response.iter().collect::<Vec<&Felt>>(),
+    )?)
+}
+
+fn encode(value: &impl Encode, call_data: &mut Vec<Felt>) -> Result<(), Error> {
+    value
+        .encode(call_data)
+        .map_err(|_| Error::RpcError("Impossible to encode the call data".to_string()))
+}
+
+/// Calls a view of the core contract taking a pool key followed by the given arguments.
+async fn call<T: starknet::providers::Provider + Send + Sync>(
+    rpc_client: T,
+    contract_address: Felt,
+    entry_point_selector: Felt,
+    pool: &PoolKey,
+    arguments: &[Felt],
+) -> Result<Vec<Felt>, Error> {
+    let mut call_data = Vec::new();
+    encode(pool, &mut call_data)?;
+    call_data.extend_from_slice(arguments);
+
+    rpc_client
+        .call(
+            FunctionCall {
+                contract_address,
+                entry_point_selector,
+                calldata: call_data,
+            },
+            BlockId::Tag(BlockTag::Latest),
+        )
+        .await
+        .map_err(|e| Error::RpcError(e.to_string()))
+}
@@ -1,10 +1,16 @@
 use crate::api::pool::Pool;
 use crate::contract::pool_price::PoolKey;
 use api::pool::get_all_pools;
-use contract::pool_price::read_pool_price;
+use contract::pool_price::{read_pool_price, I129};
+use contract::pool_state::{read_initialized_tick, read_pool_liquidity, read_tick_liquidity_delta};
+use ekubo_sdk::math::uint::U256;
+use math::swap::{swap_step, tick_to_sqrt_ratio};
 use math::u256fd128::U256FD128;
 use price::PairRatio;
+use quote::Quote;
 use reqwest::Client as ReqwestClient;
+use routing::{Hop, PoolGraph};
+use selection::{PoolSelector, PricedPool};
 pub use starknet::core::types::Felt;
 use std::sync::Arc;
 use thiserror::Error;
@@ -13,6 +19,12 @@ pub mod api;
 pub mod contract;
 pub mod math;
 pub mod price;
+pub mod quote;
+pub mod routing;
+pub mod selection;
+
+/// Maximum number of ticks crossed by a simulated swap, each one costing RPC calls.
+const MAX_CROSSED_TICKS: usize = 50;
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -82,4 +94,318 @@ where
         let sqrt_ratio: U256FD128 = response.sqrt_ratio.into();
         Ok(PairRatio(sqrt_ratio.squared()))
     }
+
+    /// Builds the graph of the pools pairing the given tokens, with the pool and price of each
+    /// pair chosen by the selector.
+    ///
+    /// The pairs whose pools could not be listed, and the pools whose price could not be read or
+    /// is zero, are left out, so that one failing pool does not prevent pricing the others.
+    pub async fn get_pool_graph(&self, tokens: &[Felt], selector: &PoolSelector) -> PoolGraph {
+        let mut pairs = Vec::new();
+
+        for (i, token0) in tokens.iter().enumerate() {
+            for token1 in &tokens[i + 1..] {
+                let pair_pools = match self.get_pools(*token0, *token1).await {
+                    Ok(pair_pools) => pair_pools,
+                    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+                    Err(err) => {
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!("Skipping the pools of {token0:#x}/{token1:#x}: {err}");
+                        continue;
+                    }
+                };
+
+                let mut pools = Vec::new();
+                for pool in pair_pools {
+                    match self.read_pool_price(&pool.key).await {
+                        Ok(price) if price.0 != U256FD128::ZERO => {
+                            pools.push(PricedPool { pool, price });
+                        }
+                        Ok(_) => {
+                            #[cfg(feature = "tracing")]
+                            tracing::warn!("Skipping pool {:?}: its price is zero", pool.key);
+                        }
+                        #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+                        Err(err) => {
+                            #[cfg(feature = "tracing")]
+                            tracing::warn!("Skipping pool {:?}: {err}", pool.key);
+                        }
+                    }
+                }
+                pairs.push(pools);
+            }
+        }
+
+        selector.build_graph(&pairs)
+    }
+
+    /// Simulates a swap of `amount` of `token_in` through a pool, crossing its initialized ticks
+    /// until the amount is spent.
+    ///
+    /// The fee is taken on the whole amount before the swap. The swap stops early after
+    /// crossing too many ticks, or when running out of liquidity, with the amount left unspent.
+    ///
+    /// # Errors
+    /// Returns an error if the state of the pool could not be read
+    pub async fn quote_pool(
+        &self,
+        pool: &PoolKey,
+        token_in: Felt,
+        amount: U256FD128,
+    ) -> Result<Quote, Error> {
+        let sells_token1 = token_in == pool.token1;
+        let state = read_pool_price(&self.rpc_client, self.contract_address, pool).await?;
+        let mut liquidity =
+            read_pool_liquidity(&self.rpc_client, self.contract_address, pool).await?;
+
+        let mut sqrt_ratio: U256FD128 = state.sqrt_ratio.into();
+        let mut tick = i128::from(state.tick);
+        let spot_price = if sells_token1 {
+            PairRatio(sqrt_ratio.squared()).inverse()
+        } else {
+            PairRatio(sqrt_ratio.squared())
+        };
+
+        let one = U256FD128::from_whole(1);
+        let fee = U256FD128::new(U256::from(pool.fee));
+        let after_fee = amount - amount * fee;
+        let mut remaining = after_fee;
+        let mut amount_out = U256FD128::ZERO;
+
+        for _ in 0..MAX_CROSSED_TICKS {
+            if remaining == U256FD128::ZERO {
+                break;
+            }
+
+            let next = read_initialized_tick(
+                &self.rpc_client,
+                self.contract_address,
+                pool,
+                I129::from(tick),
+                sells_token1,
+            )
+            .await?;
+            let next_tick = i128::from(next.tick);
+            let Some(target) = i64::try_from(next_tick).ok().and_then(tick_to_sqrt_ratio) else {
+                break;
+            };
+
+            let step = swap_step(sqrt_ratio, target, liquidity, remaining, sells_token1);
+            remaining = remaining - step.amount_in;
+            amount_out = amount_out + step.amount_out;
+            sqrt_ratio = step.sqrt_ratio;
+            if sqrt_ratio != target {
+                break;
+            }
+
+            if next.initialized {
+                let delta = i128::from(
+                    read_tick_liquidity_delta(
+                        &self.rpc_client,
+                        self.contract_address,
+                        pool,
+                        next.tick,
+                    )
+                    .await?,
+                );
+                // The delta is added when crossing upwards, removed when crossing downwards
+                let delta = if sells_token1 { delta } else { -delta };
+                liquidity = liquidity.checked_add_signed(delta).unwrap_or_default();
+            }
+            tick = if sells_token1 {
+                next_tick
+            } else {
+                next_tick - 1
+            };
+        }
+
+        let amount_in = if remaining == U256FD128::ZERO {
+            amount
+        } else {
+            (after_fee - remaining) / (one - fee)
+        };
+
+        Ok(Quote {
+            amount_in,
+            amount_out,
+            spot_price,
+        })
+    }
+
+    /// Simulates a swap of `amount` along the hops of a path, each one swapping the output of
+    /// the previous one.
+    ///
+    /// # Errors
+    /// Returns an error if the state of a pool could not be read
+    pub async fn quote_path(&self, hops: &[Hop], amount: U256FD128) -> Result<Quote, Error> {
+        let mut quote = Quote {
+            amount_in: amount,
+            amount_out: amount,
+            spot_price: PairRatio(U256FD128::from_whole(1)),
+        };
+
+        for hop in hops {
+            let next = self
+                .quote_pool(&hop.pool, hop.from, quote.amount_out)
+                .await?;
+            quote = quote.then(&next);
+        }
+
+        Ok(quote)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mockito::{Matcher, Mock, ServerGuard};
+    use starknet::{
+        macros::{felt, selector},
+        providers::{jsonrpc::HttpTransport, JsonRpcClient},
+    };
+    use url::Url;
+
+    use super::*;
+
+    /// An initialized tick below the current price, which removes all the liquidity.
+    const TICK: u128 = 1_386_294;
+
+    fn hex(values: &[Felt]) -> String {
+        let values: Vec<String> = values
+            .iter()
+            .map(|value| format!("\"{value:#x}\""))
+            .collect();
+        format!("[{}]", values.join(","))
+    }
+
+    /// Answers the calls of an entry point of the core contract for the pool, with the given
+    /// arguments after the pool key.
+    async fn mock_call(
+        server: &mut ServerGuard,
+        pool: &PoolKey,
+        entry_point: Felt,
+        arguments: &[Felt],
+        result: &[Felt],
+    ) -> Mock {
+        let mut calldata = vec![
+            pool.token0,
+            pool.token1,
+            pool.fee.into(),
+            pool.tick_spacing.into(),
+            pool.extension,
+        ];
+        calldata.extend_from_slice(arguments);
+
+        server
+            .mock("POST", "/")
+            .match_body(Matcher::PartialJsonString(format!(
+                r#"{{
+                    "method": "starknet_call",
+                    "params": {{
+                        "request": {{
+                            "entry_point_selector": "{entry_point:#x}",
+                            "calldata": {}
+                        }}
+                    }}
+                }}"#,
+                hex(&calldata)
+            )))
+            .with_header("content-type", "application/json")
+            .with_body(format!(
+                r#"{{"jsonrpc": "2.0", "id": 1, "result": {}}}"#,
+                hex(result)
+            ))
+            .expect_at_least(1)
+            .create_async()
+            .await
+    }
+
+    fn assert_close(value: U256FD128, expected: f64) {
+        let value = f64::from(value);
+        assert!(
+            (value - expected).abs() <= expected.abs() * 1e-9,
+            "{value} != {expected}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_quote_pool() {
+        let mut server = mockito::Server::new_async().await;
+        let pool = PoolKey {
+            token0: felt!("0x1"),
+            token1: felt!("0x2"),
+            fee: 0,
+            tick_spacing: 1,
+            extension: Felt::ZERO,
+        };
+
+        // A price of 1 and a liquidity of 1000, down to the tick
+        let mocks = vec![
+            mock_call(
+                &mut server,
+                &pool,
+                selector!("get_pool_price"),
+                &[],
+                &[Felt::ZERO, Felt::ONE, Felt::ZERO, Felt::ZERO],
+            )
+            .await,
+            mock_call(
+                &mut server,
+                &pool,
+                selector!("get_pool_liquidity"),
+                &[],
+                &[Felt::from(1000)],
+            )
+            .await,
+            mock_call(
+                &mut server,
+                &pool,
+                selector!("prev_initialized_tick"),
+                &[Felt::ZERO, Felt::ZERO, Felt::from(5)],
+                &[Felt::from(TICK), Felt::ONE, Felt::ONE],
+            )
+            .await,
+            mock_call(
+                &mut server,
+                &pool,
+                selector!("get_pool_tick_liquidity_delta"),
+                &[Felt::from(TICK), Felt::ONE],
+                &[Felt::from(1000), Felt::ZERO],
+            )
+            .await,
+            // Nothing else below, up to the lowest tick
+            mock_call(
+                &mut server,
+                &pool,
+                selector!("prev_initialized_tick"),
+                &[Felt::from(TICK + 1), Felt::ONE, Felt::from(5)],
+                &[Felt::from(88_722_883), Felt::ONE, Felt::ZERO],
+            )
+            .await,
+        ];
+
+        let rpc_client = JsonRpcClient::new(HttpTransport::new(Url::parse(&server.url()).unwrap()));
+        let client = EkuboClient::new(felt!("0x100"), rpc_client, String::new());
+        let target = f64::from(tick_to_sqrt_ratio(-i64::try_from(TICK).unwrap()).unwrap());
+
+        // Within the range of the current liquidity
+        let quote = client
+            .quote_pool(&pool, pool.token0, U256FD128::from_whole(500))
+            .await
+            .unwrap();
+        assert_eq!(quote.amount_in, U256FD128::from_whole(500));
+        assert_close(quote.amount_out, 1000. * (1. - 1. / 1.5));
+        assert_eq!(quote.spot_price.0, U256FD128::from_whole(1));
+
+        // Running out of liquidity after the tick, with the rest of the amount left unspent
+        let quote = client
+            .quote_pool(&pool, pool.token0, U256FD128::from_whole(5000))
+            .await
+            .unwrap();
+        assert_close(quote.amount_in, 1000. * (1. / target - 1.));
+        assert_close(quote.amount_out, 1000. * (1. - target));
+
+        for mock in mocks {
+            mock.assert_async().await;
+        }
+    }
 }
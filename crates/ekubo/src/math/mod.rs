@@ -1 +1,2 @@
+pub mod swap;
 pub mod u256fd128;
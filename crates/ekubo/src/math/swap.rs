@@ -0,0 +1,171 @@
+//! Swaps through a concentrated liquidity pool, within ranges of constant liquidity.
+//!
+//! Prices are square roots of the amount of token1 per token0, as stored by the pools.
+
+use ekubo_sdk::math::uint::U256;
+
+use super::u256fd128::{U256FD128, U512};
+
+/// Highest tick simulated, in absolute value. Beyond it the prices overflow the fixed point
+/// representation, the ticks of the pools going up to 88,722,883.
+pub const MAX_TICK: u64 = 60_000_000;
+
+/// A swap within a range of constant liquidity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapStep {
+    pub amount_in: U256FD128,
+    pub amount_out: U256FD128,
+    /// Square root of the price after the swap.
+    pub sqrt_ratio: U256FD128,
+}
+
+/// Square root of the price at a tick, each tick moving the price by 0.0001%.
+///
+/// Returns `None` for the ticks farther than [`MAX_TICK`].
+#[must_use]
+pub fn tick_to_sqrt_ratio(tick: i64) -> Option<U256FD128> {
+    let mut exponent = tick.unsigned_abs();
+    if exponent > MAX_TICK {
+        return None;
+    }
+
+    let one = U256FD128::from_whole(1);
+    let mut base = U256FD128::from_whole(1_000_001) / U256FD128::from_whole(1_000_000);
+    let mut price = one;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            price = price * base;
+        }
+        exponent >>= 1;
+        if exponent > 0 {
+            base = base * base;
+        }
+    }
+    if tick < 0 {
+        price = one / price;
+    }
+
+    // Square root of the raw value, shifted to keep the 128 decimal bits
+    let raw = U512::from(price.raw()) << 128;
+    Some(U256FD128::new(U256::from(raw.integer_sqrt())))
+}
+
+/// Swaps `amount` of token1 if `token1_in` is set, of token0 otherwise, from the current price
+/// towards the target one, with the given liquidity.
+///
+/// Token0 moves the price down and token1 moves it up: the price stops at the target when the
+/// amount is enough to reach it, and does not move if the target is on the other side.
+#[must_use]
+pub fn swap_step(
+    sqrt_ratio: U256FD128,
+    target: U256FD128,
+    liquidity: u128,
+    amount: U256FD128,
+    token1_in: bool,
+) -> SwapStep {
+    let one = U256FD128::from_whole(1);
+    let liquidity = U256FD128::from_whole(liquidity);
+
+    if token1_in == (target < sqrt_ratio) {
+        return SwapStep {
+            amount_in: U256FD128::ZERO,
+            amount_out: U256FD128::ZERO,
+            sqrt_ratio,
+        };
+    }
+
+    if token1_in {
+        // Token1 in, the price going up
+        let max_in = liquidity * (target - sqrt_ratio);
+        let (amount_in, next) = if amount >= max_in {
+            (max_in, target)
+        } else {
+            (amount, sqrt_ratio + amount / liquidity)
+        };
+
+        SwapStep {
+            amount_in,
+            amount_out: liquidity * (one / sqrt_ratio - one / next),
+            sqrt_ratio: next,
+        }
+    } else {
+        // Token0 in, the price going down
+        let max_in = liquidity * (one / target - one / sqrt_ratio);
+        let (amount_in, next) = if amount >= max_in {
+            (max_in, target)
+        } else {
+            (amount, one / (one / sqrt_ratio + amount / liquidity))
+        };
+
+        SwapStep {
+            amount_in,
+            amount_out: liquidity * (sqrt_ratio - next),
+            sqrt_ratio: next,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_close(value: U256FD128, expected: f64) {
+        let value = f64::from(value);
+        assert!(
+            (value - expected).abs() <= expected.abs() * 1e-9,
+            "{value} != {expected}"
+        );
+    }
+
+    #[test]
+    fn test_tick_to_sqrt_ratio() {
+        assert_eq!(tick_to_sqrt_ratio(0), Some(U256FD128::from_whole(1)));
+        assert_close(tick_to_sqrt_ratio(2).unwrap(), 1.000_001);
+        assert_close(tick_to_sqrt_ratio(-2).unwrap(), 1. / 1.000_001);
+        // Around a price of 2
+        assert_close(
+            tick_to_sqrt_ratio(693_147).unwrap(),
+            1.000_001f64.powf(693_147. / 2.),
+        );
+        assert_close(
+            tick_to_sqrt_ratio(-20_000_000).unwrap(),
+            1.000_001f64.powf(-10_000_000.),
+        );
+        assert!(tick_to_sqrt_ratio(88_722_883).is_none());
+    }
+
+    #[test]
+    fn test_swap_step() {
+        let one = U256FD128::from_whole(1);
+        let two = U256FD128::from_whole(2);
+
+        // Token1 in, from a price of 1 to 4: 1000 token1 fill the range
+        let step = swap_step(one, two, 1000, U256FD128::from_whole(5000), true);
+        assert_eq!(step.amount_in, U256FD128::from_whole(1000));
+        assert_close(step.amount_out, 500.);
+        assert_eq!(step.sqrt_ratio, two);
+
+        // Half of it only moves the price to 1.5²
+        let step = swap_step(one, two, 1000, U256FD128::from_whole(500), true);
+        assert_eq!(step.amount_in, U256FD128::from_whole(500));
+        assert_close(step.amount_out, 1000. / 3.);
+        assert_close(step.sqrt_ratio, 1.5);
+
+        // Token0 in, back down
+        let step = swap_step(two, one, 1000, U256FD128::from_whole(100), false);
+        assert_eq!(step.amount_in, U256FD128::from_whole(100));
+        assert_close(step.sqrt_ratio, 1. / 0.6);
+        assert_close(step.amount_out, 1000. * (2. - 1. / 0.6));
+
+        // Without liquidity, the price moves for free
+        let step = swap_step(one, two, 0, U256FD128::from_whole(100), true);
+        assert_eq!(step.amount_in, U256FD128::ZERO);
+        assert_eq!(step.amount_out, U256FD128::ZERO);
+        assert_eq!(step.sqrt_ratio, two);
+
+        // Token0 cannot move the price up
+        let step = swap_step(one, two, 1000, U256FD128::from_whole(100), false);
+        assert_eq!(step.amount_in, U256FD128::ZERO);
+        assert_eq!(step.sqrt_ratio, one);
+    }
+}
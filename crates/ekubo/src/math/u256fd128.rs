@@ -192,6 +192,17 @@ impl U256FD128 {
         result
     }
 
+    /// Drops the decimal portion, rounding towards zero
+    #[must_use]
+    pub fn trunc(&self) -> Self {
+        let whole = Self((self.abs().0 >> Self::DECIMAL_BITS) << Self::DECIMAL_BITS);
+        if self.is_negative() {
+            whole.neg()
+        } else {
+            whole
+        }
+    }
+
     /// Calculates the square of the number
     #[must_use]
     pub fn squared(&self) -> Self {
@@ -521,6 +532,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_trunc() {
+        let two = U256FD128::from_whole(2);
+        let half = U256FD128::new(U256::from(1u128) << 127); // 0.5
+
+        // 2.5 -> 2
+        assert_eq!((two + half).trunc(), two, "Expected 2.5 to truncate to 2");
+
+        // -2.5 -> -2
+        assert_eq!(
+            (two + half).neg().trunc(),
+            two.neg(),
+            "Expected -2.5 to truncate to -2"
+        );
+
+        // 0.5 -> 0
+        assert_eq!(
+            half.trunc(),
+            U256FD128::ZERO,
+            "Expected 0.5 to truncate to 0"
+        );
+    }
+
     #[test]
     fn test_negative_multiplication() {
         let one = U256FD128::from_whole(1);
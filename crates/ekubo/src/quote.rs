@@ -0,0 +1,89 @@
+//! Quotes of the swaps through the pools, simulated against their liquidity.
+
+use crate::{math::u256fd128::U256FD128, price::PairRatio};
+
+/// The outcome of a swap simulated through one or several pools.
+#[derive(Debug, Clone)]
+pub struct Quote {
+    /// Amount swapped, fees included. Lower than the requested one when the pools lack the
+    /// liquidity for it.
+    pub amount_in: U256FD128,
+    pub amount_out: U256FD128,
+    /// Amount out per amount in at the prices before the swap, without fees.
+    pub spot_price: PairRatio,
+}
+
+impl Quote {
+    /// Amount out per amount in, fees included.
+    #[must_use]
+    pub fn effective_price(&self) -> Option<PairRatio> {
+        (self.amount_in != U256FD128::ZERO).then(|| PairRatio(self.amount_out / self.amount_in))
+    }
+
+    /// Fraction of the spot price lost by the swap, fees included.
+    #[must_use]
+    pub fn price_impact(&self) -> f64 {
+        let spot = f64::from(self.spot_price.0);
+        match self.effective_price() {
+            Some(price) if spot > 0. => 1. - f64::from(price.0) / spot,
+            _ => 0.,
+        }
+    }
+
+    /// Chains this quote with the following swap of its output.
+    #[must_use]
+    pub fn then(self, next: &Quote) -> Self {
+        // Part of the output the next swap could take
+        let filled = if self.amount_out == U256FD128::ZERO {
+            U256FD128::ZERO
+        } else {
+            next.amount_in / self.amount_out
+        };
+
+        Self {
+            amount_in: if next.amount_in < self.amount_out {
+                self.amount_in * filled
+            } else {
+                self.amount_in
+            },
+            amount_out: next.amount_out,
+            spot_price: PairRatio(self.spot_price.0 * next.spot_price.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn quote(amount_in: u128, amount_out: u128, spot_price: u128) -> Quote {
+        Quote {
+            amount_in: U256FD128::from_whole(amount_in),
+            amount_out: U256FD128::from_whole(amount_out),
+            spot_price: PairRatio(U256FD128::from_whole(spot_price)),
+        }
+    }
+
+    #[test]
+    fn test_quote() {
+        let first = quote(100, 180, 2);
+        assert_eq!(
+            first.effective_price().unwrap().0,
+            U256FD128::from_whole(18) / U256FD128::from_whole(10)
+        );
+        assert!((first.price_impact() - 0.1).abs() < 1e-9);
+
+        // The next pool only takes 90 of the 180
+        let chained = first.clone().then(&quote(90, 270, 3));
+        assert_eq!(chained.amount_in, U256FD128::from_whole(50));
+        assert_eq!(chained.amount_out, U256FD128::from_whole(270));
+        assert_eq!(chained.spot_price.0, U256FD128::from_whole(6));
+        assert!((chained.price_impact() - 0.1).abs() < 1e-9);
+
+        let chained = first.then(&quote(180, 360, 3));
+        assert_eq!(chained.amount_in, U256FD128::from_whole(100));
+
+        assert!(quote(0, 0, 2).effective_price().is_none());
+        assert!(quote(0, 0, 2).price_impact().abs() < f64::EPSILON);
+    }
+}
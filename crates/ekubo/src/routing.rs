@@ -0,0 +1,213 @@
+//! Routing of the prices through intermediate tokens, for the tokens without a pool paired with
+//! the wanted one.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde::{Serialize, Serializer};
+use starknet::core::types::Felt;
+
+use crate::{contract::pool_price::PoolKey, math::u256fd128::U256FD128, price::PairRatio};
+
+/// A swap through a pool, from one of its tokens to the other.
+#[derive(Debug, Clone, Serialize)]
+pub struct Hop {
+    pub from: Felt,
+    pub to: Felt,
+    pub pool: PoolKey,
+    /// Amount of `to` per `from` through the pool.
+    #[serde(serialize_with = "serialize_ratio")]
+    pub ratio: PairRatio,
+}
+
+fn serialize_ratio<S: Serializer>(ratio: &PairRatio, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(ratio.0.into())
+}
+
+/// A price composed across the pools of a path.
+#[derive(Debug, Clone)]
+pub struct PricePath {
+    pub hops: Vec<Hop>,
+    /// Amount of the last token of the path per the first one.
+    pub ratio: PairRatio,
+}
+
+impl PricePath {
+    /// Composes the price of the given hops.
+    #[must_use]
+    pub fn new(hops: Vec<Hop>) -> Self {
+        let ratio = hops
+            .iter()
+            .fold(U256FD128::from_whole(1), |ratio, hop| ratio * hop.ratio.0);
+
+        Self {
+            hops,
+            ratio: PairRatio(ratio),
+        }
+    }
+}
+
+/// Graph of the tokens, linked by one pool and price per pair.
+#[derive(Debug, Clone, Default)]
+pub struct PoolGraph {
+    hops: BTreeMap<Felt, Vec<Hop>>,
+}
+
+impl PoolGraph {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the graph has no pool at all.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.hops.is_empty()
+    }
+
+    /// Adds a pool to the graph with its price in token1 per token0, unless its pair already has
+    /// one or the price is zero, as it could not be inverted.
+    pub fn insert(&mut self, pool: PoolKey, price: PairRatio) {
+        if price.0 == U256FD128::ZERO || self.get(pool.token0, pool.token1).is_some() {
+            return;
+        }
+
+        let inverse = price.inverse();
+        self.hops.entry(pool.token0).or_default().push(Hop {
+            from: pool.token0,
+            to: pool.token1,
+            pool: pool.clone(),
+            ratio: price,
+        });
+        self.hops.entry(pool.token1).or_default().push(Hop {
+            from: pool.token1,
+            to: pool.token0,
+            pool,
+            ratio: inverse,
+        });
+    }
+
+    /// Gets the hop from a token to another, if a pool pairs them.
+    #[must_use]
+    pub fn get(&self, from: Felt, to: Felt) -> Option<&Hop> {
+        self.hops.get(&from)?.iter().find(|hop| hop.to == to)
+    }
+
+    /// Finds the shortest path from a token to another, through at most `max_hops` pools.
+    ///
+    /// Between paths of the same length, the one going through the pools inserted first is
+    /// chosen. Returns an empty path from a token to itself.
+    #[must_use]
+    pub fn find_path(&self, from: Felt, to: Felt, max_hops: usize) -> Option<Vec<Hop>> {
+        if from == to {
+            return Some(Vec::new());
+        }
+
+        // Hop through which each token was reached first
+        let mut reached_by: HashMap<Felt, &Hop> = HashMap::new();
+        let mut visited = HashSet::from([from]);
+        let mut frontier = vec![from];
+
+        for _ in 0..max_hops {
+            let mut next = Vec::new();
+
+            for hop in frontier
+                .iter()
+                .filter_map(|token| self.hops.get(token))
+                .flatten()
+            {
+                if !visited.insert(hop.to) {
+                    continue;
+                }
+                reached_by.insert(hop.to, hop);
+
+                if hop.to == to {
+                    let mut path = vec![hop.clone()];
+                    while path[0].from != from {
+                        path.insert(0, reached_by[&path[0].from].clone());
+                    }
+                    return Some(path);
+                }
+                next.push(hop.to);
+            }
+
+            if next.is_empty() {
+                break;
+            }
+            frontier = next;
+        }
+
+        None
+    }
+
+    /// Finds the shortest path from a token to another, and composes its price.
+    #[must_use]
+    pub fn find_price(&self, from: Felt, to: Felt, max_hops: usize) -> Option<PricePath> {
+        self.find_path(from, to, max_hops).map(PricePath::new)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use starknet::macros::felt;
+
+    use super::*;
+
+    fn pool(token0: Felt, token1: Felt, fee: u128) -> PoolKey {
+        PoolKey {
+            token0,
+            token1,
+            fee,
+            tick_spacing: 1,
+            extension: Felt::ZERO,
+        }
+    }
+
+    fn price(value: u128) -> PairRatio {
+        PairRatio(U256FD128::from_whole(value))
+    }
+
+    #[test]
+    fn test_find_path() {
+        let (main, strk, eth, x, y) = (
+            felt!("0x1"),
+            felt!("0x2"),
+            felt!("0x3"),
+            felt!("0x4"),
+            felt!("0x5"),
+        );
+
+        let mut graph = PoolGraph::new();
+        assert!(graph.is_empty());
+        graph.insert(pool(main, strk, 1), price(2));
+        graph.insert(pool(main, eth, 2), price(1));
+        graph.insert(pool(eth, x, 3), price(1));
+        graph.insert(pool(strk, x, 4), price(3));
+        graph.insert(pool(x, y, 5), price(1));
+        // Only the first pool of a pair is kept
+        graph.insert(pool(main, strk, 6), price(1));
+        assert_eq!(graph.get(strk, main).map(|hop| hop.pool.fee), Some(1));
+        // A pool with a zero price could not be crossed backwards
+        graph.insert(pool(main, y, 7), price(0));
+        assert!(graph.get(y, main).is_none());
+
+        // Through the pool of the pair inserted first
+        let path = graph.find_price(main, x, 2).unwrap();
+        let fees: Vec<u128> = path.hops.iter().map(|hop| hop.pool.fee).collect();
+        assert_eq!(fees, vec![1, 4]);
+        assert_eq!(path.hops[0].from, main);
+        assert_eq!(path.hops[1].to, x);
+        // 2 STRK per main, 3 X per STRK
+        assert_eq!(path.ratio.0, U256FD128::from_whole(6));
+
+        // Backwards, the hops go from token1 to token0
+        let path = graph.find_path(y, main, 3).unwrap();
+        let tokens: Vec<Felt> = path.iter().map(|hop| hop.to).collect();
+        assert_eq!(tokens, vec![x, eth, main]);
+
+        assert!(graph.find_path(main, y, 2).is_none());
+        assert!(graph.find_path(main, felt!("0x6"), 3).is_none());
+        let path = graph.find_price(main, main, 0).unwrap();
+        assert!(path.hops.is_empty());
+        assert_eq!(path.ratio.0, U256FD128::from_whole(1));
+    }
+}
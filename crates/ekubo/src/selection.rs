@@ -0,0 +1,280 @@
+//! Selection of the pool pricing each pair, among the pools listed by the Ekubo API.
+
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+
+use crate::{
+    api::pool::Pool, contract::pool_price::PoolKey, math::u256fd128::U256FD128, price::PairRatio,
+    routing::PoolGraph,
+};
+
+/// How the price of a pair is chosen among its pools.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionStrategy {
+    /// The price of the pool with the highest total value locked.
+    #[default]
+    HighestTvl,
+    /// The price of the pool with the highest volume over the last 24 hours.
+    HighestVolume,
+    /// The average of the prices of the pools, weighted by their total value locked.
+    LiquidityWeighted,
+}
+
+/// A pool listed by the Ekubo API, with its current price.
+#[derive(Debug, Clone)]
+pub struct PricedPool {
+    pub pool: Pool,
+    /// Amount of token1 per token0.
+    pub price: PairRatio,
+}
+
+impl PricedPool {
+    /// Total value locked, in token0.
+    #[must_use]
+    pub fn tvl(&self) -> f64 {
+        self.in_token0(&self.pool.tvl0_total, &self.pool.tvl1_total)
+    }
+
+    /// Volume traded over the last 24 hours, in token0.
+    #[must_use]
+    pub fn volume(&self) -> f64 {
+        self.in_token0(&self.pool.volume0_24h, &self.pool.volume1_24h)
+    }
+
+    fn in_token0(&self, amount0: &Felt, amount1: &Felt) -> f64 {
+        let price = f64::from(self.price.0);
+        let amount1 = if price > 0. {
+            as_f64(amount1) / price
+        } else {
+            0.
+        };
+
+        as_f64(amount0) + amount1
+    }
+}
+
+fn as_f64(amount: &Felt) -> f64 {
+    amount.to_string().parse().unwrap_or_default()
+}
+
+/// Chooses the pool and the price of every pair.
+#[derive(Debug, Clone)]
+pub struct PoolSelector {
+    pub strategy: SelectionStrategy,
+    /// Pools with a lower total value locked are ignored, in the smallest unit of the main token.
+    pub min_liquidity: f64,
+    /// Token the liquidity of the pools is valued in.
+    pub main_token: Felt,
+    /// Maximum number of pools the liquidity is valued through.
+    pub max_hops: usize,
+}
+
+impl PoolSelector {
+    /// Builds the graph of the pairs, from the pools of each of them.
+    ///
+    /// The liquidity of the pools is valued in main token at the prices of the pools with the
+    /// highest total value locked. When a minimum liquidity is set, the pools that cannot be
+    /// valued are ignored.
+    #[must_use]
+    pub fn build_graph(&self, pairs: &[Vec<PricedPool>]) -> PoolGraph {
+        let mut valuation = PoolGraph::new();
+        for pools in pairs {
+            if let Some(pool) = most_liquid(pools.iter()) {
+                valuation.insert(pool.pool.key.clone(), pool.price.clone());
+            }
+        }
+
+        let mut graph = PoolGraph::new();
+        for pools in pairs {
+            let pools: Vec<&PricedPool> = pools
+                .iter()
+                .filter(|pool| self.is_liquid(pool, &valuation))
+                .collect();
+
+            if let Some((key, price)) = self.select(&pools) {
+                graph.insert(key, price);
+            }
+        }
+
+        graph
+    }
+
+    /// Chooses the pool and the price of a pair among its pools.
+    ///
+    /// With a weighted average, the pool with the highest total value locked is returned.
+    #[must_use]
+    pub fn select(&self, pools: &[&PricedPool]) -> Option<(PoolKey, PairRatio)> {
+        let most_liquid = most_liquid(pools.iter().copied())?;
+
+        let (pool, price) = match self.strategy {
+            SelectionStrategy::HighestTvl => (most_liquid, most_liquid.price.clone()),
+            SelectionStrategy::HighestVolume => {
+                let pool = pools
+                    .iter()
+                    .copied()
+                    .reduce(|best, pool| {
+                        if pool.volume() > best.volume() {
+                            pool
+                        } else {
+                            best
+                        }
+                    })
+                    .unwrap_or(most_liquid);
+                (pool, pool.price.clone())
+            }
+            SelectionStrategy::LiquidityWeighted => {
+                let total: f64 = pools.iter().map(|pool| pool.tvl()).sum();
+                if total > 0. {
+                    let price = pools.iter().fold(U256FD128::ZERO, |sum, pool| {
+                        sum + pool.price.0 * weight(pool.tvl() / total)
+                    });
+                    (most_liquid, PairRatio(price))
+                } else {
+                    (most_liquid, most_liquid.price.clone())
+                }
+            }
+        };
+
+        Some((pool.pool.key.clone(), price))
+    }
+
+    fn is_liquid(&self, pool: &PricedPool, valuation: &PoolGraph) -> bool {
+        if self.min_liquidity <= 0. {
+            return true;
+        }
+
+        // Amount of token0 per main token
+        let key = &pool.pool.key;
+        let ratio = valuation
+            .find_price(self.main_token, key.token0, self.max_hops)
+            .map(|path| f64::from(path.ratio.0))
+            .or_else(|| {
+                valuation
+                    .find_price(self.main_token, key.token1, self.max_hops)
+                    .map(|path| f64::from(path.ratio.0) / f64::from(pool.price.0))
+            });
+
+        ratio.is_some_and(|ratio| ratio > 0. && pool.tvl() / ratio >= self.min_liquidity)
+    }
+}
+
+/// The pool with the highest total value locked, the first one listed between equals.
+fn most_liquid<'a>(pools: impl Iterator<Item = &'a PricedPool>) -> Option<&'a PricedPool> {
+    pools.reduce(|best, pool| if pool.tvl() > best.tvl() { pool } else { best })
+}
+
+/// Converts a fraction between 0 and 1, with 64 bits of precision.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // Fractions are within [0, 1]
+fn weight(fraction: f64) -> U256FD128 {
+    const ONE: u128 = 1 << 64;
+    U256FD128::from_whole((fraction * 2f64.powi(64)) as u128) / U256FD128::from_whole(ONE)
+}
+
+#[cfg(test)]
+mod test {
+    use starknet::macros::felt;
+
+    use super::*;
+
+    const MAIN: Felt = Felt::from_hex_unchecked("0x1");
+    const X: Felt = Felt::from_hex_unchecked("0x2");
+    const Y: Felt = Felt::from_hex_unchecked("0x3");
+
+    fn pool(
+        token0: Felt,
+        token1: Felt,
+        fee: u128,
+        price: u128,
+        tvl: u64,
+        volume: u64,
+    ) -> PricedPool {
+        PricedPool {
+            pool: Pool {
+                key: PoolKey {
+                    token0,
+                    token1,
+                    fee,
+                    tick_spacing: 1,
+                    extension: Felt::ZERO,
+                },
+                tvl0_total: Felt::from(tvl),
+                tvl1_total: Felt::ZERO,
+                fees0_24h: Felt::ZERO,
+                fees1_24h: Felt::ZERO,
+                tvl0_delta_24h: Felt::ZERO,
+                tvl1_delta_24h: Felt::ZERO,
+                volume0_24h: Felt::from(volume),
+                volume1_24h: Felt::ZERO,
+            },
+            price: PairRatio(U256FD128::from_whole(price)),
+        }
+    }
+
+    fn selector(strategy: SelectionStrategy, min_liquidity: f64) -> PoolSelector {
+        PoolSelector {
+            strategy,
+            min_liquidity,
+            main_token: MAIN,
+            max_hops: 2,
+        }
+    }
+
+    #[test]
+    fn test_select() {
+        let pools = [
+            pool(MAIN, X, 1, 2, 100, 500),
+            pool(MAIN, X, 2, 4, 300, 10),
+            pool(MAIN, X, 3, 100, 0, 0),
+        ];
+        let pools: Vec<&PricedPool> = pools.iter().collect();
+
+        let (key, price) = selector(SelectionStrategy::HighestTvl, 0.)
+            .select(&pools)
+            .unwrap();
+        assert_eq!(key.fee, 2);
+        assert_eq!(price.0, U256FD128::from_whole(4));
+
+        let (key, _) = selector(SelectionStrategy::HighestVolume, 0.)
+            .select(&pools)
+            .unwrap();
+        assert_eq!(key.fee, 1);
+
+        // 2 * 1/4 + 4 * 3/4, the empty pool not counting
+        let (key, price) = selector(SelectionStrategy::LiquidityWeighted, 0.)
+            .select(&pools)
+            .unwrap();
+        assert_eq!(key.fee, 2);
+        assert!((f64::from(price.0) - 3.5).abs() < 1e-9);
+
+        assert!(selector(SelectionStrategy::HighestTvl, 0.)
+            .select(&[])
+            .is_none());
+    }
+
+    #[test]
+    fn test_min_liquidity() {
+        // 2 X per main, 10 Y per X
+        let pairs = vec![
+            vec![pool(MAIN, X, 1, 2, 1000, 0), pool(MAIN, X, 2, 3, 10, 0)],
+            vec![pool(X, Y, 3, 10, 100, 0)],
+            vec![pool(felt!("0x4"), felt!("0x5"), 4, 1, 1_000_000, 0)],
+        ];
+
+        let graph = selector(SelectionStrategy::LiquidityWeighted, 0.).build_graph(&pairs);
+        assert!(graph.get(X, Y).is_some());
+        assert!(graph.get(felt!("0x4"), felt!("0x5")).is_some());
+        // Both pools are averaged
+        assert!(f64::from(graph.get(MAIN, X).unwrap().ratio.0) > 2.);
+
+        // Pools of 1000 and 10 main, and of 100 X worth 50 main
+        let graph = selector(SelectionStrategy::LiquidityWeighted, 100.).build_graph(&pairs);
+        assert_eq!(
+            graph.get(MAIN, X).unwrap().ratio.0,
+            U256FD128::from_whole(2)
+        );
+        assert!(graph.get(X, Y).is_none());
+        // Cannot be valued in main token
+        assert!(graph.get(felt!("0x4"), felt!("0x5")).is_none());
+    }
+}
@@ -1,5 +1,6 @@
+use chaindata_service::webhooks::WebhookConfig;
 use confique::Config;
-use ekubo::Felt;
+use ekubo::{selection::SelectionStrategy, Felt};
 use serde::Deserialize;
 use url::Url;
 
@@ -38,6 +39,13 @@ pub struct Conf {
     #[config(nested)]
     pub gg_xyz: GgXyzConfig,
 
+    #[config(nested)]
+    pub leaderboard: LeaderboardConfig,
+
+    /// Webhooks receiving the game events, besides the ones defined in the database.
+    #[config(default = [])]
+    pub webhook: Vec<WebhookConfig>,
+
     pub default_token: String,
 }
 
@@ -57,8 +65,17 @@ pub struct ToriiConfig {
 
 #[derive(Config, Debug, Clone)]
 pub struct DatabaseConfig {
+    /// The primary database, used for writes.
     #[config(env = "DATABASE_URL")]
     pub url: Url,
+
+    /// An optional read replica, used for reads instead of the primary.
+    #[config(env = "DATABASE_READ_URL")]
+    pub read_url: Option<Url>,
+
+    /// Whether reads should go to the primary while the replica has not caught up with our own writes.
+    #[config(default = false, env = "DATABASE_READ_YOUR_WRITES")]
+    pub read_your_writes: bool,
 }
 
 #[derive(Config, Debug, Clone)]
@@ -67,6 +84,19 @@ pub struct EkuboConfig {
     pub api_url: Url,
     #[config(env = "EKUBO_CORE_CONTRACT_ADDRESS")]
     pub core_contract_address: Felt,
+    /// Maximum number of pools a price is routed through, for the tokens without a pool paired
+    /// with the main token.
+    #[config(default = 2, env = "EKUBO_MAX_HOPS")]
+    pub max_hops: usize,
+    /// Tokens the prices can be routed through, besides the listed ones.
+    #[config(default = [])]
+    pub routing_tokens: Vec<Felt>,
+    /// How the price of each pair is chosen among its pools.
+    #[config(default = "highest_tvl", env = "EKUBO_POOL_SELECTION")]
+    pub pool_selection: SelectionStrategy,
+    /// Pools with a lower total value locked are ignored, in the smallest unit of the main token.
+    #[config(default = 0.0, env = "EKUBO_MIN_LIQUIDITY")]
+    pub min_liquidity: f64,
 }
 
 #[derive(Config, Debug, Clone)]
@@ -85,6 +115,16 @@ pub struct GgXyzConfig {
     pub api_key: String,
 }
 
+#[derive(Config, Debug, Clone)]
+pub struct LeaderboardConfig {
+    /// Addresses never ranked nor listed among the token holders, such as the ones of the AI
+    /// players.
+    #[config(default = [])]
+    pub blacklist: Vec<Felt>,
+    /// Token whose top holders are ranked, such as BTC. None are ranked without it.
+    pub holders_token: Option<Felt>,
+}
+
 #[derive(Config, Debug, Clone)]
 pub struct Monitoring {
     /// Whether monitoring is enabled or not
@@ -1,23 +1,40 @@
 #![allow(clippy::missing_errors_doc)]
 
-use std::{env, sync::Arc};
+use std::{env, future::ready, sync::Arc};
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use axum::{
-    http::{HeaderValue, Method},
+    http::{HeaderValue, Method, StatusCode},
     middleware,
     routing::get,
     Json, Router,
 };
-use chaindata_repository::LandRepository;
-use chaindata_service::{ChainDataService, ChainDataServiceConfiguration};
+use chaindata_repository::{
+    Database, DeadLetterRepository, EventRepository, GgXyzOutboxRepository, LandRepository,
+    LandStakeRepository, LandTenureRepository, LeaderboardRepository, NukeForecastRepository,
+    PlayerRepository, RawDataRepository, ReindexRepository, TaxLedgerRepository,
+    TokenPriceRepository, TokenRepository, WebhookRepository, WriterLock,
+};
+use chaindata_service::{
+    dead_letters::retry_dead_letters,
+    reindex::{fetch_raw_data, reindex},
+    tasks::supervisor::TaskHealth,
+    ChainDataService, ChainDataServiceConfiguration,
+};
+use clap::{Parser, Subcommand};
 use config::Conf;
 use confique::Config;
 use migrations::MIGRATOR;
-use monitoring::listen_monitoring;
-use routes::{lands::LandsRoute, price::PriceRoute, tokens::TokenRoute};
+use monitoring::{listen_monitoring, AdminState};
+use routes::{
+    lands::LandsRoute, leaderboard::LeaderboardRoute, players::PlayersRoute, price::PriceRoute,
+    taxes::TaxesRoute, tokens::TokenRoute,
+};
 use serde::{Deserialize, Serialize};
-use service::{ekubo::EkuboService, token::TokenService};
+use service::{
+    ekubo::EkuboService, leaderboard::LeaderboardService, nuke_forecast::NukeForecastService,
+    token::TokenService,
+};
 use sqlx::{postgres::PgConnectOptions, ConnectOptions, PgPool};
 use state::AppState;
 use tokio::{
@@ -39,6 +56,32 @@ pub mod routes;
 
 pub mod monitoring;
 
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    /// Runs a maintenance command instead of the indexer
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Decodes the dead letters again, and stores what now decodes, while the indexer is stopped
+    RetryDeadLetters,
+    /// Rebuilds the events, lands, tenures and market aggregates from the stored Torii rows with
+    /// the current decoders, and swaps them in at once. Refused while the indexer is running.
+    Reindex {
+        /// Fetches every row from Torii first, which is needed once for the databases created
+        /// before the rows were stored
+        #[arg(long)]
+        fetch: bool,
+        /// Swaps the rebuilt tables in even if they hold fewer events, lands or land stakes than
+        /// the current ones, which drops the ones whose rows are missing from the stored Torii rows
+        #[arg(long)]
+        force: bool,
+    },
+}
+
 #[tokio::main]
 #[allow(clippy::too_many_lines)] //TODO: Split the state into multiple functions / files
 async fn main() -> Result<()> {
@@ -51,6 +94,8 @@ async fn main() -> Result<()> {
         .with_env_filter(filter)
         .init();
 
+    let cli = Cli::parse();
+
     let config_path = env::var("CONFIG_PATH").unwrap_or("./config.toml".to_string());
 
     let mut config = Conf::builder();
@@ -67,16 +112,6 @@ async fn main() -> Result<()> {
         .load()
         .with_context(|| "Impossible to read config")?;
 
-    let monitor = MonitorManager::new();
-
-    let token_service = Arc::new(
-        TokenService::new(&config).with_context(|| "Error while setting up token service")?,
-    );
-
-    let ekubo = EkuboService::new(&config, token_service.clone(), &monitor)
-        .await
-        .with_context(|| "Error while setting up ekubo config")?;
-
     let options = PgConnectOptions::from_url(&config.database.url)
         .with_context(|| "Error while setting up database connection")?
         .application_name("ponzidexer");
@@ -91,14 +126,60 @@ async fn main() -> Result<()> {
         .await
         .with_context(|| "Error while migrating database")?;
 
+    let database = if let Some(read_url) = &config.database.read_url {
+        let options = PgConnectOptions::from_url(read_url)
+            .with_context(|| "Error while setting up read replica connection")?
+            .application_name("ponzidexer");
+
+        let reader = PgPool::connect_with(options)
+            .await
+            .with_context(|| "Impossible to connect to read replica")?;
+
+        Database::with_replica(pool, reader)
+    } else {
+        Database::new(pool)
+    }
+    .read_your_writes(config.database.read_your_writes);
+
+    if let Some(command) = cli.command {
+        return run_command(command, &config, &database).await;
+    }
+
+    // Held while running, so that a reindex cannot swap the tables meanwhile
+    let _writer_lock = if let Some(lock) = WriterLock::try_acquire(&database).await? {
+        lock
+    } else {
+        info!("Waiting for the reindex running on this database to finish");
+        WriterLock::acquire(&database).await?
+    };
+
+    let monitor = MonitorManager::new();
+
+    let token_service = Arc::new(
+        TokenService::new(&config).with_context(|| "Error while setting up token service")?,
+    );
+
+    let token_price_repository = Arc::new(TokenPriceRepository::new(database.clone()));
+    let ekubo = EkuboService::new(
+        &config,
+        token_service.clone(),
+        token_price_repository.clone(),
+        &monitor,
+    )
+    .await
+    .with_context(|| "Error while setting up ekubo config")?;
+
     let chaindata_service = ChainDataService::new(
-        pool.clone(),
+        database.clone(),
         ChainDataServiceConfiguration {
             torii_url: config.torii.torii_url.clone().into(),
             world_address: config.torii.world_address,
             gg_xyz_enabled: config.gg_xyz.enabled,
             gg_xyz_api_url: config.gg_xyz.api_url.clone(),
             gg_xyz_api_key: config.gg_xyz.api_key.clone(),
+            auction_token: token_service.main_token().address,
+            rpc_url: config.starknet.rpc_url.clone(),
+            webhooks: config.webhook.clone(),
         },
     )
     .await
@@ -107,12 +188,35 @@ async fn main() -> Result<()> {
     // Start it for the test
     chaindata_service.start();
 
-    let land_repository = Arc::new(LandRepository::new(pool.clone()));
+    let land_repository = Arc::new(LandRepository::new(database.clone()));
+    let land_stake_repository = Arc::new(LandStakeRepository::new(database.clone()));
+    let event_repository = Arc::new(EventRepository::new(database.clone()));
+    let player_repository = Arc::new(PlayerRepository::new(database.clone()));
+    let leaderboard_repository = Arc::new(LeaderboardRepository::new(database.clone()));
+    let tax_ledger_repository = Arc::new(TaxLedgerRepository::new(database.clone()));
+    let nuke_forecast_repository = Arc::new(NukeForecastRepository::new(database.clone()));
+
+    LeaderboardService::new(
+        &config,
+        leaderboard_repository.clone(),
+        &token_service,
+        &monitor,
+    )
+    .with_context(|| "Error while setting up the leaderboard")?;
+    NukeForecastService::new(nuke_forecast_repository.clone(), &monitor)
+        .with_context(|| "Error while setting up the nuke forecasts")?;
 
     let app_state = AppState {
         token_service: token_service.clone(),
         ekubo_service: ekubo.clone(),
         land_repository,
+        land_stake_repository,
+        event_repository,
+        player_repository,
+        leaderboard_repository,
+        tax_ledger_repository,
+        nuke_forecast_repository,
+        token_price_repository,
     };
 
     let cors = CorsLayer::new()
@@ -135,7 +239,15 @@ async fn main() -> Result<()> {
 
     // build our application with a route
     let app = Router::new()
-        .nest("/tokens", TokenRoute::new(token_service).router())
+        .nest(
+            "/tokens",
+            TokenRoute::new(
+                token_service,
+                Arc::new(TokenRepository::new(database.clone())),
+                &config.leaderboard.blacklist,
+            )
+            .router(),
+        )
         .nest(
             "/price",
             PriceRoute::new().router().with_state(app_state.clone()),
@@ -144,8 +256,29 @@ async fn main() -> Result<()> {
             "/lands",
             LandsRoute::new().router().with_state(app_state.clone()),
         )
+        .nest(
+            "/leaderboard",
+            LeaderboardRoute::new()
+                .router()
+                .with_state(app_state.clone()),
+        )
+        .nest(
+            "/players",
+            PlayersRoute::new().router().with_state(app_state.clone()),
+        )
+        .nest(
+            "/taxes",
+            TaxesRoute::new().router().with_state(app_state.clone()),
+        )
         // `GET /` goes to `root`
         .route("/", get(root))
+        .route(
+            "/health",
+            get({
+                let chaindata_service = chaindata_service.clone();
+                move || ready(health(&chaindata_service))
+            }),
+        )
         .layer(cors)
         .layer(middleware::from_fn(crate::monitoring::axum::track_metrics));
 
@@ -172,7 +305,15 @@ async fn main() -> Result<()> {
 
     let http = axum::serve(listener, app);
     let monitor = monitor.build().run();
-    let monitoring = listen_monitoring(&config).await?;
+    let monitoring = listen_monitoring(
+        &config,
+        chaindata_service.clone(),
+        AdminState {
+            gg_xyz_outbox: Arc::new(GgXyzOutboxRepository::new(database.clone())),
+            webhooks: Arc::new(WebhookRepository::new(database.clone())),
+        },
+    )
+    .await?;
 
     select! {
         _ = http => {},
@@ -188,6 +329,74 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+async fn run_command(command: Command, config: &Conf, database: &Database) -> Result<()> {
+    match command {
+        Command::RetryDeadLetters => {
+            // The consumers of a running indexer would move past the recovered events
+            let Some(_lock) = WriterLock::try_acquire(database).await? else {
+                bail!("The indexer is running on this database, stop it before retrying");
+            };
+
+            let report = retry_dead_letters(
+                &DeadLetterRepository::new(database.clone()),
+                &EventRepository::new(database.clone()).with_gg_xyz_outbox(config.gg_xyz.enabled),
+                &LandRepository::new(database.clone()),
+                &LandStakeRepository::new(database.clone()),
+                &LandTenureRepository::new(database.clone()),
+                &TaxLedgerRepository::new(database.clone()),
+            )
+            .await
+            .with_context(|| "Error while retrying dead letters")?;
+
+            info!(
+                "{} dead letters decoded, {} still failing, {} skipped",
+                report.decoded, report.failed, report.skipped
+            );
+        }
+        Command::Reindex { fetch, force } => {
+            // Held until the swap is done, so that the indexer cannot start meanwhile
+            let Some(_lock) = WriterLock::try_acquire(database).await? else {
+                bail!("The indexer is running on this database, stop it before reindexing");
+            };
+
+            let raw_repository = RawDataRepository::new(database.clone());
+            if fetch {
+                fetch_raw_data(
+                    config.torii.torii_url.as_str(),
+                    config.torii.world_address,
+                    &raw_repository,
+                )
+                .await
+                .with_context(|| "Error while fetching the rows from Torii")?;
+            }
+
+            // Replaying nothing would empty every table
+            if raw_repository.count().await? == 0 {
+                bail!("No Torii rows are stored yet, run the command with --fetch");
+            }
+
+            let token_service = TokenService::new(config)
+                .with_context(|| "Error while setting up token service")?;
+            let report = reindex(
+                &raw_repository,
+                &ReindexRepository::new(database.clone()).with_gg_xyz_outbox(config.gg_xyz.enabled),
+                &DeadLetterRepository::new(database.clone()),
+                &format!("{:#x}", token_service.main_token().address),
+                force,
+            )
+            .await
+            .with_context(|| "Error while reindexing")?;
+
+            info!(
+                "{} events ({} new), {} lands and {} land stakes reindexed, {} failed",
+                report.events, report.new_events, report.lands, report.land_stakes, report.failed
+            );
+        }
+    }
+
+    Ok(())
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct RootValue {
     message: &'static str,
@@ -202,3 +411,15 @@ async fn root() -> Json<RootValue> {
         git_hash: env!("GIT_HASH"),
     })
 }
+
+/// Health of the chain data tasks, with a `503` status if any of them was given up on.
+fn health(chaindata_service: &ChainDataService) -> (StatusCode, Json<Vec<TaskHealth>>) {
+    let tasks = chaindata_service.health();
+    let status = if tasks.iter().all(TaskHealth::is_healthy) {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (status, Json(tasks))
+}
@@ -1,7 +1,10 @@
-use std::future::ready;
+use std::{future::ready, sync::Arc};
 
+use ::axum::extract::FromRef;
 use ::axum::{routing::get, serve, serve::Serve, Router};
 use anyhow::{Context, Result};
+use chaindata_repository::{GgXyzOutboxRepository, WebhookRepository};
+use chaindata_service::{tasks::supervisor::TaskState, ChainDataService};
 use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
 use tracing::info;
 
@@ -9,6 +12,27 @@ use crate::config::Conf;
 
 pub mod apalis;
 pub mod axum;
+pub mod outbox;
+pub mod webhooks;
+
+/// Repositories behind the admin endpoints.
+#[derive(Clone)]
+pub struct AdminState {
+    pub gg_xyz_outbox: Arc<GgXyzOutboxRepository>,
+    pub webhooks: Arc<WebhookRepository>,
+}
+
+impl FromRef<AdminState> for Arc<GgXyzOutboxRepository> {
+    fn from_ref(state: &AdminState) -> Self {
+        state.gg_xyz_outbox.clone()
+    }
+}
+
+impl FromRef<AdminState> for Arc<WebhookRepository> {
+    fn from_ref(state: &AdminState) -> Self {
+        state.webhooks.clone()
+    }
+}
 
 /// Listen for monitoring requests.
 ///
@@ -18,6 +42,8 @@ pub mod axum;
 ///
 pub async fn listen_monitoring(
     config: &Conf,
+    chaindata_service: Arc<ChainDataService>,
+    admin: AdminState,
 ) -> Result<Serve<tokio::net::TcpListener, Router, Router>> {
     let recorder = recorder()?;
 
@@ -38,12 +64,32 @@ pub async fn listen_monitoring(
         // `GET /` goes to `root`
         .route(
             &config.monitoring.path,
-            get(move || ready(recorder.render())),
-        );
+            get(move || {
+                record_task_health(&chaindata_service);
+                ready(recorder.render())
+            }),
+        )
+        // Only exposed on the monitoring port, as they show player addresses and delivery errors
+        .route("/admin/gg-xyz/outbox", get(outbox::get_outbox))
+        .route("/admin/webhooks/deliveries", get(webhooks::get_deliveries))
+        .with_state(admin);
 
     Ok(serve(listener, app))
 }
 
+/// Record the health of the chain data tasks, right before it is scraped.
+fn record_task_health(chaindata_service: &ChainDataService) {
+    for health in chaindata_service.health() {
+        let labels = [("task", health.name)];
+        metrics::gauge!("task_up", &labels).set(if health.state == TaskState::Running {
+            1.0
+        } else {
+            0.0
+        });
+        metrics::gauge!("task_restarts", &labels).set(f64::from(health.restart_count));
+    }
+}
+
 /// Create a Prometheus recorder.
 ///
 /// # Errors
@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use chaindata_repository::{GgXyzOutboxRepository, OutboxEntry, OutboxStatus};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+const DEFAULT_LIMIT: u32 = 50;
+const MAX_LIMIT: u32 = 500;
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+impl From<Status> for OutboxStatus {
+    fn from(status: Status) -> Self {
+        match status {
+            Status::Pending => OutboxStatus::Pending,
+            Status::Delivered => OutboxStatus::Delivered,
+            Status::Failed => OutboxStatus::Failed,
+        }
+    }
+}
+
+impl From<OutboxStatus> for Status {
+    fn from(status: OutboxStatus) -> Self {
+        match status {
+            OutboxStatus::Pending => Status::Pending,
+            OutboxStatus::Delivered => Status::Delivered,
+            OutboxStatus::Failed => Status::Failed,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OutboxQuery {
+    pub status: Option<Status>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Entry {
+    pub idempotency_key: String,
+    pub event_id: String,
+    pub player_address: String,
+    pub action: String,
+    pub status: Status,
+    pub attempts: u32,
+    pub next_attempt_at: NaiveDateTime,
+    pub last_error: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub delivered_at: Option<NaiveDateTime>,
+}
+
+impl From<OutboxEntry> for Entry {
+    fn from(entry: OutboxEntry) -> Self {
+        Self {
+            idempotency_key: entry.idempotency_key,
+            event_id: entry.event_id.as_string(),
+            player_address: entry.player_address,
+            action: entry.action,
+            status: entry.status.into(),
+            attempts: entry.attempts,
+            next_attempt_at: entry.next_attempt_at,
+            last_error: entry.last_error,
+            created_at: entry.created_at,
+            delivered_at: entry.delivered_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct OutboxResponse {
+    pub pending: u64,
+    pub delivered: u64,
+    pub failed: u64,
+    pub oldest_pending: Option<NaiveDateTime>,
+    /// Most recent entries first.
+    pub entries: Vec<Entry>,
+}
+
+/// Shows the state of the gg.xyz outbox, to follow deliveries and inspect failures.
+pub async fn get_outbox(
+    Query(query): Query<OutboxQuery>,
+    State(outbox): State<Arc<GgXyzOutboxRepository>>,
+) -> Result<Json<OutboxResponse>, StatusCode> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+
+    let (stats, entries) = tokio::join!(
+        outbox.get_stats(),
+        outbox.get_entries(query.status.map(Into::into), limit)
+    );
+    let (stats, entries) = match (stats, entries) {
+        (Ok(stats), Ok(entries)) => (stats, entries),
+        (stats, entries) => {
+            error!(
+                "Error while fetching the gg.xyz outbox: {:?} {:?}",
+                stats.err(),
+                entries.err()
+            );
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    Ok(Json(OutboxResponse {
+        pending: stats.pending,
+        delivered: stats.delivered,
+        failed: stats.failed,
+        oldest_pending: stats.oldest_pending,
+        entries: entries.into_iter().map(Entry::from).collect(),
+    }))
+}
@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use chaindata_repository::{WebhookDelivery, WebhookRepository};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use super::outbox::Status;
+
+const DEFAULT_LIMIT: u32 = 50;
+const MAX_LIMIT: u32 = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct DeliveriesQuery {
+    pub status: Option<Status>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Delivery {
+    pub id: i64,
+    pub webhook: String,
+    pub event_id: String,
+    pub status: Status,
+    pub attempts: u32,
+    pub next_attempt_at: NaiveDateTime,
+    pub last_error: Option<String>,
+    pub response_status: Option<u16>,
+    pub created_at: NaiveDateTime,
+    pub delivered_at: Option<NaiveDateTime>,
+}
+
+impl From<WebhookDelivery> for Delivery {
+    fn from(delivery: WebhookDelivery) -> Self {
+        Self {
+            id: delivery.id,
+            webhook: delivery.webhook,
+            event_id: delivery.event_id.as_string(),
+            status: delivery.status.into(),
+            attempts: delivery.attempts,
+            next_attempt_at: delivery.next_attempt_at,
+            last_error: delivery.last_error,
+            response_status: delivery.response_status,
+            created_at: delivery.created_at,
+            delivered_at: delivery.delivered_at,
+        }
+    }
+}
+
+/// Shows the webhook delivery log, most recent deliveries first.
+pub async fn get_deliveries(
+    Query(query): Query<DeliveriesQuery>,
+    State(webhooks): State<Arc<WebhookRepository>>,
+) -> Result<Json<Vec<Delivery>>, StatusCode> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+
+    let deliveries = webhooks
+        .get_deliveries(query.status.map(Into::into), limit)
+        .await
+        .map_err(|err| {
+            error!("Error while fetching webhook deliveries: {:?}", err);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(deliveries.into_iter().map(Delivery::from).collect()))
+}
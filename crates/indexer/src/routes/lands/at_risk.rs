@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use chaindata_models::shared::{Location, U256};
+use chaindata_repository::{NukeForecast, NukeForecastRepository};
+use chrono::{Duration, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::routes::parse_address;
+
+/// Lands that can be nuked within an hour are returned by default.
+const DEFAULT_WITHIN: u64 = 3600;
+
+#[derive(Debug, Deserialize)]
+pub struct AtRiskQuery {
+    /// Seconds from now within which the lands can be nuked.
+    #[serde(default = "default_within")]
+    pub within: u64,
+    /// Only returns the lands of this owner.
+    pub owner: Option<String>,
+}
+
+fn default_within() -> u64 {
+    DEFAULT_WITHIN
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LandAtRisk {
+    pub location: Location,
+    pub owner: String,
+    pub token_used: String,
+    pub stake: U256,
+    pub neighbors: i32,
+    pub tax_per_neighbor: U256,
+    pub nuke_at: NaiveDateTime,
+    /// Zero when the land can already be nuked.
+    pub seconds_to_nuke: i64,
+}
+
+impl LandAtRisk {
+    fn new(forecast: NukeForecast, now: NaiveDateTime) -> Option<Self> {
+        let nuke_at = forecast.nuke_at?;
+
+        Some(Self {
+            location: forecast.location,
+            owner: forecast.owner,
+            token_used: forecast.token_used,
+            stake: forecast.stake,
+            neighbors: forecast.neighbors,
+            tax_per_neighbor: forecast.tax_per_neighbor,
+            nuke_at,
+            seconds_to_nuke: (nuke_at - now).num_seconds().max(0),
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AtRiskResponse {
+    /// When the forecasts were computed, if any land is at risk.
+    pub computed_at: Option<NaiveDateTime>,
+    pub lands: Vec<LandAtRisk>,
+}
+
+/// Lands that can be nuked within the given time, the soonest first.
+pub async fn get_at_risk(
+    Query(query): Query<AtRiskQuery>,
+    State(repository): State<Arc<NukeForecastRepository>>,
+) -> Result<Json<AtRiskResponse>, StatusCode> {
+    let owner = query.owner.as_deref().map(parse_address).transpose()?;
+    let within = i64::try_from(query.within)
+        .ok()
+        .and_then(Duration::try_seconds)
+        .ok_or(StatusCode::BAD_REQUEST)?;
+
+    let now = Utc::now().naive_utc();
+    let before = now.checked_add_signed(within).unwrap_or(NaiveDateTime::MAX);
+
+    match repository.get_at_risk(before, owner.as_deref()).await {
+        Ok(forecasts) => Ok(Json(AtRiskResponse {
+            computed_at: forecasts.first().map(|forecast| forecast.computed_at),
+            lands: forecasts
+                .into_iter()
+                .filter_map(|forecast| LandAtRisk::new(forecast, now))
+                .collect(),
+        })),
+        Err(err) => {
+            error!("Error while fetching the lands at risk: {err}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
@@ -0,0 +1,336 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chaindata_models::{
+    events::{
+        actions::{
+            AuctionFinishedEventModel, LandBoughtEventModel, LandNukedEventModel,
+            NewAuctionEventModel,
+        },
+        EventDataModel, EventId, EventType, FetchedEvent,
+    },
+    models::{LandModel, LandStakeModel},
+    shared::Location,
+};
+use chaindata_repository::{
+    EventFilter, EventStore, LandStakeStore, LandStore, Pagination, TimeRange,
+};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// Cursor returned as `next` by the previous page.
+    pub after: Option<String>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum LandHistoryKind {
+    Land(LandModel),
+    Stake(LandStakeModel),
+    LandBought(LandBoughtEventModel),
+    LandNuked(LandNukedEventModel),
+    AuctionStarted(NewAuctionEventModel),
+    AuctionFinished(AuctionFinishedEventModel),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LandHistoryEntry {
+    pub id: String,
+    pub at: NaiveDateTime,
+    #[serde(flatten)]
+    pub kind: LandHistoryKind,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LandHistoryResponse {
+    pub location: Location,
+    pub entries: Vec<LandHistoryEntry>,
+    /// Cursor to pass as `after` to get the next page, if there might be one.
+    pub next: Option<String>,
+}
+
+impl LandHistoryEntry {
+    fn from_event(event: FetchedEvent) -> Option<Self> {
+        let kind = match event.data {
+            EventDataModel::LandBought(data) => LandHistoryKind::LandBought(data),
+            EventDataModel::LandNuked(data) => LandHistoryKind::LandNuked(data),
+            EventDataModel::NewAuction(data) => LandHistoryKind::AuctionStarted(data),
+            EventDataModel::AuctionFinished(data) => LandHistoryKind::AuctionFinished(data),
+            _ => return None,
+        };
+
+        Some(Self {
+            id: event.id.as_string(),
+            at: event.at,
+            kind,
+        })
+    }
+}
+
+/// Merges the different sources of a land history into a single timeline, ordered by chain position.
+///
+/// Each source must be ordered by chain position, and contain at most `limit` elements after the cursor,
+/// so that the first `limit` merged entries are the right ones.
+/// Entries sharing the id of the last kept entry are kept as well, as the next page starts strictly after it.
+fn merge_history(
+    lands: Vec<LandModel>,
+    stakes: Vec<LandStakeModel>,
+    events: Vec<FetchedEvent>,
+    limit: usize,
+) -> Vec<LandHistoryEntry> {
+    let mut entries: Vec<LandHistoryEntry> = lands
+        .into_iter()
+        .map(|land| LandHistoryEntry {
+            id: land.id.as_string(),
+            at: land.at,
+            kind: LandHistoryKind::Land(land),
+        })
+        .chain(stakes.into_iter().map(|stake| LandHistoryEntry {
+            id: stake.id.as_string(),
+            at: stake.at,
+            kind: LandHistoryKind::Stake(stake),
+        }))
+        .chain(events.into_iter().filter_map(LandHistoryEntry::from_event))
+        .collect();
+
+    // The string representation of ids keeps the chain ordering
+    entries.sort_by(|a, b| a.id.cmp(&b.id));
+
+    if let Some(last) = entries.get(limit.saturating_sub(1)).map(|e| e.id.clone()) {
+        let end = entries
+            .iter()
+            .position(|e| e.id > last)
+            .unwrap_or(entries.len());
+        entries.truncate(end);
+    }
+
+    entries
+}
+
+pub async fn get_history(
+    Path(location): Path<u64>,
+    Query(query): Query<HistoryQuery>,
+    State(land_repository): State<Arc<dyn LandStore>>,
+    State(land_stake_repository): State<Arc<dyn LandStakeStore>>,
+    State(event_repository): State<Arc<dyn EventStore>>,
+) -> Result<Json<LandHistoryResponse>, StatusCode> {
+    let location = Location::new(location);
+
+    let after = query
+        .after
+        .map(|after| after.parse::<EventId>())
+        .transpose()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let pagination = Pagination::new(after, query.limit.unwrap_or(Pagination::DEFAULT_LIMIT));
+    let range = TimeRange::new(
+        query.from.map(|from| from.naive_utc()),
+        query.to.map(|to| to.naive_utc()),
+    );
+
+    let filter = EventFilter {
+        event_types: vec![
+            EventType::LandBought,
+            EventType::LandNuked,
+            EventType::NewAuction,
+            EventType::AuctionFinished,
+        ],
+        location: Some(location),
+        from_time: range.from,
+        to_time: range.to,
+        ..Default::default()
+    };
+
+    let lands = land_repository.get_history(location, range, &pagination);
+    let stakes = land_stake_repository.get_history(location, range, &pagination);
+    let events = event_repository.get_events(&filter, &pagination);
+
+    let (lands, stakes, events) = tokio::join!(lands, stakes, events);
+    let (lands, stakes, events) = match (lands, stakes, events) {
+        (Ok(lands), Ok(stakes), Ok(events)) => (lands, stakes, events),
+        (lands, stakes, events) => {
+            error!(
+                "Error while fetching history of land {}: {:?} {:?} {:?}",
+                *location,
+                lands.err(),
+                stakes.err(),
+                events.err()
+            );
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let limit = usize::try_from(pagination.sql_limit()).unwrap_or(usize::MAX);
+    let entries = merge_history(lands, stakes, events, limit);
+    let next = entries
+        .last()
+        .filter(|_| entries.len() >= limit)
+        .map(|entry| entry.id.clone());
+
+    Ok(Json(LandHistoryResponse {
+        location,
+        entries,
+        next,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use chaindata_models::{models::Level, shared::U256};
+    use chaindata_repository::memory::MemoryRepository;
+
+    fn land(id: EventId) -> LandModel {
+        let now = Utc::now().naive_utc();
+        LandModel {
+            id,
+            at: now,
+            location: Location::new(1),
+            bought_at: now,
+            owner: "0x1".to_string(),
+            sell_price: U256::from_str("1").unwrap(),
+            token_used: "0x2".to_string(),
+            level: Level::Zero,
+        }
+    }
+
+    fn stake(id: EventId) -> LandStakeModel {
+        let now = Utc::now().naive_utc();
+        LandStakeModel {
+            id,
+            at: now,
+            location: Location::new(1),
+            last_pay_time: now,
+            amount: U256::from_str("1").unwrap(),
+        }
+    }
+
+    fn nuked(id: EventId) -> FetchedEvent {
+        FetchedEvent {
+            id,
+            at: Utc::now().naive_utc(),
+            data: EventDataModel::LandNuked(LandNukedEventModel {
+                id: None,
+                location: Location::new(1),
+                owner: "0x1".to_string(),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_merge_history_ordering() {
+        let entries = merge_history(
+            vec![
+                land(EventId::new_test(1, 0, 0)),
+                land(EventId::new_test(3, 0, 0)),
+            ],
+            vec![stake(EventId::new_test(2, 0, 0))],
+            vec![nuked(EventId::new_test(1, 0, 1))],
+            10,
+        );
+
+        let ids: Vec<String> = entries.iter().map(|e| e.id.clone()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                EventId::new_test(1, 0, 0).as_string(),
+                EventId::new_test(1, 0, 1).as_string(),
+                EventId::new_test(2, 0, 0).as_string(),
+                EventId::new_test(3, 0, 0).as_string(),
+            ]
+        );
+        assert!(matches!(entries[1].kind, LandHistoryKind::LandNuked(_)));
+    }
+
+    #[test]
+    fn test_merge_history_limit_keeps_ties() {
+        let entries = merge_history(
+            vec![
+                land(EventId::new_test(1, 0, 0)),
+                land(EventId::new_test(2, 0, 0)),
+            ],
+            vec![stake(EventId::new_test(1, 0, 0))],
+            vec![],
+            1,
+        );
+
+        // Both entries of the first id are kept, as the next page starts after it
+        assert_eq!(entries.len(), 2);
+        assert!(entries
+            .iter()
+            .all(|e| e.id == EventId::new_test(1, 0, 0).as_string()));
+    }
+
+    #[tokio::test]
+    async fn test_get_history_pagination() {
+        let repo = Arc::new(MemoryRepository::new());
+
+        LandStore::save(repo.as_ref(), land(EventId::new_test(1, 0, 0)))
+            .await
+            .unwrap();
+        LandStakeStore::save(repo.as_ref(), stake(EventId::new_test(2, 0, 0)))
+            .await
+            .unwrap();
+        repo.save_events(vec![
+            nuked(EventId::new_test(3, 0, 0)),
+            FetchedEvent {
+                data: EventDataModel::LandNuked(LandNukedEventModel {
+                    id: None,
+                    location: Location::new(2),
+                    owner: "0x1".to_string(),
+                }),
+                ..nuked(EventId::new_test(4, 0, 0))
+            },
+        ])
+        .await
+        .unwrap();
+
+        let query = |after: Option<String>| HistoryQuery {
+            from: None,
+            to: None,
+            after,
+            limit: Some(2),
+        };
+
+        let Json(first) = get_history(
+            Path(1),
+            Query(query(None)),
+            State(repo.clone()),
+            State(repo.clone()),
+            State(repo.clone()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(first.entries.len(), 2);
+        assert!(matches!(first.entries[1].kind, LandHistoryKind::Stake(_)));
+
+        let Json(second) = get_history(
+            Path(1),
+            Query(query(first.next)),
+            State(repo.clone()),
+            State(repo.clone()),
+            State(repo),
+        )
+        .await
+        .unwrap();
+        // The nuke of the other location is not part of the history
+        assert_eq!(second.entries.len(), 1);
+        assert!(matches!(
+            second.entries[0].kind,
+            LandHistoryKind::LandNuked(_)
+        ));
+        assert_eq!(second.next, None);
+    }
+}
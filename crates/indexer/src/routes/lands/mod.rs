@@ -1,5 +1,5 @@
 use axum::{extract::State, routing::get, Json, Router};
-use chaindata_repository::LandRepository;
+use chaindata_repository::LandStore;
 use serde::Serialize;
 use std::{
     sync::{Arc, OnceLock},
@@ -7,7 +7,10 @@ use std::{
 };
 use tokio::sync::RwLock;
 
-use crate::state::AppState;
+use crate::{routes::taxes, state::AppState};
+
+pub mod at_risk;
+pub mod history;
 
 #[derive(Debug, Clone, Serialize)]
 pub struct TokenDistribution {
@@ -53,12 +56,16 @@ impl LandsRoute {
     }
 
     pub fn router(self) -> Router<AppState> {
-        Router::new().route("/distribution", get(Self::get_distribution))
+        Router::new()
+            .route("/distribution", get(Self::get_distribution))
+            .route("/at-risk", get(at_risk::get_at_risk))
+            .route("/{location}/history", get(history::get_history))
+            .route("/{location}/taxes", get(taxes::get_land_taxes))
     }
 
     #[allow(clippy::cast_precision_loss)]
     async fn get_distribution(
-        State(land_repository): State<Arc<LandRepository>>,
+        State(land_repository): State<Arc<dyn LandStore>>,
     ) -> Json<LandDistributionResponse> {
         // Check if we have valid cached data
         let cache = DISTRIBUTION_CACHE.get_or_init(|| Arc::new(RwLock::new(None)));
@@ -0,0 +1,164 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use chaindata_repository::{
+    LeaderboardCategory, LeaderboardEntry, LeaderboardFilter, LeaderboardRepository,
+    LeaderboardWindow,
+};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{routes::parse_address, state::AppState};
+
+/// Addresses with fewer transactions are not ranked by default.
+const DEFAULT_MIN_TRANSACTIONS: u64 = 30;
+const MAX_LIMIT: u32 = 100;
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    TopTaxLord,
+    LandBaronAuction,
+    LandBaronPlayer,
+    TaxDodger,
+    BiggestLoser,
+    TopHolders,
+}
+
+impl From<Category> for LeaderboardCategory {
+    fn from(category: Category) -> Self {
+        match category {
+            Category::TopTaxLord => LeaderboardCategory::TopTaxLord,
+            Category::LandBaronAuction => LeaderboardCategory::LandBaronAuction,
+            Category::LandBaronPlayer => LeaderboardCategory::LandBaronPlayer,
+            Category::TaxDodger => LeaderboardCategory::TaxDodger,
+            Category::BiggestLoser => LeaderboardCategory::BiggestLoser,
+            Category::TopHolders => LeaderboardCategory::TopHolders,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Window {
+    Day,
+    Week,
+    Month,
+    #[default]
+    All,
+}
+
+impl From<Window> for LeaderboardWindow {
+    fn from(window: Window) -> Self {
+        match window {
+            Window::Day => LeaderboardWindow::Day,
+            Window::Week => LeaderboardWindow::Week,
+            Window::Month => LeaderboardWindow::Month,
+            Window::All => LeaderboardWindow::All,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LeaderboardQuery {
+    pub category: Category,
+    #[serde(default)]
+    pub window: Window,
+    pub min_transactions: Option<u64>,
+    /// Comma separated addresses not to rank.
+    pub exclude: Option<String>,
+    /// Defaults to the size of the category on the leaderboard.
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Entry {
+    pub rank: u64,
+    pub address: String,
+    pub score: f64,
+    pub transactions: u64,
+}
+
+impl From<LeaderboardEntry> for Entry {
+    fn from(entry: LeaderboardEntry) -> Self {
+        Self {
+            rank: entry.rank,
+            address: entry.address,
+            score: entry.score,
+            transactions: entry.transactions,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LeaderboardResponse {
+    pub category: Category,
+    pub window: Window,
+    /// When the ranked scores were computed, if they were.
+    pub computed_at: Option<NaiveDateTime>,
+    pub entries: Vec<Entry>,
+}
+
+pub struct LeaderboardRoute;
+
+impl Default for LeaderboardRoute {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LeaderboardRoute {
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn router(self) -> Router<AppState> {
+        Router::new().route("/", get(Self::get_leaderboard))
+    }
+
+    async fn get_leaderboard(
+        Query(query): Query<LeaderboardQuery>,
+        State(repository): State<Arc<LeaderboardRepository>>,
+    ) -> Result<Json<LeaderboardResponse>, StatusCode> {
+        let exclude = query
+            .exclude
+            .iter()
+            .flat_map(|exclude| exclude.split(','))
+            .map(str::trim)
+            .filter(|address| !address.is_empty())
+            .map(parse_address)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let category = LeaderboardCategory::from(query.category);
+        let filter = LeaderboardFilter {
+            min_transactions: query.min_transactions.unwrap_or(DEFAULT_MIN_TRANSACTIONS),
+            exclude,
+        };
+        let limit = query
+            .limit
+            .unwrap_or(category.default_limit())
+            .min(MAX_LIMIT);
+
+        let entries = repository
+            .get(category, query.window.into(), &filter, limit)
+            .await
+            .map_err(|err| {
+                error!("Error while fetching the leaderboard: {}", err);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+
+        Ok(Json(LeaderboardResponse {
+            category: query.category,
+            window: query.window,
+            computed_at: entries.first().map(|entry| entry.computed_at),
+            entries: entries.into_iter().map(Entry::from).collect(),
+        }))
+    }
+}
@@ -1,3 +1,19 @@
+use axum::http::StatusCode;
+use starknet::core::types::Felt;
+
 pub mod lands;
+pub mod leaderboard;
+pub mod players;
 pub mod price;
+pub mod taxes;
 pub mod tokens;
+
+/// Parses an address sent by the user into the short hex form addresses are stored in.
+///
+/// # Errors
+/// Returns `BAD_REQUEST` if the address is not a valid hex felt.
+pub fn parse_address(address: &str) -> Result<String, StatusCode> {
+    Felt::from_hex(address)
+        .map(|address| format!("{address:#x}"))
+        .map_err(|_| StatusCode::BAD_REQUEST)
+}
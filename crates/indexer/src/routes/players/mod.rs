@@ -0,0 +1,138 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use chaindata_models::{
+    models::{LandModel, LandStakeModel},
+    shared::U256,
+};
+use chaindata_repository::{OwnedLand, PlayerRepository, PlayerStats};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{
+    routes::{parse_address, taxes},
+    state::AppState,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct PlayerQuery {
+    /// If set, returns the lands owned at that time instead of the current ones.
+    pub at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayerLand {
+    #[serde(flatten)]
+    pub land: LandModel,
+    pub stake: Option<LandStakeModel>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PlayerStatsResponse {
+    pub purchases: u64,
+    pub sales: u64,
+    pub nukes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayerResponse {
+    pub address: String,
+    pub lands: Vec<PlayerLand>,
+    /// Total amount currently staked on the lands of the player, per token.
+    pub stake_per_token: HashMap<String, U256>,
+    pub stats: PlayerStatsResponse,
+}
+
+impl From<OwnedLand> for PlayerLand {
+    fn from(owned: OwnedLand) -> Self {
+        Self {
+            land: owned.land,
+            stake: owned.stake,
+        }
+    }
+}
+
+impl From<PlayerStats> for PlayerStatsResponse {
+    fn from(stats: PlayerStats) -> Self {
+        Self {
+            purchases: stats.purchases,
+            sales: stats.sales,
+            nukes: stats.nukes,
+        }
+    }
+}
+
+pub struct PlayersRoute;
+
+impl Default for PlayersRoute {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PlayersRoute {
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn router(self) -> Router<AppState> {
+        Router::new()
+            .route("/{address}", get(Self::get_player))
+            .route("/{address}/taxes", get(taxes::get_player_taxes))
+    }
+
+    async fn get_player(
+        Path(address): Path<String>,
+        Query(query): Query<PlayerQuery>,
+        State(player_repository): State<Arc<PlayerRepository>>,
+    ) -> Result<Json<PlayerResponse>, StatusCode> {
+        let address = parse_address(&address)?;
+
+        let lands = match query.at {
+            Some(at) => player_repository
+                .get_owned_lands_at(&address, at.naive_utc())
+                .await
+                .map(|lands| {
+                    lands
+                        .into_iter()
+                        .map(|land| PlayerLand { land, stake: None })
+                        .collect::<Vec<_>>()
+                }),
+            None => player_repository
+                .get_owned_lands(&address)
+                .await
+                .map(|lands| lands.into_iter().map(PlayerLand::from).collect()),
+        };
+
+        let (stake_per_token, stats) = tokio::join!(
+            player_repository.get_stake_per_token(&address),
+            player_repository.get_stats(&address)
+        );
+
+        match (lands, stake_per_token, stats) {
+            (Ok(lands), Ok(stake_per_token), Ok(stats)) => Ok(Json(PlayerResponse {
+                address,
+                lands,
+                stake_per_token,
+                stats: stats.into(),
+            })),
+            (lands, stake_per_token, stats) => {
+                error!(
+                    "Error while fetching player {}: {:?} {:?} {:?}",
+                    address,
+                    lands.err(),
+                    stake_per_token.err(),
+                    stats.err()
+                );
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
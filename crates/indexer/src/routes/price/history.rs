@@ -0,0 +1,154 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chaindata_repository::{Candle, TimeRange, TokenPriceRepository};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::routes::parse_address;
+
+/// Candles of an hour are returned by default.
+const DEFAULT_INTERVAL: u64 = 3600;
+/// Most candles returned at once.
+const MAX_CANDLES: i64 = 1000;
+
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+    /// A day before `to` by default.
+    pub from: Option<DateTime<Utc>>,
+    /// Now by default.
+    pub to: Option<DateTime<Utc>>,
+    /// Duration of the candles, in seconds.
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+}
+
+fn default_interval() -> u64 {
+    DEFAULT_INTERVAL
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TwapQuery {
+    /// An hour before `to` by default.
+    pub from: Option<DateTime<Utc>>,
+    /// Now by default.
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Amounts of the token per main token over a candle.
+#[derive(Debug, Clone, Serialize)]
+pub struct PriceCandle {
+    pub start: NaiveDateTime,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub samples: i64,
+}
+
+impl From<Candle> for PriceCandle {
+    fn from(candle: Candle) -> Self {
+        Self {
+            start: candle.start,
+            open: candle.open,
+            high: candle.high,
+            low: candle.low,
+            close: candle.close,
+            samples: candle.samples,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PriceHistoryResponse {
+    pub token: String,
+    pub interval: u64,
+    pub candles: Vec<PriceCandle>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwapResponse {
+    pub token: String,
+    pub from: NaiveDateTime,
+    pub to: NaiveDateTime,
+    /// Time-weighted average amount of the token per main token, if any price was sampled.
+    pub ratio: Option<f64>,
+}
+
+/// Bounds of the query, `from` defaulting to `default` before `to`.
+fn bounds(
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    default: Duration,
+) -> Result<(NaiveDateTime, NaiveDateTime), StatusCode> {
+    let to = to.unwrap_or_else(Utc::now).naive_utc();
+    let from = match from {
+        Some(from) => from.naive_utc(),
+        None => to.checked_sub_signed(default).unwrap_or(NaiveDateTime::MIN),
+    };
+
+    if from >= to {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok((from, to))
+}
+
+/// Candles of the price of a token, the oldest first.
+pub async fn get_history(
+    Path(token): Path<String>,
+    Query(query): Query<HistoryQuery>,
+    State(repository): State<Arc<TokenPriceRepository>>,
+) -> Result<Json<PriceHistoryResponse>, StatusCode> {
+    let token = parse_address(&token)?;
+    let (from, to) = bounds(query.from, query.to, Duration::days(1))?;
+    let interval = i64::try_from(query.interval)
+        .ok()
+        .filter(|interval| *interval > 0)
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    if (to - from).num_seconds() / interval > MAX_CANDLES {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    match repository
+        .get_candles(&token, interval, TimeRange::new(Some(from), Some(to)))
+        .await
+    {
+        Ok(candles) => Ok(Json(PriceHistoryResponse {
+            token,
+            interval: query.interval,
+            candles: candles.into_iter().map(PriceCandle::from).collect(),
+        })),
+        Err(err) => {
+            error!("Error while fetching the price history: {err}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Average price of a token over a time range, each price weighted by how long it lasted.
+pub async fn get_twap(
+    Path(token): Path<String>,
+    Query(query): Query<TwapQuery>,
+    State(repository): State<Arc<TokenPriceRepository>>,
+) -> Result<Json<TwapResponse>, StatusCode> {
+    let token = parse_address(&token)?;
+    let (from, to) = bounds(query.from, query.to, Duration::hours(1))?;
+
+    match repository.get_twap(&token, from, to).await {
+        Ok(ratio) => Ok(Json(TwapResponse {
+            token,
+            from,
+            to,
+            ratio,
+        })),
+        Err(err) => {
+            error!("Error while computing the time-weighted price: {err}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
@@ -1,13 +1,27 @@
-use axum::{extract::State, routing::get, Json, Router};
-use ekubo::{contract::pool_price::PoolKey, price::PairRatio};
-use serde::Serialize;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use ekubo::{
+    contract::pool_price::PoolKey, math::u256fd128::U256FD128, price::PairRatio, routing::Hop,
+};
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
 use std::sync::Arc;
+use tracing::error;
 
 use crate::{
-    service::{ekubo::EkuboService, token::TokenService},
+    service::{
+        ekubo::{EkuboService, QuoteError},
+        token::TokenService,
+    },
     state::AppState,
 };
 
+pub mod history;
+
 #[repr(transparent)]
 #[derive(Debug)]
 pub struct Price(pub(crate) PairRatio);
@@ -27,7 +41,37 @@ pub struct TokenWithPrice {
     pub address: String,
     pub ratio: Option<Price>,
     pub best_pool: Option<PoolKey>,
+    /// Pools the ratio was routed through, from the main token.
+    pub path: Option<Vec<Hop>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuoteQuery {
+    /// Token swapped.
+    pub from: String,
+    /// Token received, the main token by default.
+    pub to: Option<String>,
+    /// Amount swapped, in the smallest unit of the token.
+    pub amount: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuoteResponse {
+    pub from: String,
+    pub to: String,
+    /// Amount swapped, lower than the requested one when the pools lack the liquidity for it.
+    pub amount_in: String,
+    pub amount_out: String,
+    /// Amount out per amount in, before the swap and without fees.
+    pub spot_price: Price,
+    /// Amount out per amount in, fees included.
+    pub effective_price: Option<Price>,
+    /// Fraction of the spot price lost by the swap, fees included.
+    pub price_impact: f64,
+    /// Pools the swap goes through.
+    pub path: Vec<Hop>,
 }
+
 pub struct PriceRoute;
 
 impl Default for PriceRoute {
@@ -43,7 +87,11 @@ impl PriceRoute {
     }
 
     pub fn router(self) -> Router<AppState> {
-        Router::new().route("/", get(Self::get_price))
+        Router::new()
+            .route("/", get(Self::get_price))
+            .route("/quote", get(Self::get_quote))
+            .route("/{token}/history", get(history::get_history))
+            .route("/{token}/twap", get(history::get_twap))
     }
 
     #[allow(clippy::unused_async)] // required for axum
@@ -63,6 +111,7 @@ impl PriceRoute {
                         address: token.address.to_fixed_hex_string(),
                         ratio: Some(Price(ratio.ratio)),
                         best_pool: Some(ratio.pool),
+                        path: Some(ratio.path),
                     }
                 } else {
                     TokenWithPrice {
@@ -70,10 +119,55 @@ impl PriceRoute {
                         address: token.address.to_fixed_hex_string(),
                         ratio: None,
                         best_pool: None,
+                        path: None,
                     }
                 }
             })
             .collect();
         Json(tokens)
     }
+
+    /// Simulates a swap against the liquidity of the pools, to show how much the amount would
+    /// really get.
+    ///
+    /// Answers `503 Service Unavailable` while too many swaps are being simulated.
+    async fn get_quote(
+        Query(query): Query<QuoteQuery>,
+        State(token_service): State<Arc<TokenService>>,
+        State(ekubo_service): State<Arc<EkuboService>>,
+    ) -> Result<Json<QuoteResponse>, StatusCode> {
+        let from = Felt::from_hex(&query.from).map_err(|_| StatusCode::BAD_REQUEST)?;
+        let to = match query.to {
+            Some(to) => Felt::from_hex(&to).map_err(|_| StatusCode::BAD_REQUEST)?,
+            None => token_service.main_token().address,
+        };
+        let amount = query
+            .amount
+            .parse::<u128>()
+            .ok()
+            .filter(|amount| *amount > 0)
+            .ok_or(StatusCode::BAD_REQUEST)?;
+
+        match ekubo_service
+            .quote(from, to, U256FD128::from_whole(amount))
+            .await
+        {
+            Ok(Some((quote, path))) => Ok(Json(QuoteResponse {
+                from: from.to_fixed_hex_string(),
+                to: to.to_fixed_hex_string(),
+                amount_in: quote.amount_in.trunc().to_string(),
+                amount_out: quote.amount_out.trunc().to_string(),
+                effective_price: quote.effective_price().map(Price),
+                price_impact: quote.price_impact(),
+                spot_price: Price(quote.spot_price),
+                path,
+            })),
+            Ok(None) => Err(StatusCode::NOT_FOUND),
+            Err(QuoteError::Busy) => Err(StatusCode::SERVICE_UNAVAILABLE),
+            Err(err) => {
+                error!("Error while quoting a swap: {err}");
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
 }
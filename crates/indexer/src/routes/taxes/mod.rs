@@ -0,0 +1,166 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use chaindata_models::shared::{Location, U256};
+use chaindata_repository::{TaxLedgerRepository, TaxTotals, TimeRange, TokenTaxes};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{routes::parse_address, service::token::TokenService, state::AppState};
+
+#[derive(Debug, Deserialize)]
+pub struct TaxesQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl TaxesQuery {
+    fn range(&self) -> TimeRange {
+        TimeRange::new(
+            self.from.map(|from| from.naive_utc()),
+            self.to.map(|to| to.naive_utc()),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenTotals {
+    pub token: String,
+    pub paid: U256,
+    pub received: U256,
+    pub transfers: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaxesResponse {
+    pub tokens: Vec<TokenTotals>,
+    /// Received minus paid, in the main token, each transfer valued at the price of its token
+    /// when it happened.
+    pub net_in_main_token: f64,
+    /// Tokens with transfers older than their first stored price, left out of the net amount.
+    pub unpriced_tokens: Vec<String>,
+}
+
+impl From<Vec<TaxTotals>> for TaxesResponse {
+    fn from(totals: Vec<TaxTotals>) -> Self {
+        Self {
+            net_in_main_token: totals.iter().map(|total| total.net_in_main_token).sum(),
+            unpriced_tokens: totals
+                .iter()
+                .filter(|total| total.unpriced_transfers > 0)
+                .map(|total| total.token.clone())
+                .collect(),
+            tokens: totals
+                .into_iter()
+                .map(|total| TokenTotals {
+                    token: total.token,
+                    paid: total.paid,
+                    received: total.received,
+                    transfers: total.transfers,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenTaxesResponse {
+    pub token: String,
+    pub amount: U256,
+    pub transfers: i64,
+}
+
+impl From<TokenTaxes> for TokenTaxesResponse {
+    fn from(taxes: TokenTaxes) -> Self {
+        Self {
+            token: taxes.token,
+            amount: taxes.amount,
+            transfers: taxes.transfers,
+        }
+    }
+}
+
+pub struct TaxesRoute;
+
+impl Default for TaxesRoute {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TaxesRoute {
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn router(self) -> Router<AppState> {
+        Router::new().route("/", get(Self::get_totals_by_token))
+    }
+
+    async fn get_totals_by_token(
+        Query(query): Query<TaxesQuery>,
+        State(tax_ledger_repository): State<Arc<TaxLedgerRepository>>,
+    ) -> Result<Json<Vec<TokenTaxesResponse>>, StatusCode> {
+        match tax_ledger_repository
+            .get_totals_by_token(query.range())
+            .await
+        {
+            Ok(totals) => Ok(Json(totals.into_iter().map(Into::into).collect())),
+            Err(err) => {
+                error!("Error while fetching the taxes per token: {err}");
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+/// Taxes paid and received by a player, through all the lands they owned.
+pub async fn get_player_taxes(
+    Path(address): Path<String>,
+    Query(query): Query<TaxesQuery>,
+    State(tax_ledger_repository): State<Arc<TaxLedgerRepository>>,
+    State(token_service): State<Arc<TokenService>>,
+) -> Result<Json<TaxesResponse>, StatusCode> {
+    let main_token = format!("{:#x}", token_service.main_token().address);
+    let address = parse_address(&address)?;
+
+    match tax_ledger_repository
+        .get_totals_by_owner(&address, &main_token, query.range())
+        .await
+    {
+        Ok(totals) => Ok(Json(totals.into())),
+        Err(err) => {
+            error!("Error while fetching the taxes of player {address}: {err}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Taxes paid and received by a land, whoever owned it.
+pub async fn get_land_taxes(
+    Path(location): Path<u64>,
+    Query(query): Query<TaxesQuery>,
+    State(tax_ledger_repository): State<Arc<TaxLedgerRepository>>,
+    State(token_service): State<Arc<TokenService>>,
+) -> Result<Json<TaxesResponse>, StatusCode> {
+    let main_token = format!("{:#x}", token_service.main_token().address);
+    let location = Location::new(location);
+
+    match tax_ledger_repository
+        .get_totals_by_location(location, &main_token, query.range())
+        .await
+    {
+        Ok(totals) => Ok(Json(totals.into())),
+        Err(err) => {
+            error!("Error while fetching the taxes of land {location:?}: {err}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
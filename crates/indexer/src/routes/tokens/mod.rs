@@ -1,9 +1,21 @@
 use std::sync::Arc;
 
-use axum::{extract::State, routing::get, Json, Router};
-use serde::Serialize;
+use axum::{
+    extract::{FromRef, Path, Query, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use chaindata_models::shared::U256;
+use chaindata_repository::{TokenHolder, TokenRepository};
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+use tracing::error;
 
-use crate::service::token::TokenService;
+use crate::{routes::parse_address, service::token::TokenService};
+
+/// Maximum number of holders returned at once.
+const MAX_HOLDERS: u32 = 100;
 
 #[derive(Debug, Serialize)]
 pub struct Token {
@@ -11,19 +23,69 @@ pub struct Token {
     pub address: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct HoldersQuery {
+    #[serde(default = "default_holders")]
+    pub limit: u32,
+}
+
+fn default_holders() -> u32 {
+    10
+}
+
+#[derive(Debug, Serialize)]
+pub struct Holder {
+    pub account: String,
+    pub balance: U256,
+}
+
+impl From<TokenHolder> for Holder {
+    fn from(holder: TokenHolder) -> Self {
+        Self {
+            account: holder.account,
+            balance: holder.balance,
+        }
+    }
+}
+
 #[derive(Clone)]
-pub struct TokenRoute(Arc<TokenService>);
+pub struct TokenRoute {
+    token_service: Arc<TokenService>,
+    token_repository: Arc<TokenRepository>,
+    /// Addresses left out of the holders, formatted as stored.
+    blacklist: Arc<Vec<String>>,
+}
+
+impl FromRef<TokenRoute> for Arc<TokenService> {
+    fn from_ref(route: &TokenRoute) -> Self {
+        route.token_service.clone()
+    }
+}
 
 impl TokenRoute {
     #[must_use]
-    pub fn new(token_service: Arc<TokenService>) -> Self {
-        Self(token_service)
+    pub fn new(
+        token_service: Arc<TokenService>,
+        token_repository: Arc<TokenRepository>,
+        blacklist: &[Felt],
+    ) -> Self {
+        Self {
+            token_service,
+            token_repository,
+            blacklist: Arc::new(
+                blacklist
+                    .iter()
+                    .map(|address| format!("{address:#x}"))
+                    .collect(),
+            ),
+        }
     }
 
     pub fn router(self) -> Router {
         Router::new()
             .route("/", get(Self::list_tokens))
-            .with_state(self.0.clone())
+            .route("/{address}/holders", get(Self::get_holders))
+            .with_state(self)
     }
 
     #[allow(clippy::unused_async)] // required for axum
@@ -38,4 +100,24 @@ impl TokenRoute {
             .collect();
         Json(tokens)
     }
+
+    async fn get_holders(
+        Path(address): Path<String>,
+        Query(query): Query<HoldersQuery>,
+        State(route): State<TokenRoute>,
+    ) -> Result<Json<Vec<Holder>>, StatusCode> {
+        let token = parse_address(&address)?;
+
+        match route
+            .token_repository
+            .get_top_holders(&token, &route.blacklist, query.limit.min(MAX_HOLDERS))
+            .await
+        {
+            Ok(holders) => Ok(Json(holders.into_iter().map(Holder::from).collect())),
+            Err(err) => {
+                error!("Error while fetching the holders of {token}: {err}");
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
 }
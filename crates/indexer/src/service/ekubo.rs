@@ -1,18 +1,35 @@
-use std::{collections::HashMap, str::FromStr, sync::Arc};
+use std::{collections::HashMap, str::FromStr, sync::Arc, time::Duration};
 
 use anyhow::{Context, Result};
 use apalis::prelude::*;
 use apalis_cron::{CronContext, CronStream, Schedule};
 use arc_swap::ArcSwap;
-use chrono::Utc;
-use ekubo::{contract::pool_price::PoolKey, price::PairRatio, EkuboClient};
+use chaindata_models::shared::U256;
+use chaindata_repository::{TokenPrice, TokenPriceRepository};
+use chrono::{NaiveDateTime, Utc};
+use ekubo::{
+    contract::pool_price::PoolKey,
+    math::u256fd128::U256FD128,
+    price::PairRatio,
+    quote::Quote,
+    routing::{Hop, PoolGraph},
+    selection::PoolSelector,
+    EkuboClient, Felt,
+};
 use starknet::providers::{jsonrpc::HttpTransport, JsonRpcClient};
+use tokio::sync::Semaphore;
 use tracing::{error, info};
 
 use crate::{config::Conf, monitoring::apalis::MonitoringLayer, worker::MonitorManager};
 
 use super::token::TokenService;
 
+/// Maximum number of swaps simulated at once, each one reading up to hundreds of pool states.
+const MAX_CONCURRENT_QUOTES: usize = 4;
+
+/// How long a quote waits for the others to finish before being refused.
+const QUOTE_WAIT: Duration = Duration::from_secs(2);
+
 #[derive(Debug, Default, Clone)]
 pub struct EkuboJob;
 
@@ -31,23 +48,85 @@ pub struct EkuboService {
     token_service: Arc<TokenService>,
     exchange_rate: ArcSwap<PriceInformation>,
     client: ekubo::EkuboClient<JsonRpcClient<HttpTransport>>,
+    max_hops: usize,
+    /// Tokens the prices can be routed through, besides the listed ones.
+    routing_tokens: Vec<Felt>,
+    selector: PoolSelector,
+    /// Where every update of the prices is kept.
+    token_price_repository: Arc<TokenPriceRepository>,
+    /// Bounds the RPC calls made by the quotes, however many are requested.
+    quotes: Semaphore,
+}
+
+/// Why a swap could not be quoted.
+#[derive(Debug)]
+pub enum QuoteError {
+    /// Too many swaps are being simulated, the quote can be asked again later.
+    Busy,
+    /// The state of a pool could not be read.
+    Ekubo(ekubo::Error),
+}
+
+impl std::fmt::Display for QuoteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuoteError::Busy => write!(f, "Too many swaps are being simulated"),
+            QuoteError::Ekubo(err) => write!(f, "Could not read the state of a pool: {err}"),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct EkuboTokenInformation {
+    /// Amount of the token per main token.
     pub ratio: PairRatio,
+    /// Pool holding the token, the last one of the path.
     pub pool: PoolKey,
+    /// Pools the price was routed through, from the main token.
+    pub path: Vec<Hop>,
 }
 
 #[derive(Default, Debug)]
 pub struct PriceInformation {
     inner: HashMap<String, EkuboTokenInformation>,
+    /// Pools the prices were chosen from.
+    graph: PoolGraph,
+}
+
+impl PriceInformation {
+    /// The prices of the tokens as stored in the history.
+    fn samples(&self, at: NaiveDateTime) -> Vec<TokenPrice> {
+        self.inner
+            .values()
+            .filter_map(|info| {
+                let token = info.path.last()?.to;
+                let pool_fee = match U256::from_str(&info.pool.fee.to_string()) {
+                    Ok(pool_fee) => pool_fee,
+                    Err(err) => {
+                        error!("Not storing the price of {token:#x}, invalid pool fee: {err}");
+                        return None;
+                    }
+                };
+                Some(TokenPrice {
+                    token: format!("{token:#x}"),
+                    at,
+                    ratio: info.ratio.0.into(),
+                    pool_token0: format!("{:#x}", info.pool.token0),
+                    pool_token1: format!("{:#x}", info.pool.token1),
+                    pool_fee,
+                    pool_tick_spacing: info.pool.tick_spacing.into(),
+                    pool_extension: format!("{:#x}", info.pool.extension),
+                })
+            })
+            .collect()
+    }
 }
 
 impl EkuboService {
     pub async fn new(
         config: &Conf,
         token_service: Arc<TokenService>,
+        token_price_repository: Arc<TokenPriceRepository>,
         monitor: &MonitorManager,
     ) -> Result<Arc<Self>> {
         let schedule =
@@ -56,13 +135,23 @@ impl EkuboService {
         let rpc_client = JsonRpcClient::new(HttpTransport::new(config.starknet.rpc_url.clone()));
 
         let this = Arc::new(Self {
-            token_service,
             exchange_rate: ArcSwap::new(Arc::new(PriceInformation::default())),
             client: EkuboClient::new(
                 config.ekubo.core_contract_address,
                 rpc_client,
                 config.ekubo.api_url.to_string(),
             ),
+            max_hops: config.ekubo.max_hops,
+            routing_tokens: config.ekubo.routing_tokens.clone(),
+            selector: PoolSelector {
+                strategy: config.ekubo.pool_selection,
+                min_liquidity: config.ekubo.min_liquidity,
+                main_token: token_service.main_token().address,
+                max_hops: config.ekubo.max_hops,
+            },
+            token_service,
+            token_price_repository,
+            quotes: Semaphore::new(MAX_CONCURRENT_QUOTES),
         });
 
         // queue initial update
@@ -86,54 +175,104 @@ impl EkuboService {
         self.exchange_rate.load().inner.get(token).cloned()
     }
 
-    #[allow(clippy::missing_panics_doc)]
+    /// Simulates a swap of `amount` from a token to another, through the pools of the last
+    /// update.
+    ///
+    /// Returns `None` when no path links the tokens within the configured number of pools.
+    ///
+    /// # Errors
+    /// Returns [`QuoteError::Busy`] if too many swaps are already being simulated, or an error
+    /// if the state of a pool could not be read
+    pub async fn quote(
+        &self,
+        from: Felt,
+        to: Felt,
+        amount: U256FD128,
+    ) -> Result<Option<(Quote, Vec<Hop>)>, QuoteError> {
+        let Some(path) = self
+            .exchange_rate
+            .load()
+            .graph
+            .find_path(from, to, self.max_hops)
+        else {
+            return Ok(None);
+        };
+
+        let Ok(Ok(_permit)) = tokio::time::timeout(QUOTE_WAIT, self.quotes.acquire()).await else {
+            return Err(QuoteError::Busy);
+        };
+        let quote = self
+            .client
+            .quote_path(&path, amount)
+            .await
+            .map_err(QuoteError::Ekubo)?;
+        Ok(Some((quote, path)))
+    }
+
     /// Update the exchange rate information.
+    ///
+    /// Tokens without a pool paired with the main token are priced through the other tokens, up
+    /// to the configured number of pools.
     pub async fn update(&self) {
         let main_token = self.token_service.main_token().address;
+        let tokens = self.token_service.list();
 
-        let mut price_info = PriceInformation::default();
-
-        'token_loop: for token in self.token_service.list() {
-            let pool = match self.client.get_pools(main_token, token.address).await {
-                Ok(vec) if !vec.is_empty() => vec.into_iter().next().unwrap(), // safe because of the bounds check
-                Ok(_) => {
-                    // No pools found for token, go to the next one.
-                    continue 'token_loop;
-                }
-                Err(err) => {
-                    error!(
-                        "Failed to fetch pool for token {}: {:#?}",
-                        token.address, err
-                    );
-                    continue 'token_loop;
-                }
-            };
+        let mut graph_tokens: Vec<Felt> = tokens.iter().map(|token| token.address).collect();
+        for token in &self.routing_tokens {
+            if !graph_tokens.contains(token) {
+                graph_tokens.push(*token);
+            }
+        }
 
-            let price = match self.client.read_pool_price(&pool.key).await {
-                Ok(price) => price,
-                Err(err) => {
-                    error!("Failed to fetch price for pool {}: {:#?}", pool.key, err);
-                    continue 'token_loop;
-                }
+        let graph = self
+            .client
+            .get_pool_graph(&graph_tokens, &self.selector)
+            .await;
+        if graph.is_empty() {
+            // Keep the previous prices rather than losing all of them
+            error!("None of the pools of the tokens could be read");
+            return;
+        }
+
+        let mut price_info = PriceInformation {
+            graph,
+            ..PriceInformation::default()
+        };
+
+        for token in tokens {
+            if token.address == main_token {
+                continue;
+            }
+
+            let Some(path) = price_info
+                .graph
+                .find_price(main_token, token.address, self.max_hops)
+            else {
+                // No path found for token, go to the next one.
+                continue;
             };
 
-            let price = if main_token < token.address {
-                price
-            } else {
-                price.inverse()
+            let Some(last) = path.hops.last() else {
+                continue;
             };
 
             price_info.inner.insert(
                 token.address.to_fixed_hex_string(),
                 EkuboTokenInformation {
-                    pool: pool.key,
-                    ratio: price,
+                    pool: last.pool.clone(),
+                    ratio: path.ratio,
+                    path: path.hops,
                 },
             );
         }
 
         info!("Finished ekubo update!");
 
+        let samples = price_info.samples(Utc::now().naive_utc());
+        if let Err(err) = self.token_price_repository.save(&samples).await {
+            error!("Failed to save the token prices: {:#?}", err);
+        }
+
         // Once everything is done, update the exchange rate
         self.exchange_rate.swap(Arc::new(price_info));
     }
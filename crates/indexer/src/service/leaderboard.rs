@@ -0,0 +1,96 @@
+use std::{str::FromStr, sync::Arc};
+
+use anyhow::{Context, Result};
+use apalis::prelude::*;
+use apalis_cron::{CronContext, CronStream, Schedule};
+use chaindata_repository::{LeaderboardRepository, LeaderboardWindow};
+use chrono::Utc;
+use tracing::{error, info};
+
+use crate::{
+    config::Conf, monitoring::apalis::MonitoringLayer, service::token::TokenService,
+    worker::MonitorManager,
+};
+
+#[derive(Debug, Default, Clone)]
+pub struct LeaderboardJob;
+
+pub async fn update_leaderboard(
+    _: LeaderboardJob,
+    _ctx: CronContext<Utc>,
+    leaderboard: Data<Arc<LeaderboardService>>,
+) {
+    leaderboard.update().await;
+}
+
+/// Recomputes the leaderboard snapshots every five minutes.
+pub struct LeaderboardService {
+    repository: Arc<LeaderboardRepository>,
+    /// Addresses never ranked, formatted as stored.
+    blacklist: Vec<String>,
+    /// Token in which amounts are compared, formatted as stored.
+    main_token: String,
+    /// Token whose top holders are ranked, formatted as stored.
+    holders_token: Option<String>,
+}
+
+impl LeaderboardService {
+    pub fn new(
+        config: &Conf,
+        repository: Arc<LeaderboardRepository>,
+        token_service: &TokenService,
+        monitor: &MonitorManager,
+    ) -> Result<Arc<Self>> {
+        let schedule =
+            Schedule::from_str("0 0/5 * * * *").with_context(|| "Could not parse Schedule")?;
+
+        let this = Arc::new(Self {
+            repository,
+            blacklist: config
+                .leaderboard
+                .blacklist
+                .iter()
+                .map(|address| format!("{address:#x}"))
+                .collect(),
+            main_token: format!("{:#x}", token_service.main_token().address),
+            holders_token: config
+                .leaderboard
+                .holders_token
+                .map(|token| format!("{token:#x}")),
+        });
+
+        let worker = WorkerBuilder::new("leaderboard")
+            .enable_tracing()
+            .concurrency(1)
+            .layer(MonitoringLayer::new("leaderboard-update"))
+            .data(this.clone())
+            .backend(CronStream::new_with_timezone(schedule, Utc))
+            .build_fn(update_leaderboard);
+
+        monitor.register(move |mon| mon.register(worker));
+
+        Ok(this)
+    }
+
+    /// Recomputes the snapshot of every window.
+    pub async fn update(&self) {
+        let now = Utc::now().naive_utc();
+
+        for window in LeaderboardWindow::ALL {
+            match self
+                .repository
+                .refresh(
+                    window,
+                    now,
+                    &self.blacklist,
+                    &self.main_token,
+                    self.holders_token.as_deref(),
+                )
+                .await
+            {
+                Ok(scores) => info!("Computed {scores} leaderboard scores for {window:?}"),
+                Err(err) => error!("Failed to compute the leaderboard for {window:?}: {err}"),
+            }
+        }
+    }
+}
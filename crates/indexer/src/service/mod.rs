@@ -1,2 +1,4 @@
 pub mod ekubo;
+pub mod leaderboard;
+pub mod nuke_forecast;
 pub mod token;
@@ -0,0 +1,58 @@
+use std::{str::FromStr, sync::Arc};
+
+use anyhow::{Context, Result};
+use apalis::prelude::*;
+use apalis_cron::{CronContext, CronStream, Schedule};
+use chaindata_repository::NukeForecastRepository;
+use chrono::Utc;
+use tracing::{error, info};
+
+use crate::{monitoring::apalis::MonitoringLayer, worker::MonitorManager};
+
+#[derive(Debug, Default, Clone)]
+pub struct NukeForecastJob;
+
+pub async fn update_nuke_forecasts(
+    _: NukeForecastJob,
+    _ctx: CronContext<Utc>,
+    forecaster: Data<Arc<NukeForecastService>>,
+) {
+    forecaster.update().await;
+}
+
+/// Recomputes when each owned land can be nuked every minute.
+pub struct NukeForecastService {
+    repository: Arc<NukeForecastRepository>,
+}
+
+impl NukeForecastService {
+    pub fn new(
+        repository: Arc<NukeForecastRepository>,
+        monitor: &MonitorManager,
+    ) -> Result<Arc<Self>> {
+        let schedule =
+            Schedule::from_str("0 * * * * *").with_context(|| "Could not parse Schedule")?;
+
+        let this = Arc::new(Self { repository });
+
+        let worker = WorkerBuilder::new("nuke-forecast")
+            .enable_tracing()
+            .concurrency(1)
+            .layer(MonitoringLayer::new("nuke-forecast-update"))
+            .data(this.clone())
+            .backend(CronStream::new_with_timezone(schedule, Utc))
+            .build_fn(update_nuke_forecasts);
+
+        monitor.register(move |mon| mon.register(worker));
+
+        Ok(this)
+    }
+
+    /// Recomputes the forecast of every owned land.
+    pub async fn update(&self) {
+        match self.repository.refresh(Utc::now().naive_utc()).await {
+            Ok(lands) => info!("Forecast the nukes of {lands} lands"),
+            Err(err) => error!("Failed to forecast the nukes: {err}"),
+        }
+    }
+}
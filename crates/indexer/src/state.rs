@@ -1,7 +1,10 @@
 use std::sync::Arc;
 
 use axum::extract::FromRef;
-use chaindata_repository::LandRepository;
+use chaindata_repository::{
+    EventStore, LandStakeStore, LandStore, LeaderboardRepository, NukeForecastRepository,
+    PlayerRepository, TaxLedgerRepository, TokenPriceRepository,
+};
 
 use crate::service::{ekubo::EkuboService, token::TokenService};
 
@@ -9,19 +12,41 @@ use crate::service::{ekubo::EkuboService, token::TokenService};
 pub struct AppState {
     pub token_service: Arc<TokenService>,
     pub ekubo_service: Arc<EkuboService>,
-    pub land_repository: Arc<LandRepository>,
+    pub land_repository: Arc<dyn LandStore>,
+    pub land_stake_repository: Arc<dyn LandStakeStore>,
+    pub event_repository: Arc<dyn EventStore>,
+    pub player_repository: Arc<PlayerRepository>,
+    pub leaderboard_repository: Arc<LeaderboardRepository>,
+    pub tax_ledger_repository: Arc<TaxLedgerRepository>,
+    pub nuke_forecast_repository: Arc<NukeForecastRepository>,
+    pub token_price_repository: Arc<TokenPriceRepository>,
 }
 
 impl AppState {
+    #[allow(clippy::too_many_arguments)] // One per dependency shared by the routes
     pub fn new(
         token_service: Arc<TokenService>,
         ekubo_service: Arc<EkuboService>,
-        land_repository: Arc<LandRepository>,
+        land_repository: Arc<dyn LandStore>,
+        land_stake_repository: Arc<dyn LandStakeStore>,
+        event_repository: Arc<dyn EventStore>,
+        player_repository: Arc<PlayerRepository>,
+        leaderboard_repository: Arc<LeaderboardRepository>,
+        tax_ledger_repository: Arc<TaxLedgerRepository>,
+        nuke_forecast_repository: Arc<NukeForecastRepository>,
+        token_price_repository: Arc<TokenPriceRepository>,
     ) -> Self {
         Self {
             token_service,
             ekubo_service,
             land_repository,
+            land_stake_repository,
+            event_repository,
+            player_repository,
+            leaderboard_repository,
+            tax_ledger_repository,
+            nuke_forecast_repository,
+            token_price_repository,
         }
     }
 }
@@ -38,8 +63,50 @@ impl FromRef<AppState> for Arc<EkuboService> {
     }
 }
 
-impl FromRef<AppState> for Arc<LandRepository> {
+impl FromRef<AppState> for Arc<dyn LandStore> {
     fn from_ref(app_state: &AppState) -> Self {
         app_state.land_repository.clone()
     }
 }
+
+impl FromRef<AppState> for Arc<dyn LandStakeStore> {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.land_stake_repository.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn EventStore> {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.event_repository.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<PlayerRepository> {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.player_repository.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<LeaderboardRepository> {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.leaderboard_repository.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<TaxLedgerRepository> {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.tax_ledger_repository.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<NukeForecastRepository> {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.nuke_forecast_repository.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<TokenPriceRepository> {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.token_price_repository.clone()
+    }
+}
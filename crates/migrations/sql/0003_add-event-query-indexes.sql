@@ -0,0 +1,25 @@
+-- The verifier updated events were never given a table, so they could not be stored nor read back.
+CREATE TABLE event_verifier_updated (
+    id TEXT NOT NULL PRIMARY KEY,
+    new_verifier text NOT NULL,
+    old_verifier text NOT NULL
+);
+
+-- Indexes used to filter events
+CREATE INDEX event_at_idx ON event (at);
+CREATE INDEX event_event_type_idx ON event (event_type, id);
+
+CREATE INDEX event_auction_finished_location_idx ON event_auction_finished (location);
+CREATE INDEX event_auction_finished_buyer_idx ON event_auction_finished (buyer);
+
+CREATE INDEX event_land_bought_location_idx ON event_land_bought (location);
+CREATE INDEX event_land_bought_buyer_idx ON event_land_bought (buyer);
+CREATE INDEX event_land_bought_seller_idx ON event_land_bought (seller);
+
+CREATE INDEX event_land_nuked_location_idx ON event_land_nuked (location);
+CREATE INDEX event_land_nuked_owner_idx ON event_land_nuked (owner);
+
+CREATE INDEX event_new_auction_location_idx ON event_new_auction (location);
+
+CREATE INDEX event_address_authorized_address_idx ON event_address_authorized (address);
+CREATE INDEX event_address_removed_address_idx ON event_address_removed (address);
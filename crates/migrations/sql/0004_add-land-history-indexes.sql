@@ -0,0 +1,3 @@
+-- Indexes used to walk the history of a single location
+CREATE INDEX land_location_id_idx ON land (location, id);
+CREATE INDEX land_stake_location_id_idx ON land_stake (location, id);
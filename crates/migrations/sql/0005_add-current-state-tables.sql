@@ -0,0 +1,34 @@
+-- Latest version of each land / stake, maintained alongside the history tables
+-- so that "now" queries don't have to scan the whole history.
+CREATE TABLE land_current (
+    location INT4 PRIMARY KEY,
+    id TEXT NOT NULL,
+    at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    bought_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    owner TEXT NOT NULL,
+    sell_price uint_256 NOT NULL,
+    token_used TEXT NOT NULL,
+    level INT4 NOT NULL
+);
+
+CREATE INDEX land_current_owner_idx ON land_current (owner);
+
+CREATE TABLE land_stake_current (
+    location INT4 PRIMARY KEY,
+    id TEXT NOT NULL,
+    at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    last_pay_time TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    amount uint_256 NOT NULL
+);
+
+INSERT INTO land_current (location, id, at, bought_at, owner, sell_price, token_used, level)
+SELECT DISTINCT ON (location)
+    location, id, at, bought_at, owner, sell_price, token_used, level
+FROM land
+ORDER BY location, id DESC;
+
+INSERT INTO land_stake_current (location, id, at, last_pay_time, amount)
+SELECT DISTINCT ON (location)
+    location, id, at, last_pay_time, amount
+FROM land_stake
+ORDER BY location, id DESC;
@@ -0,0 +1,21 @@
+-- Market activity per token, aggregated in hourly and daily buckets.
+-- Buckets are recomputed from the event tables each time new events land in them.
+CREATE TYPE market_bucket AS ENUM ('hour', 'day');
+
+CREATE TABLE market_aggregate (
+    bucket market_bucket NOT NULL,
+    bucket_start TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    token TEXT NOT NULL,
+    trade_count INT8 NOT NULL,
+    volume uint_256 NOT NULL,
+    -- Price statistics of the trades, NULL if the bucket only has auctions
+    min_price uint_256,
+    max_price uint_256,
+    median_price uint_256,
+    auctions_started INT8 NOT NULL,
+    auctions_finished INT8 NOT NULL,
+    unique_buyers INT8 NOT NULL,
+    PRIMARY KEY (bucket, token, bucket_start)
+);
+
+CREATE INDEX market_aggregate_bucket_start_idx ON market_aggregate (bucket, bucket_start);
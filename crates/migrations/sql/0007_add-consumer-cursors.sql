@@ -0,0 +1,8 @@
+-- Last event handled by each consumer of the event dispatcher.
+-- Unlogged to keep the frequent updates cheap: the table is emptied after a crash,
+-- in which case the consumers start again from the latest event.
+CREATE UNLOGGED TABLE consumer_cursor (
+    consumer_id TEXT PRIMARY KEY,
+    last_event_id TEXT NOT NULL,
+    updated_at TIMESTAMP WITHOUT TIME ZONE NOT NULL
+);
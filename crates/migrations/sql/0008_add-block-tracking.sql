@@ -0,0 +1,7 @@
+-- Hashes of the recent blocks we stored events from, as seen when they were stored.
+-- A different hash on chain means the block was reorganized.
+CREATE TABLE block (
+    number INT8 PRIMARY KEY,
+    hash TEXT NOT NULL,
+    seen_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT NOW()
+);
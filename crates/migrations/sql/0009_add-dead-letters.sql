@@ -0,0 +1,21 @@
+CREATE TYPE dead_letter_kind AS ENUM ('event', 'model');
+
+-- Torii rows that could not be decoded, kept to be retried once the decoder is fixed.
+CREATE TABLE dead_letter (
+    id BIGSERIAL PRIMARY KEY,
+    kind dead_letter_kind NOT NULL,
+    -- Name of the event or model, as given by Torii
+    name TEXT NOT NULL,
+    -- Missing for data received through gRPC
+    torii_event_id TEXT,
+    at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    raw JSONB NOT NULL,
+    -- Error of the last attempt, with its causes
+    error TEXT NOT NULL,
+    attempts INT4 NOT NULL DEFAULT 1,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT NOW(),
+    last_attempt_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- The listeners poll with an overlap, so the same row can fail more than once
+CREATE UNIQUE INDEX dead_letter_torii_event_idx ON dead_letter (kind, name, torii_event_id);
@@ -0,0 +1,19 @@
+CREATE TYPE outbox_status AS ENUM ('pending', 'delivered', 'failed');
+
+-- Actions to deliver to gg.xyz, written in the same transaction as their event.
+CREATE TABLE gg_xyz_outbox (
+    -- Derived from the event id, and sent along so that gg.xyz can ignore repeated deliveries
+    idempotency_key TEXT PRIMARY KEY,
+    event_id TEXT NOT NULL REFERENCES event (id) ON DELETE CASCADE,
+    player_address TEXT NOT NULL,
+    action TEXT NOT NULL,
+    status outbox_status NOT NULL DEFAULT 'pending',
+    attempts INT4 NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT NOW(),
+    last_error TEXT,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT NOW(),
+    delivered_at TIMESTAMP WITHOUT TIME ZONE
+);
+
+CREATE INDEX gg_xyz_outbox_due_idx ON gg_xyz_outbox (next_attempt_at) WHERE status = 'pending';
+CREATE INDEX gg_xyz_outbox_event_idx ON gg_xyz_outbox (event_id);
@@ -0,0 +1,33 @@
+-- Subscribers to the game events, defined in the configuration or directly in the database.
+CREATE TABLE webhook (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    url TEXT NOT NULL,
+    -- Key of the HMAC-SHA256 signature sent with every delivery
+    secret TEXT NOT NULL,
+    -- Empty filters match every event
+    event_types event_type[] NOT NULL DEFAULT '{}',
+    addresses TEXT[] NOT NULL DEFAULT '{}',
+    locations INT4[] NOT NULL DEFAULT '{}',
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Deliveries of the events to the webhooks, kept as a log once done.
+CREATE TABLE webhook_delivery (
+    id BIGSERIAL PRIMARY KEY,
+    webhook_id BIGINT NOT NULL REFERENCES webhook (id) ON DELETE CASCADE,
+    event_id TEXT NOT NULL REFERENCES event (id) ON DELETE CASCADE,
+    payload JSONB NOT NULL,
+    status outbox_status NOT NULL DEFAULT 'pending',
+    attempts INT4 NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT NOW(),
+    last_error TEXT,
+    response_status INT4,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT NOW(),
+    delivered_at TIMESTAMP WITHOUT TIME ZONE,
+    UNIQUE (webhook_id, event_id)
+);
+
+CREATE INDEX webhook_delivery_due_idx ON webhook_delivery (next_attempt_at) WHERE status = 'pending';
+CREATE INDEX webhook_delivery_event_idx ON webhook_delivery (event_id);
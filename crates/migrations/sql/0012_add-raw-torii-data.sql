@@ -0,0 +1,18 @@
+CREATE TYPE raw_data_kind AS ENUM ('event', 'model');
+
+-- Rows as received from Torii, so that the derived tables can be rebuilt with newer decoders
+-- without fetching everything again.
+CREATE TABLE raw_torii_data (
+    -- Keeps the order of reception, to replay the rows by pages
+    id BIGSERIAL PRIMARY KEY,
+    kind raw_data_kind NOT NULL,
+    name TEXT NOT NULL,
+    torii_event_id TEXT NOT NULL,
+    at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    data JSONB NOT NULL,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Rows are fetched again after restarts, and several models can change in the same event
+CREATE UNIQUE INDEX raw_torii_data_unique_idx ON raw_torii_data (kind, name, torii_event_id);
+CREATE INDEX raw_torii_data_kind_idx ON raw_torii_data (kind, id);
@@ -0,0 +1,15 @@
+CREATE TYPE leaderboard_metric AS ENUM ('auction_purchases', 'player_purchases', 'tax_ratio');
+CREATE TYPE leaderboard_window AS ENUM ('day', 'week', 'month', 'all');
+
+-- Scores of every ranked address, recomputed periodically from the event and land tables.
+-- Ranks are only computed when reading, as they depend on the filters.
+CREATE TABLE leaderboard_snapshot (
+    metric leaderboard_metric NOT NULL,
+    time_window leaderboard_window NOT NULL,
+    address TEXT NOT NULL,
+    score DOUBLE PRECISION NOT NULL,
+    -- Over the whole history, whatever the window
+    transactions INT8 NOT NULL,
+    computed_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    PRIMARY KEY (metric, time_window, address)
+);
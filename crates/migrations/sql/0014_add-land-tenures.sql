@@ -0,0 +1,26 @@
+CREATE TYPE tenure_outcome AS ENUM ('sold', 'nuked');
+
+-- Each ownership of a land, from its purchase until it was bought again or nuked.
+-- Rebuilt per location from the events and the land and stake versions, so the columns
+-- read from versions stay NULL until these are stored.
+CREATE TABLE land_tenure (
+    -- Id of the purchase event, from a player or an auction
+    id TEXT PRIMARY KEY,
+    location INT4 NOT NULL,
+    owner TEXT NOT NULL,
+    token_used TEXT,
+    purchase_price uint_256 NOT NULL,
+    from_auction BOOLEAN NOT NULL,
+    bought_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    -- Stake set in the transaction of the purchase
+    initial_stake uint_256,
+    -- Id of the event ending the tenure, NULL while the land is owned
+    ended_by TEXT,
+    ended_at TIMESTAMP WITHOUT TIME ZONE,
+    outcome tenure_outcome,
+    -- Stake left just before the land was sold
+    final_stake uint_256
+);
+
+CREATE INDEX land_tenure_location_idx ON land_tenure (location, id);
+CREATE INDEX land_tenure_owner_idx ON land_tenure (owner, id);
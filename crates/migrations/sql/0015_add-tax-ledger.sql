@@ -0,0 +1,31 @@
+ALTER TYPE event_type ADD VALUE 'ponzi_land-LandTransferEvent';
+
+CREATE TABLE event_land_transfer (
+    id TEXT NOT NULL PRIMARY KEY,
+    from_location INT4 NOT NULL,
+    to_location INT4 NOT NULL,
+    token_address text NOT NULL,
+    amount uint_256 NOT NULL
+);
+
+-- Taxes paid from a land to a neighbour, one row per transfer event.
+-- Owners are read from the land version active before the transaction of the transfer,
+-- so they stay NULL until that version is stored.
+CREATE TABLE tax_ledger (
+    -- Id of the transfer event
+    id TEXT PRIMARY KEY,
+    at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    token TEXT NOT NULL,
+    amount uint_256 NOT NULL,
+    payer_location INT4 NOT NULL,
+    payer_owner TEXT,
+    recipient_location INT4 NOT NULL,
+    recipient_owner TEXT
+);
+
+CREATE INDEX tax_ledger_payer_owner_idx ON tax_ledger (payer_owner, at);
+CREATE INDEX tax_ledger_recipient_owner_idx ON tax_ledger (recipient_owner, at);
+CREATE INDEX tax_ledger_payer_location_idx ON tax_ledger (payer_location, at);
+CREATE INDEX tax_ledger_recipient_location_idx ON tax_ledger (recipient_location, at);
+CREATE INDEX tax_ledger_unresolved_idx ON tax_ledger (id)
+WHERE payer_owner IS NULL OR recipient_owner IS NULL;
@@ -0,0 +1,23 @@
+-- ERC20 balances and transfers, mirrored from torii.
+CREATE TABLE token_balance (
+    token TEXT NOT NULL,
+    account TEXT NOT NULL,
+    balance uint_256 NOT NULL,
+    PRIMARY KEY (token, account)
+);
+
+CREATE INDEX token_balance_holders_idx ON token_balance (token, balance DESC);
+
+CREATE TABLE token_transfer (
+    -- Id given by torii
+    id TEXT PRIMARY KEY,
+    at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    token TEXT NOT NULL,
+    from_address TEXT NOT NULL,
+    to_address TEXT NOT NULL,
+    amount uint_256 NOT NULL
+);
+
+CREATE INDEX token_transfer_at_idx ON token_transfer (at);
+CREATE INDEX token_transfer_from_idx ON token_transfer (from_address, at);
+CREATE INDEX token_transfer_to_idx ON token_transfer (to_address, at);
@@ -0,0 +1,17 @@
+-- Time at which each owned land can be nuked if nothing changes, recomputed periodically from
+-- the current land and stake state with the tax rules of the contract.
+CREATE TABLE nuke_forecast (
+    location INT4 PRIMARY KEY,
+    owner TEXT NOT NULL,
+    token_used TEXT NOT NULL,
+    stake uint_256 NOT NULL,
+    -- Occupied neighbors, paid by the land
+    neighbors INT4 NOT NULL,
+    -- Taxes paid to each neighbor per hour
+    tax_per_neighbor uint_256 NOT NULL,
+    -- NULL when the land pays no taxes
+    nuke_at TIMESTAMP WITHOUT TIME ZONE,
+    computed_at TIMESTAMP WITHOUT TIME ZONE NOT NULL
+);
+
+CREATE INDEX nuke_forecast_nuke_at_idx ON nuke_forecast (nuke_at) WHERE nuke_at IS NOT NULL;
@@ -0,0 +1,14 @@
+-- Prices of the tokens sampled from the Ekubo pools at every price update.
+CREATE TABLE token_price (
+    token TEXT NOT NULL,
+    at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    -- Amount of the token per main token
+    ratio DOUBLE PRECISION NOT NULL,
+    -- Key of the pool holding the token, the last one of the price path
+    pool_token0 TEXT NOT NULL,
+    pool_token1 TEXT NOT NULL,
+    pool_fee uint_256 NOT NULL,
+    pool_tick_spacing INT8 NOT NULL,
+    pool_extension TEXT NOT NULL,
+    PRIMARY KEY (token, at)
+);
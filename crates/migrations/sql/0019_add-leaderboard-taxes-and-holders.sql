@@ -0,0 +1,25 @@
+ALTER TYPE leaderboard_metric ADD VALUE 'taxes_received';
+ALTER TYPE leaderboard_metric ADD VALUE 'token_balance';
+
+-- Value of an amount of a token in the main token, at the last price stored before the given
+-- time, or NULL if there is none.
+CREATE FUNCTION main_token_value(
+    token TEXT,
+    amount NUMERIC,
+    at TIMESTAMP WITHOUT TIME ZONE,
+    main_token TEXT
+) RETURNS DOUBLE PRECISION
+LANGUAGE SQL STABLE
+AS $$
+    SELECT CASE
+        WHEN token = main_token THEN amount::float8
+        ELSE (
+            SELECT amount::float8 / p.ratio
+            FROM token_price p
+            WHERE p.token = main_token_value.token AND p.at <= main_token_value.at
+                AND p.ratio > 0
+            ORDER BY p.at DESC
+            LIMIT 1
+        )
+    END
+$$;
@@ -0,0 +1,30 @@
+-- Position of each event in the order they were stored.
+-- Events can be stored out of chain order (late pages, reorgs, reindexes, recovered
+-- dead letters), so the consumers follow this sequence instead of the event ids.
+ALTER TABLE event ADD COLUMN seq BIGINT;
+
+UPDATE event SET seq = ordered.seq
+FROM (SELECT id, ROW_NUMBER() OVER (ORDER BY id) AS seq FROM event) AS ordered
+WHERE event.id = ordered.id;
+
+CREATE SEQUENCE event_seq_seq OWNED BY event.seq;
+SELECT setval('event_seq_seq', COALESCE((SELECT MAX(seq) FROM event), 0) + 1, false);
+
+ALTER TABLE event
+    ALTER COLUMN seq SET DEFAULT nextval('event_seq_seq'),
+    ALTER COLUMN seq SET NOT NULL;
+
+CREATE UNIQUE INDEX event_seq_idx ON event (seq);
+CREATE INDEX event_event_type_seq_idx ON event (event_type, seq);
+
+-- The consumers resume after the last sequence number they handled
+ALTER TABLE consumer_cursor ADD COLUMN last_seq BIGINT;
+
+UPDATE consumer_cursor SET last_seq = COALESCE(
+    (SELECT MAX(seq) FROM event WHERE event.id <= consumer_cursor.last_event_id),
+    0
+);
+
+ALTER TABLE consumer_cursor
+    ALTER COLUMN last_seq SET NOT NULL,
+    DROP COLUMN last_event_id;
@@ -0,0 +1,9 @@
+-- Block of each raw row, to remove the rows of the reorganized blocks along with what was
+-- decoded from them. Torii event ids start with the block number, in hex.
+ALTER TABLE raw_torii_data ADD COLUMN block BIGINT;
+
+UPDATE raw_torii_data
+SET block = ('x' || lpad(ltrim(substr(split_part(torii_event_id, ':', 1), 3), '0'), 16, '0'))::bit(64)::bigint
+WHERE split_part(torii_event_id, ':', 1) ~ '^0x0*[0-9a-fA-F]{1,15}$';
+
+CREATE INDEX raw_torii_data_block_idx ON raw_torii_data (block);
@@ -1,9 +1,11 @@
 mod auction_finished;
 mod land_bought;
 mod land_nuked;
+mod land_transfer;
 mod new_auction;
 
 pub use auction_finished::AuctionFinishedEvent;
 pub use land_bought::LandBoughtEvent;
 pub use land_nuked::LandNukedEvent;
+pub use land_transfer::LandTransferEvent;
 pub use new_auction::NewAuctionEvent;
@@ -2,7 +2,9 @@ use serde_json::Value;
 use torii_ingester::prelude::Struct;
 use torii_ingester::{error::ToriiConversionError, RawToriiData};
 
-use super::actions::{AuctionFinishedEvent, LandBoughtEvent, LandNukedEvent, NewAuctionEvent};
+use super::actions::{
+    AuctionFinishedEvent, LandBoughtEvent, LandNukedEvent, LandTransferEvent, NewAuctionEvent,
+};
 use super::auth::{AddressAuthorizedEvent, AddressRemovedEvent, VerifierUpdatedEvent};
 
 #[derive(Clone, Debug)]
@@ -10,6 +12,7 @@ pub enum EventData {
     AuctionFinished(AuctionFinishedEvent),
     LandBought(LandBoughtEvent),
     LandNuked(LandNukedEvent),
+    LandTransfer(LandTransferEvent),
     NewAuction(NewAuctionEvent),
     AddressAuthorized(AddressAuthorizedEvent),
     AddressRemoved(AddressRemovedEvent),
@@ -71,6 +74,9 @@ impl EventData {
             }
             "ponzi_land-LandBoughtEvent" => EventData::LandBought(serde_json::from_value(json)?),
             "ponzi_land-LandNukedEvent" => EventData::LandNuked(serde_json::from_value(json)?),
+            "ponzi_land-LandTransferEvent" => {
+                EventData::LandTransfer(serde_json::from_value(json)?)
+            }
             "ponzi_land-NewAuctionEvent" => EventData::NewAuction(serde_json::from_value(json)?),
             "ponzi_land-AddressAuthorizedEvent" => {
                 EventData::AddressAuthorized(serde_json::from_value(json)?)
@@ -100,6 +106,9 @@ impl TryFrom<Struct> for EventData {
                 EventData::LandBought(LandBoughtEvent::try_from(value)?)
             }
             "ponzi_land-LandNukedEvent" => EventData::LandNuked(LandNukedEvent::try_from(value)?),
+            "ponzi_land-LandTransferEvent" => {
+                EventData::LandTransfer(LandTransferEvent::try_from(value)?)
+            }
             "ponzi_land-NewAuctionEvent" => {
                 EventData::NewAuction(NewAuctionEvent::try_from(value)?)
             }
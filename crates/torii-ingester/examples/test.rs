@@ -22,6 +22,6 @@ pub async fn main() {
     let mut event_stream = client.get_all_events().expect("Failed to fetch events");
 
     while let Some(event) = event_stream.next().await {
-        println!("{event:?}");
+        println!("{:?}", event.expect("Failed to fetch a page of events"));
     }
 }
@@ -4,6 +4,8 @@ pub mod torii_sql;
 
 pub mod torii_events;
 
+pub mod torii_tokens;
+
 pub use torii_client::*;
 
 pub mod prelude;
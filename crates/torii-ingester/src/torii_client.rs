@@ -1,4 +1,5 @@
 use crate::torii_sql::SqlClient;
+use crate::torii_tokens::{TokenBalance, TokenTransfer};
 use async_stream::stream;
 use chrono::{DateTime, NaiveDateTime, Utc};
 use dojo_types::schema::Struct;
@@ -9,7 +10,7 @@ use starknet::core::types::Felt;
 use thiserror::Error;
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
-use tokio_stream::Stream;
+use tokio_stream::{Stream, StreamExt};
 use torii_client::Client as GrpcClient;
 
 // TODO(Red): Make sure we loose no messages between the catchup and the listen
@@ -90,41 +91,85 @@ impl ToriiClient {
     /// Get all events after a given instant with microsecond precision.
     ///
     /// # Errors
-    /// Returns an error if the SQL query fails.
+    /// The stream yields an error, and ends, if the SQL query fails.
     pub fn get_all_events_after(
         &self,
         instant: chrono::DateTime<Utc>,
-    ) -> Result<impl Stream<Item = RawToriiData>, Error> {
+    ) -> Result<impl Stream<Item = Result<RawToriiData, Error>>, Error> {
         self.do_events_sql_request(format!("em.created_at > \"{}\"", instant.format("%F %T")))
     }
 
     /// Get all events.
     ///
     /// # Errors
-    /// Returns an error if the SQL query fails.
-    pub fn get_all_events(&self) -> Result<impl Stream<Item = RawToriiData>, Error> {
+    /// The stream yields an error, and ends, if the SQL query fails.
+    pub fn get_all_events(
+        &self,
+    ) -> Result<impl Stream<Item = Result<RawToriiData, Error>>, Error> {
         self.do_events_sql_request("1=1")
     }
 
     /// Get all entities.
     ///
     /// # Errors
-    /// Returns an error if the SQL query fails.
-    pub fn get_all_entities(&self) -> Result<impl Stream<Item = RawToriiData>, Error> {
+    /// The stream yields an error, and ends, if the SQL query fails.
+    pub fn get_all_entities(
+        &self,
+    ) -> Result<impl Stream<Item = Result<RawToriiData, Error>>, Error> {
         self.do_entities_sql_request("1=1")
     }
 
     /// Get all entities after a given instant.
     ///
     /// # Errors
-    /// Returns an error if the SQL query fails.
+    /// The stream yields an error, and ends, if the SQL query fails.
     pub fn get_all_entities_after(
         &self,
         instant: chrono::DateTime<Utc>,
-    ) -> Result<impl Stream<Item = RawToriiData>, Error> {
+    ) -> Result<impl Stream<Item = Result<RawToriiData, Error>>, Error> {
         self.do_entities_sql_request(format!("e.created_at > \"{}\"", instant.format("%F %T")))
     }
 
+    /// Get the balances of every account in the ERC20 tokens indexed by torii.
+    ///
+    /// # Errors
+    /// The stream yields an error, and ends, if the SQL query fails.
+    pub fn get_token_balances(
+        &self,
+    ) -> Result<impl Stream<Item = Result<TokenBalance, Error>>, Error> {
+        self.do_query(|current_offset| {
+            format!(r"
+                SELECT b.contract_address as contract_address, b.account_address as account_address, b.balance as balance
+                FROM token_balances b
+                JOIN contracts c on c.contract_address = b.contract_address
+                WHERE c.contract_type = 'ERC20'
+                ORDER BY b.id
+                LIMIT 100 OFFSET {current_offset};
+                ")
+        })
+    }
+
+    /// Get the ERC20 transfers executed after a given instant, with second precision.
+    ///
+    /// # Errors
+    /// The stream yields an error, and ends, if the SQL query fails.
+    pub fn get_token_transfers_after(
+        &self,
+        instant: chrono::DateTime<Utc>,
+    ) -> Result<impl Stream<Item = Result<TokenTransfer, Error>>, Error> {
+        let after = instant.format("%F %T").to_string();
+        self.do_query(move |current_offset| {
+            format!(r"
+                SELECT t.id as id, t.contract_address as contract_address, t.from_address as from_address, t.to_address as to_address, t.amount as amount, t.executed_at as executed_at
+                FROM token_transfers t
+                JOIN contracts c on c.contract_address = t.contract_address
+                WHERE c.contract_type = 'ERC20' AND datetime(t.executed_at) > datetime('{after}')
+                ORDER BY t.executed_at, t.id
+                LIMIT 100 OFFSET {current_offset};
+                ")
+        })
+    }
+
     /// Subscribe to events.
     ///
     /// # Errors
@@ -186,7 +231,7 @@ impl ToriiClient {
     fn do_entities_sql_request(
         &self,
         r#where: impl Into<String>,
-    ) -> Result<impl Stream<Item = RawToriiData>, Error> {
+    ) -> Result<impl Stream<Item = Result<RawToriiData, Error>>, Error> {
         let r#where = r#where.into();
         self.do_request(move |current_offset| {
             format!(r"
@@ -194,6 +239,7 @@ impl ToriiClient {
                 FROM entities_historical e
                 LEFT JOIN models m on e.model_id = m.id
                 WHERE {where}
+                ORDER BY e.created_at, e.event_id
                 LIMIT 100 OFFSET {current_offset};
                 ")
         })
@@ -202,7 +248,7 @@ impl ToriiClient {
     fn do_events_sql_request(
         &self,
         r#where: impl Into<String>,
-    ) -> Result<impl Stream<Item = RawToriiData>, Error> {
+    ) -> Result<impl Stream<Item = Result<RawToriiData, Error>>, Error> {
         let r#where = r#where.into();
         self.do_request(move |current_offset| {
             format!(r"
@@ -210,13 +256,40 @@ impl ToriiClient {
                 FROM event_messages_historical em
                 LEFT JOIN models m on em.model_id = m.id
                 WHERE {where}
+                ORDER BY em.created_at, em.event_id
                 LIMIT 100 OFFSET {current_offset};
                 ")
         })
     }
 
+    fn do_request<F, T>(
+        &self,
+        request: F,
+    ) -> Result<impl Stream<Item = Result<RawToriiData, Error>>, Error>
+    where
+        T: Into<String>,
+        F: 'static + Send + Fn(u64) -> T,
+    {
+        Ok(self.do_query(request)?.map(|elem| {
+            elem.map(|elem: QueryResponse| {
+                RawToriiData::Json {
+                    name: elem.selector,
+                    data: elem.data,
+                    event_id: elem.event_id,
+                    // TODO: Migrate this to something else than panics
+                    at: NaiveDateTime::parse_from_str(&elem.created_at, "%F %T")
+                        .unwrap()
+                        .and_utc(),
+                }
+            })
+        }))
+    }
+
+    /// Runs a paginated query, and streams its rows.
+    ///
+    /// If a page could not be fetched, the stream yields the error and ends.
     #[allow(clippy::unnecessary_wraps)] // This actually makes sense
-    fn do_request<F, T>(&self, request: F) -> Result<impl Stream<Item = RawToriiData>, Error>
+    fn do_query<F, T, R>(&self, request: F) -> Result<impl Stream<Item = Result<R, Error>>, Error>
     where
         T: Into<String>,
         // We need a function that:
@@ -224,21 +297,25 @@ impl ToriiClient {
         // - that lives for the entire duration of the program (easy if no internal state is used)
         // - Takes a u64 as a parameter, and returns something that can be .into() to a String (for move sementics purposes)
         F: 'static + Send + Fn(u64) -> T,
+        R: 'static + Send + DeserializeOwned,
     {
         let sql_client = self.sql_client.clone();
 
-        let (tx, rx) = mpsc::channel::<RawToriiData>(32);
+        let (tx, rx) = mpsc::channel::<Result<R, Error>>(32);
 
         tokio::spawn(async move {
             let mut current_offset = 0;
 
             loop {
                 // TODO(red): Add base offset support
-                let request: Vec<QueryResponse> = sql_client
-                    .query(request(current_offset).into())
-                    .await
-                    // TODO: Remove usage of panics
-                    .expect("ohno");
+                let request: Vec<R> = match sql_client.query(request(current_offset).into()).await {
+                    Ok(request) => request,
+                    Err(err) => {
+                        // Whether it is still listened to or not, the stream ends here
+                        let _ = tx.send(Err(err.into())).await;
+                        return;
+                    }
+                };
 
                 if request.is_empty() {
                     break;
@@ -248,17 +325,10 @@ impl ToriiClient {
 
                 // We can send data through the wire.
                 for elem in request {
-                    let event = RawToriiData::Json {
-                        name: elem.selector,
-                        data: elem.data,
-                        event_id: elem.event_id,
-                        // TODO: Migrate this to something else than panics
-                        at: NaiveDateTime::parse_from_str(&elem.created_at, "%F %T")
-                            .unwrap()
-                            .and_utc(),
-                    };
-                    // TODO: Migrate this to something else than panics
-                    tx.send(event).await.expect("Error");
+                    if tx.send(Ok(elem)).await.is_err() {
+                        // The stream was dropped, nobody is listening anymore
+                        return;
+                    }
                 }
             }
         });
@@ -0,0 +1,37 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Deserializer};
+use starknet::core::types::Felt;
+
+use crate::u256::U256;
+
+/// Balance of an account in an ERC20 token, as indexed by torii.
+#[derive(Deserialize, Debug, Clone)]
+pub struct TokenBalance {
+    pub contract_address: Felt,
+    pub account_address: Felt,
+    pub balance: U256,
+}
+
+/// Transfer of an ERC20 token, as indexed by torii.
+#[derive(Deserialize, Debug, Clone)]
+pub struct TokenTransfer {
+    pub id: String,
+    pub contract_address: Felt,
+    pub from_address: Felt,
+    pub to_address: Felt,
+    pub amount: U256,
+    #[serde(deserialize_with = "deserialize_torii_date")]
+    pub executed_at: DateTime<Utc>,
+}
+
+/// Torii stores its dates either in RFC 3339 or in the `SQLite` format, depending on the table.
+fn deserialize_torii_date<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let date = String::deserialize(deserializer)?;
+    DateTime::parse_from_rfc3339(&date)
+        .map(|date| date.with_timezone(&Utc))
+        .or_else(|_| NaiveDateTime::parse_from_str(&date, "%F %T").map(|date| date.and_utc()))
+        .map_err(serde::de::Error::custom)
+}